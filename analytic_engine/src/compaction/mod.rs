@@ -25,6 +25,8 @@ pub mod scheduler;
 pub enum Error {
     #[snafu(display("Unable to parse compaction strategy, value: {}", value))]
     ParseStrategy { value: String, backtrace: Backtrace },
+    #[snafu(display("Compaction strategy is not supported yet, value: {}", value))]
+    UnsupportedStrategy { value: String, backtrace: Backtrace },
     #[snafu(display("Unable to parse float, key: {}, value: {}", key, value))]
     ParseFloat {
         key: String,
@@ -46,6 +48,13 @@ pub enum Error {
         error: String,
         backtrace: Backtrace,
     },
+    #[snafu(display("Unable to parse bool, key: {}, value: {}", key, value))]
+    ParseBool {
+        key: String,
+        value: String,
+        source: std::str::ParseBoolError,
+        backtrace: Backtrace,
+    },
     #[snafu(display("Unable to parse time unit, key: {}, value: {}", key, value))]
     ParseTimeUnit {
         key: String,
@@ -62,6 +71,7 @@ pub enum CompactionStrategy {
     Default,
     TimeWindow(TimeWindowCompactionOptions),
     SizeTiered(SizeTieredCompactionOptions),
+    Leveled(LeveledCompactionOptions),
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
@@ -78,6 +88,23 @@ pub struct TimeWindowCompactionOptions {
     pub size_tiered: SizeTieredCompactionOptions,
     // TODO(boyan) In fact right now we only supports TimeUnit::Milliseconds resolution.
     pub timestamp_resolution: TimeUnit,
+    /// Align the compaction windows to the ttl of the table, so the data
+    /// expiring at different time is never merged into the same sst, and the
+    /// windows about to expire are not compacted any more. Only takes effect
+    /// if the ttl is a multiple of the segment duration.
+    pub ttl_aligned: bool,
+}
+
+/// Options of leveled compaction, the size limit of level `n` (n >= 1) is
+/// `target_file_size * level_size_multiplier ^ n`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub struct LeveledCompactionOptions {
+    /// Number of levels, including level 0.
+    pub max_levels: usize,
+    /// Size ratio between two adjacent levels.
+    pub level_size_multiplier: usize,
+    /// Target size of the output sst file.
+    pub target_file_size: ReadableSize,
 }
 
 impl Default for SizeTieredCompactionOptions {
@@ -97,6 +124,17 @@ impl Default for TimeWindowCompactionOptions {
         Self {
             size_tiered: SizeTieredCompactionOptions::default(),
             timestamp_resolution: TimeUnit::Milliseconds,
+            ttl_aligned: false,
+        }
+    }
+}
+
+impl Default for LeveledCompactionOptions {
+    fn default() -> Self {
+        Self {
+            max_levels: 4,
+            level_size_multiplier: 10,
+            target_file_size: ReadableSize::mb(64),
         }
     }
 }
@@ -113,9 +151,14 @@ const MIN_THRESHOLD_KEY: &str = "compaction_min_threshold";
 const MAX_THRESHOLD_KEY: &str = "compaction_max_threshold";
 const MIN_SSTABLE_SIZE_KEY: &str = "compaction_min_sstable_size";
 const TIMESTAMP_RESOLUTION_KEY: &str = "compaction_timestamp_resolution";
+const TTL_ALIGNED_KEY: &str = "compaction_ttl_aligned";
+const MAX_LEVELS_KEY: &str = "compaction_max_levels";
+const LEVEL_SIZE_MULTIPLIER_KEY: &str = "compaction_level_size_multiplier";
+const TARGET_FILE_SIZE_KEY: &str = "compaction_target_file_size";
 const DEFAULT_STRATEGY: &str = "default";
 const STC_STRATEGY: &str = "size_tiered";
 const TWC_STRATEGY: &str = "time_window";
+const LEVELED_STRATEGY: &str = "leveled";
 
 impl CompactionStrategy {
     pub(crate) fn parse_from(
@@ -130,6 +173,12 @@ impl CompactionStrategy {
             TWC_STRATEGY => Ok(CompactionStrategy::TimeWindow(
                 TimeWindowCompactionOptions::parse_from(options)?,
             )),
+            // TODO: Accept leveled compaction once its picker is implemented, the
+            // options are still validated to report the invalid ones first.
+            LEVELED_STRATEGY => {
+                LeveledCompactionOptions::parse_from(options)?;
+                UnsupportedStrategy { value }.fail()
+            }
             _ => ParseStrategy {
                 value: value.to_string(),
             }
@@ -153,6 +202,13 @@ impl CompactionStrategy {
                 m.insert(COMPACTION_STRATEGY.to_string(), TWC_STRATEGY.to_string());
                 opts.fill_raw_map(m);
             }
+            CompactionStrategy::Leveled(opts) => {
+                m.insert(
+                    COMPACTION_STRATEGY.to_string(),
+                    LEVELED_STRATEGY.to_string(),
+                );
+                opts.fill_raw_map(m);
+            }
         }
     }
 }
@@ -168,6 +224,33 @@ impl SizeTieredCompactionOptions {
                 ),
             }
         );
+        ensure!(
+            self.bucket_low > 0.0,
+            InvalidOption {
+                error: format!(
+                    "{} value({}) is not positive",
+                    BUCKET_LOW_KEY, self.bucket_low
+                ),
+            }
+        );
+        ensure!(
+            self.min_threshold >= 2,
+            InvalidOption {
+                error: format!(
+                    "{} value({}) is less than 2",
+                    MIN_THRESHOLD_KEY, self.min_threshold
+                ),
+            }
+        );
+        ensure!(
+            self.max_threshold >= self.min_threshold,
+            InvalidOption {
+                error: format!(
+                    "{} value({}) is less than the {} value({})",
+                    MAX_THRESHOLD_KEY, self.max_threshold, MIN_THRESHOLD_KEY, self.min_threshold
+                ),
+            }
+        );
 
         Ok(())
     }
@@ -195,7 +278,7 @@ impl SizeTieredCompactionOptions {
         let mut opts = SizeTieredCompactionOptions::default();
         if let Some(v) = options.get(BUCKET_LOW_KEY) {
             opts.bucket_low = v.parse().context(ParseFloat {
-                key: BUCKET_HIGH_KEY,
+                key: BUCKET_LOW_KEY,
                 value: v,
             })?;
         }
@@ -252,6 +335,7 @@ impl TimeWindowCompactionOptions {
             TIMESTAMP_RESOLUTION_KEY.to_string(),
             format!("{}", self.timestamp_resolution),
         );
+        m.insert(TTL_ALIGNED_KEY.to_string(), format!("{}", self.ttl_aligned));
     }
 
     pub(crate) fn validate(&self) -> Result<(), Error> {
@@ -285,6 +369,85 @@ impl TimeWindowCompactionOptions {
                     backtrace: Backtrace::generate(),
                 })?;
         }
+        if let Some(v) = options.get(TTL_ALIGNED_KEY) {
+            opts.ttl_aligned = v.parse().context(ParseBool {
+                key: TTL_ALIGNED_KEY,
+                value: v,
+            })?;
+        }
+
+        opts.validate()?;
+
+        Ok(opts)
+    }
+}
+
+impl LeveledCompactionOptions {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        ensure!(
+            self.max_levels >= 2,
+            InvalidOption {
+                error: format!(
+                    "{} value({}) is less than 2",
+                    MAX_LEVELS_KEY, self.max_levels
+                ),
+            }
+        );
+        ensure!(
+            self.level_size_multiplier >= 2,
+            InvalidOption {
+                error: format!(
+                    "{} value({}) is less than 2",
+                    LEVEL_SIZE_MULTIPLIER_KEY, self.level_size_multiplier
+                ),
+            }
+        );
+        ensure!(
+            self.target_file_size.0 > 0,
+            InvalidOption {
+                error: format!("{} value is zero", TARGET_FILE_SIZE_KEY),
+            }
+        );
+
+        Ok(())
+    }
+
+    fn fill_raw_map(&self, m: &mut HashMap<String, String>) {
+        m.insert(MAX_LEVELS_KEY.to_string(), format!("{}", self.max_levels));
+        m.insert(
+            LEVEL_SIZE_MULTIPLIER_KEY.to_string(),
+            format!("{}", self.level_size_multiplier),
+        );
+        m.insert(
+            TARGET_FILE_SIZE_KEY.to_string(),
+            format!("{}", self.target_file_size.0),
+        );
+    }
+
+    pub(crate) fn parse_from(
+        options: &HashMap<String, String>,
+    ) -> Result<LeveledCompactionOptions, Error> {
+        let mut opts = LeveledCompactionOptions::default();
+        if let Some(v) = options.get(MAX_LEVELS_KEY) {
+            opts.max_levels = v.parse().context(ParseInt {
+                key: MAX_LEVELS_KEY,
+                value: v,
+            })?;
+        }
+        if let Some(v) = options.get(LEVEL_SIZE_MULTIPLIER_KEY) {
+            opts.level_size_multiplier = v.parse().context(ParseInt {
+                key: LEVEL_SIZE_MULTIPLIER_KEY,
+                value: v,
+            })?;
+        }
+        if let Some(v) = options.get(TARGET_FILE_SIZE_KEY) {
+            opts.target_file_size = v.parse::<ReadableSize>().map_err(|err| Error::ParseSize {
+                key: TARGET_FILE_SIZE_KEY.to_string(),
+                value: v.to_string(),
+                error: err,
+                backtrace: Backtrace::generate(),
+            })?;
+        }
 
         opts.validate()?;
 
@@ -360,6 +523,9 @@ impl PickerManager {
             CompactionStrategy::Default => self.default_picker.clone(),
             CompactionStrategy::SizeTiered(_) => self.size_tiered_picker.clone(),
             CompactionStrategy::TimeWindow(_) => self.time_window_picker.clone(),
+            // Leveled compaction is rejected when parsing the options until its
+            // picker is implemented.
+            CompactionStrategy::Leveled(_) => self.size_tiered_picker.clone(),
         }
     }
 }
@@ -466,7 +632,7 @@ mod tests {
         let mut m = HashMap::new();
         c.fill_raw_map(&mut m);
 
-        assert_eq!(7, m.len());
+        assert_eq!(8, m.len());
         assert_eq!(m[COMPACTION_STRATEGY], "time_window");
         assert_eq!(m[BUCKET_LOW_KEY], "0.1");
         assert_eq!(m[BUCKET_HIGH_KEY], "1.5");
@@ -474,9 +640,73 @@ mod tests {
         assert_eq!(m[MIN_THRESHOLD_KEY], "4");
         assert_eq!(m[MAX_THRESHOLD_KEY], "10");
         assert_eq!(m[TIMESTAMP_RESOLUTION_KEY], "milliseconds");
+        assert_eq!(m[TTL_ALIGNED_KEY], "false");
         assert_eq!(
             c,
             CompactionStrategy::parse_from("time_window", &m).unwrap()
         );
+
+        let twc_opts = TimeWindowCompactionOptions {
+            size_tiered: opts,
+            ttl_aligned: true,
+            ..Default::default()
+        };
+        let c = CompactionStrategy::TimeWindow(twc_opts);
+        let mut m = HashMap::new();
+        c.fill_raw_map(&mut m);
+        assert_eq!(m[TTL_ALIGNED_KEY], "true");
+        assert_eq!(
+            c,
+            CompactionStrategy::parse_from("time_window", &m).unwrap()
+        );
+
+        m.insert(TTL_ALIGNED_KEY.to_string(), "yes".to_string());
+        assert!(CompactionStrategy::parse_from("time_window", &m).is_err());
+
+        let leveled_opts = LeveledCompactionOptions {
+            max_levels: 5,
+            target_file_size: ReadableSize(4096),
+            ..Default::default()
+        };
+        let c = CompactionStrategy::Leveled(leveled_opts);
+        let mut m = HashMap::new();
+        c.fill_raw_map(&mut m);
+
+        assert_eq!(4, m.len());
+        assert_eq!(m[COMPACTION_STRATEGY], "leveled");
+        assert_eq!(m[MAX_LEVELS_KEY], "5");
+        assert_eq!(m[LEVEL_SIZE_MULTIPLIER_KEY], "10");
+        assert_eq!(m[TARGET_FILE_SIZE_KEY], "4096");
+        assert_eq!(
+            leveled_opts,
+            LeveledCompactionOptions::parse_from(&m).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_leveled_strategy_unsupported() {
+        let m = HashMap::new();
+        let err = CompactionStrategy::parse_from("leveled", &m).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedStrategy { .. }), "{}", err);
+
+        let mut m = HashMap::new();
+        m.insert(MAX_LEVELS_KEY.to_string(), "1".to_string());
+        let err = CompactionStrategy::parse_from("leveled", &m).unwrap_err();
+        assert!(matches!(err, Error::InvalidOption { .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_invalid_leveled_options() {
+        let mut m = HashMap::new();
+        m.insert(MAX_LEVELS_KEY.to_string(), "1".to_string());
+        assert!(LeveledCompactionOptions::parse_from(&m).is_err());
+
+        let mut m = HashMap::new();
+        m.insert(LEVEL_SIZE_MULTIPLIER_KEY.to_string(), "x".to_string());
+        assert!(LeveledCompactionOptions::parse_from(&m).is_err());
+
+        let mut m = HashMap::new();
+        m.insert(TARGET_FILE_SIZE_KEY.to_string(), "0".to_string());
+        assert!(LeveledCompactionOptions::parse_from(&m).is_err());
     }
 }
@@ -22,6 +22,7 @@ use crate::{
         file::{FileHandle, Level},
         manager::LevelsController,
     },
+    table_options::StorageFormat,
 };
 
 #[derive(Debug, Snafu)]
@@ -35,6 +36,9 @@ pub struct PickerContext {
     /// The ttl of the data in sst.
     pub ttl: Option<Duration>,
     pub strategy: CompactionStrategy,
+    /// Ssts not in this storage format should be rewritten, None if no rewrite
+    /// is pending.
+    pub rewrite_storage_format: Option<StorageFormat>,
 }
 
 impl PickerContext {
@@ -86,9 +90,11 @@ pub struct CommonCompactionPicker {
 impl CommonCompactionPicker {
     pub fn new(strategy: CompactionStrategy) -> Self {
         let level_picker: LevelPickerRef = match strategy {
-            CompactionStrategy::SizeTiered(_) | CompactionStrategy::Default => {
-                Arc::new(SizeTieredPicker::default())
-            }
+            // TODO: Level picker for leveled compaction is not implemented yet, the
+            // strategy is rejected when parsing the options.
+            CompactionStrategy::SizeTiered(_)
+            | CompactionStrategy::Default
+            | CompactionStrategy::Leveled(_) => Arc::new(SizeTieredPicker::default()),
             CompactionStrategy::TimeWindow(_) => Arc::new(TimeWindowPicker::default()),
         };
         Self { level_picker }
@@ -120,6 +126,31 @@ impl CommonCompactionPicker {
 
         None
     }
+
+    /// Pick a sst not in the `storage_format` to rewrite, one sst is rewritten
+    /// at a time to avoid merging ssts across segments.
+    fn pick_rewrite_candidates(
+        &self,
+        levels_controller: &LevelsController,
+        expire_time: Option<Timestamp>,
+        storage_format: StorageFormat,
+    ) -> Option<CompactionInputFiles> {
+        let num_levels = levels_controller.num_levels();
+        for level in 0..num_levels {
+            let file = find_uncompact_files(levels_controller, level, expire_time)
+                .into_iter()
+                .find(|file| file.storage_format() != storage_format);
+            if let Some(file) = file {
+                return Some(CompactionInputFiles {
+                    level,
+                    files: vec![file],
+                    output_level: level,
+                });
+            }
+        }
+
+        None
+    }
 }
 
 impl CompactionPicker for CommonCompactionPicker {
@@ -143,6 +174,17 @@ impl CompactionPicker for CommonCompactionPicker {
             );
 
             compaction_task.compaction_inputs = vec![input_files];
+        } else if let Some(storage_format) = ctx.rewrite_storage_format {
+            if let Some(input_files) =
+                self.pick_rewrite_candidates(levels_controller, expire_time, storage_format)
+            {
+                info!(
+                    "Compaction picker pick files to rewrite into storage format:{:?}, input_files:{:?}",
+                    storage_format, input_files
+                );
+
+                compaction_task.compaction_inputs = vec![input_files];
+            }
         }
 
         Ok(compaction_task)
@@ -456,6 +498,55 @@ impl TimeWindowPicker {
         (buckets, max_ts)
     }
 
+    /// Whether the windows of `segment_duration` expire window by window
+    /// under the `ttl`, that is, the ttl is a multiple of the segment duration.
+    fn is_ttl_aligned(segment_duration: &Duration, ttl: &Duration) -> bool {
+        let (window_secs, ttl_secs) = (segment_duration.as_secs(), ttl.as_secs());
+        if window_secs == 0 || ttl_secs == 0 {
+            return false;
+        }
+
+        ttl_secs % window_secs == 0
+    }
+
+    /// Group files into buckets like [Self::get_buckets], but keep the data
+    /// expiring in different windows isolated:
+    ///  - Files spanning several windows are grouped into the window of their
+    ///    max timestamp, so they are still compacted.
+    ///  - Windows partially expired are never picked, they will be dropped as a
+    ///    whole soon.
+    fn get_ttl_aligned_buckets(
+        files: &[FileHandle],
+        window: &Duration,
+        timestamp_resolution: TimeUnit,
+        expire_time: Option<Timestamp>,
+    ) -> (HashMap<i64, Vec<FileHandle>>, i64) {
+        let mut max_ts = 0i64;
+        let mut buckets: HashMap<i64, Vec<FileHandle>> = HashMap::new();
+        for f in files {
+            let max_ts_of_file = Self::resolve_timetamp(
+                f.time_range_ref().exclusive_end().as_i64() - 1,
+                timestamp_resolution,
+            );
+
+            let (left, _) = Self::get_window_bounds_in_millis(window, max_ts_of_file);
+            if let Some(expire_time) = expire_time {
+                if left <= expire_time.as_i64() {
+                    continue;
+                }
+            }
+
+            buckets.entry(left).or_insert_with(Vec::new).push(f.clone());
+
+            if left > max_ts {
+                max_ts = left;
+            }
+        }
+
+        debug!("Group files into ttl aligned buckets: {:?}", buckets);
+        (buckets, max_ts)
+    }
+
     fn newest_bucket(
         buckets: HashMap<i64, Vec<FileHandle>>,
         size_tiered_opts: SizeTieredCompactionOptions,
@@ -559,18 +650,27 @@ impl LevelPicker for TimeWindowPicker {
 
         debug!("TWCS compaction options: {:?}", opts);
 
-        let (buckets, ts) = Self::get_buckets(
-            &uncompact_files,
-            &ctx.segment_duration,
-            opts.timestamp_resolution,
-        );
+        // Only align the windows to the ttl if the ttl is a multiple of the segment
+        // duration, otherwise fall back to the plain windows of segment duration.
+        let ttl_aligned = opts.ttl_aligned
+            && ctx
+                .ttl
+                .map(|ttl| Self::is_ttl_aligned(&ctx.segment_duration, &ttl))
+                .unwrap_or(false);
+        let window = ctx.segment_duration;
+        let (buckets, ts) = if ttl_aligned {
+            Self::get_ttl_aligned_buckets(
+                &uncompact_files,
+                &window,
+                opts.timestamp_resolution,
+                expire_time,
+            )
+        } else {
+            Self::get_buckets(&uncompact_files, &window, opts.timestamp_resolution)
+        };
 
-        let now = Self::get_current_window(
-            levels_controller,
-            level,
-            &ctx.segment_duration,
-            opts.timestamp_resolution,
-        );
+        let now =
+            Self::get_current_window(levels_controller, level, &window, opts.timestamp_resolution);
         debug!("now {}, max_ts: {}", now, ts);
         assert!(now >= ts);
 
@@ -587,9 +687,13 @@ mod tests {
         tests::build_schema,
         time::{TimeRange, Timestamp},
     };
+    use common_util::config::TimeUnit;
 
     use crate::{
-        compaction::{picker::PickerContext, CompactionStrategy, PickerManager},
+        compaction::{
+            picker::{LevelPicker, PickerContext, TimeWindowPicker},
+            CompactionStrategy, PickerManager, TimeWindowCompactionOptions,
+        },
         sst::{
             file::SstMetaData,
             manager::{tests::LevelsControllerMockBuilder, LevelsController},
@@ -700,6 +804,7 @@ mod tests {
             segment_duration: Duration::from_millis(1000),
             ttl: Some(Duration::from_secs(100000)),
             strategy: CompactionStrategy::Default,
+            rewrite_storage_format: None,
         };
         let now = Timestamp::now();
         {
@@ -739,4 +844,171 @@ mod tests {
             assert!(task.expired[0].files.is_empty());
         }
     }
+
+    #[test]
+    fn test_pick_storage_format_rewrite() {
+        let picker_manager = PickerManager::default();
+        let picker = picker_manager.get_picker(CompactionStrategy::Default);
+        let mut ctx = PickerContext {
+            segment_duration: Duration::from_millis(1000),
+            ttl: Some(Duration::from_secs(100000)),
+            strategy: CompactionStrategy::Default,
+            rewrite_storage_format: None,
+        };
+        let now = Timestamp::now();
+        let lc = build_newest_bucket_no_match_case(now.as_i64());
+
+        // All ssts are already columnar.
+        ctx.rewrite_storage_format = Some(StorageFormat::Columnar);
+        let task = picker.pick_compaction(ctx.clone(), &lc).unwrap();
+        assert!(task.compaction_inputs.is_empty());
+
+        ctx.rewrite_storage_format = Some(StorageFormat::Hybrid);
+        let task = picker.pick_compaction(ctx, &lc).unwrap();
+        assert_eq!(1, task.compaction_inputs.len());
+        assert_eq!(1, task.compaction_inputs[0].files.len());
+        assert_eq!(0, task.compaction_inputs[0].files[0].id());
+    }
+
+    #[test]
+    fn test_is_ttl_aligned() {
+        let segment_duration = Duration::from_secs(2 * 3600);
+        // The ttl is a multiple of the segment duration.
+        assert!(TimeWindowPicker::is_ttl_aligned(
+            &segment_duration,
+            &Duration::from_secs(7 * 24 * 3600),
+        ));
+        // The ttl is not a multiple of the segment duration.
+        assert!(!TimeWindowPicker::is_ttl_aligned(
+            &segment_duration,
+            &Duration::from_secs(3 * 3600),
+        ));
+        assert!(!TimeWindowPicker::is_ttl_aligned(
+            &segment_duration,
+            &Duration::from_secs(1800),
+        ));
+        // Sub-second durations are not aligned.
+        assert!(!TimeWindowPicker::is_ttl_aligned(
+            &Duration::from_millis(500),
+            &Duration::from_secs(1800),
+        ));
+    }
+
+    #[test]
+    fn test_ttl_aligned_buckets() {
+        let window = Duration::from_secs(10);
+        let builder = LevelsControllerMockBuilder::default();
+        let sst_meta_vec = vec![
+            // Window [0, 10000), partially expired.
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(1000), Timestamp::new(9000)),
+                2,
+            ),
+            // Window [10000, 20000).
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(11000), Timestamp::new(20000)),
+                2,
+            ),
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(10000), Timestamp::new(15000)),
+                2,
+            ),
+            // Spans windows [10000, 20000) and [20000, 30000).
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(15000), Timestamp::new(25000)),
+                2,
+            ),
+            // Window [20000, 30000).
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(20000), Timestamp::new(21000)),
+                2,
+            ),
+        ];
+        let lc = builder.add_sst(sst_meta_vec).build();
+        let files = lc.iter_ssts_at_level(0).cloned().collect::<Vec<_>>();
+
+        let bucket_ids = |expire_time| {
+            let (buckets, max_ts) = TimeWindowPicker::get_ttl_aligned_buckets(
+                &files,
+                &window,
+                TimeUnit::Milliseconds,
+                expire_time,
+            );
+            let mut ids = buckets
+                .into_iter()
+                .map(|(left, files)| {
+                    let mut ids = files.iter().map(|f| f.id()).collect::<Vec<_>>();
+                    ids.sort_unstable();
+                    (left, ids)
+                })
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            (ids, max_ts)
+        };
+
+        // The file spanning two windows falls into the window of its max timestamp.
+        let (ids, max_ts) = bucket_ids(None);
+        assert_eq!(
+            vec![(0, vec![0]), (10000, vec![1, 2]), (20000, vec![3, 4])],
+            ids
+        );
+        assert_eq!(20000, max_ts);
+
+        let (ids, max_ts) = bucket_ids(Some(Timestamp::new(5000)));
+        assert_eq!(vec![(10000, vec![1, 2]), (20000, vec![3, 4])], ids);
+        assert_eq!(20000, max_ts);
+    }
+
+    #[test]
+    fn test_ttl_not_multiple_of_segment() {
+        let segment_duration = Duration::from_secs(10);
+        let builder = LevelsControllerMockBuilder::default();
+        let sst_meta_vec = vec![
+            // Window [10000, 20000).
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(10000), Timestamp::new(12000)),
+                2,
+            ),
+            // Spans windows [10000, 20000) and [20000, 30000).
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(15000), Timestamp::new(25000)),
+                2,
+            ),
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(16000), Timestamp::new(28000)),
+                2,
+            ),
+            // Window [30000, 40000), the current window.
+            build_sst_meta_data(
+                TimeRange::new_unchecked(Timestamp::new(30000), Timestamp::new(31000)),
+                2,
+            ),
+        ];
+        let lc = builder.add_sst(sst_meta_vec).build();
+
+        let mut opts = TimeWindowCompactionOptions {
+            ttl_aligned: true,
+            ..Default::default()
+        };
+        opts.size_tiered.min_threshold = 2;
+        let ctx = PickerContext {
+            segment_duration,
+            // Not a multiple of the segment duration.
+            ttl: Some(Duration::from_secs(15)),
+            strategy: CompactionStrategy::TimeWindow(opts),
+            rewrite_storage_format: None,
+        };
+
+        let picker = TimeWindowPicker::default();
+        // The window falls back to the segment duration and the files spanning
+        // windows are still compacted.
+        let mut files = picker
+            .pick_candidates_at_level(&ctx, &lc, 0, None)
+            .unwrap()
+            .iter()
+            .map(|f| f.id())
+            .collect::<Vec<_>>();
+        files.sort_unstable();
+        assert_eq!(vec![1, 2], files);
+    }
 }
@@ -37,7 +37,12 @@ use crate::{
         metrics::COMPACTION_PENDING_REQUEST_GAUGE, picker::PickerContext, CompactionTask,
         PickerManager, TableCompactionRequest, WaitError, WaiterNotifier,
     },
-    instance::{flush_compaction::TableFlushOptions, Instance, SpaceStore},
+    instance::{
+        flush_compaction::TableFlushOptions, write_worker::FinishStorageFormatRewriteCommand,
+        Instance, SpaceStore,
+    },
+    table::data::TableDataRef,
+    table_options::StorageFormat,
     TableOptions,
 };
 
@@ -391,6 +396,7 @@ impl ScheduleWorker {
                 return;
             }
         };
+        let rewrite_storage_format = picker_ctx.rewrite_storage_format;
         let version = table_data.current_version();
 
         // Pick compaction task.
@@ -445,6 +451,10 @@ impl ScheduleWorker {
             // Notify the background compact table result.
             match res {
                 Ok(()) => {
+                    if let Some(storage_format) = rewrite_storage_format {
+                        finish_storage_format_rewrite_if_done(&table_data, storage_format);
+                    }
+
                     let new_compaction_notifier = compaction_notifier.clone();
                     compaction_notifier.notify_ok();
                     waiter_notifier.notify_wait_result(Ok(()));
@@ -554,6 +564,28 @@ impl ScheduleWorker {
     }
 }
 
+// The write worker waits for all background jobs before exit, so the command
+// must not be sent in a blocking way. If it is not sent, the next compaction
+// of the table will try again.
+fn finish_storage_format_rewrite_if_done(table_data: &TableDataRef, storage_format: StorageFormat) {
+    if table_data
+        .current_version()
+        .has_sst_not_in_format(storage_format)
+    {
+        return;
+    }
+
+    let cmd = FinishStorageFormatRewriteCommand {
+        table_data: table_data.clone(),
+    };
+    if !table_data.write_handle.try_send_command(cmd.into_command()) {
+        warn!(
+            "Failed to send finish storage format rewrite command, table:{}, table_id:{}",
+            table_data.name, table_data.id
+        );
+    }
+}
+
 // If segment duration is None, then no compaction should be triggered, but we
 // return a None context instead of panic here.
 fn new_picker_context(table_opts: &TableOptions) -> Option<PickerContext> {
@@ -563,6 +595,9 @@ fn new_picker_context(table_opts: &TableOptions) -> Option<PickerContext> {
             segment_duration,
             ttl: table_opts.ttl().map(|ttl| ttl.0),
             strategy: table_opts.compaction_strategy,
+            rewrite_storage_format: table_opts
+                .rewrite_storage_format
+                .then_some(table_opts.storage_format),
         })
 }
 
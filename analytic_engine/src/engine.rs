@@ -2,15 +2,15 @@
 
 //! Implements the TableEngine trait
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 use log::info;
 use snafu::ResultExt;
 use table_engine::{
     engine::{
-        Close, CloseTableRequest, CreateTableRequest, DropTableRequest, OpenTableRequest, Result,
-        TableEngine,
+        Close, CloseTableRequest, CreateTableRequest, DropTableRequest, InvalidArguments,
+        OpenTableRequest, Result, TableEngine,
     },
     table::{SchemaId, TableRef},
     ANALYTIC_ENGINE_TYPE,
@@ -135,6 +135,21 @@ impl TableEngine for TableEngineImpl {
 
         Ok(())
     }
+
+    fn resolve_create_options(
+        &self,
+        _engine: &str,
+        table: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        let table_opts = self
+            .instance
+            .resolve_create_options(options)
+            .map_err(|e| Box::new(e) as _)
+            .context(InvalidArguments { table })?;
+
+        Ok(table_opts.to_raw_map())
+    }
 }
 
 /// Generate the space id from the schema id with assumption schema id is unique
@@ -268,6 +268,10 @@ impl Instance {
         );
         let mut table_opts =
             table_options::merge_table_options_for_alter(&options, &current_table_options)
+                .and_then(|opts| {
+                    opts.column_stats.validate(&table_data.schema())?;
+                    Ok(opts)
+                })
                 .map_err(|e| Box::new(e) as _)
                 .context(InvalidOptions {
                     space_id: table_data.space_id,
@@ -275,6 +279,11 @@ impl Instance {
                     table_id: table_data.id,
                 })?;
         table_opts.sanitize();
+        let changes = table_options::diff_table_options(&current_table_options, &table_opts);
+        info!(
+            "Instance alter options, space_id:{}, table:{}, changes:{:?}",
+            table_data.space_id, table_data.name, changes
+        );
         let manifest_update = AlterOptionsMeta {
             space_id: table_data.space_id,
             table_id: table_data.id,
@@ -334,4 +343,50 @@ impl Instance {
         table_data.set_table_options(worker_local, table_opts);
         Ok(())
     }
+
+    /// Clear the `rewrite_storage_format` flag once all ssts are in the new
+    /// storage format, must called by write worker in write thread
+    /// sequentially.
+    pub(crate) async fn finish_storage_format_rewrite(
+        self: &Arc<Self>,
+        worker_local: &mut WorkerLocal,
+        table_data: &TableDataRef,
+    ) -> Result<()> {
+        let current_table_options = table_data.table_options();
+        // The format may be altered again or new ssts may be added after the
+        // command is sent, so check again in the write worker.
+        if table_data.is_dropped()
+            || !current_table_options.rewrite_storage_format
+            || table_data
+                .current_version()
+                .has_sst_not_in_format(current_table_options.storage_format)
+        {
+            return Ok(());
+        }
+
+        let mut table_opts = (*current_table_options).clone();
+        table_opts.rewrite_storage_format = false;
+        info!(
+            "Instance finish storage format rewrite, space_id:{}, table:{}, storage_format:{:?}",
+            table_data.space_id, table_data.name, table_opts.storage_format
+        );
+
+        let meta_update = MetaUpdate::AlterOptions(AlterOptionsMeta {
+            space_id: table_data.space_id,
+            table_id: table_data.id,
+            options: table_opts.clone(),
+        });
+        self.space_store
+            .manifest
+            .store_update(MetaUpdateRequest::new(table_data.location(), meta_update))
+            .await
+            .context(WriteManifest {
+                space_id: table_data.space_id,
+                table: &table_data.name,
+                table_id: table_data.id,
+            })?;
+
+        table_data.set_table_options(worker_local, table_opts);
+        Ok(())
+    }
 }
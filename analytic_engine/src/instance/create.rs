@@ -2,7 +2,7 @@
 
 //! Create table logic of instance
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use log::info;
 use snafu::ResultExt;
@@ -18,10 +18,26 @@ use crate::{
     meta::meta_update::{AddTableMeta, MetaUpdate, MetaUpdateRequest},
     space::SpaceRef,
     table::data::{TableData, TableDataRef},
-    table_options,
+    table_options::{self, TableOptions},
 };
 
 impl Instance {
+    /// Resolve the options to create a table with, i.e. the `options` merged
+    /// with the global table options and presets, and then sanitized.
+    pub fn resolve_create_options(
+        &self,
+        options: &HashMap<String, String>,
+    ) -> table_options::Result<TableOptions> {
+        let mut table_opts = table_options::merge_table_options_for_create(
+            options,
+            &self.table_opts,
+            &self.table_option_presets,
+        )?;
+        table_opts.sanitize();
+
+        Ok(table_opts)
+    }
+
     /// Create table need to be handled by write worker.
     pub async fn do_create_table(
         &self,
@@ -30,16 +46,18 @@ impl Instance {
     ) -> Result<TableDataRef> {
         info!("Instance create table, request:{:?}", request);
 
-        let mut table_opts =
-            table_options::merge_table_options_for_create(&request.options, &self.table_opts)
-                .map_err(|e| Box::new(e) as _)
-                .context(InvalidOptions {
-                    space_id: space.id,
-                    table: &request.table_name,
-                    table_id: request.table_id,
-                })?;
-        // Sanitize options before creating table.
-        table_opts.sanitize();
+        let table_opts = self
+            .resolve_create_options(&request.options)
+            .and_then(|opts| {
+                opts.column_stats.validate(&request.table_schema)?;
+                Ok(opts)
+            })
+            .map_err(|e| Box::new(e) as _)
+            .context(InvalidOptions {
+                space_id: space.id,
+                table: &request.table_name,
+                table_id: request.table_id,
+            })?;
 
         if let Some(table_data) = space.find_table_by_id(request.table_id) {
             return Ok(table_data);
@@ -213,6 +213,9 @@ pub enum Error {
         table_location: Location,
         source: wal::manager::Error,
     },
+
+    #[snafu(display("Invalid table option presets, err:{}", source))]
+    InvalidOptionPresets { source: crate::table_options::Error },
 }
 
 define_result!(Error);
@@ -242,7 +245,8 @@ impl From<Error> for table_engine::engine::Error {
             | Error::FlushTable { .. }
             | Error::StoreVersionEdit { .. }
             | Error::GetLogBatchEncoder { .. }
-            | Error::EncodePayloads { .. } => Self::Unexpected {
+            | Error::EncodePayloads { .. }
+            | Error::InvalidOptionPresets { .. } => Self::Unexpected {
                 source: Box::new(err),
             },
         }
@@ -13,7 +13,7 @@ use common_types::{
     time::TimeRange,
     SequenceNumber,
 };
-use common_util::{config::ReadableDuration, define_result, runtime::Runtime, time};
+use common_util::{define_result, runtime::Runtime, time};
 use futures::{
     channel::{mpsc, mpsc::channel},
     future::try_join_all,
@@ -95,6 +95,9 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[snafu(display("Failed to complete segment duration sampling, err:{}", source))]
+    CompleteSampling { source: crate::table_options::Error },
+
     #[snafu(display("Background flush failed, cannot schedule flush task, err:{}", source))]
     BackgroundFlushFailed {
         source: crate::instance::write_worker::Error,
@@ -286,10 +289,10 @@ impl Instance {
                 "Update segment duration, table:{}, table_id:{}, segment_duration:{:?}",
                 table_data.name, table_data.id, suggest_segment_duration
             );
-            assert!(!suggest_segment_duration.is_zero());
-
-            let mut new_table_opts = (*table_data.table_options()).clone();
-            new_table_opts.segment_duration = Some(ReadableDuration(suggest_segment_duration));
+            let new_table_opts = table_data
+                .table_options()
+                .complete_segment_duration_sampling(suggest_segment_duration)
+                .context(CompleteSampling)?;
 
             // Now persist the new options, the `worker_local` ensure there is no race
             // condition.
@@ -600,6 +603,7 @@ impl Instance {
             sst_type: table_data.sst_type,
             num_rows_per_row_group: table_data.table_options().num_rows_per_row_group,
             compression: table_data.table_options().compression,
+            column_stats: table_data.table_options().column_stats.clone(),
         };
 
         for time_range in &time_ranges {
@@ -737,6 +741,7 @@ impl Instance {
             sst_type: table_data.sst_type,
             num_rows_per_row_group: table_data.table_options().num_rows_per_row_group,
             compression: table_data.table_options().compression,
+            column_stats: table_data.table_options().column_stats.clone(),
         };
         let mut builder = self
             .space_store
@@ -885,6 +890,7 @@ impl SpaceStore {
                 sst_type: table_data.sst_type,
                 read_batch_row_num: table_options.num_rows_per_row_group,
                 reverse: false,
+                use_page_index: false,
                 projected_schema: projected_schema.clone(),
                 predicate: Arc::new(Predicate::empty()),
                 meta_cache: self.meta_cache.clone(),
@@ -924,6 +930,9 @@ impl SpaceStore {
         };
 
         let mut sst_meta = file::merge_sst_meta(&input.files, schema);
+        // Always write in the storage format of the table, so ssts in the old format
+        // are rewritten after the storage format is altered.
+        sst_meta.storage_format_opts = StorageFormatOptions::new(table_options.storage_format);
 
         // Alloc file id for the merged sst.
         let file_id = table_data.alloc_file_id();
@@ -933,6 +942,7 @@ impl SpaceStore {
             sst_type: table_data.sst_type,
             num_rows_per_row_group: table_options.num_rows_per_row_group,
             compression: table_options.compression,
+            column_stats: table_options.column_stats.clone(),
         };
         let mut sst_builder = self
             .sst_factory
@@ -38,7 +38,7 @@ use crate::{
     sst::{factory::FactoryRef as SstFactoryRef, file::FilePurger, meta_cache::MetaCacheRef},
     table::data::TableDataRef,
     wal_synchronizer::WalSynchronizer,
-    TableOptions,
+    TableOptionPresets, TableOptions,
 };
 
 #[allow(clippy::enum_variant_names)]
@@ -156,6 +156,8 @@ pub struct Instance {
     /// Global table options, overwrite mutable options in each table's
     /// TableOptions.
     table_opts: TableOptions,
+    /// Option presets to create tables with.
+    table_option_presets: TableOptionPresets,
 
     // Write group options:
     write_group_worker_num: usize,
@@ -177,6 +179,8 @@ pub struct Instance {
     pub(crate) replay_batch_size: usize,
     /// batch size for scan sst
     pub(crate) scan_batch_size: usize,
+    /// Prune the pages of ssts by the page index when reading
+    pub(crate) sst_use_page_index: bool,
 }
 
 impl Instance {
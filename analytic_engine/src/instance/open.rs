@@ -23,8 +23,8 @@ use crate::{
     context::OpenContext,
     instance::{
         engine::{
-            ApplyMemTable, FlushTable, OperateByWriteWorker, ReadMetaUpdate, ReadWal,
-            RecoverTableData, Result,
+            ApplyMemTable, FlushTable, InvalidOptionPresets, OperateByWriteWorker, ReadMetaUpdate,
+            ReadWal, RecoverTableData, Result,
         },
         flush_compaction::{TableFlushOptions, TableFlushPolicy},
         mem_collector::MemUsageCollector,
@@ -49,6 +49,11 @@ impl Instance {
         store: ObjectStoreRef,
         sst_factory: SstFactoryRef,
     ) -> Result<Arc<Self>> {
+        let table_option_presets = ctx.config.table_option_presets.clone();
+        table_option_presets
+            .validate()
+            .context(InvalidOptionPresets)?;
+
         let space_store = Arc::new(SpaceStore {
             spaces: RwLock::new(Spaces::default()),
             manifest,
@@ -77,6 +82,7 @@ impl Instance {
             space_store,
             runtimes: ctx.runtimes.clone(),
             table_opts: ctx.config.table_opts.clone(),
+            table_option_presets,
             write_group_worker_num: ctx.config.write_group_worker_num,
             write_group_command_channel_cap: ctx.config.write_group_command_channel_cap,
             compaction_scheduler,
@@ -89,6 +95,7 @@ impl Instance {
             space_write_buffer_size: ctx.config.space_write_buffer_size,
             replay_batch_size: ctx.config.replay_batch_size,
             scan_batch_size: ctx.config.scan_batch_size,
+            sst_use_page_index: ctx.config.sst_use_page_index,
         });
 
         Ok(instance)
@@ -154,8 +154,9 @@ impl Instance {
         let projected_schema = request.projected_schema.clone();
         let sst_reader_options = SstReaderOptions {
             sst_type: table_data.sst_type,
-            read_batch_row_num: table_options.num_rows_per_row_group,
+            read_batch_row_num: table_options.read_batch_row_num(),
             reverse: request.order.is_in_desc_order(),
+            use_page_index: self.sst_use_page_index,
             projected_schema: projected_schema.clone(),
             predicate: request.predicate.clone(),
             meta_cache: self.meta_cache.clone(),
@@ -214,9 +215,10 @@ impl Instance {
 
         let sst_reader_options = SstReaderOptions {
             sst_type: table_data.sst_type,
-            read_batch_row_num: table_options.num_rows_per_row_group,
+            read_batch_row_num: table_options.read_batch_row_num(),
             // no need to read in order so just read in asc order by default.
             reverse: false,
+            use_page_index: self.sst_use_page_index,
             projected_schema: projected_schema.clone(),
             predicate: request.predicate.clone(),
             meta_cache: self.meta_cache.clone(),
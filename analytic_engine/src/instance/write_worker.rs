@@ -461,6 +461,21 @@ impl CompactTableCommand {
     }
 }
 
+/// Finish rewriting ssts into the new storage format.
+///
+/// Sent by compaction without waiting for the result, so the result is only
+/// logged by the worker.
+pub struct FinishStorageFormatRewriteCommand {
+    pub table_data: TableDataRef,
+}
+
+impl FinishStorageFormatRewriteCommand {
+    /// Convert into [Command]
+    pub fn into_command(self) -> Command {
+        Command::FinishStorageFormatRewrite(self)
+    }
+}
+
 /// Command sent to write worker
 pub enum Command {
     /// Write to table
@@ -490,6 +505,9 @@ pub enum Command {
     /// Compact table
     Compact(CompactTableCommand),
 
+    /// Finish rewriting ssts into the new storage format
+    FinishStorageFormatRewrite(FinishStorageFormatRewriteCommand),
+
     /// Exit the worker
     Exit,
 }
@@ -515,6 +533,14 @@ impl WriteHandle {
         }
     }
 
+    /// Try to send command to write worker without waiting.
+    ///
+    /// Returns false if the channel is full or disconnected, so background
+    /// jobs can use it without blocking the worker from exiting.
+    pub fn try_send_command(&self, cmd: Command) -> bool {
+        self.worker_data.tx.try_send(cmd).is_ok()
+    }
+
     /// Returns the id of the worker
     pub fn worker_id(&self) -> usize {
         self.worker_data.id
@@ -780,6 +806,9 @@ impl WriteWorker {
                 Command::Compact(cmd) => {
                     self.handle_compact_table(cmd).await;
                 }
+                Command::FinishStorageFormatRewrite(cmd) => {
+                    self.handle_finish_storage_format_rewrite(cmd).await;
+                }
                 Command::Exit => {
                     info!(
                         "Write worker recv Command::Exit, exit, space_id:{}, id:{}",
@@ -977,6 +1006,24 @@ impl WriteWorker {
         }
     }
 
+    async fn handle_finish_storage_format_rewrite(
+        &mut self,
+        cmd: FinishStorageFormatRewriteCommand,
+    ) {
+        let FinishStorageFormatRewriteCommand { table_data } = cmd;
+
+        if let Err(e) = self
+            .instance
+            .finish_storage_format_rewrite(&mut self.local, &table_data)
+            .await
+        {
+            error!(
+                "Failed to finish storage format rewrite, table:{}, table_id:{}, err:{}",
+                table_data.name, table_data.id, e
+            );
+        }
+    }
+
     #[inline]
     fn space_id(&self) -> SpaceId {
         self.local.data.space_id
@@ -29,7 +29,10 @@ use storage_options::{LocalOptions, StorageOptions};
 use table_kv::config::ObkvConfig;
 use wal::table_kv_impl::model::NamespaceConfig;
 
-pub use crate::{compaction::scheduler::SchedulerConfig, table_options::TableOptions};
+pub use crate::{
+    compaction::scheduler::SchedulerConfig,
+    table_options::{TableOptionPresets, TableOptions},
+};
 
 /// Config of analytic engine.
 #[derive(Debug, Clone, Deserialize)]
@@ -51,6 +54,8 @@ pub struct Config {
     // End of write group options.
     /// Default options for table.
     pub table_opts: TableOptions,
+    /// Named option presets to create tables with.
+    pub table_option_presets: TableOptionPresets,
 
     pub compaction_config: SchedulerConfig,
 
@@ -71,6 +76,9 @@ pub struct Config {
 
     // Batch size for scan sst
     pub scan_batch_size: usize,
+    /// Prune the pages of ssts by the page index when reading, which speeds up
+    /// the selective scans on large row groups.
+    pub sst_use_page_index: bool,
 
     // Obkv wal config.
     pub obkv_wal: ObkvWalConfig,
@@ -88,6 +96,7 @@ impl Default for Config {
             write_group_worker_num: 8,
             write_group_command_channel_cap: 128,
             table_opts: TableOptions::default(),
+            table_option_presets: TableOptionPresets::default(),
             compaction_config: SchedulerConfig::default(),
             sst_meta_cache_cap: Some(1000),
             sst_data_cache_cap: Some(1000),
@@ -99,6 +108,7 @@ impl Default for Config {
             /// it.
             db_write_buffer_size: 0,
             scan_batch_size: 500,
+            sst_use_page_index: false,
             obkv_wal: ObkvWalConfig::default(),
         }
     }
@@ -4,7 +4,7 @@
 
 use std::{fmt, sync::Arc};
 
-use arena::CollectorRef;
+use arena::{BlockGrowth, CollectorRef};
 use common_types::{schema::Schema, SequenceNumber};
 use common_util::define_result;
 use snafu::Snafu;
@@ -22,6 +22,8 @@ pub struct Options {
     pub schema: Schema,
     /// Block size of arena in bytes.
     pub arena_block_size: u32,
+    /// Growth policy of the arena blocks.
+    pub arena_growth: BlockGrowth,
     /// Log sequence at the memtable creation.
     pub creation_sequence: SequenceNumber,
     /// Memory usage colllector
@@ -19,7 +19,11 @@ pub struct SkiplistMemTableFactory;
 
 impl Factory for SkiplistMemTableFactory {
     fn create_memtable(&self, opts: Options) -> Result<MemTableRef> {
-        let arena = MonoIncArena::with_collector(opts.arena_block_size as usize, opts.collector);
+        let arena = MonoIncArena::with_growth(
+            opts.arena_block_size as usize,
+            opts.arena_growth,
+            opts.collector,
+        );
         let skiplist = Skiplist::with_arena(BytewiseComparator, arena);
         let memtable = Arc::new(SkiplistMemTable {
             schema: opts.schema,
@@ -172,7 +172,7 @@ mod tests {
 
     use std::{ops::Bound, sync::Arc};
 
-    use arena::NoopCollector;
+    use arena::{BlockGrowth, NoopCollector};
     use common_types::{
         bytes::ByteVec,
         datum::Datum,
@@ -299,6 +299,7 @@ mod tests {
             .create_memtable(Options {
                 schema: schema.clone(),
                 arena_block_size: 512,
+                arena_growth: BlockGrowth::Fixed,
                 creation_sequence: 1,
                 collector: Arc::new(NoopCollector {}),
             })
@@ -4,11 +4,11 @@
 
 use std::{fmt::Debug, sync::Arc};
 
-use common_types::projected_schema::ProjectedSchema;
+use common_types::{projected_schema::ProjectedSchema, schema::Schema};
 use common_util::runtime::Runtime;
 use object_store::{ObjectStoreRef, Path};
 use parquet_ext::DataCacheRef;
-use table_engine::predicate::PredicateRef;
+use table_engine::predicate::{Predicate, PredicateRef};
 
 use crate::{
     sst::{
@@ -20,7 +20,7 @@ use crate::{
         },
         reader::SstReader,
     },
-    table_options::Compression,
+    table_options::{ColumnStatsOptions, Compression},
 };
 
 pub trait Factory: Send + Sync + Debug {
@@ -39,6 +39,9 @@ pub trait Factory: Send + Sync + Debug {
     ) -> Option<Box<dyn SstBuilder + Send + 'a>>;
 }
 
+/// Default row number of the batches read from ssts.
+pub const DEFAULT_READ_BATCH_ROW_NUM: usize = 8192;
+
 #[derive(Debug, Copy, Clone)]
 pub enum SstType {
     Parquet,
@@ -49,6 +52,9 @@ pub struct SstReaderOptions {
     pub sst_type: SstType,
     pub read_batch_row_num: usize,
     pub reverse: bool,
+    /// Prune the pages of the row groups by the page index of the ssts, only
+    /// the forward reads of [ParquetSstReader] support it now.
+    pub use_page_index: bool,
     pub projected_schema: ProjectedSchema,
     pub predicate: PredicateRef,
     pub meta_cache: Option<MetaCacheRef>,
@@ -56,11 +62,116 @@ pub struct SstReaderOptions {
     pub runtime: Arc<Runtime>,
 }
 
+impl SstReaderOptions {
+    /// Create a [SstReaderOptionsBuilder] reading ssts of `schema` on
+    /// `runtime`.
+    pub fn builder(schema: Schema, runtime: Arc<Runtime>) -> SstReaderOptionsBuilder {
+        SstReaderOptionsBuilder::new(schema, runtime)
+    }
+}
+
+/// Builder for [SstReaderOptions].
+///
+/// By default, the parquet ssts are scanned forward in batches of
+/// [DEFAULT_READ_BATCH_ROW_NUM] rows, without projection, predicate or caches.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SstReaderOptionsBuilder {
+    sst_type: SstType,
+    read_batch_row_num: usize,
+    reverse: bool,
+    use_page_index: bool,
+    schema: Schema,
+    projected_schema: Option<ProjectedSchema>,
+    predicate: Option<PredicateRef>,
+    meta_cache: Option<MetaCacheRef>,
+    data_cache: Option<DataCacheRef>,
+    runtime: Arc<Runtime>,
+}
+
+impl SstReaderOptionsBuilder {
+    pub fn new(schema: Schema, runtime: Arc<Runtime>) -> Self {
+        Self {
+            sst_type: SstType::Parquet,
+            read_batch_row_num: DEFAULT_READ_BATCH_ROW_NUM,
+            reverse: false,
+            use_page_index: false,
+            schema,
+            projected_schema: None,
+            predicate: None,
+            meta_cache: None,
+            data_cache: None,
+            runtime,
+        }
+    }
+
+    pub fn with_sst_type(mut self, sst_type: SstType) -> Self {
+        self.sst_type = sst_type;
+        self
+    }
+
+    pub fn with_read_batch_row_num(mut self, read_batch_row_num: usize) -> Self {
+        self.read_batch_row_num = read_batch_row_num;
+        self
+    }
+
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn with_page_index(mut self, use_page_index: bool) -> Self {
+        self.use_page_index = use_page_index;
+        self
+    }
+
+    /// Set the projection, which should be on the schema given to the builder.
+    pub fn with_projected_schema(mut self, projected_schema: ProjectedSchema) -> Self {
+        self.projected_schema = Some(projected_schema);
+        self
+    }
+
+    pub fn with_predicate(mut self, predicate: PredicateRef) -> Self {
+        self.predicate = Some(predicate);
+        self
+    }
+
+    pub fn with_meta_cache(mut self, meta_cache: Option<MetaCacheRef>) -> Self {
+        self.meta_cache = meta_cache;
+        self
+    }
+
+    pub fn with_data_cache(mut self, data_cache: Option<DataCacheRef>) -> Self {
+        self.data_cache = data_cache;
+        self
+    }
+
+    pub fn build(self) -> SstReaderOptions {
+        let schema = self.schema;
+        SstReaderOptions {
+            sst_type: self.sst_type,
+            read_batch_row_num: self.read_batch_row_num,
+            reverse: self.reverse,
+            use_page_index: self.use_page_index,
+            projected_schema: self
+                .projected_schema
+                .unwrap_or_else(|| ProjectedSchema::no_projection(schema)),
+            predicate: self
+                .predicate
+                .unwrap_or_else(|| Arc::new(Predicate::empty())),
+            meta_cache: self.meta_cache,
+            data_cache: self.data_cache,
+            runtime: self.runtime,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SstBuilderOptions {
     pub sst_type: SstType,
     pub num_rows_per_row_group: usize,
     pub compression: Compression,
+    pub column_stats: ColumnStatsOptions,
 }
 
 #[derive(Debug, Default)]
@@ -101,3 +212,59 @@ impl Factory for FactoryImpl {
 
 /// Sst factory reference
 pub type FactoryRef = Arc<dyn Factory>;
+
+#[cfg(test)]
+mod tests {
+    use common_types::tests::build_schema;
+    use common_util::runtime;
+    use table_engine::predicate::PredicateBuilder;
+
+    use super::*;
+    use crate::sst::meta_cache::MetaCache;
+
+    #[test]
+    fn test_sst_reader_options_builder() {
+        let runtime = Arc::new(runtime::Builder::default().build().unwrap());
+        let schema = build_schema();
+
+        let manual = SstReaderOptions {
+            sst_type: SstType::Parquet,
+            read_batch_row_num: DEFAULT_READ_BATCH_ROW_NUM,
+            reverse: false,
+            use_page_index: false,
+            projected_schema: ProjectedSchema::no_projection(schema.clone()),
+            predicate: Arc::new(Predicate::empty()),
+            meta_cache: None,
+            data_cache: None,
+            runtime: runtime.clone(),
+        };
+        let built = SstReaderOptions::builder(schema.clone(), runtime.clone()).build();
+        assert_eq!(format!("{:?}", manual), format!("{:?}", built));
+        assert!(Arc::ptr_eq(&manual.runtime, &built.runtime));
+
+        let projected_schema = ProjectedSchema::new(schema.clone(), Some(vec![0, 1])).unwrap();
+        let predicate = PredicateBuilder::default().build();
+        let meta_cache: Option<MetaCacheRef> = Some(Arc::new(MetaCache::new(10)));
+        let manual = SstReaderOptions {
+            sst_type: SstType::Parquet,
+            read_batch_row_num: 100,
+            reverse: true,
+            use_page_index: true,
+            projected_schema: projected_schema.clone(),
+            predicate: predicate.clone(),
+            meta_cache: meta_cache.clone(),
+            data_cache: None,
+            runtime: runtime.clone(),
+        };
+        let built = SstReaderOptions::builder(schema, runtime)
+            .with_read_batch_row_num(100)
+            .with_reverse(true)
+            .with_page_index(true)
+            .with_projected_schema(projected_schema)
+            .with_predicate(predicate)
+            .with_meta_cache(meta_cache)
+            .build();
+        assert_eq!(format!("{:?}", manual), format!("{:?}", built));
+        assert!(built.meta_cache.is_some());
+    }
+}
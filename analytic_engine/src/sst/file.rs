@@ -29,7 +29,7 @@ use common_util::{
 };
 use ethbloom::Bloom;
 use log::{debug, error, info};
-use object_store::ObjectStoreRef;
+use object_store::{util::DEFAULT_DELETE_MANY_CONCURRENCY, ObjectStoreRef, Path};
 use proto::{common as common_pb, sst as sst_pb};
 use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
 use table_engine::table::TableId;
@@ -692,35 +692,66 @@ impl FilePurger {
     async fn purge_file_loop(store: ObjectStoreRef, mut receiver: UnboundedReceiver<Request>) {
         info!("File purger start");
 
-        while let Some(request) = receiver.recv().await {
-            match request {
-                Request::Purge(purge_request) => {
-                    let sst_file_path = sst_util::new_sst_file_path(
-                        purge_request.space_id,
-                        purge_request.table_id,
-                        purge_request.file_id,
-                    );
-
-                    info!(
-                        "File purger delete file, purge_request:{:?}, sst_file_path:{}",
-                        purge_request,
-                        sst_file_path.to_string()
-                    );
-
-                    if let Err(e) = store.delete(&sst_file_path).await {
-                        error!(
-                            "File purger failed to delete file, sst_file_path:{}, err:{}",
-                            sst_file_path.to_string(),
-                            e
+        let mut exit = false;
+        while !exit {
+            let request = match receiver.recv().await {
+                Some(v) => v,
+                None => break,
+            };
+
+            // Purge the files of all the pending requests together, e.g. the
+            // files obsoleted by a compaction.
+            let mut sst_file_paths = Vec::new();
+            let mut next_request = Some(request);
+            while let Some(request) = next_request {
+                match request {
+                    Request::Purge(purge_request) => {
+                        let sst_file_path = sst_util::new_sst_file_path(
+                            purge_request.space_id,
+                            purge_request.table_id,
+                            purge_request.file_id,
                         );
+
+                        info!(
+                            "File purger delete file, purge_request:{:?}, sst_file_path:{}",
+                            purge_request,
+                            sst_file_path.to_string()
+                        );
+                        sst_file_paths.push(sst_file_path);
+                    }
+                    Request::Exit => {
+                        exit = true;
+                        break;
                     }
                 }
-                Request::Exit => break,
+                next_request = receiver.try_recv().ok();
             }
+
+            Self::purge_files(&store, &sst_file_paths).await;
         }
 
         info!("File purger exit");
     }
+
+    async fn purge_files(store: &ObjectStoreRef, sst_file_paths: &[Path]) {
+        if sst_file_paths.is_empty() {
+            return;
+        }
+
+        let result = object_store::delete_many(
+            store.as_ref(),
+            sst_file_paths,
+            DEFAULT_DELETE_MANY_CONCURRENCY,
+        )
+        .await;
+        for (sst_file_path, e) in result.errors {
+            error!(
+                "File purger failed to delete file, sst_file_path:{}, err:{}",
+                sst_file_path.to_string(),
+                e
+            );
+        }
+    }
 }
 
 /// Merge sst meta of given `files`, panic if `files` is empty.
@@ -7,6 +7,7 @@ use common_types::time::{TimeRange, Timestamp};
 use crate::{
     compaction::ExpiredFiles,
     sst::file::{FileHandle, FileMeta, FilePurgeQueue, Iter, Level, LevelHandler},
+    table_options::StorageFormat,
 };
 
 /// Id for a sst file
@@ -106,6 +107,15 @@ impl LevelsController {
             .any(|level_handler| level_handler.has_expired_sst(expire_time))
     }
 
+    /// Returns true if any sst is not stored in `storage_format`.
+    pub fn has_sst_not_in_format(&self, storage_format: StorageFormat) -> bool {
+        self.levels.iter().any(|level_handler| {
+            level_handler
+                .iter_ssts()
+                .any(|file| file.storage_format() != storage_format)
+        })
+    }
+
     pub fn expired_ssts(&self, expire_time: Option<Timestamp>) -> Vec<ExpiredFiles> {
         let mut expired = Vec::new();
         let num_levels = self.num_levels();
@@ -10,12 +10,14 @@ use std::{
     time::Instant,
 };
 
-use arrow::{datatypes::SchemaRef, record_batch::RecordBatch as ArrowRecordBatch};
+use arrow::record_batch::RecordBatch as ArrowRecordBatch;
 use async_trait::async_trait;
 use bytes::Bytes;
 use common_types::{
     projected_schema::{ProjectedSchema, RowProjector},
     record_batch::{ArrowRecordBatchProjector, RecordBatchWithKey},
+    schema::Schema,
+    time::TimeRange,
 };
 use common_util::{runtime::Runtime, time::InstantExt};
 use datafusion::datasource::file_format;
@@ -39,10 +41,10 @@ use crate::{
         factory::SstReaderOptions,
         file::{BloomFilter, SstMetaData},
         meta_cache::{MetaCacheRef, MetaData},
-        parquet::{encoding::ParquetDecoder, row_group_filter::RowGroupFilter},
+        parquet::{encoding::ParquetDecoder, hybrid, row_group_filter::RowGroupFilter},
         reader::{error::*, Result, SstReader},
     },
-    table_options::StorageFormatOptions,
+    table_options::{StorageFormat, StorageFormatOptions},
 };
 
 type SendableRecordBatchStream = Pin<Box<dyn Stream<Item = Result<ArrowRecordBatch>> + Send>>;
@@ -81,16 +83,14 @@ impl<'a> Reader<'a> {
 
     fn filter_row_groups(
         &self,
-        schema: SchemaRef,
+        schema: &Schema,
         row_groups: &[RowGroupMetaData],
         bloom_filter: &BloomFilter,
     ) -> Result<Vec<usize>> {
-        let filter = RowGroupFilter::try_new(
-            &schema,
-            row_groups,
-            bloom_filter.filters(),
-            self.predicate.exprs(),
-        )?;
+        let arrow_schema = schema.to_arrow_schema_ref();
+        let exprs = self.predicate.pushdown_exprs(schema.timestamp_name());
+        let filter =
+            RowGroupFilter::try_new(&arrow_schema, row_groups, bloom_filter.filters(), &exprs)?;
 
         Ok(filter.filter())
     }
@@ -107,7 +107,7 @@ impl<'a> Reader<'a> {
             self.data_cache.clone(),
         );
         let filtered_row_groups = self.filter_row_groups(
-            meta_data.custom().schema.to_arrow_schema_ref(),
+            &meta_data.custom().schema,
             meta_data.parquet().row_groups(),
             &meta_data.custom().bloom_filter,
         )?;
@@ -118,10 +118,13 @@ impl<'a> Reader<'a> {
             filtered_row_groups.len()
         );
 
-        let proj_mask = ProjectionMask::leaves(
-            meta_data.parquet().file_metadata().schema_descr(),
-            row_projector.existed_source_projection().iter().copied(),
-        );
+        let schema_descr = meta_data.parquet().file_metadata().schema_descr();
+        let mut leaves = row_projector.existed_source_projection();
+        if let StorageFormat::Hybrid = meta_data.custom().storage_format_opts.format {
+            // Read the time bounds to skip the collapsed rows out of the time range.
+            leaves.extend(hybrid::time_bound_leaves(schema_descr));
+        }
+        let proj_mask = ProjectionMask::leaves(schema_descr, leaves);
 
         let builder = ParquetRecordBatchStreamBuilder::new(file_reader)
             .await
@@ -277,6 +280,59 @@ impl AsyncFileReader for CachableParquetFileReader {
             .boxed()
     }
 
+    fn get_byte_ranges(
+        &mut self,
+        ranges: Vec<Range<usize>>,
+    ) -> BoxFuture<'_, parquet::errors::Result<Vec<Bytes>>> {
+        async move {
+            let mut results = vec![None; ranges.len()];
+            let mut missed_indexes = Vec::new();
+            let mut missed_ranges = Vec::new();
+            for (idx, range) in ranges.into_iter().enumerate() {
+                self.metrics.bytes_scanned += range.end - range.start;
+
+                let cached_bytes = self.data_cache.as_ref().and_then(|cache| {
+                    cache.get(&Self::cache_key(self.path.as_ref(), range.start, range.end))
+                });
+                match cached_bytes {
+                    Some(cached_bytes) => {
+                        self.metrics.cache_hit += 1;
+                        results[idx] = Some(Bytes::from(cached_bytes.to_vec()));
+                    }
+                    None => {
+                        self.metrics.cache_miss += 1;
+                        missed_indexes.push(idx);
+                        missed_ranges.push(range);
+                    }
+                }
+            }
+
+            if !missed_ranges.is_empty() {
+                let fetched =
+                    object_store::get_ranges(self.storage.as_ref(), &self.path, &missed_ranges)
+                        .await
+                        .map_err(|e| {
+                            parquet::errors::ParquetError::General(format!(
+                                "CachableParquetFileReader::get_byte_ranges error: {}",
+                                e
+                            ))
+                        })?;
+                for ((idx, range), bytes) in
+                    missed_indexes.into_iter().zip(missed_ranges).zip(fetched)
+                {
+                    if let Some(cache) = &self.data_cache {
+                        let key = Self::cache_key(self.path.as_ref(), range.start, range.end);
+                        cache.put(key, Arc::new(bytes.to_vec()));
+                    }
+                    results[idx] = Some(bytes);
+                }
+            }
+
+            Ok(results.into_iter().map(Option::unwrap).collect())
+        }
+        .boxed()
+    }
+
     fn get_metadata(
         &mut self,
     ) -> BoxFuture<'_, parquet::errors::Result<Arc<parquet::file::metadata::ParquetMetaData>>> {
@@ -289,6 +345,7 @@ struct RecordBatchProjector {
     stream: SendableRecordBatchStream,
     row_projector: ArrowRecordBatchProjector,
     storage_format_opts: StorageFormatOptions,
+    time_range: TimeRange,
 
     row_num: usize,
     start_time: Instant,
@@ -300,12 +357,14 @@ impl RecordBatchProjector {
         stream: SendableRecordBatchStream,
         row_projector: ArrowRecordBatchProjector,
         storage_format_opts: StorageFormatOptions,
+        time_range: TimeRange,
     ) -> Self {
         Self {
             path,
             stream,
             row_projector,
             storage_format_opts,
+            time_range,
             row_num: 0,
             start_time: Instant::now(),
         }
@@ -337,8 +396,10 @@ impl Stream for RecordBatchProjector {
                 {
                     Err(e) => Poll::Ready(Some(Err(e))),
                     Ok(record_batch) => {
-                        let parquet_decoder =
-                            ParquetDecoder::new(projector.storage_format_opts.clone());
+                        let parquet_decoder = ParquetDecoder::with_time_range(
+                            projector.storage_format_opts.clone(),
+                            projector.time_range,
+                        );
                         let record_batch = parquet_decoder
                             .decode_record_batch(record_batch)
                             .map_err(|e| Box::new(e) as _)
@@ -397,6 +458,7 @@ impl<'a> SstReader for Reader<'a> {
             stream,
             row_projector,
             storage_format_opts,
+            self.predicate.time_range(),
         )))
     }
 }
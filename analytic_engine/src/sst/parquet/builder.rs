@@ -9,18 +9,21 @@ use std::sync::{
 
 use async_trait::async_trait;
 use common_types::{record_batch::RecordBatchWithKey, request_id::RequestId};
-use datafusion::parquet::basic::Compression;
+use datafusion::parquet::basic::Compression as ParquetCompression;
 use ethbloom::{Bloom, Input};
 use futures::StreamExt;
 use log::debug;
 use object_store::{ObjectStoreRef, Path};
 use snafu::ResultExt;
 
-use crate::sst::{
-    builder::{RecordBatchStream, SstBuilder, *},
-    factory::SstBuilderOptions,
-    file::{BloomFilter, SstMetaData},
-    parquet::encoding::ParquetEncoder,
+use crate::{
+    sst::{
+        builder::{RecordBatchStream, SstBuilder, *},
+        factory::SstBuilderOptions,
+        file::{BloomFilter, SstMetaData},
+        parquet::encoding::{self, ParquetEncoder},
+    },
+    table_options::{ColumnStatsOptions, Compression},
 };
 
 /// The implementation of sst based on parquet and object storage.
@@ -33,6 +36,7 @@ pub struct ParquetSstBuilder<'a> {
     /// Max row group size.
     num_rows_per_row_group: usize,
     compression: Compression,
+    column_stats: ColumnStatsOptions,
 }
 
 impl<'a> ParquetSstBuilder<'a> {
@@ -41,7 +45,8 @@ impl<'a> ParquetSstBuilder<'a> {
             path,
             storage,
             num_rows_per_row_group: options.num_rows_per_row_group,
-            compression: options.compression.into(),
+            compression: options.compression,
+            column_stats: options.column_stats.clone(),
         }
     }
 }
@@ -53,6 +58,7 @@ struct RecordBytesReader {
     record_stream: RecordBatchStream,
     num_rows_per_row_group: usize,
     compression: Compression,
+    column_stats: ColumnStatsOptions,
     meta_data: SstMetaData,
     total_row_num: Arc<AtomicUsize>,
     // Record batch partitioned by exactly given `num_rows_per_row_group`
@@ -157,14 +163,38 @@ impl RecordBytesReader {
         BloomFilter::new(filters)
     }
 
+    /// Choose the compression by the encoded size of the first row group if
+    /// the compression is [Compression::Auto].
+    fn resolve_compression(&self) -> Result<ParquetCompression> {
+        if let Some(compression) = self.compression.parquet_compression() {
+            return Ok(compression);
+        }
+
+        let sample = self
+            .partitioned_record_batch
+            .first()
+            .map(|row_group| {
+                row_group
+                    .iter()
+                    .map(|batch| batch.as_arrow_record_batch().clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        encoding::select_compression(&self.column_stats, &self.meta_data, &sample)
+            .map_err(|e| Box::new(e) as _)
+            .context(EncodeRecordBatch)
+    }
+
     async fn read_all(mut self) -> Result<Vec<u8>> {
         self.partition_record_batch().await?;
+        let compression = self.resolve_compression()?;
         let filters = self.build_bloom_filter();
         self.meta_data.bloom_filter = filters;
 
         let mut parquet_encoder = ParquetEncoder::try_new(
             self.num_rows_per_row_group,
-            self.compression,
+            compression,
+            &self.column_stats,
             self.meta_data,
         )
         .map_err(|e| Box::new(e) as _)
@@ -213,6 +243,7 @@ impl<'a> SstBuilder for ParquetSstBuilder<'a> {
             record_stream,
             num_rows_per_row_group: self.num_rows_per_row_group,
             compression: self.compression,
+            column_stats: self.column_stats.clone(),
             total_row_num: total_row_num.clone(),
             // TODO(xikai): should we avoid this clone?
             meta_data: meta.to_owned(),
@@ -241,6 +272,7 @@ mod tests {
     use common_types::{
         bytes::Bytes,
         projected_schema::ProjectedSchema,
+        schema::Schema,
         tests::{build_row, build_schema},
         time::{TimeRange, Timestamp},
     };
@@ -250,7 +282,7 @@ mod tests {
     };
     use futures::stream;
     use object_store::LocalFileSystem;
-    use table_engine::predicate::Predicate;
+    use table_engine::predicate::{Predicate, PredicateBuilder};
     use tempfile::tempdir;
 
     use super::*;
@@ -302,13 +334,6 @@ mod tests {
         async_reader: bool,
     ) {
         runtime.block_on(async {
-            let sst_factory = FactoryImpl;
-            let sst_builder_options = SstBuilderOptions {
-                sst_type: SstType::Parquet,
-                num_rows_per_row_group,
-                compression: table_options::Compression::Uncompressed,
-            };
-
             let dir = tempdir().unwrap();
             let root = dir.path();
             let store = Arc::new(LocalFileSystem::new_with_prefix(root).unwrap()) as _;
@@ -316,51 +341,15 @@ mod tests {
 
             let schema = build_schema();
             let projected_schema = ProjectedSchema::no_projection(schema.clone());
-            let sst_meta = SstMetaData {
-                min_key: Bytes::from_static(b"100"),
-                max_key: Bytes::from_static(b"200"),
-                time_range: TimeRange::new_unchecked(Timestamp::new(1), Timestamp::new(2)),
-                max_sequence: 200,
-                schema: schema.clone(),
-                size: 10,
-                row_num: 2,
-                storage_format_opts: Default::default(),
-                bloom_filter: Default::default(),
-            };
-
-            let mut counter = 5;
-            let record_batch_stream = Box::new(stream::poll_fn(move |_| -> Poll<Option<_>> {
-                if counter == 0 {
-                    return Poll::Ready(None);
-                }
-                counter -= 1;
-
-                // reach here when counter is 9 7 5 3 1
-                let ts = 100 + counter;
-                let rows = vec![
-                    build_row(b"a", ts, 10.0, "v4"),
-                    build_row(b"b", ts, 10.0, "v4"),
-                    build_row(b"c", ts, 10.0, "v4"),
-                ];
-                let batch = build_record_batch_with_key(schema.clone(), rows);
-                Poll::Ready(Some(Ok(batch)))
-            }));
-
-            let mut builder = sst_factory
-                .new_sst_builder(&sst_builder_options, &sst_file_path, &store)
-                .unwrap();
-            let sst_info = builder
-                .build(RequestId::next_id(), &sst_meta, record_batch_stream)
-                .await
-                .unwrap();
-
-            assert_eq!(15, sst_info.row_num);
+            let sst_meta =
+                write_test_sst(&store, &sst_file_path, schema, num_rows_per_row_group).await;
 
             // read sst back to test
             let sst_reader_options = SstReaderOptions {
                 sst_type: SstType::Parquet,
                 read_batch_row_num: 5,
                 reverse: false,
+                use_page_index: false,
                 projected_schema,
                 predicate: Arc::new(Predicate::empty()),
                 meta_cache: None,
@@ -428,6 +417,177 @@ mod tests {
         });
     }
 
+    /// Write 15 rows to the sst at `sst_file_path`, and returns the meta of the
+    /// sst.
+    async fn write_test_sst(
+        store: &ObjectStoreRef,
+        sst_file_path: &Path,
+        schema: Schema,
+        num_rows_per_row_group: usize,
+    ) -> SstMetaData {
+        let sst_builder_options = SstBuilderOptions {
+            sst_type: SstType::Parquet,
+            num_rows_per_row_group,
+            compression: table_options::Compression::Uncompressed,
+            column_stats: Default::default(),
+        };
+        let sst_meta = SstMetaData {
+            min_key: Bytes::from_static(b"100"),
+            max_key: Bytes::from_static(b"200"),
+            time_range: TimeRange::new_unchecked(Timestamp::new(1), Timestamp::new(2)),
+            max_sequence: 200,
+            schema: schema.clone(),
+            size: 10,
+            row_num: 2,
+            storage_format_opts: Default::default(),
+            bloom_filter: Default::default(),
+        };
+
+        let mut counter = 5;
+        let record_batch_stream = Box::new(stream::poll_fn(move |_| -> Poll<Option<_>> {
+            if counter == 0 {
+                return Poll::Ready(None);
+            }
+            counter -= 1;
+
+            // reach here when counter is 9 7 5 3 1
+            let ts = 100 + counter;
+            let rows = vec![
+                build_row(b"a", ts, 10.0, "v4"),
+                build_row(b"b", ts, 10.0, "v4"),
+                build_row(b"c", ts, 10.0, "v4"),
+            ];
+            let batch = build_record_batch_with_key(schema.clone(), rows);
+            Poll::Ready(Some(Ok(batch)))
+        }));
+
+        let mut builder = FactoryImpl
+            .new_sst_builder(&sst_builder_options, sst_file_path, store)
+            .unwrap();
+        let sst_info = builder
+            .build(RequestId::next_id(), &sst_meta, record_batch_stream)
+            .await
+            .unwrap();
+
+        assert_eq!(15, sst_info.row_num);
+
+        sst_meta
+    }
+
+    #[test]
+    fn test_parquet_read_batch_row_num() {
+        init_log_for_test();
+
+        let runtime = Arc::new(runtime::Builder::default().build().unwrap());
+        for async_reader in [false, true] {
+            runtime.block_on(async {
+                let dir = tempdir().unwrap();
+                let store = Arc::new(LocalFileSystem::new_with_prefix(dir.path()).unwrap()) as _;
+                let sst_file_path = Path::from("data.par");
+                let schema = build_schema();
+                // All the rows are in one row group.
+                write_test_sst(&store, &sst_file_path, schema.clone(), 100).await;
+
+                let sst_reader_options = SstReaderOptions {
+                    sst_type: SstType::Parquet,
+                    read_batch_row_num: 4,
+                    reverse: false,
+                    use_page_index: false,
+                    projected_schema: ProjectedSchema::no_projection(schema),
+                    predicate: Arc::new(Predicate::empty()),
+                    meta_cache: None,
+                    data_cache: None,
+                    runtime: runtime.clone(),
+                };
+                let mut reader: Box<dyn SstReader + Send> = if async_reader {
+                    Box::new(AsyncParquetReader::new(
+                        &sst_file_path,
+                        &store,
+                        &sst_reader_options,
+                    ))
+                } else {
+                    Box::new(ParquetSstReader::new(
+                        &sst_file_path,
+                        &store,
+                        &sst_reader_options,
+                    ))
+                };
+
+                let mut stream = reader.read().await.unwrap();
+                let mut batch_rows = Vec::new();
+                while let Some(batch) = stream.next().await {
+                    batch_rows.push(batch.unwrap().num_rows());
+                }
+                // Only the last batch is partial.
+                assert_eq!(
+                    vec![4, 4, 4, 3],
+                    batch_rows,
+                    "async_reader:{}",
+                    async_reader
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_parquet_prune_row_groups_by_predicate() {
+        init_log_for_test();
+
+        let runtime = Arc::new(runtime::Builder::default().build().unwrap());
+        for async_reader in [false, true] {
+            runtime.block_on(async {
+                let dir = tempdir().unwrap();
+                let store = Arc::new(LocalFileSystem::new_with_prefix(dir.path()).unwrap()) as _;
+                let sst_file_path = Path::from("data.par");
+                let schema = build_schema();
+                // 5 row groups, the timestamps of them are 104, 103, ..., 100.
+                write_test_sst(&store, &sst_file_path, schema.clone(), 3).await;
+
+                // Only the row group of timestamp 102 is in the time range.
+                let predicate = PredicateBuilder::default()
+                    .set_time_range(TimeRange::new_unchecked(
+                        Timestamp::new(102),
+                        Timestamp::new(103),
+                    ))
+                    .build();
+                let sst_reader_options = SstReaderOptions {
+                    sst_type: SstType::Parquet,
+                    read_batch_row_num: 100,
+                    reverse: false,
+                    use_page_index: false,
+                    projected_schema: ProjectedSchema::no_projection(schema),
+                    predicate,
+                    meta_cache: None,
+                    data_cache: None,
+                    runtime: runtime.clone(),
+                };
+                let mut reader: Box<dyn SstReader + Send> = if async_reader {
+                    Box::new(AsyncParquetReader::new(
+                        &sst_file_path,
+                        &store,
+                        &sst_reader_options,
+                    ))
+                } else {
+                    Box::new(ParquetSstReader::new(
+                        &sst_file_path,
+                        &store,
+                        &sst_reader_options,
+                    ))
+                };
+
+                // Rows are not filtered by the readers, so only the rows of the
+                // remaining row group are read if the others are skipped.
+                let mut stream = reader.read().await.unwrap();
+                let expect_rows = vec![
+                    build_row(b"a", 102, 10.0, "v4"),
+                    build_row(b"b", 102, 10.0, "v4"),
+                    build_row(b"c", 102, 10.0, "v4"),
+                ];
+                check_stream(&mut stream, expect_rows).await;
+            });
+        }
+    }
+
     #[tokio::test]
     async fn test_partition_record_batch() {
         // rows per group: 10
@@ -479,7 +639,7 @@ mod tests {
             request_id: RequestId::next_id(),
             record_stream: record_batch_stream,
             num_rows_per_row_group,
-            compression: Compression::UNCOMPRESSED,
+            compression: Compression::Uncompressed,
             meta_data: SstMetaData {
                 min_key: Default::default(),
                 max_key: Default::default(),
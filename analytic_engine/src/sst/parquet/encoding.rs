@@ -16,14 +16,19 @@ use arrow::{
 use common_types::{
     bytes::{BytesMut, SafeBufMut},
     datum::DatumKind,
-    schema::{ArrowSchema, ArrowSchemaRef, DataType, Field},
+    schema::{ArrowSchema, ArrowSchemaRef, DataType, Field, Schema},
+    time::TimeRange,
 };
 use common_util::define_result;
-use log::trace;
+use log::{debug, trace};
 use parquet::{
     arrow::ArrowWriter,
     basic::Compression,
-    file::{metadata::KeyValue, properties::WriterProperties},
+    file::{
+        metadata::KeyValue,
+        properties::{EnabledStatistics, WriterProperties, WriterPropertiesBuilder},
+    },
+    schema::types::ColumnPath,
 };
 use prost::Message;
 use proto::sst::SstMetaData as SstMetaDataPb;
@@ -34,11 +39,14 @@ use crate::{
         file::SstMetaData,
         parquet::hybrid::{self, IndexedType},
     },
-    table_options::{StorageFormat, StorageFormatOptions},
+    table_options::{ColumnStatsOptions, StorageFormat, StorageFormatOptions},
 };
 
 // TODO: Only support i32 offset now, consider i64 here?
 const OFFSET_SIZE: usize = std::mem::size_of::<i32>();
+/// Min fraction of the size saved by zstd compared to snappy to choose zstd
+/// in the auto compression.
+const AUTO_COMPRESSION_MIN_ZSTD_GAIN: f64 = 0.1;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -265,15 +273,18 @@ impl ColumnarRecordEncoder {
     fn try_new(
         num_rows_per_row_group: usize,
         compression: Compression,
+        column_stats: &ColumnStatsOptions,
         meta_data: SstMetaData,
     ) -> Result<Self> {
         let arrow_schema = meta_data.schema.to_arrow_schema_ref();
 
         let write_props = WriterProperties::builder()
-            .set_key_value_metadata(Some(vec![encode_sst_meta_data(meta_data)?]))
             .set_max_row_group_size(num_rows_per_row_group)
-            .set_compression(compression)
-            .build();
+            .set_compression(compression);
+        let write_props =
+            disable_column_statistics(write_props, &meta_data.schema, column_stats, false)
+                .set_key_value_metadata(Some(vec![encode_sst_meta_data(meta_data)?]))
+                .build();
 
         let buf = EncodingWriter(Arc::new(Mutex::new(Vec::new())));
         let arrow_writer =
@@ -326,6 +337,7 @@ struct HybridRecordEncoder {
     arrow_writer: Option<ArrowWriter<EncodingWriter>>,
     arrow_schema: ArrowSchemaRef,
     tsid_type: IndexedType,
+    timestamp_idx: usize,
     non_collapsible_col_types: Vec<IndexedType>,
     // columns that can be collpased into list
     collapsible_col_types: Vec<IndexedType>,
@@ -335,6 +347,7 @@ impl HybridRecordEncoder {
     fn try_new(
         num_rows_per_row_group: usize,
         compression: Compression,
+        column_stats: &ColumnStatsOptions,
         mut meta_data: SstMetaData,
     ) -> Result<Self> {
         // TODO: What we really want here is a unique ID, tsid is one case
@@ -377,12 +390,15 @@ impl HybridRecordEncoder {
         }
 
         let arrow_schema = hybrid::build_hybrid_arrow_schema(&meta_data.schema);
+        let timestamp_idx = meta_data.schema.timestamp_index();
 
         let write_props = WriterProperties::builder()
-            .set_key_value_metadata(Some(vec![encode_sst_meta_data(meta_data)?]))
             .set_max_row_group_size(num_rows_per_row_group)
-            .set_compression(compression)
-            .build();
+            .set_compression(compression);
+        let write_props =
+            disable_column_statistics(write_props, &meta_data.schema, column_stats, true)
+                .set_key_value_metadata(Some(vec![encode_sst_meta_data(meta_data)?]))
+                .build();
 
         let buf = EncodingWriter(Arc::new(Mutex::new(Vec::new())));
         let arrow_writer =
@@ -394,6 +410,7 @@ impl HybridRecordEncoder {
             arrow_writer: Some(arrow_writer),
             arrow_schema,
             tsid_type,
+            timestamp_idx,
             non_collapsible_col_types,
             collapsible_col_types,
         })
@@ -406,6 +423,7 @@ impl RecordEncoder for HybridRecordEncoder {
 
         let record_batch = hybrid::convert_to_hybrid_record(
             &self.tsid_type,
+            self.timestamp_idx,
             &self.non_collapsible_col_types,
             &self.collapsible_col_types,
             self.arrow_schema.clone(),
@@ -436,6 +454,37 @@ impl RecordEncoder for HybridRecordEncoder {
     }
 }
 
+/// Disable the statistics of the columns not enabled by `column_stats`.
+///
+/// The collapsible columns of hybrid format are stored as lists, whose
+/// statistics are on the list items.
+fn disable_column_statistics(
+    mut builder: WriterPropertiesBuilder,
+    schema: &Schema,
+    column_stats: &ColumnStatsOptions,
+    is_hybrid: bool,
+) -> WriterPropertiesBuilder {
+    for idx in 0..schema.num_columns() {
+        if column_stats.is_enabled(schema, idx) {
+            continue;
+        }
+
+        let name = schema.column(idx).name.clone();
+        let column_path = if is_hybrid && schema.is_collapsible_column(idx) {
+            ColumnPath::new(vec![
+                name,
+                "list".to_string(),
+                hybrid::LIST_ITEM_NAME.to_string(),
+            ])
+        } else {
+            ColumnPath::new(vec![name])
+        };
+        builder = builder.set_column_statistics_enabled(column_path, EnabledStatistics::None);
+    }
+
+    builder
+}
+
 pub struct ParquetEncoder {
     record_encoder: Box<dyn RecordEncoder + Send>,
 }
@@ -444,17 +493,20 @@ impl ParquetEncoder {
     pub fn try_new(
         num_rows_per_row_group: usize,
         compression: Compression,
+        column_stats: &ColumnStatsOptions,
         meta_data: SstMetaData,
     ) -> Result<Self> {
         let record_encoder: Box<dyn RecordEncoder + Send> = match meta_data.storage_format() {
             StorageFormat::Hybrid => Box::new(HybridRecordEncoder::try_new(
                 num_rows_per_row_group,
                 compression,
+                column_stats,
                 meta_data,
             )?),
             StorageFormat::Columnar => Box::new(ColumnarRecordEncoder::try_new(
                 num_rows_per_row_group,
                 compression,
+                column_stats,
                 meta_data,
             )?),
         };
@@ -480,6 +532,75 @@ impl ParquetEncoder {
     }
 }
 
+/// Result of encoding a sample with a compression.
+#[derive(Debug)]
+struct CompressionSample {
+    encoded_size: usize,
+}
+
+fn sample_compression(
+    compression: Compression,
+    column_stats: &ColumnStatsOptions,
+    meta_data: &SstMetaData,
+    sample: &[ArrowRecordBatch],
+) -> Result<CompressionSample> {
+    let num_rows = sample.iter().map(|v| v.num_rows()).sum::<usize>();
+    let mut encoder = ParquetEncoder::try_new(
+        num_rows.max(1),
+        compression,
+        column_stats,
+        meta_data.clone(),
+    )?;
+    encoder.encode_record_batch(sample.to_vec())?;
+    let encoded_size = encoder.close()?.len();
+
+    Ok(CompressionSample { encoded_size })
+}
+
+/// Choose zstd only if it saves enough size compared to snappy.
+///
+/// Only the sizes are compared, so the choice is deterministic for the same
+/// data, regardless of the load of the machine.
+fn choose_compression(snappy: &CompressionSample, zstd: &CompressionSample) -> Compression {
+    let saved = snappy.encoded_size.saturating_sub(zstd.encoded_size) as f64;
+    if saved >= snappy.encoded_size as f64 * AUTO_COMPRESSION_MIN_ZSTD_GAIN {
+        Compression::ZSTD
+    } else {
+        Compression::SNAPPY
+    }
+}
+
+/// Choose the compression between snappy and zstd for the sst with
+/// `meta_data`, by encoding the `sample` (usually the first row group) with
+/// both of them.
+///
+/// Only the encoded sizes of the sample are compared, see
+/// [AUTO_COMPRESSION_MIN_ZSTD_GAIN], the cpu cost of encoding is not
+/// considered.
+///
+/// The chosen compression is recorded in the parquet meta data of the sst, so
+/// no extra information is required to read it.
+pub fn select_compression(
+    column_stats: &ColumnStatsOptions,
+    meta_data: &SstMetaData,
+    sample: &[ArrowRecordBatch],
+) -> Result<Compression> {
+    if sample.iter().all(|v| v.num_rows() == 0) {
+        return Ok(Compression::SNAPPY);
+    }
+
+    let snappy = sample_compression(Compression::SNAPPY, column_stats, meta_data, sample)?;
+    let zstd = sample_compression(Compression::ZSTD, column_stats, meta_data, sample)?;
+    let compression = choose_compression(&snappy, &zstd);
+
+    debug!(
+        "Select compression of sst, compression:{:?}, snappy:{:?}, zstd:{:?}",
+        compression, snappy, zstd
+    );
+
+    Ok(compression)
+}
+
 /// RecordDecoder is used for decoding ArrowRecordBatch based on
 /// `schema.StorageFormat`
 trait RecordDecoder {
@@ -496,9 +617,33 @@ impl RecordDecoder for ColumnarRecordDecoder {
 
 struct HybridRecordDecoder {
     storage_format_opts: StorageFormatOptions,
+    /// Collapsed rows out of this time range are skipped.
+    time_range: TimeRange,
 }
 
 impl HybridRecordDecoder {
+    /// Remove the min/max time columns, which are not part of the table schema.
+    fn remove_time_bound_columns(arrow_record_batch: ArrowRecordBatch) -> Result<ArrowRecordBatch> {
+        let schema = arrow_record_batch.schema();
+        let (fields, arrays): (Vec<_>, Vec<_>) = schema
+            .fields()
+            .iter()
+            .zip(arrow_record_batch.columns())
+            .filter(|(field, _)| {
+                field.name() != hybrid::MIN_TIME_COLUMN && field.name() != hybrid::MAX_TIME_COLUMN
+            })
+            .map(|(field, array)| (field.clone(), array.clone()))
+            .unzip();
+        if fields.len() == schema.fields().len() {
+            return Ok(arrow_record_batch);
+        }
+
+        let schema = ArrowSchema::new_with_metadata(fields, schema.metadata().clone());
+        ArrowRecordBatch::try_new(Arc::new(schema), arrays)
+            .map_err(|e| Box::new(e) as _)
+            .context(EncodeRecordBatch)
+    }
+
     /// Convert `ListArray` fields to underlying data type
     fn convert_schema(arrow_schema: ArrowSchemaRef) -> ArrowSchemaRef {
         let new_fields: Vec<_> = arrow_schema
@@ -658,6 +803,10 @@ impl HybridRecordDecoder {
 impl RecordDecoder for HybridRecordDecoder {
     /// Decode records from hybrid to columnar format
     fn decode(&self, arrow_record_batch: ArrowRecordBatch) -> Result<ArrowRecordBatch> {
+        let arrow_record_batch = hybrid::filter_by_time_range(arrow_record_batch, self.time_range)
+            .map_err(|e| Box::new(e) as _)
+            .context(EncodeRecordBatch)?;
+        let arrow_record_batch = Self::remove_time_bound_columns(arrow_record_batch)?;
         let new_arrow_schema = Self::convert_schema(arrow_record_batch.schema());
         let arrays = arrow_record_batch.columns();
 
@@ -713,9 +862,22 @@ pub struct ParquetDecoder {
 
 impl ParquetDecoder {
     pub fn new(storage_format_opts: StorageFormatOptions) -> Self {
+        Self::with_time_range(storage_format_opts, TimeRange::min_to_max())
+    }
+
+    /// Create a decoder skipping the collapsed rows of hybrid format whose
+    /// timestamps are all out of `time_range`.
+    ///
+    /// Note that the rows in the collapsed rows are not filtered, and rows of
+    /// columnar format are never skipped.
+    pub fn with_time_range(
+        storage_format_opts: StorageFormatOptions,
+        time_range: TimeRange,
+    ) -> Self {
         let record_decoder: Box<dyn RecordDecoder> = match storage_format_opts.format {
             StorageFormat::Hybrid => Box::new(HybridRecordDecoder {
                 storage_format_opts,
+                time_range,
             }),
             StorageFormat::Columnar => Box::new(ColumnarRecordDecoder {}),
         };
@@ -734,7 +896,9 @@ impl ParquetDecoder {
 #[cfg(test)]
 mod tests {
 
-    use arrow::array::{Int32Array, StringArray, TimestampMillisecondArray, UInt64Array};
+    use arrow::array::{
+        BinaryArray, Int32Array, ListArray, StringArray, TimestampMillisecondArray, UInt64Array,
+    };
     use common_types::{
         bytes::Bytes,
         column_schema,
@@ -905,8 +1069,13 @@ mod tests {
             storage_format_opts,
             bloom_filter: Default::default(),
         };
-        let mut encoder =
-            HybridRecordEncoder::try_new(100, Compression::ZSTD, meta_data.clone()).unwrap();
+        let mut encoder = HybridRecordEncoder::try_new(
+            100,
+            Compression::ZSTD,
+            &ColumnStatsOptions::default(),
+            meta_data.clone(),
+        )
+        .unwrap();
 
         let columns = vec![
             Arc::new(UInt64Array::from(vec![1, 1, 2, 2])) as ArrayRef,
@@ -951,6 +1120,7 @@ mod tests {
 
         let decoder = HybridRecordDecoder {
             storage_format_opts: meta_data.storage_format_opts,
+            time_range: TimeRange::min_to_max(),
         };
         let decoded_record_batch = decoder.decode(hybrid_record_batch).unwrap();
 
@@ -959,4 +1129,226 @@ mod tests {
         // assert_eq!(decoded_record_batch.schema(), input_record_batch.schema());
         assert_eq!(decoded_record_batch.columns(), input_record_batch.columns());
     }
+
+    #[test]
+    fn hybrid_record_time_bounds() {
+        let schema = build_schema();
+        let meta_data = SstMetaData {
+            min_key: Bytes::from_static(b"100"),
+            max_key: Bytes::from_static(b"200"),
+            time_range: TimeRange::new_unchecked(Timestamp::new(100), Timestamp::new(131)),
+            max_sequence: 200,
+            schema: schema.clone(),
+            size: 10,
+            row_num: 6,
+            storage_format_opts: StorageFormatOptions::new(StorageFormat::Hybrid),
+            bloom_filter: Default::default(),
+        };
+        let mut encoder = HybridRecordEncoder::try_new(
+            100,
+            Compression::ZSTD,
+            &ColumnStatsOptions::default(),
+            meta_data.clone(),
+        )
+        .unwrap();
+
+        let new_record_batch = |tsids: Vec<u64>, timestamps: Vec<i64>| {
+            let num_rows = tsids.len();
+            let hosts = tsids
+                .iter()
+                .map(|tsid| format!("host{}", tsid))
+                .collect::<Vec<_>>();
+            let columns = vec![
+                Arc::new(UInt64Array::from(tsids)) as ArrayRef,
+                timestamp_array(timestamps),
+                string_array(hosts.iter().map(|v| Some(v.as_str())).collect()),
+                string_array(vec![Some("region"); num_rows]),
+                int32_array(vec![Some(1); num_rows]),
+                string_array(vec![Some("value"); num_rows]),
+            ];
+            ArrowRecordBatch::try_new(schema.to_arrow_schema_ref(), columns).unwrap()
+        };
+        // Rows of the same tsid are collapsed across the record batches.
+        let row_nums = encoder
+            .encode(vec![
+                new_record_batch(vec![1, 1, 2], vec![105, 100, 120]),
+                new_record_batch(vec![1, 2, 2], vec![103, 130, 125]),
+            ])
+            .unwrap();
+        assert_eq!(2, row_nums);
+
+        let encoded_bytes = encoder.close().unwrap();
+        let read_hybrid_record_batch = || {
+            ParquetRecordBatchReaderBuilder::try_new(Bytes::from(encoded_bytes.clone()))
+                .unwrap()
+                .build()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+        };
+
+        // The time bounds match the collapsed timestamp lists.
+        let hybrid_record_batch = read_hybrid_record_batch();
+        let timestamps = hybrid_record_batch
+            .column(schema.timestamp_index())
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        let (min_times, max_times) = hybrid::time_bound_columns(&hybrid_record_batch).unwrap();
+        for row_idx in 0..hybrid_record_batch.num_rows() {
+            let row_timestamps = timestamps.value(row_idx);
+            let row_timestamps = row_timestamps
+                .as_any()
+                .downcast_ref::<TimestampMillisecondArray>()
+                .unwrap();
+            let expect_min = row_timestamps.iter().flatten().min().unwrap();
+            let expect_max = row_timestamps.iter().flatten().max().unwrap();
+            assert_eq!(expect_min, min_times.value(row_idx));
+            assert_eq!(expect_max, max_times.value(row_idx));
+        }
+        assert_eq!(vec![100, 120], min_times.values().to_vec());
+        assert_eq!(vec![105, 130], max_times.values().to_vec());
+
+        // Collapsed rows out of the time range are skipped.
+        let mut storage_format_opts = meta_data.storage_format_opts;
+        collect_collapsible_cols_idx(&schema, &mut storage_format_opts.collapsible_cols_idx);
+        let decoded_tsids = |time_range| {
+            let decoder = ParquetDecoder::with_time_range(storage_format_opts.clone(), time_range);
+            let record_batch = decoder
+                .decode_record_batch(read_hybrid_record_batch())
+                .unwrap();
+            assert_eq!(schema.num_columns(), record_batch.num_columns());
+            record_batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap()
+                .values()
+                .to_vec()
+        };
+        assert_eq!(
+            vec![1, 1, 1, 2, 2, 2],
+            decoded_tsids(TimeRange::min_to_max())
+        );
+        assert_eq!(
+            vec![2, 2, 2],
+            decoded_tsids(TimeRange::new_unchecked_for_test(106, 200))
+        );
+        assert_eq!(
+            vec![1, 1, 1],
+            decoded_tsids(TimeRange::new_unchecked_for_test(0, 120))
+        );
+        assert!(decoded_tsids(TimeRange::new_unchecked_for_test(131, 200)).is_empty());
+    }
+
+    /// Build a columnar sample of `num_rows` rows, whose payloads are
+    /// generated by `payload_byte` from a pseudo random number.
+    fn build_compression_sample(
+        num_rows: usize,
+        payload_byte: impl Fn(u64) -> u8,
+    ) -> (SstMetaData, ArrowRecordBatch) {
+        let schema = Builder::new()
+            .auto_increment_column_id(true)
+            .enable_tsid_primary_key(true)
+            .add_key_column(
+                column_schema::Builder::new(TSID_COLUMN.to_string(), DatumKind::UInt64)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+            .add_key_column(
+                column_schema::Builder::new("timestamp".to_string(), DatumKind::Timestamp)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+            .add_normal_column(
+                column_schema::Builder::new("payload".to_string(), DatumKind::Varbinary)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // Xorshift, enough for generating the payloads.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let payloads = (0..num_rows)
+            .map(|_| {
+                (0..256)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        payload_byte(state)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let columns = vec![
+            Arc::new(UInt64Array::from(vec![1; num_rows])) as ArrayRef,
+            timestamp_array((0..num_rows as i64).collect()),
+            Arc::new(BinaryArray::from(
+                payloads.iter().map(|v| v.as_slice()).collect::<Vec<_>>(),
+            )) as ArrayRef,
+        ];
+        let record_batch =
+            ArrowRecordBatch::try_new(schema.to_arrow_schema_ref(), columns).unwrap();
+
+        let meta_data = SstMetaData {
+            min_key: Bytes::from_static(b"100"),
+            max_key: Bytes::from_static(b"200"),
+            time_range: TimeRange::new_unchecked(Timestamp::new(0), Timestamp::new(1)),
+            max_sequence: 200,
+            schema,
+            size: 0,
+            row_num: num_rows as u64,
+            storage_format_opts: StorageFormatOptions::new(StorageFormat::Columnar),
+            bloom_filter: Default::default(),
+        };
+        (meta_data, record_batch)
+    }
+
+    #[test]
+    fn test_select_compression() {
+        let column_stats = ColumnStatsOptions::default();
+
+        // Payloads of only 4 distinct bytes are highly compressible, which zstd
+        // does much better than snappy.
+        let (meta_data, sample) =
+            build_compression_sample(2048, |v| b"ACGT"[(v >> 60) as usize % 4]);
+        assert_eq!(
+            Compression::ZSTD,
+            select_compression(&column_stats, &meta_data, &[sample]).unwrap()
+        );
+
+        // Random payloads are incompressible, zstd doesn't save enough size.
+        let (meta_data, sample) = build_compression_sample(2048, |v| (v >> 56) as u8);
+        assert_eq!(
+            Compression::SNAPPY,
+            select_compression(&column_stats, &meta_data, &[sample]).unwrap()
+        );
+
+        // Nothing to sample.
+        assert_eq!(
+            Compression::SNAPPY,
+            select_compression(&column_stats, &meta_data, &[]).unwrap()
+        );
+
+        // Zstd is chosen only if it saves enough size.
+        let snappy = CompressionSample { encoded_size: 1000 };
+        let cases = [
+            (500, Compression::ZSTD),
+            (900, Compression::ZSTD),
+            (901, Compression::SNAPPY),
+            (1200, Compression::SNAPPY),
+        ];
+        for (zstd_size, expect) in cases {
+            let zstd = CompressionSample {
+                encoded_size: zstd_size,
+            };
+            assert_eq!(expect, choose_compression(&snappy, &zstd), "{}", zstd_size);
+        }
+    }
 }
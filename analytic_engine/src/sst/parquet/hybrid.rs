@@ -4,25 +4,34 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use arrow::{
     array::{
-        Array, ArrayData, ArrayDataBuilder, ArrayRef, BinaryArray, ListArray, StringArray,
-        UInt64Array,
+        Array, ArrayData, ArrayDataBuilder, ArrayRef, BinaryArray, BooleanArray, ListArray,
+        StringArray, TimestampMillisecondArray, UInt64Array,
     },
     bitmap::Bitmap,
     buffer::{Buffer, MutableBuffer},
-    datatypes::Schema as ArrowSchema,
+    compute,
+    datatypes::{Schema as ArrowSchema, TimeUnit},
     record_batch::RecordBatch as ArrowRecordBatch,
     util::bit_util,
 };
 use common_types::{
     datum::DatumKind,
     schema::{ArrowSchemaRef, DataType, Field, Schema},
+    time::TimeRange,
 };
+use parquet::schema::types::SchemaDescriptor;
 use snafu::{Backtrace, ResultExt, Snafu};
 
 use crate::sst::builder::{EncodeRecordBatch, Result};
 
 //  hard coded in https://github.com/apache/arrow-rs/blob/20.0.0/arrow/src/array/array_list.rs#L185
-const LIST_ITEM_NAME: &str = "item";
+pub const LIST_ITEM_NAME: &str = "item";
+/// Column of the min timestamp of each collapsed row, appended after the
+/// columns of the table schema.
+pub const MIN_TIME_COLUMN: &str = "__min_time__";
+/// Column of the max timestamp of each collapsed row, appended after the
+/// `MIN_TIME_COLUMN`.
+pub const MAX_TIME_COLUMN: &str = "__max_time__";
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -88,6 +97,9 @@ impl ArrayHandle {
 struct TsidBatch {
     non_collapsible_col_values: Vec<String>,
     collapsible_col_arrays: Vec<ArrayHandle>,
+    /// Min and max timestamp of the collected rows.
+    min_time: i64,
+    max_time: i64,
 }
 
 impl TsidBatch {
@@ -98,13 +110,20 @@ impl TsidBatch {
                 .into_iter()
                 .map(|f| ArrayHandle::new(f))
                 .collect(),
+            min_time: i64::MAX,
+            max_time: i64::MIN,
         }
     }
 
-    fn append_slice_arg(&mut self, arg: SliceArg) {
+    fn append_slice_arg(&mut self, arg: SliceArg, timestamps: &TimestampMillisecondArray) {
         for handle in &mut self.collapsible_col_arrays {
             handle.append_slice_arg(arg);
         }
+        for row_idx in arg.offset..arg.offset + arg.length {
+            let timestamp = timestamps.value(row_idx);
+            self.min_time = self.min_time.min(timestamp);
+            self.max_time = self.max_time.max(timestamp);
+        }
     }
 }
 
@@ -119,7 +138,8 @@ struct IndexedArray {
     array: ArrayRef,
 }
 
-/// Convert collapsible columns to list type
+/// Convert collapsible columns to list type, and append the min/max time
+/// columns.
 pub fn build_hybrid_arrow_schema(schema: &Schema) -> ArrowSchemaRef {
     let arrow_schema = schema.to_arrow_schema_ref();
     let new_fields = arrow_schema
@@ -138,6 +158,13 @@ pub fn build_hybrid_arrow_schema(schema: &Schema) -> ArrowSchemaRef {
                 field.clone()
             }
         })
+        .chain([MIN_TIME_COLUMN, MAX_TIME_COLUMN].into_iter().map(|name| {
+            Field::new(
+                name,
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                false,
+            )
+        }))
         .collect::<Vec<_>>();
     Arc::new(ArrowSchema::new_with_metadata(
         new_fields,
@@ -145,6 +172,72 @@ pub fn build_hybrid_arrow_schema(schema: &Schema) -> ArrowSchemaRef {
     ))
 }
 
+/// Returns the min/max time columns of the hybrid `record_batch`, returns None
+/// if they don't exist, e.g. the sst is written before the columns are added or
+/// they are not projected.
+pub fn time_bound_columns(
+    record_batch: &ArrowRecordBatch,
+) -> Option<(&TimestampMillisecondArray, &TimestampMillisecondArray)> {
+    let schema = record_batch.schema();
+    let column = |name| {
+        let idx = schema.index_of(name).ok()?;
+        record_batch
+            .column(idx)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+    };
+
+    Some((column(MIN_TIME_COLUMN)?, column(MAX_TIME_COLUMN)?))
+}
+
+/// Returns the leaf indexes of the min/max time columns in the parquet
+/// `schema`, which should be projected to filter collapsed rows by time.
+pub fn time_bound_leaves(schema: &SchemaDescriptor) -> Vec<usize> {
+    schema
+        .columns()
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| {
+            let name = column.path().string();
+            name == MIN_TIME_COLUMN || name == MAX_TIME_COLUMN
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Filter out the collapsed rows of hybrid `record_batch` whose timestamps are
+/// all out of `time_range`, according to the min/max time columns.
+///
+/// The `record_batch` is returned as is if it has no min/max time columns.
+pub fn filter_by_time_range(
+    record_batch: ArrowRecordBatch,
+    time_range: TimeRange,
+) -> Result<ArrowRecordBatch> {
+    let (min_times, max_times) = match time_bound_columns(&record_batch) {
+        Some(v) => v,
+        None => return Ok(record_batch),
+    };
+
+    let start = time_range.inclusive_start().as_i64();
+    let end = time_range.exclusive_end().as_i64();
+    let mask = min_times
+        .iter()
+        .zip(max_times.iter())
+        .map(|(min_time, max_time)| {
+            let overlapped =
+                min_time.unwrap_or(i64::MIN) < end && max_time.unwrap_or(i64::MAX) >= start;
+            Some(overlapped)
+        })
+        .collect::<BooleanArray>();
+    if mask.true_count() == mask.len() {
+        return Ok(record_batch);
+    }
+
+    compute::filter_record_batch(&record_batch, &mask)
+        .map_err(|e| Box::new(e) as _)
+        .context(EncodeRecordBatch)
+}
+
 struct StringArrayWrapper<'a>(&'a StringArray);
 struct BinaryArrayWrapper<'a>(&'a BinaryArray);
 
@@ -436,6 +529,10 @@ fn build_hybrid_record(
     batch_by_tsid: BTreeMap<u64, TsidBatch>,
 ) -> Result<ArrowRecordBatch> {
     let tsid_array = UInt64Array::from_iter_values(batch_by_tsid.keys().cloned());
+    let min_time_array =
+        TimestampMillisecondArray::from_iter_values(batch_by_tsid.values().map(|v| v.min_time));
+    let max_time_array =
+        TimestampMillisecondArray::from_iter_values(batch_by_tsid.values().map(|v| v.max_time));
     let mut collapsible_col_arrays = vec![Vec::new(); collapsible_col_types.len()];
     let mut non_collapsible_col_arrays = vec![Vec::new(); non_collapsible_col_types.len()];
 
@@ -479,6 +576,10 @@ fn build_hybrid_record(
     .map(|indexed_array| (indexed_array.idx, indexed_array.array))
     .collect::<BTreeMap<_, _>>()
     .into_values()
+    .chain([
+        Arc::new(min_time_array) as ArrayRef,
+        Arc::new(max_time_array) as ArrayRef,
+    ])
     .collect::<Vec<_>>();
 
     ArrowRecordBatch::try_new(arrow_schema, all_columns)
@@ -490,6 +591,7 @@ fn build_hybrid_record(
 /// `StorageFormat::Hybrid`
 pub fn convert_to_hybrid_record(
     tsid_type: &IndexedType,
+    timestamp_idx: usize,
     non_collapsible_col_types: &[IndexedType],
     collapsible_col_types: &[IndexedType],
     hybrid_arrow_schema: ArrowSchemaRef,
@@ -508,6 +610,12 @@ pub fn convert_to_hybrid_record(
             continue;
         }
 
+        let timestamp_array = record_batch
+            .column(timestamp_idx)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .expect("timestamp column must be timestamp type");
+
         let non_collapsible_col_values = non_collapsible_col_types
             .iter()
             .map(|col| {
@@ -550,7 +658,7 @@ pub fn convert_to_hybrid_record(
                         .collect(),
                 )
             });
-            batch.append_slice_arg(SliceArg { offset, length });
+            batch.append_slice_arg(SliceArg { offset, length }, timestamp_array);
         }
     }
 
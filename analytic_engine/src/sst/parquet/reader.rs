@@ -11,7 +11,7 @@ use std::{
 
 use arrow::{error::Result as ArrowResult, record_batch::RecordBatch};
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use common_types::{
     projected_schema::{ProjectedSchema, RowProjector},
     record_batch::{ArrowRecordBatchProjector, RecordBatchWithKey},
@@ -20,26 +20,45 @@ use common_types::{
 use common_util::runtime::Runtime;
 use futures::Stream;
 use log::{debug, error, trace};
-use object_store::{ObjectStoreRef, Path};
+use object_store::{ObjectStoreRef, Path, SuffixGetResult};
 use parquet::{
-    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask},
-    file::metadata::{ParquetMetaData, RowGroupMetaData},
+    arrow::{
+        arrow_reader::{ArrowReaderOptions, ParquetRecordBatchReaderBuilder, RowSelection},
+        ProjectionMask,
+    },
+    errors::ParquetError,
+    file::{
+        footer,
+        metadata::{ParquetMetaData, RowGroupMetaData},
+        FOOTER_SIZE,
+    },
+};
+use parquet_ext::{
+    prune::page, reverse_reader::Builder as ReverseRecordBatchReaderBuilder, DataCacheRef,
 };
-use parquet_ext::{reverse_reader::Builder as ReverseRecordBatchReaderBuilder, DataCacheRef};
 use snafu::{ensure, OptionExt, ResultExt};
 use table_engine::predicate::PredicateRef;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
 use super::row_group_filter::RowGroupFilter;
-use crate::sst::{
-    factory::SstReaderOptions,
-    file::SstMetaData,
-    meta_cache::MetaCacheRef,
-    parquet::encoding::{self, ParquetDecoder},
-    reader::{error::*, SstReader},
+use crate::{
+    sst::{
+        factory::SstReaderOptions,
+        file::SstMetaData,
+        meta_cache::MetaCacheRef,
+        parquet::{
+            encoding::{self, ParquetDecoder},
+            hybrid,
+        },
+        reader::{error::*, SstReader},
+    },
+    table_options::StorageFormat,
 };
 
 const DEFAULT_CHANNEL_CAP: usize = 1000;
+/// Bytes read from the end of the sst at first by [fetch_parquet_meta_data],
+/// which are enough to hold the footer of most ssts.
+const FOOTER_PREFETCH_SIZE: usize = 64 * 1024;
 
 pub async fn make_sst_chunk_reader(storage: &ObjectStoreRef, path: &Path) -> Result<Bytes> {
     let get_result = storage
@@ -62,10 +81,78 @@ pub async fn make_sst_chunk_reader(storage: &ObjectStoreRef, path: &Path) -> Res
         })
 }
 
+/// Fetch the parquet meta data of the sst at `path` from its footer, by one or
+/// two range reads instead of reading the whole sst.
+///
+/// The last [FOOTER_PREFETCH_SIZE] bytes are read first, and the rest of the
+/// footer is read by another range read only if the footer is larger.
+pub async fn fetch_parquet_meta_data(
+    storage: &ObjectStoreRef,
+    path: &Path,
+) -> Result<ParquetMetaData> {
+    let SuffixGetResult { meta, bytes } =
+        object_store::get_suffix(storage.as_ref(), path, FOOTER_PREFETCH_SIZE)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .with_context(|| ReadPersist {
+                path: path.to_string(),
+            })?;
+    let invalid_footer = || {
+        Box::new(ParquetError::General(format!(
+            "invalid parquet footer, path:{}, size:{}",
+            path, meta.size
+        ))) as _
+    };
+
+    let footer_start = bytes
+        .len()
+        .checked_sub(FOOTER_SIZE)
+        .ok_or_else(invalid_footer)
+        .context(DecodeSstMeta)?;
+    let mut footer = [0; FOOTER_SIZE];
+    footer.copy_from_slice(&bytes[footer_start..]);
+    let meta_data_len = footer::decode_footer(&footer)
+        .map_err(|e| Box::new(e) as _)
+        .context(DecodeSstMeta)?;
+
+    let meta_data_bytes = match footer_start.checked_sub(meta_data_len) {
+        Some(meta_data_start) => bytes.slice(meta_data_start..footer_start),
+        None => {
+            // The meta data is larger than the prefetched bytes, read the rest of it.
+            let rest_len = meta_data_len - footer_start;
+            let rest_end = meta.size - bytes.len();
+            let rest_start = rest_end
+                .checked_sub(rest_len)
+                .ok_or_else(invalid_footer)
+                .context(DecodeSstMeta)?;
+            let rest = storage
+                .get_range(path, rest_start..rest_end)
+                .await
+                .map_err(|e| Box::new(e) as _)
+                .with_context(|| ReadPersist {
+                    path: path.to_string(),
+                })?;
+
+            let mut buf = BytesMut::with_capacity(meta_data_len);
+            buf.extend_from_slice(&rest);
+            buf.extend_from_slice(&bytes[..footer_start]);
+            buf.freeze()
+        }
+    };
+
+    footer::decode_metadata(&meta_data_bytes)
+        .map_err(|e| Box::new(e) as _)
+        .context(DecodeSstMeta)
+}
+
+/// Create the reader builder of the sst in `chunk_reader`, the page index is
+/// loaded if `use_page_index` is true.
 pub fn make_sst_reader_builder(
     chunk_reader: Bytes,
+    use_page_index: bool,
 ) -> Result<ParquetRecordBatchReaderBuilder<Bytes>> {
-    ParquetRecordBatchReaderBuilder::try_new(chunk_reader)
+    let options = ArrowReaderOptions::new().with_page_index(use_page_index);
+    ParquetRecordBatchReaderBuilder::try_new_with_options(chunk_reader, options)
         .map_err(|e| Box::new(e) as _)
         .context(DecodeRecordBatch)
 }
@@ -99,6 +186,8 @@ pub struct ParquetSstReader<'a> {
     batch_size: usize,
     /// Read the rows in reverse order.
     reverse: bool,
+    /// Prune the pages by the page index, ignored by the reverse reads.
+    use_page_index: bool,
     channel_cap: usize,
 
     #[allow(unused)]
@@ -121,6 +210,7 @@ impl<'a> ParquetSstReader<'a> {
             reader_builder: None,
             batch_size: options.read_batch_row_num,
             reverse: options.reverse,
+            use_page_index: options.use_page_index && !options.reverse,
             channel_cap: DEFAULT_CHANNEL_CAP,
             meta_cache: options.meta_cache.clone(),
             data_cache: options.data_cache.clone(),
@@ -136,7 +226,7 @@ impl<'a> ParquetSstReader<'a> {
         }
 
         let chunk_reader = make_sst_chunk_reader(self.storage, self.path).await?;
-        let reader_builder = make_sst_reader_builder(chunk_reader.clone())?;
+        let reader_builder = make_sst_reader_builder(chunk_reader.clone(), self.use_page_index)?;
         let meta_data = read_sst_meta(reader_builder.metadata())?;
 
         self.reader_builder = Some(reader_builder);
@@ -166,6 +256,7 @@ impl<'a> ParquetSstReader<'a> {
             .context(Projection)?;
         let predicate = self.predicate.clone();
         let reverse = self.reverse;
+        let use_page_index = self.use_page_index;
 
         let meta_data = self.meta_data.take().unwrap();
         let _ = self.runtime.spawn_blocking(move || {
@@ -195,6 +286,7 @@ impl<'a> ParquetSstReader<'a> {
                 predicate,
                 batch_size,
                 reverse,
+                use_page_index,
                 meta_data,
             };
 
@@ -247,21 +339,43 @@ struct ProjectAndFilterReader {
     predicate: PredicateRef,
     batch_size: usize,
     reverse: bool,
+    use_page_index: bool,
     meta_data: SstMetaData,
 }
 
 impl ProjectAndFilterReader {
     fn filter_row_groups(&self, row_groups: &[RowGroupMetaData]) -> Result<Vec<usize>> {
+        let exprs = self.predicate.pushdown_exprs(self.schema.timestamp_name());
         let filter = RowGroupFilter::try_new(
             self.schema.as_arrow_schema_ref(),
             row_groups,
             self.meta_data.bloom_filter.filters(),
-            self.predicate.exprs(),
+            &exprs,
         )?;
 
         Ok(filter.filter())
     }
 
+    /// Select the rows of the `row_groups` by the page index, None if all the
+    /// rows should be read.
+    fn select_rows(
+        &self,
+        meta_data: &ParquetMetaData,
+        row_groups: &[usize],
+    ) -> Option<RowSelection> {
+        if !self.use_page_index {
+            return None;
+        }
+
+        let exprs = self.predicate.pushdown_exprs(self.schema.timestamp_name());
+        page::select_rows(
+            self.schema.as_arrow_schema_ref().clone(),
+            &exprs,
+            meta_data,
+            row_groups,
+        )
+    }
+
     /// Generate the reader which has processed projection and filter.
     /// This `file_reader` is consumed after calling this method.
     fn project_and_filter_reader(
@@ -296,20 +410,24 @@ impl ProjectAndFilterReader {
 
             Ok(Box::new(reverse_reader))
         } else {
-            let builder = reader_builder
+            let row_selection = self.select_rows(reader_builder.metadata(), &filtered_row_groups);
+            let mut builder = reader_builder
                 .with_batch_size(self.batch_size)
                 .with_row_groups(filtered_row_groups);
+            if let Some(row_selection) = row_selection {
+                builder = builder.with_row_selection(row_selection);
+            }
 
             let builder = if self.projected_schema.is_all_projection() {
                 builder
             } else {
-                let proj_mask = ProjectionMask::leaves(
-                    builder.metadata().file_metadata().schema_descr(),
-                    self.row_projector
-                        .existed_source_projection()
-                        .iter()
-                        .copied(),
-                );
+                let schema_descr = builder.metadata().file_metadata().schema_descr();
+                let mut leaves = self.row_projector.existed_source_projection();
+                if let StorageFormat::Hybrid = self.meta_data.storage_format_opts.format {
+                    // Read the time bounds to skip the collapsed rows out of the time range.
+                    leaves.extend(hybrid::time_bound_leaves(schema_descr));
+                }
+                let proj_mask = ProjectionMask::leaves(schema_descr, leaves);
                 builder.with_projection(proj_mask)
             };
             let reader = builder
@@ -330,7 +448,10 @@ impl ProjectAndFilterReader {
         let reader = self.project_and_filter_reader()?;
 
         let arrow_record_batch_projector = ArrowRecordBatchProjector::from(self.row_projector);
-        let parquet_decoder = ParquetDecoder::new(self.meta_data.storage_format_opts);
+        let parquet_decoder = ParquetDecoder::with_time_range(
+            self.meta_data.storage_format_opts,
+            self.predicate.time_range(),
+        );
         let mut row_num = 0;
         for record_batch in reader {
             trace!(
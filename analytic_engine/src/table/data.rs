@@ -364,6 +364,7 @@ impl TableData {
         let memtable_opts = MemTableOptions {
             schema: table_schema.clone(),
             arena_block_size: table_options.arena_block_size,
+            arena_growth: table_options.arena_block_growth(),
             creation_sequence: last_sequence,
             collector: self.mem_usage_collector.clone(),
         };
@@ -591,7 +592,7 @@ impl Default for TableDataSet {
 pub mod tests {
     use std::sync::Arc;
 
-    use arena::NoopCollector;
+    use arena::{BlockGrowth, NoopCollector};
     use common_types::{datum::DatumKind, table::DEFAULT_SHARD_ID};
     use common_util::config::ReadableDuration;
     use table_engine::{engine::TableState, table::SchemaId};
@@ -624,6 +625,7 @@ pub mod tests {
             let memtable_opts = MemTableOptions {
                 schema: default_schema(),
                 arena_block_size: 1024 * 1024,
+                arena_growth: BlockGrowth::Fixed,
                 creation_sequence: 1000,
                 collector: Arc::new(NoopCollector),
             };
@@ -36,6 +36,7 @@ use crate::{
         data::MemTableId,
         version_edit::{AddFile, VersionEdit},
     },
+    table_options::StorageFormat,
 };
 
 #[derive(Debug, Snafu)]
@@ -745,6 +746,12 @@ impl TableVersion {
         inner.levels.has_expired_sst(expire_time)
     }
 
+    pub fn has_sst_not_in_format(&self, storage_format: StorageFormat) -> bool {
+        let inner = self.inner.read().unwrap();
+
+        inner.levels.has_sst_not_in_format(storage_format)
+    }
+
     pub fn expired_ssts(&self, expire_time: Option<Timestamp>) -> Vec<ExpiredFiles> {
         let inner = self.inner.read().unwrap();
 
@@ -2,41 +2,60 @@
 
 //! Constants for table options.
 
-use std::{collections::HashMap, string::ToString, time::Duration};
+use std::{borrow::Cow, collections::HashMap, fmt::Display, string::ToString, time::Duration};
 
-use common_types::time::Timestamp;
+use arena::BlockGrowth;
+use common_types::{schema::Schema, time::Timestamp};
 use common_util::{
     config::{ReadableDuration, ReadableSize, TimeUnit},
     define_result,
     time::DurationExt,
 };
 use datafusion::parquet::basic::Compression as ParquetCompression;
+use lazy_static::lazy_static;
+use log::warn;
+use prometheus::{register_int_counter_vec, IntCounterVec};
 use proto::analytic_common as common_pb;
 use serde_derive::Deserialize;
-use snafu::{Backtrace, GenerateBacktrace, ResultExt, Snafu};
+use snafu::{ensure, Backtrace, GenerateBacktrace, OptionExt, ResultExt, Snafu};
 use table_engine::OPTION_KEY_ENABLE_TTL;
+use wal::manager::DEFAULT_CLEAN_BATCH_SIZE;
 
 use crate::compaction::{
-    CompactionStrategy, SizeTieredCompactionOptions, TimeWindowCompactionOptions,
+    CompactionStrategy, LeveledCompactionOptions, SizeTieredCompactionOptions,
+    TimeWindowCompactionOptions,
 };
 
 pub const SEGMENT_DURATION: &str = "segment_duration";
 pub const ENABLE_TTL: &str = OPTION_KEY_ENABLE_TTL;
 pub const TTL: &str = "ttl";
 pub const ARENA_BLOCK_SIZE: &str = "arena_block_size";
+pub const ARENA_GROWTH: &str = "arena_growth";
+pub const ARENA_MAX_BLOCK_SIZE: &str = "arena_max_block_size";
 pub const WRITE_BUFFER_SIZE: &str = "write_buffer_size";
 pub const COMPACTION_STRATEGY: &str = "compaction_strategy";
 pub const NUM_ROWS_PER_ROW_GROUP: &str = "num_rows_per_row_group";
 pub const UPDATE_MODE: &str = "update_mode";
 pub const COMPRESSION: &str = "compression";
 pub const STORAGE_FORMAT: &str = "storage_format";
+pub const REWRITE_STORAGE_FORMAT: &str = "rewrite_storage_format";
+pub const READ_BATCH_ROW_NUM: &str = "read_batch_row_num";
+pub const IMMUTABLE: &str = "immutable";
+pub const STATS_INCLUDE_COLUMNS: &str = "stats_include_columns";
+pub const STATS_EXCLUDE_COLUMNS: &str = "stats_exclude_columns";
+/// Name of the [TableOptionPresets] to take as the base options on creation.
+pub const PRESET: &str = "preset";
 
 const UPDATE_MODE_OVERWRITE: &str = "OVERWRITE";
 const UPDATE_MODE_APPEND: &str = "APPEND";
+const ARENA_GROWTH_FIXED: &str = "FIXED";
+const ARENA_GROWTH_GEOMETRIC: &str = "GEOMETRIC";
 const COMPRESSION_UNCOMPRESSED: &str = "UNCOMPRESSED";
 const COMPRESSION_LZ4: &str = "LZ4";
+const COMPRESSION_LZ4_RAW: &str = "LZ4_RAW";
 const COMPRESSION_SNAPPY: &str = "SNAPPY";
 const COMPRESSION_ZSTD: &str = "ZSTD";
+const COMPRESSION_AUTO: &str = "AUTO";
 const STORAGE_FORMAT_COLUMNAR: &str = "COLUMNAR";
 const STORAGE_FORMAT_HYBRID: &str = "HYBRID";
 
@@ -46,6 +65,8 @@ const BUCKET_DURATION_1D: Duration = Duration::from_secs(24 * 60 * 60);
 pub const DEFAULT_SEGMENT_DURATION: Duration = Duration::from_secs(60 * 60 * 2);
 /// Default arena block size (2M).
 const DEFAULT_ARENA_BLOCK_SIZE: u32 = 2 * 1024 * 1024;
+/// Default max arena block size of the geometric growth (64M).
+const DEFAULT_ARENA_MAX_BLOCK_SIZE: u32 = 64 * 1024 * 1024;
 /// Default write buffer size (32M).
 const DEFAULT_WRITE_BUFFER_SIZE: u32 = 32 * 1024 * 1024;
 /// Default ttl of table (7d).
@@ -59,6 +80,19 @@ const MAX_ARENA_BLOCK_SIZE: u32 = 2 * 1024 * 1024 * 1024;
 const MIN_ARENA_BLOCK_SIZE: u32 = 1024;
 const MIN_NUM_ROWS_PER_ROW_GROUP: usize = 100;
 const MAX_NUM_ROWS_PER_ROW_GROUP: usize = 10_000_000;
+/// Min segment duration (1m) learned by sampling.
+const MIN_SEGMENT_DURATION: Duration = Duration::from_secs(60);
+/// Max segment duration (3650d) learned by sampling.
+const MAX_SEGMENT_DURATION: Duration = Duration::from_secs(3650 * 24 * 60 * 60);
+
+lazy_static! {
+    static ref TABLE_OPTION_SANITIZED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "table_option_sanitized_counter",
+        "Number of table options changed by sanitizing",
+        &["field"]
+    )
+    .unwrap();
+}
 
 #[derive(Debug, Snafu)]
 #[allow(clippy::enum_variant_names)]
@@ -90,6 +124,12 @@ pub enum Error {
         backtrace
     ))]
     ParseUpdateMode { s: String, backtrace: Backtrace },
+    #[snafu(display(
+        "Failed to parse arena growth, raw str:{}.\nBacktrace:\n{}",
+        s,
+        backtrace
+    ))]
+    ParseArenaGrowth { s: String, backtrace: Backtrace },
     #[snafu(display(
         "Failed to parse compression, name:{}.\nBacktrace:\n{}",
         name,
@@ -103,6 +143,118 @@ pub enum Error {
         backtrace
     ))]
     UnknownStorageFormat { value: String, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Invalid read batch row num, value:{}.\nBacktrace:\n{}",
+        value,
+        backtrace
+    ))]
+    InvalidReadBatchRowNum { value: usize, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Option of immutable table can't be altered, key:{}, value:{}.\nBacktrace:\n{}",
+        key,
+        value,
+        backtrace
+    ))]
+    AlterImmutableTable {
+        key: String,
+        value: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Mutable table can't be altered to be immutable, value:{}.\nBacktrace:\n{}",
+        value,
+        backtrace
+    ))]
+    AlterToImmutableTable { value: String, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Immutable table must be in append mode, update_mode:{}.\nBacktrace:\n{}",
+        update_mode,
+        backtrace
+    ))]
+    ImmutableTableNotAppend {
+        update_mode: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Write buffer size is smaller than arena block size, write_buffer_size:{}, arena_block_size:{}.\nBacktrace:\n{}",
+        write_buffer_size,
+        arena_block_size,
+        backtrace
+    ))]
+    WriteBufferTooSmall {
+        write_buffer_size: u32,
+        arena_block_size: u32,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Invalid arena max block size, max_block_size:{}, valid range:[{}, {}].\nBacktrace:\n{}",
+        max_block_size,
+        arena_block_size,
+        MAX_ARENA_BLOCK_SIZE,
+        backtrace
+    ))]
+    InvalidArenaMaxBlockSize {
+        max_block_size: u64,
+        arena_block_size: u32,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Invalid sampled segment duration, duration:{:?}, valid range:[{:?}, {:?}].\nBacktrace:\n{}",
+        duration,
+        MIN_SEGMENT_DURATION,
+        MAX_SEGMENT_DURATION,
+        backtrace
+    ))]
+    InvalidSegmentDuration {
+        duration: Duration,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Segment duration is not sampling, segment_duration:{}.\nBacktrace:\n{}",
+        segment_duration,
+        backtrace
+    ))]
+    SegmentDurationNotSampling {
+        segment_duration: ReadableDuration,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Unknown table option preset, name:{}.\nBacktrace:\n{}",
+        name,
+        backtrace
+    ))]
+    UnknownPreset { name: String, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Table option preset refers to another preset, name:{}.\nBacktrace:\n{}",
+        name,
+        backtrace
+    ))]
+    NestedPreset { name: String, backtrace: Backtrace },
+
+    #[snafu(display("Invalid table option preset, name:{}, err:{}", name, source))]
+    InvalidPreset { name: String, source: Box<Error> },
+
+    #[snafu(display(
+        "Unknown column in statistics options, key:{}, column:{}.\nBacktrace:\n{}",
+        key,
+        column,
+        backtrace
+    ))]
+    UnknownStatsColumn {
+        key: String,
+        column: String,
+        backtrace: Backtrace,
+    },
 }
 
 define_result!(Error);
@@ -134,12 +286,74 @@ impl ToString for UpdateMode {
     }
 }
 
+/// Growth policy of the arena blocks of the memtable.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub enum ArenaGrowth {
+    /// All the blocks are of `arena_block_size`.
+    Fixed,
+    /// Every new block doubles the last one, from `arena_block_size` up to
+    /// `arena_max_block_size`, so memtables of bursty tables allocate far
+    /// fewer blocks.
+    Geometric,
+}
+
+impl ArenaGrowth {
+    pub fn parse_from(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case(ARENA_GROWTH_FIXED) {
+            Ok(ArenaGrowth::Fixed)
+        } else if s.eq_ignore_ascii_case(ARENA_GROWTH_GEOMETRIC) {
+            Ok(ArenaGrowth::Geometric)
+        } else {
+            ParseArenaGrowth { s }.fail()
+        }
+    }
+}
+
+impl ToString for ArenaGrowth {
+    fn to_string(&self) -> String {
+        match self {
+            ArenaGrowth::Fixed => ARENA_GROWTH_FIXED.to_string(),
+            ArenaGrowth::Geometric => ARENA_GROWTH_GEOMETRIC.to_string(),
+        }
+    }
+}
+
+impl From<ArenaGrowth> for common_pb::ArenaGrowth {
+    fn from(v: ArenaGrowth) -> Self {
+        match v {
+            ArenaGrowth::Fixed => common_pb::ArenaGrowth::Fixed,
+            ArenaGrowth::Geometric => common_pb::ArenaGrowth::Geometric,
+        }
+    }
+}
+
+impl From<common_pb::ArenaGrowth> for ArenaGrowth {
+    fn from(v: common_pb::ArenaGrowth) -> Self {
+        match v {
+            common_pb::ArenaGrowth::Fixed => ArenaGrowth::Fixed,
+            common_pb::ArenaGrowth::Geometric => ArenaGrowth::Geometric,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
 pub enum Compression {
     Uncompressed,
+    /// Framed lz4 of the parquet, which may be unreadable by other parquet
+    /// implementations, `Lz4Raw` is preferred.
     Lz4,
+    /// Lz4 block compression, which is widely supported by parquet tools.
+    Lz4Raw,
     Snappy,
     Zstd,
+    /// Choose between `Snappy` and `Zstd` for every sst by sampling its first
+    /// row group, the chosen codec is recorded in the parquet meta data of
+    /// the sst.
+    ///
+    /// The choice is made by the encoded size of the sample only, `Zstd` is
+    /// chosen if it saves enough size compared to `Snappy`, and the cpu cost
+    /// of the codecs is not taken into account.
+    Auto,
 }
 
 impl Compression {
@@ -148,10 +362,14 @@ impl Compression {
             Ok(Compression::Uncompressed)
         } else if name.eq_ignore_ascii_case(COMPRESSION_LZ4) {
             Ok(Compression::Lz4)
+        } else if name.eq_ignore_ascii_case(COMPRESSION_LZ4_RAW) {
+            Ok(Compression::Lz4Raw)
         } else if name.eq_ignore_ascii_case(COMPRESSION_SNAPPY) {
             Ok(Compression::Snappy)
         } else if name.eq_ignore_ascii_case(COMPRESSION_ZSTD) {
             Ok(Compression::Zstd)
+        } else if name.eq_ignore_ascii_case(COMPRESSION_AUTO) {
+            Ok(Compression::Auto)
         } else {
             ParseCompressionName { name }.fail()
         }
@@ -163,8 +381,10 @@ impl ToString for Compression {
         match self {
             Compression::Uncompressed => COMPRESSION_UNCOMPRESSED.to_string(),
             Compression::Lz4 => COMPRESSION_LZ4.to_string(),
+            Compression::Lz4Raw => COMPRESSION_LZ4_RAW.to_string(),
             Compression::Snappy => COMPRESSION_SNAPPY.to_string(),
             Compression::Zstd => COMPRESSION_ZSTD.to_string(),
+            Compression::Auto => COMPRESSION_AUTO.to_string(),
         }
     }
 }
@@ -174,8 +394,10 @@ impl From<Compression> for common_pb::Compression {
         match compression {
             Compression::Uncompressed => common_pb::Compression::Uncompressed,
             Compression::Lz4 => common_pb::Compression::Lz4,
+            Compression::Lz4Raw => common_pb::Compression::Lz4Raw,
             Compression::Snappy => common_pb::Compression::Snappy,
             Compression::Zstd => common_pb::Compression::Zstd,
+            Compression::Auto => common_pb::Compression::Auto,
         }
     }
 }
@@ -185,19 +407,25 @@ impl From<common_pb::Compression> for Compression {
         match compression {
             common_pb::Compression::Uncompressed => Compression::Uncompressed,
             common_pb::Compression::Lz4 => Compression::Lz4,
+            common_pb::Compression::Lz4Raw => Compression::Lz4Raw,
             common_pb::Compression::Snappy => Compression::Snappy,
             common_pb::Compression::Zstd => Compression::Zstd,
+            common_pb::Compression::Auto => Compression::Auto,
         }
     }
 }
 
-impl From<Compression> for ParquetCompression {
-    fn from(compression: Compression) -> Self {
-        match compression {
-            Compression::Uncompressed => ParquetCompression::UNCOMPRESSED,
-            Compression::Lz4 => ParquetCompression::LZ4,
-            Compression::Snappy => ParquetCompression::SNAPPY,
-            Compression::Zstd => ParquetCompression::ZSTD,
+impl Compression {
+    /// The compression of parquet, `None` if it is [Compression::Auto], which
+    /// must be resolved by sampling the data before encoding.
+    pub fn parquet_compression(&self) -> Option<ParquetCompression> {
+        match self {
+            Compression::Uncompressed => Some(ParquetCompression::UNCOMPRESSED),
+            Compression::Lz4 => Some(ParquetCompression::LZ4),
+            Compression::Lz4Raw => Some(ParquetCompression::LZ4_RAW),
+            Compression::Snappy => Some(ParquetCompression::SNAPPY),
+            Compression::Zstd => Some(ParquetCompression::ZSTD),
+            Compression::Auto => None,
         }
     }
 }
@@ -228,8 +456,9 @@ pub enum StorageFormat {
     /// Whether a column is collapsible is decided by
     /// `Schema::is_collapsible_column`
     ///
-    /// Note: minTime/maxTime is optional and not implemented yet, mainly used
-    /// for time-range pushdown filter
+    /// The minTime/maxTime of each collapsed row are stored in two extra
+    /// columns, readers skip the collapsed rows out of the queried time range
+    /// by them.
     ///
     ///```plaintext
     /// | Device ID | Timestamp           | Status Code | Tag 1 | Tag 2 | minTime | maxTime |
@@ -322,6 +551,58 @@ impl From<common_pb::StorageFormatOptions> for StorageFormatOptions {
     }
 }
 
+/// Options of the columns to generate min/max statistics for in ssts.
+///
+/// The statistics are generated for all the columns by default. If
+/// [STATS_INCLUDE_COLUMNS] is set, they are only generated for the timestamp,
+/// primary key and tag columns, on which the predicates mostly are, and the
+/// included columns. The columns are given by names, and
+/// [STATS_EXCLUDE_COLUMNS] takes precedence over [STATS_INCLUDE_COLUMNS].
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ColumnStatsOptions {
+    /// Columns with statistics besides the timestamp, primary key and tag
+    /// columns, all the columns have statistics if it is empty.
+    pub include: Vec<String>,
+    /// Columns without statistics.
+    pub exclude: Vec<String>,
+}
+
+impl ColumnStatsOptions {
+    /// Whether to generate statistics for the column at `idx` of `schema`.
+    pub fn is_enabled(&self, schema: &Schema, idx: usize) -> bool {
+        let name = &schema.column(idx).name;
+        if self.exclude.contains(name) {
+            return false;
+        }
+        if self.include.is_empty() {
+            return true;
+        }
+
+        self.include.contains(name)
+            || idx == schema.timestamp_index()
+            || schema.is_primary_key_index(&idx)
+            || schema.is_tag_column(idx)
+    }
+
+    /// Ensure all the configured columns exist in the `schema`.
+    pub fn validate(&self, schema: &Schema) -> Result<()> {
+        for (key, columns) in [
+            (STATS_INCLUDE_COLUMNS, &self.include),
+            (STATS_EXCLUDE_COLUMNS, &self.exclude),
+        ] {
+            if let Some(column) = columns
+                .iter()
+                .find(|column| schema.index_of(column).is_none())
+            {
+                return UnknownStatsColumn { key, column }.fail();
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Options for a table.
 #[derive(Debug, Clone, Deserialize, PartialEq)]
 #[serde(default)]
@@ -335,7 +616,24 @@ pub struct TableOptions {
     /// Table update mode, now support Overwrite(Default) and Append
     pub update_mode: UpdateMode,
     /// Column's format in underlying storage
+    ///
+    /// It can only be altered by scheduling a rewrite of existing ssts, see
+    /// `rewrite_storage_format`.
     pub storage_format: StorageFormat,
+    /// Whether ssts in format other than `storage_format` should be rewritten
+    /// by compaction, it is set once the storage format is altered and cleared
+    /// once no sst in other formats remains.
+    ///
+    /// Ssts of both formats coexist during the migration, so readers must
+    /// decode each sst according to the format recorded in its own meta
+    /// instead of `storage_format`.
+    pub rewrite_storage_format: bool,
+    /// Whether the table is append-only and its data never expires.
+    ///
+    /// It can only be set on creation, and an immutable table must be in the
+    /// append mode. Its ttl is never applied, and altering the update mode or
+    /// enabling the ttl is rejected.
+    pub immutable: bool,
 
     // The following options can be altered.
     /// Enable ttl
@@ -344,6 +642,10 @@ pub struct TableOptions {
     pub ttl: ReadableDuration,
     /// Arena block size of memtable.
     pub arena_block_size: u32,
+    /// Growth policy of the arena blocks of memtable.
+    pub arena_growth: ArenaGrowth,
+    /// Max arena block size of memtable, only used by the geometric growth.
+    pub arena_max_block_size: u32,
     /// Write buffer size of memtable.
     pub write_buffer_size: u32,
     /// Compaction strategy of the table.
@@ -352,6 +654,11 @@ pub struct TableOptions {
     pub num_rows_per_row_group: usize,
     /// Table Compression
     pub compression: Compression,
+    /// Row number of the batches read from ssts by queries, `None` means
+    /// `num_rows_per_row_group`.
+    pub read_batch_row_num: Option<usize>,
+    /// Columns to generate statistics for, only applied to new ssts.
+    pub column_stats: ColumnStatsOptions,
 }
 
 impl TableOptions {
@@ -360,9 +667,25 @@ impl TableOptions {
         self.segment_duration.map(|v| v.0)
     }
 
+    #[inline]
+    pub fn read_batch_row_num(&self) -> usize {
+        self.read_batch_row_num
+            .unwrap_or(self.num_rows_per_row_group)
+    }
+
+    /// Growth policy of the arena blocks of the memtables.
+    pub fn arena_block_growth(&self) -> BlockGrowth {
+        match self.arena_growth {
+            ArenaGrowth::Fixed => BlockGrowth::Fixed,
+            ArenaGrowth::Geometric => BlockGrowth::Geometric {
+                max_block_size: self.arena_max_block_size as usize,
+            },
+        }
+    }
+
     #[inline]
     pub fn ttl(&self) -> Option<ReadableDuration> {
-        if self.enable_ttl {
+        if self.enable_ttl && !self.immutable {
             Some(self.ttl)
         } else {
             None
@@ -398,43 +721,94 @@ impl TableOptions {
         ]
         .into_iter()
         .collect();
+        if let Some(num) = self.read_batch_row_num {
+            m.insert(READ_BATCH_ROW_NUM.to_string(), num.to_string());
+        }
+        if self.arena_growth != ArenaGrowth::Fixed {
+            m.insert(ARENA_GROWTH.to_string(), self.arena_growth.to_string());
+            m.insert(
+                ARENA_MAX_BLOCK_SIZE.to_string(),
+                format!("{}", self.arena_max_block_size),
+            );
+        }
+        if self.immutable {
+            m.insert(IMMUTABLE.to_string(), self.immutable.to_string());
+        }
+        // Only shown to report the progress of the rewrite, it can't be set.
+        if self.rewrite_storage_format {
+            m.insert(
+                REWRITE_STORAGE_FORMAT.to_string(),
+                self.rewrite_storage_format.to_string(),
+            );
+        }
+        if !self.column_stats.include.is_empty() {
+            m.insert(
+                STATS_INCLUDE_COLUMNS.to_string(),
+                self.column_stats.include.join(","),
+            );
+        }
+        if !self.column_stats.exclude.is_empty() {
+            m.insert(
+                STATS_EXCLUDE_COLUMNS.to_string(),
+                self.column_stats.exclude.join(","),
+            );
+        }
         self.compaction_strategy.fill_raw_map(&mut m);
 
         m
     }
 
-    /// Sanitize options silently.
+    /// Sanitize options, the changed fields are reported by a warning and the
+    /// sanitized counter.
     pub fn sanitize(&mut self) {
         let one_day_secs = BUCKET_DURATION_1D.as_secs();
 
-        if let Some(segment_duration) = self.segment_duration {
+        if let Some(segment_duration) = &mut self.segment_duration {
             let mut segment_duration_secs = segment_duration.as_secs();
             if segment_duration_secs == 0 {
                 segment_duration_secs = DEFAULT_SEGMENT_DURATION.as_secs()
             };
-            self.segment_duration = Some(ReadableDuration::secs(segment_duration_secs));
+            sanitize_field(
+                SEGMENT_DURATION,
+                segment_duration,
+                ReadableDuration::secs(segment_duration_secs),
+            );
         }
 
         let ttl_secs = self.ttl.as_secs();
         // Ttl must align to day.
         let ttl_secs = ttl_secs / one_day_secs * one_day_secs;
-        self.ttl = ReadableDuration::secs(ttl_secs);
-
-        if self.arena_block_size < MIN_ARENA_BLOCK_SIZE {
-            self.arena_block_size = MIN_ARENA_BLOCK_SIZE;
+        sanitize_field(TTL, &mut self.ttl, ReadableDuration::secs(ttl_secs));
+
+        let arena_block_size = self
+            .arena_block_size
+            .clamp(MIN_ARENA_BLOCK_SIZE, MAX_ARENA_BLOCK_SIZE);
+        sanitize_field(
+            ARENA_BLOCK_SIZE,
+            &mut self.arena_block_size,
+            arena_block_size,
+        );
+
+        // The cap is only used by the geometric growth.
+        if self.arena_growth == ArenaGrowth::Geometric {
+            let arena_max_block_size = self
+                .arena_max_block_size
+                .clamp(self.arena_block_size, MAX_ARENA_BLOCK_SIZE);
+            sanitize_field(
+                ARENA_MAX_BLOCK_SIZE,
+                &mut self.arena_max_block_size,
+                arena_max_block_size,
+            );
         }
 
-        if self.arena_block_size > MAX_ARENA_BLOCK_SIZE {
-            self.arena_block_size = MAX_ARENA_BLOCK_SIZE;
-        }
-
-        if self.num_rows_per_row_group < MIN_NUM_ROWS_PER_ROW_GROUP {
-            self.num_rows_per_row_group = MIN_NUM_ROWS_PER_ROW_GROUP;
-        }
-
-        if self.num_rows_per_row_group > MAX_NUM_ROWS_PER_ROW_GROUP {
-            self.num_rows_per_row_group = MAX_NUM_ROWS_PER_ROW_GROUP;
-        }
+        let num_rows_per_row_group = self
+            .num_rows_per_row_group
+            .clamp(MIN_NUM_ROWS_PER_ROW_GROUP, MAX_NUM_ROWS_PER_ROW_GROUP);
+        sanitize_field(
+            NUM_ROWS_PER_ROW_GROUP,
+            &mut self.num_rows_per_row_group,
+            num_rows_per_row_group,
+        );
     }
 
     pub fn need_dedup(&self) -> bool {
@@ -444,8 +818,55 @@ impl TableOptions {
         }
     }
 
+    /// Returns the expiry cutoff at `now`, timestamps before the cutoff are
+    /// expired. Returns None if ttl is disabled or the table is immutable.
+    pub fn expiry_cutoff(&self, now: Timestamp) -> Option<Timestamp> {
+        self.ttl().map(|ttl| now.sub_duration_or_min(ttl.0))
+    }
+
     pub fn is_expired(&self, timestamp: Timestamp) -> bool {
-        self.enable_ttl && timestamp.is_expired(Timestamp::expire_time(self.ttl.0))
+        self.expiry_cutoff(Timestamp::now())
+            .map_or(false, |cutoff| timestamp.is_expired(cutoff))
+    }
+
+    /// Batch size to clean the deleted wal logs of the table.
+    ///
+    /// Tables with larger row groups are expected to write more, so the batch
+    /// size scales linearly with `num_rows_per_row_group`, and the default row
+    /// number of a row group maps to [DEFAULT_CLEAN_BATCH_SIZE]. The Wal may
+    /// still bound it by its own limits.
+    pub fn wal_clean_batch_size(&self) -> usize {
+        let batch_size = DEFAULT_CLEAN_BATCH_SIZE.saturating_mul(self.num_rows_per_row_group)
+            / DEFAULT_NUM_ROW_PER_ROW_GROUP;
+        batch_size.max(1)
+    }
+
+    /// Returns whether the table is still sampling its segment duration.
+    #[inline]
+    pub fn is_sampling_segment_duration(&self) -> bool {
+        self.segment_duration.is_none()
+    }
+
+    /// Complete the segment duration sampling, returns the new options with
+    /// the `sampled_duration` as the segment duration.
+    ///
+    /// The segment duration is immutable once fixed, so it fails if the table
+    /// is not sampling.
+    pub fn complete_segment_duration_sampling(&self, sampled_duration: Duration) -> Result<Self> {
+        if let Some(segment_duration) = self.segment_duration {
+            return SegmentDurationNotSampling { segment_duration }.fail();
+        }
+        ensure!(
+            (MIN_SEGMENT_DURATION..=MAX_SEGMENT_DURATION).contains(&sampled_duration),
+            InvalidSegmentDuration {
+                duration: sampled_duration,
+            }
+        );
+
+        Ok(Self {
+            segment_duration: Some(ReadableDuration(sampled_duration)),
+            ..self.clone()
+        })
     }
 }
 
@@ -457,8 +878,13 @@ impl From<SizeTieredCompactionOptions> for common_pb::CompactionOptions {
             min_sstable_size: opts.min_sstable_size.0 as u32,
             min_threshold: opts.min_threshold as u32,
             max_threshold: opts.max_threshold as u32,
-            // FIXME: Is it ok to use the default timestamp resolution here?
-            timestamp_resolution: common_pb::TimeUnit::Nanoseconds as i32,
+            // Size tiered compaction has no timestamp resolution, write the default
+            // resolution of time window compaction so the options read back as time
+            // window options are the same as the ones derived from size tiered options.
+            timestamp_resolution: common_pb::TimeUnit::from(
+                TimeWindowCompactionOptions::default().timestamp_resolution,
+            ) as i32,
+            ..Default::default()
         }
     }
 }
@@ -484,6 +910,8 @@ impl From<TimeWindowCompactionOptions> for common_pb::CompactionOptions {
             min_threshold: v.size_tiered.min_threshold as u32,
             max_threshold: v.size_tiered.max_threshold as u32,
             timestamp_resolution: common_pb::TimeUnit::from(v.timestamp_resolution) as i32,
+            ttl_aligned: v.ttl_aligned,
+            ..Default::default()
         }
     }
 }
@@ -495,6 +923,28 @@ impl From<common_pb::CompactionOptions> for TimeWindowCompactionOptions {
         Self {
             size_tiered,
             timestamp_resolution: TimeUnit::from(opts.timestamp_resolution()),
+            ttl_aligned: opts.ttl_aligned,
+        }
+    }
+}
+
+impl From<LeveledCompactionOptions> for common_pb::CompactionOptions {
+    fn from(opts: LeveledCompactionOptions) -> Self {
+        common_pb::CompactionOptions {
+            max_levels: opts.max_levels as u32,
+            level_size_multiplier: opts.level_size_multiplier as u32,
+            target_file_size: opts.target_file_size.0,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<common_pb::CompactionOptions> for LeveledCompactionOptions {
+    fn from(opts: common_pb::CompactionOptions) -> Self {
+        Self {
+            max_levels: opts.max_levels as usize,
+            level_size_multiplier: opts.level_size_multiplier as usize,
+            target_file_size: ReadableSize(opts.target_file_size),
         }
     }
 }
@@ -517,6 +967,10 @@ impl From<TableOptions> for common_pb::TableOptions {
                 common_pb::CompactionStrategy::TimeWindow,
                 Some(common_pb::CompactionOptions::from(v)),
             ),
+            CompactionStrategy::Leveled(v) => (
+                common_pb::CompactionStrategy::Leveled,
+                Some(common_pb::CompactionOptions::from(v)),
+            ),
         };
 
         common_pb::TableOptions {
@@ -524,6 +978,8 @@ impl From<TableOptions> for common_pb::TableOptions {
             enable_ttl: opts.enable_ttl,
             ttl: opts.ttl.0.as_millis_u64(),
             arena_block_size: opts.arena_block_size,
+            arena_growth: common_pb::ArenaGrowth::from(opts.arena_growth) as i32,
+            arena_max_block_size: opts.arena_max_block_size,
             num_rows_per_row_group: opts.num_rows_per_row_group as u64,
             compaction_strategy: compaction_strategy as i32,
             compaction_options,
@@ -532,6 +988,11 @@ impl From<TableOptions> for common_pb::TableOptions {
             compression: common_pb::Compression::from(opts.compression) as i32,
             sampling_segment_duration,
             storage_format: common_pb::StorageFormat::from(opts.storage_format) as i32,
+            rewrite_storage_format: opts.rewrite_storage_format,
+            read_batch_row_num: opts.read_batch_row_num.unwrap_or(0) as u64,
+            immutable: opts.immutable,
+            stats_include_columns: opts.column_stats.include,
+            stats_exclude_columns: opts.column_stats.exclude,
         }
     }
 }
@@ -559,6 +1020,7 @@ impl From<common_pb::TableOptions> for TableOptions {
         let compression = opts.compression();
         let storage_format = opts.storage_format();
         let update_mode = opts.update_mode();
+        let arena_growth = opts.arena_growth();
 
         let compaction_strategy = match opts.compaction_strategy() {
             common_pb::CompactionStrategy::Default => CompactionStrategy::default(),
@@ -576,6 +1038,13 @@ impl From<common_pb::TableOptions> for TableOptions {
                     .unwrap_or_default();
                 CompactionStrategy::TimeWindow(opts)
             }
+            common_pb::CompactionStrategy::Leveled => {
+                let opts = opts
+                    .compaction_options
+                    .map(LeveledCompactionOptions::from)
+                    .unwrap_or_default();
+                CompactionStrategy::Leveled(opts)
+            }
         };
 
         let segment_duration = if opts.sampling_segment_duration {
@@ -595,12 +1064,26 @@ impl From<common_pb::TableOptions> for TableOptions {
             enable_ttl: opts.enable_ttl,
             ttl: Duration::from_millis(opts.ttl).into(),
             arena_block_size: opts.arena_block_size,
+            arena_growth: ArenaGrowth::from(arena_growth),
+            arena_max_block_size: if opts.arena_max_block_size == 0 {
+                DEFAULT_ARENA_MAX_BLOCK_SIZE
+            } else {
+                opts.arena_max_block_size
+            },
             compaction_strategy,
             num_rows_per_row_group: opts.num_rows_per_row_group as usize,
             update_mode: UpdateMode::from(update_mode),
             write_buffer_size: opts.write_buffer_size,
             compression: Compression::from(compression),
             storage_format: StorageFormat::from(storage_format),
+            rewrite_storage_format: opts.rewrite_storage_format,
+            read_batch_row_num: (opts.read_batch_row_num != 0)
+                .then_some(opts.read_batch_row_num as usize),
+            immutable: opts.immutable,
+            column_stats: ColumnStatsOptions {
+                include: opts.stats_include_columns,
+                exclude: opts.stats_exclude_columns,
+            },
         }
     }
 }
@@ -612,21 +1095,118 @@ impl Default for TableOptions {
             enable_ttl: true,
             ttl: DEFAULT_TTL.into(),
             arena_block_size: DEFAULT_ARENA_BLOCK_SIZE,
+            arena_growth: ArenaGrowth::Fixed,
+            arena_max_block_size: DEFAULT_ARENA_MAX_BLOCK_SIZE,
             compaction_strategy: CompactionStrategy::default(),
             num_rows_per_row_group: DEFAULT_NUM_ROW_PER_ROW_GROUP,
             update_mode: UpdateMode::Overwrite,
             write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
             compression: Compression::Zstd,
             storage_format: StorageFormat::default(),
+            rewrite_storage_format: false,
+            read_batch_row_num: None,
+            immutable: false,
+            column_stats: ColumnStatsOptions::default(),
+        }
+    }
+}
+
+/// A changed field of [TableOptions], represented by its option key and raw
+/// values. `None` means the option is absent, e.g. option of another compaction
+/// strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOptionChange {
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Diff the raw options of `old` and `new`, the returned changes are sorted by
+/// key.
+pub fn diff_table_options(old: &TableOptions, new: &TableOptions) -> Vec<TableOptionChange> {
+    let mut old_map = old.to_raw_map();
+    let new_map = new.to_raw_map();
+
+    let mut changes = Vec::new();
+    for (key, after) in new_map {
+        let before = old_map.remove(&key);
+        if before.as_ref() != Some(&after) {
+            changes.push(TableOptionChange {
+                key,
+                before,
+                after: Some(after),
+            });
+        }
+    }
+    changes.extend(old_map.into_iter().map(|(key, before)| TableOptionChange {
+        key,
+        before: Some(before),
+        after: None,
+    }));
+    changes.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+    changes
+}
+
+/// Named sets of raw table options registered at startup.
+///
+/// A table created with the option [PRESET] takes the options of the named
+/// preset as the base, and the options specified in the request override
+/// them key by key.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct TableOptionPresets(HashMap<String, HashMap<String, String>>);
+
+impl TableOptionPresets {
+    pub fn new(presets: HashMap<String, HashMap<String, String>>) -> Self {
+        Self(presets)
+    }
+
+    /// Ensure every preset is valid to create a table and refers to no other
+    /// preset.
+    pub fn validate(&self) -> Result<()> {
+        for (name, options) in &self.0 {
+            ensure!(!options.contains_key(PRESET), NestedPreset { name });
+
+            merge_table_options(options, &TableOptions::default(), true)
+                .map_err(Box::new)
+                .context(InvalidPreset { name })?;
         }
+
+        Ok(())
+    }
+
+    /// Expand the preset specified by `options`, returns the `options` as is if
+    /// no preset is specified.
+    pub fn expand<'a>(
+        &self,
+        options: &'a HashMap<String, String>,
+    ) -> Result<Cow<'a, HashMap<String, String>>> {
+        let name = match options.get(PRESET) {
+            Some(v) => v,
+            None => return Ok(Cow::Borrowed(options)),
+        };
+        let preset = self.0.get(name).context(UnknownPreset { name })?;
+
+        let mut expanded = preset.clone();
+        for (key, value) in options {
+            if key != PRESET {
+                expanded.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(Cow::Owned(expanded))
     }
 }
 
 pub fn merge_table_options_for_create(
     options: &HashMap<String, String>,
     table_opts: &TableOptions,
+    presets: &TableOptionPresets,
 ) -> Result<TableOptions> {
-    merge_table_options(options, table_opts, true)
+    let options = presets.expand(options)?;
+
+    merge_table_options(&options, table_opts, true)
 }
 
 pub fn merge_table_options_for_alter(
@@ -650,6 +1230,23 @@ fn merge_table_options(
         if let Some(v) = options.get(UPDATE_MODE) {
             table_opts.update_mode = UpdateMode::parse_from(v)?;
         }
+        if let Some(v) = options.get(IMMUTABLE) {
+            table_opts.immutable = v.parse::<bool>().context(ParseBool)?;
+        }
+        // Rows of an immutable table are never overwritten.
+        ensure!(
+            !table_opts.immutable || table_opts.update_mode == UpdateMode::Append,
+            ImmutableTableNotAppend {
+                update_mode: table_opts.update_mode.to_string(),
+            }
+        );
+    } else if table_old_opts.immutable {
+        ensure_immutable_table_unchanged(options, table_old_opts)?;
+    } else if let Some(v) = options.get(IMMUTABLE) {
+        // The rows of a mutable table may have been overwritten or expired, so
+        // the immutability is only decided on creation.
+        let immutable = v.parse::<bool>().context(ParseBool)?;
+        ensure!(!immutable, AlterToImmutableTable { value: v });
     }
 
     if let Some(v) = options.get(TTL) {
@@ -662,6 +1259,20 @@ fn merge_table_options(
         let size = parse_size(v)?;
         table_opts.arena_block_size = size.0 as u32;
     }
+    if let Some(v) = options.get(ARENA_GROWTH) {
+        table_opts.arena_growth = ArenaGrowth::parse_from(v)?;
+    }
+    if let Some(v) = options.get(ARENA_MAX_BLOCK_SIZE) {
+        let size = parse_size(v)?;
+        ensure!(
+            size.0 <= MAX_ARENA_BLOCK_SIZE as u64,
+            InvalidArenaMaxBlockSize {
+                max_block_size: size.0,
+                arena_block_size: table_opts.arena_block_size,
+            }
+        );
+        table_opts.arena_max_block_size = size.0 as u32;
+    }
     if let Some(v) = options.get(WRITE_BUFFER_SIZE) {
         let size = parse_size(v)?;
         table_opts.write_buffer_size = size.0 as u32;
@@ -676,12 +1287,100 @@ fn merge_table_options(
     if let Some(v) = options.get(COMPRESSION) {
         table_opts.compression = Compression::parse_from(v)?;
     }
+    if let Some(v) = options.get(READ_BATCH_ROW_NUM) {
+        table_opts.read_batch_row_num = Some(parse_read_batch_row_num(v)?);
+    }
+    if let Some(v) = options.get(STATS_INCLUDE_COLUMNS) {
+        table_opts.column_stats.include = parse_column_names(v);
+    }
+    if let Some(v) = options.get(STATS_EXCLUDE_COLUMNS) {
+        table_opts.column_stats.exclude = parse_column_names(v);
+    }
     if let Some(v) = options.get(STORAGE_FORMAT) {
-        table_opts.storage_format = v.as_str().try_into()?;
+        let storage_format: StorageFormat = v.as_str().try_into()?;
+        // Existing ssts are still in the old format, mark them to be rewritten by
+        // compaction.
+        if !is_create && storage_format != table_old_opts.storage_format {
+            table_opts.rewrite_storage_format = true;
+        }
+        table_opts.storage_format = storage_format;
     }
+
+    // The blocks never grow if the cap is smaller than the initial block.
+    let arena_changed = [ARENA_BLOCK_SIZE, ARENA_GROWTH, ARENA_MAX_BLOCK_SIZE]
+        .iter()
+        .any(|key| options.contains_key(*key));
+    if arena_changed && table_opts.arena_growth == ArenaGrowth::Geometric {
+        ensure!(
+            table_opts.arena_max_block_size >= table_opts.arena_block_size,
+            InvalidArenaMaxBlockSize {
+                max_block_size: table_opts.arena_max_block_size as u64,
+                arena_block_size: table_opts.arena_block_size,
+            }
+        );
+    }
+
+    // The memtable will be flushed before even one arena block is filled if the
+    // write buffer is smaller than the arena block.
+    if options.contains_key(ARENA_BLOCK_SIZE) || options.contains_key(WRITE_BUFFER_SIZE) {
+        ensure!(
+            table_opts.write_buffer_size >= table_opts.arena_block_size,
+            WriteBufferTooSmall {
+                write_buffer_size: table_opts.write_buffer_size,
+                arena_block_size: table_opts.arena_block_size,
+            }
+        );
+    }
+
     Ok(table_opts)
 }
 
+/// Ensure the `options` altering an immutable table neither enable the ttl nor
+/// change the update mode or the immutability.
+fn ensure_immutable_table_unchanged(
+    options: &HashMap<String, String>,
+    table_opts: &TableOptions,
+) -> Result<()> {
+    let unchanged = |key: &str, is_unchanged: bool| -> Result<()> {
+        match options.get(key) {
+            Some(v) if !is_unchanged => AlterImmutableTable { key, value: v }.fail(),
+            _ => Ok(()),
+        }
+    };
+
+    if let Some(v) = options.get(OPTION_KEY_ENABLE_TTL) {
+        let enable_ttl = v.parse::<bool>().context(ParseBool)?;
+        unchanged(OPTION_KEY_ENABLE_TTL, !enable_ttl)?;
+    }
+    if let Some(v) = options.get(UPDATE_MODE) {
+        let update_mode = UpdateMode::parse_from(v)?;
+        unchanged(UPDATE_MODE, update_mode == table_opts.update_mode)?;
+    }
+    if let Some(v) = options.get(IMMUTABLE) {
+        let immutable = v.parse::<bool>().context(ParseBool)?;
+        unchanged(IMMUTABLE, immutable)?;
+    }
+
+    Ok(())
+}
+
+/// Set the option `field` to the `sanitized` value, a warning is logged and the
+/// sanitized counter of the field is increased if the value is changed.
+fn sanitize_field<T: PartialEq + Display>(field: &str, value: &mut T, sanitized: T) {
+    if *value == sanitized {
+        return;
+    }
+
+    warn!(
+        "Table option is sanitized, field:{}, from:{}, to:{}",
+        field, value, sanitized
+    );
+    TABLE_OPTION_SANITIZED_COUNTER
+        .with_label_values(&[field])
+        .inc();
+    *value = sanitized;
+}
+
 fn parse_duration(v: &str) -> Result<ReadableDuration> {
     v.parse::<ReadableDuration>()
         .map_err(|err| Error::ParseDuration {
@@ -690,9 +1389,853 @@ fn parse_duration(v: &str) -> Result<ReadableDuration> {
         })
 }
 
+fn parse_read_batch_row_num(v: &str) -> Result<usize> {
+    let num = v.parse::<usize>().context(ParseInt)?;
+    ensure!(num > 0, InvalidReadBatchRowNum { value: num });
+
+    Ok(num)
+}
+
+/// Parse the comma separated column names, the empty names are skipped.
+fn parse_column_names(v: &str) -> Vec<String> {
+    v.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
 fn parse_size(v: &str) -> Result<ReadableSize> {
     v.parse::<ReadableSize>().map_err(|err| Error::ParseSize {
         err,
         backtrace: Backtrace::generate(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use common_types::{column_schema, datum::DatumKind, schema};
+
+    use super::*;
+
+    #[test]
+    fn test_leveled_compaction_pb_round_trip() {
+        let opts = TableOptions {
+            compaction_strategy: CompactionStrategy::Leveled(LeveledCompactionOptions {
+                max_levels: 6,
+                level_size_multiplier: 8,
+                target_file_size: ReadableSize::mb(32),
+            }),
+            ..Default::default()
+        };
+
+        let pb_opts = common_pb::TableOptions::from(opts.clone());
+        assert_eq!(
+            common_pb::CompactionStrategy::Leveled,
+            pb_opts.compaction_strategy()
+        );
+        assert_eq!(opts, TableOptions::from(pb_opts));
+    }
+
+    #[test]
+    fn test_size_tiered_compaction_pb_round_trip() {
+        let size_tiered = SizeTieredCompactionOptions {
+            bucket_low: 0.4,
+            bucket_high: 1.6,
+            min_sstable_size: ReadableSize::mb(20),
+            min_threshold: 3,
+            max_threshold: 12,
+        };
+        let opts = TableOptions {
+            compaction_strategy: CompactionStrategy::SizeTiered(size_tiered),
+            ..Default::default()
+        };
+
+        let pb_opts = common_pb::TableOptions::from(opts.clone());
+        assert_eq!(
+            common_pb::CompactionStrategy::SizeTiered,
+            pb_opts.compaction_strategy()
+        );
+        let pb_compaction_opts = pb_opts.compaction_options.clone().unwrap();
+        assert_eq!(opts, TableOptions::from(pb_opts));
+
+        // No resolution is injected by the size tiered options.
+        let time_window = TimeWindowCompactionOptions::from(pb_compaction_opts);
+        assert_eq!(
+            TimeWindowCompactionOptions {
+                size_tiered,
+                ..Default::default()
+            },
+            time_window
+        );
+    }
+
+    #[test]
+    fn test_merge_inconsistent_compaction_options() {
+        let invalid_options = [
+            vec![
+                ("compaction_bucket_low", "1.5"),
+                ("compaction_bucket_high", "0.5"),
+            ],
+            vec![("compaction_bucket_low", "0")],
+            vec![("compaction_min_threshold", "1")],
+            vec![
+                ("compaction_min_threshold", "8"),
+                ("compaction_max_threshold", "4"),
+            ],
+        ];
+
+        for strategy in ["size_tiered", "time_window"] {
+            for kvs in &invalid_options {
+                let mut options: HashMap<_, _> = kvs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                options.insert(COMPACTION_STRATEGY.to_string(), strategy.to_string());
+
+                let table_opts = TableOptions::default();
+                let err = merge_table_options_for_create(
+                    &options,
+                    &table_opts,
+                    &TableOptionPresets::default(),
+                )
+                .unwrap_err();
+                assert!(matches!(err, Error::ParseStrategy { .. }), "{:?}", kvs);
+                let err = merge_table_options_for_alter(&options, &table_opts).unwrap_err();
+                assert!(matches!(err, Error::ParseStrategy { .. }), "{:?}", kvs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_expiry_cutoff() {
+        let now = Timestamp::new(10_000);
+        let mut opts = TableOptions {
+            ttl: Duration::from_millis(3000).into(),
+            ..Default::default()
+        };
+        assert_eq!(Some(Timestamp::new(7000)), opts.expiry_cutoff(now));
+
+        opts.ttl = Duration::from_millis(20_000).into();
+        assert_eq!(Some(Timestamp::new(-10_000)), opts.expiry_cutoff(now));
+
+        opts.enable_ttl = false;
+        assert_eq!(None, opts.expiry_cutoff(now));
+        assert!(!opts.is_expired(Timestamp::MIN));
+    }
+
+    #[test]
+    fn test_diff_table_options() {
+        let old_opts = TableOptions::default();
+        assert!(diff_table_options(&old_opts, &old_opts.clone()).is_empty());
+
+        let options = [
+            (TTL, "3d"),
+            (WRITE_BUFFER_SIZE, "1024"),
+            (COMPACTION_STRATEGY, "size_tiered"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let new_opts = merge_table_options_for_alter(&options, &old_opts).unwrap();
+        let changes = diff_table_options(&old_opts, &new_opts);
+
+        let keys: Vec<_> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            vec![
+                "compaction_bucket_high",
+                "compaction_bucket_low",
+                "compaction_max_threshold",
+                "compaction_min_sstable_size",
+                "compaction_min_threshold",
+                COMPACTION_STRATEGY,
+                TTL,
+                WRITE_BUFFER_SIZE,
+            ],
+            keys
+        );
+        let strategy_change = &changes[5];
+        assert_eq!(Some("default"), strategy_change.before.as_deref());
+        assert_eq!(Some("size_tiered"), strategy_change.after.as_deref());
+        let ttl_change = &changes[6];
+        assert_eq!(Some("7d"), ttl_change.before.as_deref());
+        assert_eq!(Some("3d"), ttl_change.after.as_deref());
+        assert_eq!(None, changes[0].before);
+    }
+
+    #[test]
+    fn test_alter_storage_format() {
+        let options = HashMap::from([(STORAGE_FORMAT.to_string(), "hybrid".to_string())]);
+        let opts = merge_table_options_for_create(
+            &options,
+            &TableOptions::default(),
+            &TableOptionPresets::default(),
+        )
+        .unwrap();
+        assert_eq!(StorageFormat::Hybrid, opts.storage_format);
+        assert!(!opts.rewrite_storage_format);
+
+        // Alter to the same format needs no rewrite.
+        let altered_opts = merge_table_options_for_alter(&options, &opts).unwrap();
+        assert!(!altered_opts.rewrite_storage_format);
+
+        let options = HashMap::from([(STORAGE_FORMAT.to_string(), "columnar".to_string())]);
+        let altered_opts = merge_table_options_for_alter(&options, &opts).unwrap();
+        assert_eq!(StorageFormat::Columnar, altered_opts.storage_format);
+        assert!(altered_opts.rewrite_storage_format);
+
+        let pb_opts = common_pb::TableOptions::from(altered_opts.clone());
+        assert!(pb_opts.rewrite_storage_format);
+        assert_eq!(altered_opts, TableOptions::from(pb_opts));
+    }
+
+    #[test]
+    fn test_write_buffer_size_at_least_arena_block() {
+        let table_opts = TableOptions::default();
+
+        // Equal sizes are allowed.
+        let options = HashMap::from([
+            (ARENA_BLOCK_SIZE.to_string(), "4MB".to_string()),
+            (WRITE_BUFFER_SIZE.to_string(), "4MB".to_string()),
+        ]);
+        let opts =
+            merge_table_options_for_create(&options, &table_opts, &TableOptionPresets::default())
+                .unwrap();
+        assert_eq!(opts.arena_block_size, opts.write_buffer_size);
+
+        let options = HashMap::from([
+            (ARENA_BLOCK_SIZE.to_string(), "8MB".to_string()),
+            (WRITE_BUFFER_SIZE.to_string(), "4MB".to_string()),
+        ]);
+        let err =
+            merge_table_options_for_create(&options, &table_opts, &TableOptionPresets::default())
+                .unwrap_err();
+        assert!(matches!(err, Error::WriteBufferTooSmall { .. }), "{}", err);
+
+        // Altering one of them is validated against the other one of the table.
+        let options = HashMap::from([(WRITE_BUFFER_SIZE.to_string(), "1MB".to_string())]);
+        let err = merge_table_options_for_alter(&options, &table_opts).unwrap_err();
+        assert!(matches!(err, Error::WriteBufferTooSmall { .. }), "{}", err);
+        let options = HashMap::from([(ARENA_BLOCK_SIZE.to_string(), "64MB".to_string())]);
+        let err = merge_table_options_for_alter(&options, &table_opts).unwrap_err();
+        assert!(matches!(err, Error::WriteBufferTooSmall { .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_arena_growth_options() {
+        let opts = TableOptions::default();
+        assert_eq!(ArenaGrowth::Fixed, opts.arena_growth);
+        assert_eq!(BlockGrowth::Fixed, opts.arena_block_growth());
+        assert!(!opts.to_raw_map().contains_key(ARENA_GROWTH));
+        assert!(!opts.to_raw_map().contains_key(ARENA_MAX_BLOCK_SIZE));
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+
+        let options = HashMap::from([
+            (ARENA_GROWTH.to_string(), "geometric".to_string()),
+            (ARENA_MAX_BLOCK_SIZE.to_string(), "16MB".to_string()),
+        ]);
+        let opts = merge_table_options_for_create(&options, &opts, &TableOptionPresets::default())
+            .unwrap();
+        assert_eq!(ArenaGrowth::Geometric, opts.arena_growth);
+        assert_eq!(
+            BlockGrowth::Geometric {
+                max_block_size: 16 * 1024 * 1024
+            },
+            opts.arena_block_growth()
+        );
+        let raw_map = opts.to_raw_map();
+        assert_eq!("GEOMETRIC", raw_map[ARENA_GROWTH]);
+        assert_eq!("16777216", raw_map[ARENA_MAX_BLOCK_SIZE]);
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+
+        // Alter back to the fixed growth.
+        let options = HashMap::from([(ARENA_GROWTH.to_string(), "FIXED".to_string())]);
+        let altered_opts = merge_table_options_for_alter(&options, &opts).unwrap();
+        assert_eq!(BlockGrowth::Fixed, altered_opts.arena_block_growth());
+
+        let options = HashMap::from([(ARENA_GROWTH.to_string(), "doubling".to_string())]);
+        let err = merge_table_options_for_alter(&options, &opts).unwrap_err();
+        assert!(matches!(err, Error::ParseArenaGrowth { .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_arena_max_block_size() {
+        let opts = TableOptions::default();
+
+        // The cap is bounded by the max arena block size.
+        let options = HashMap::from([(ARENA_MAX_BLOCK_SIZE.to_string(), "4GB".to_string())]);
+        let err = merge_table_options_for_alter(&options, &opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidArenaMaxBlockSize { .. }),
+            "{}",
+            err
+        );
+
+        // The cap is smaller than the initial block.
+        let options = HashMap::from([
+            (ARENA_GROWTH.to_string(), "GEOMETRIC".to_string()),
+            (ARENA_MAX_BLOCK_SIZE.to_string(), "1MB".to_string()),
+        ]);
+        let err = merge_table_options_for_alter(&options, &opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidArenaMaxBlockSize { .. }),
+            "{}",
+            err
+        );
+
+        // The cap of the fixed growth is not used.
+        let options = HashMap::from([(ARENA_MAX_BLOCK_SIZE.to_string(), "1MB".to_string())]);
+        assert!(merge_table_options_for_alter(&options, &opts).is_ok());
+
+        let mut opts = TableOptions {
+            arena_growth: ArenaGrowth::Geometric,
+            arena_max_block_size: 1024,
+            ..Default::default()
+        };
+        opts.sanitize();
+        assert_eq!(opts.arena_block_size, opts.arena_max_block_size);
+    }
+
+    #[test]
+    fn test_sanitize_counter() {
+        let sanitized_count = |field: &str| {
+            TABLE_OPTION_SANITIZED_COUNTER
+                .with_label_values(&[field])
+                .get()
+        };
+
+        // Sanitizing the valid options changes nothing.
+        let mut opts = TableOptions::default();
+        let before = sanitized_count(NUM_ROWS_PER_ROW_GROUP);
+        opts.sanitize();
+        assert_eq!(TableOptions::default(), opts);
+        assert_eq!(before, sanitized_count(NUM_ROWS_PER_ROW_GROUP));
+
+        let mut opts = TableOptions {
+            arena_block_size: MAX_ARENA_BLOCK_SIZE + 1,
+            ..Default::default()
+        };
+        let before = sanitized_count(ARENA_BLOCK_SIZE);
+        opts.sanitize();
+        assert_eq!(MAX_ARENA_BLOCK_SIZE, opts.arena_block_size);
+        // Other tests may sanitize the arena block size concurrently.
+        assert!(sanitized_count(ARENA_BLOCK_SIZE) > before);
+    }
+
+    #[test]
+    fn test_wal_clean_batch_size() {
+        let opts = TableOptions::default();
+        assert_eq!(DEFAULT_CLEAN_BATCH_SIZE, opts.wal_clean_batch_size());
+
+        // Busy tables with larger row groups clean more aggressively.
+        let opts = TableOptions {
+            num_rows_per_row_group: DEFAULT_NUM_ROW_PER_ROW_GROUP * 4,
+            ..Default::default()
+        };
+        assert_eq!(DEFAULT_CLEAN_BATCH_SIZE * 4, opts.wal_clean_batch_size());
+
+        let opts = TableOptions {
+            num_rows_per_row_group: DEFAULT_NUM_ROW_PER_ROW_GROUP / 2,
+            ..Default::default()
+        };
+        assert_eq!(DEFAULT_CLEAN_BATCH_SIZE / 2, opts.wal_clean_batch_size());
+
+        // The batch size is never zero.
+        let opts = TableOptions {
+            num_rows_per_row_group: MIN_NUM_ROWS_PER_ROW_GROUP,
+            ..Default::default()
+        };
+        assert!(opts.wal_clean_batch_size() >= 1);
+    }
+
+    #[test]
+    fn test_parse_compression() {
+        let cases = [
+            ("uncompressed", Compression::Uncompressed),
+            ("lz4", Compression::Lz4),
+            ("LZ4_RAW", Compression::Lz4Raw),
+            ("lz4_raw", Compression::Lz4Raw),
+            ("Snappy", Compression::Snappy),
+            ("ZSTD", Compression::Zstd),
+            ("auto", Compression::Auto),
+        ];
+        for (name, expect) in cases {
+            let compression = Compression::parse_from(name).unwrap();
+            assert_eq!(expect, compression);
+            assert_eq!(
+                compression,
+                Compression::parse_from(&compression.to_string()).unwrap()
+            );
+        }
+
+        for invalid in ["lz4raw", "lz4-raw", "gzip"] {
+            let err = Compression::parse_from(invalid).unwrap_err();
+            assert!(matches!(err, Error::ParseCompressionName { .. }), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_compression_conversions() {
+        let cases = [
+            (
+                Compression::Uncompressed,
+                Some(ParquetCompression::UNCOMPRESSED),
+            ),
+            (Compression::Lz4, Some(ParquetCompression::LZ4)),
+            (Compression::Lz4Raw, Some(ParquetCompression::LZ4_RAW)),
+            (Compression::Snappy, Some(ParquetCompression::SNAPPY)),
+            (Compression::Zstd, Some(ParquetCompression::ZSTD)),
+            // Auto is never converted to a parquet compression silently.
+            (Compression::Auto, None),
+        ];
+        for (compression, parquet_compression) in cases {
+            assert_eq!(parquet_compression, compression.parquet_compression());
+            let pb_compression = common_pb::Compression::from(compression);
+            assert_eq!(compression, Compression::from(pb_compression));
+
+            let opts = TableOptions {
+                compression,
+                ..Default::default()
+            };
+            assert_eq!(
+                opts,
+                TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+            );
+        }
+
+        // The values persisted before are not changed.
+        assert_eq!(1, common_pb::Compression::Lz4 as i32);
+        assert_eq!(4, common_pb::Compression::Lz4Raw as i32);
+        assert_eq!(5, common_pb::Compression::Auto as i32);
+    }
+
+    #[test]
+    fn test_complete_segment_duration_sampling() {
+        let opts = TableOptions::default();
+        assert!(opts.is_sampling_segment_duration());
+
+        let sampled = Duration::from_secs(24 * 60 * 60);
+        let fixed_opts = opts.complete_segment_duration_sampling(sampled).unwrap();
+        assert!(!fixed_opts.is_sampling_segment_duration());
+        assert_eq!(Some(sampled), fixed_opts.segment_duration());
+        // Other options are kept.
+        assert_eq!(
+            opts,
+            TableOptions {
+                segment_duration: None,
+                ..fixed_opts.clone()
+            }
+        );
+
+        // The segment duration can't be changed once it is fixed.
+        let err = fixed_opts
+            .complete_segment_duration_sampling(sampled)
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::SegmentDurationNotSampling { .. }),
+            "{}",
+            err
+        );
+
+        for invalid in [
+            Duration::ZERO,
+            Duration::from_secs(1),
+            MAX_SEGMENT_DURATION + Duration::from_secs(1),
+        ] {
+            let err = opts
+                .complete_segment_duration_sampling(invalid)
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidSegmentDuration { .. }),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_segment_duration_sampling_pb() {
+        // Sampling options.
+        let opts = TableOptions::default();
+        let pb_opts = common_pb::TableOptions::from(opts.clone());
+        assert!(pb_opts.sampling_segment_duration);
+        assert_eq!(0, pb_opts.segment_duration);
+        assert_eq!(opts, TableOptions::from(pb_opts));
+
+        // The flag is flipped once the sampling is completed.
+        let sampled = Duration::from_secs(2 * 60 * 60);
+        let fixed_opts = opts.complete_segment_duration_sampling(sampled).unwrap();
+        let pb_opts = common_pb::TableOptions::from(fixed_opts.clone());
+        assert!(!pb_opts.sampling_segment_duration);
+        assert_eq!(sampled.as_millis() as u64, pb_opts.segment_duration);
+        assert_eq!(fixed_opts, TableOptions::from(pb_opts));
+
+        // Options persisted by elder versions without the sampling flag have zero
+        // segment duration, which falls back to the default one.
+        let elder_pb_opts = common_pb::TableOptions {
+            sampling_segment_duration: false,
+            segment_duration: 0,
+            ..common_pb::TableOptions::from(opts)
+        };
+        assert_eq!(
+            Some(DEFAULT_SEGMENT_DURATION),
+            TableOptions::from(elder_pb_opts).segment_duration()
+        );
+    }
+
+    #[test]
+    fn test_read_batch_row_num() {
+        let opts = TableOptions::default();
+        assert_eq!(opts.num_rows_per_row_group, opts.read_batch_row_num());
+        assert!(!opts.to_raw_map().contains_key(READ_BATCH_ROW_NUM));
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+
+        let options = HashMap::from([(READ_BATCH_ROW_NUM.to_string(), "1024".to_string())]);
+        let opts = merge_table_options_for_alter(&options, &opts).unwrap();
+        assert_eq!(1024, opts.read_batch_row_num());
+        assert_eq!("1024", opts.to_raw_map()[READ_BATCH_ROW_NUM]);
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+
+        for invalid in ["0", "-1", "abc"] {
+            let options = HashMap::from([(READ_BATCH_ROW_NUM.to_string(), invalid.to_string())]);
+            assert!(merge_table_options_for_create(
+                &options,
+                &opts,
+                &TableOptionPresets::default()
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_column_stats_options() {
+        let opts = TableOptions::default();
+        assert_eq!(ColumnStatsOptions::default(), opts.column_stats);
+        let raw_map = opts.to_raw_map();
+        assert!(!raw_map.contains_key(STATS_INCLUDE_COLUMNS));
+        assert!(!raw_map.contains_key(STATS_EXCLUDE_COLUMNS));
+
+        let options = HashMap::from([
+            (
+                STATS_INCLUDE_COLUMNS.to_string(),
+                "value, host,".to_string(),
+            ),
+            (STATS_EXCLUDE_COLUMNS.to_string(), "trace_id".to_string()),
+        ]);
+        let opts = merge_table_options_for_create(&options, &opts, &TableOptionPresets::default())
+            .unwrap();
+        assert_eq!(
+            ColumnStatsOptions {
+                include: vec!["value".to_string(), "host".to_string()],
+                exclude: vec!["trace_id".to_string()],
+            },
+            opts.column_stats
+        );
+        let raw_map = opts.to_raw_map();
+        assert_eq!("value,host", raw_map[STATS_INCLUDE_COLUMNS]);
+        assert_eq!("trace_id", raw_map[STATS_EXCLUDE_COLUMNS]);
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+
+        // Clear the included columns by altering.
+        let options = HashMap::from([(STATS_INCLUDE_COLUMNS.to_string(), "".to_string())]);
+        let opts = merge_table_options_for_alter(&options, &opts).unwrap();
+        assert!(opts.column_stats.include.is_empty());
+        assert!(!opts.to_raw_map().contains_key(STATS_INCLUDE_COLUMNS));
+        assert_eq!(vec!["trace_id".to_string()], opts.column_stats.exclude);
+    }
+
+    #[test]
+    fn test_column_stats_enabled() {
+        let new_column = |name: &str, kind, is_tag| {
+            column_schema::Builder::new(name.to_string(), kind)
+                .is_tag(is_tag)
+                .build()
+                .unwrap()
+        };
+        let schema = schema::Builder::new()
+            .auto_increment_column_id(true)
+            .add_key_column(new_column("ts", DatumKind::Timestamp, false))
+            .unwrap()
+            .add_normal_column(new_column("host", DatumKind::String, true))
+            .unwrap()
+            .add_normal_column(new_column("value", DatumKind::Double, false))
+            .unwrap()
+            .add_normal_column(new_column("trace_id", DatumKind::String, false))
+            .unwrap()
+            .build()
+            .unwrap();
+        let enabled = |opts: &ColumnStatsOptions| {
+            (0..schema.num_columns())
+                .filter(|idx| opts.is_enabled(&schema, *idx))
+                .map(|idx| schema.column(idx).name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        // All the columns are enabled by default.
+        let opts = ColumnStatsOptions::default();
+        opts.validate(&schema).unwrap();
+        assert_eq!(vec!["ts", "host", "value", "trace_id"], enabled(&opts));
+
+        let opts = ColumnStatsOptions {
+            include: Vec::new(),
+            exclude: vec!["trace_id".to_string()],
+        };
+        assert_eq!(vec!["ts", "host", "value"], enabled(&opts));
+
+        let opts = ColumnStatsOptions {
+            include: vec!["value".to_string()],
+            exclude: Vec::new(),
+        };
+        assert_eq!(vec!["ts", "host", "value"], enabled(&opts));
+
+        let opts = ColumnStatsOptions {
+            include: vec!["value".to_string()],
+            exclude: vec!["host".to_string(), "value".to_string()],
+        };
+        opts.validate(&schema).unwrap();
+        assert_eq!(vec!["ts"], enabled(&opts));
+
+        // The columns must exist in the schema.
+        for (include, exclude) in [(vec!["unknown"], vec![]), (vec![], vec!["host", "unknown"])] {
+            let opts = ColumnStatsOptions {
+                include: include.into_iter().map(String::from).collect(),
+                exclude: exclude.into_iter().map(String::from).collect(),
+            };
+            let err = opts.validate(&schema).unwrap_err();
+            assert!(matches!(err, Error::UnknownStatsColumn { .. }), "{}", err);
+        }
+    }
+
+    fn new_options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn new_presets() -> TableOptionPresets {
+        TableOptionPresets::new(HashMap::from([(
+            "metrics".to_string(),
+            new_options(&[
+                (TTL, "3d"),
+                (COMPRESSION, "SNAPPY"),
+                (WRITE_BUFFER_SIZE, "64M"),
+                (UPDATE_MODE, "APPEND"),
+            ]),
+        )]))
+    }
+
+    #[test]
+    fn test_expand_preset() {
+        let presets = new_presets();
+        presets.validate().unwrap();
+        let table_opts = TableOptions::default();
+
+        let options = new_options(&[(PRESET, "metrics")]);
+        let opts = merge_table_options_for_create(&options, &table_opts, &presets).unwrap();
+        assert_eq!(ReadableDuration::days(3), opts.ttl);
+        assert_eq!(Compression::Snappy, opts.compression);
+        assert_eq!(64 * 1024 * 1024, opts.write_buffer_size);
+        assert_eq!(UpdateMode::Append, opts.update_mode);
+        // Options absent in the preset are the defaults.
+        assert_eq!(table_opts.arena_block_size, opts.arena_block_size);
+
+        // No preset is specified.
+        let options = new_options(&[(TTL, "5d")]);
+        assert!(matches!(
+            presets.expand(&options).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        let opts = merge_table_options_for_create(&options, &table_opts, &presets).unwrap();
+        assert_eq!(ReadableDuration::days(5), opts.ttl);
+        assert_eq!(table_opts.compression, opts.compression);
+
+        let options = new_options(&[(PRESET, "not_exist")]);
+        assert!(matches!(
+            merge_table_options_for_create(&options, &table_opts, &presets),
+            Err(Error::UnknownPreset { .. })
+        ));
+    }
+
+    #[test]
+    fn test_preset_override_precedence() {
+        let presets = new_presets();
+        let table_opts = TableOptions::default();
+
+        let options = new_options(&[
+            (PRESET, "metrics"),
+            (TTL, "1d"),
+            (NUM_ROWS_PER_ROW_GROUP, "1000"),
+        ]);
+        let expanded = presets.expand(&options).unwrap();
+        assert!(!expanded.contains_key(PRESET));
+        assert_eq!("1d", expanded[TTL]);
+        assert_eq!("SNAPPY", expanded[COMPRESSION]);
+
+        // Specified options override the preset, others are kept.
+        let opts = merge_table_options_for_create(&options, &table_opts, &presets).unwrap();
+        assert_eq!(ReadableDuration::days(1), opts.ttl);
+        assert_eq!(1000, opts.num_rows_per_row_group);
+        assert_eq!(Compression::Snappy, opts.compression);
+        assert_eq!(64 * 1024 * 1024, opts.write_buffer_size);
+
+        // The merged options are validated as a whole.
+        let options = new_options(&[(PRESET, "metrics"), (ARENA_BLOCK_SIZE, "128M")]);
+        assert!(matches!(
+            merge_table_options_for_create(&options, &table_opts, &presets),
+            Err(Error::WriteBufferTooSmall { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_presets() {
+        let invalid_presets = [
+            new_options(&[(TTL, "3x")]),
+            new_options(&[(PRESET, "metrics")]),
+        ];
+        for options in invalid_presets {
+            let presets = TableOptionPresets::new(HashMap::from([("bad".to_string(), options)]));
+            assert!(presets.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_immutable_table_never_expires() {
+        let options = new_options(&[(IMMUTABLE, "true"), (UPDATE_MODE, "APPEND")]);
+        let opts = merge_table_options_for_create(
+            &options,
+            &TableOptions::default(),
+            &TableOptionPresets::default(),
+        )
+        .unwrap();
+        assert!(opts.immutable);
+        // Ttl is enabled by default, but never applied.
+        assert!(opts.enable_ttl);
+        assert!(opts.ttl().is_none());
+        assert!(opts.expiry_cutoff(Timestamp::now()).is_none());
+        assert!(!opts.is_expired(Timestamp::new(0)));
+        assert!(TableOptions::default().is_expired(Timestamp::new(0)));
+
+        assert_eq!("true", opts.to_raw_map()[IMMUTABLE]);
+        assert!(!TableOptions::default().to_raw_map().contains_key(IMMUTABLE));
+        assert_eq!(
+            opts,
+            TableOptions::from(common_pb::TableOptions::from(opts.clone()))
+        );
+    }
+
+    #[test]
+    fn test_immutable_table_in_overwrite_mode() {
+        // The update mode is overwrite by default.
+        let invalid_options = [
+            new_options(&[(IMMUTABLE, "true")]),
+            new_options(&[(IMMUTABLE, "true"), (UPDATE_MODE, "OVERWRITE")]),
+        ];
+        for options in invalid_options {
+            assert!(
+                matches!(
+                    merge_table_options_for_create(
+                        &options,
+                        &TableOptions::default(),
+                        &TableOptionPresets::default(),
+                    ),
+                    Err(Error::ImmutableTableNotAppend { .. })
+                ),
+                "{:?}",
+                options
+            );
+        }
+
+        let options = new_options(&[(IMMUTABLE, "false"), (UPDATE_MODE, "OVERWRITE")]);
+        assert!(merge_table_options_for_create(
+            &options,
+            &TableOptions::default(),
+            &TableOptionPresets::default(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_alter_immutable_table() {
+        let options = new_options(&[
+            (IMMUTABLE, "true"),
+            (UPDATE_MODE, "APPEND"),
+            (ENABLE_TTL, "false"),
+        ]);
+        let opts = merge_table_options_for_create(
+            &options,
+            &TableOptions::default(),
+            &TableOptionPresets::default(),
+        )
+        .unwrap();
+
+        let rejected_options = [
+            new_options(&[(ENABLE_TTL, "true")]),
+            new_options(&[(UPDATE_MODE, "OVERWRITE")]),
+            new_options(&[(IMMUTABLE, "false")]),
+            new_options(&[(TTL, "1d"), (ENABLE_TTL, "true")]),
+        ];
+        for options in rejected_options {
+            assert!(
+                matches!(
+                    merge_table_options_for_alter(&options, &opts),
+                    Err(Error::AlterImmutableTable { .. })
+                ),
+                "{:?}",
+                options
+            );
+        }
+
+        let accepted_options = [
+            new_options(&[(ENABLE_TTL, "false")]),
+            new_options(&[(UPDATE_MODE, "APPEND")]),
+            new_options(&[(IMMUTABLE, "true")]),
+            new_options(&[(TTL, "1d"), (WRITE_BUFFER_SIZE, "64M")]),
+        ];
+        for options in accepted_options {
+            let altered_opts = merge_table_options_for_alter(&options, &opts).unwrap();
+            assert!(altered_opts.immutable);
+            assert!(!altered_opts.enable_ttl);
+        }
+
+        // Mutable tables can still enable the ttl.
+        let options = new_options(&[(ENABLE_TTL, "true")]);
+        let mutable_opts = TableOptions {
+            enable_ttl: false,
+            ..Default::default()
+        };
+        assert!(
+            merge_table_options_for_alter(&options, &mutable_opts)
+                .unwrap()
+                .enable_ttl
+        );
+
+        // But they can't be altered to be immutable.
+        let options = new_options(&[(IMMUTABLE, "true"), (UPDATE_MODE, "APPEND")]);
+        assert!(matches!(
+            merge_table_options_for_alter(&options, &mutable_opts),
+            Err(Error::AlterToImmutableTable { .. })
+        ));
+        let options = new_options(&[(IMMUTABLE, "false")]);
+        assert!(
+            !merge_table_options_for_alter(&options, &mutable_opts)
+                .unwrap()
+                .immutable
+        );
+    }
+}
@@ -2,12 +2,15 @@
 
 //! Compaction integration tests.
 
+use std::collections::HashMap;
+
 use common_types::time::Timestamp;
 use table_engine::table::FlushRequest;
 
 use crate::{
     compaction::SizeTieredCompactionOptions,
     setup::{EngineBuilder, MemWalEngineBuilder, RocksEngineBuilder},
+    table_options::{REWRITE_STORAGE_FORMAT, STORAGE_FORMAT},
     tests::util::{self, TestEnv},
 };
 
@@ -98,3 +101,84 @@ fn test_table_compact_current_segment<T: EngineBuilder>() {
         .await;
     });
 }
+
+#[test]
+fn test_table_compact_finish_storage_format_rewrite_rocks() {
+    test_table_compact_finish_storage_format_rewrite::<RocksEngineBuilder>();
+}
+
+#[test]
+fn test_table_compact_finish_storage_format_rewrite_mem_wal() {
+    test_table_compact_finish_storage_format_rewrite::<MemWalEngineBuilder>();
+}
+
+fn test_table_compact_finish_storage_format_rewrite<T: EngineBuilder>() {
+    let env = TestEnv::builder().build();
+    let mut test_ctx = env.new_context::<T>();
+
+    env.block_on(async {
+        test_ctx.open().await;
+
+        let test_table1 = "test_table1";
+        let fixed_schema_table = test_ctx.create_fixed_schema_table(test_table1).await;
+
+        let start_ms = test_ctx.start_ms();
+        let rows = [(
+            "key1",
+            Timestamp::new(start_ms),
+            "tag1-1",
+            11.0,
+            110.0,
+            "tag2-1",
+        )];
+        let row_group = fixed_schema_table.rows_to_row_group(&rows);
+        test_ctx.write_to_table(test_table1, row_group).await;
+        // Generate a sst in the columnar format.
+        test_ctx
+            .flush_table_with_request(
+                test_table1,
+                FlushRequest {
+                    compact_after_flush: false,
+                    sync: true,
+                },
+            )
+            .await;
+
+        // Altering the storage format marks the existing sst to be rewritten, and
+        // the mark is kept even if the format is altered back before the rewrite.
+        for storage_format in ["HYBRID", "COLUMNAR"] {
+            let opts = HashMap::from([(STORAGE_FORMAT.to_string(), storage_format.to_string())]);
+            test_ctx.try_alter_options(test_table1, opts).await.unwrap();
+
+            let opts = test_ctx.table(test_table1).options();
+            assert_eq!(storage_format, opts[STORAGE_FORMAT]);
+            assert_eq!("true", opts[REWRITE_STORAGE_FORMAT]);
+        }
+
+        // All ssts are in the columnar format now, the compaction finishes the
+        // rewrite.
+        test_ctx.compact_table(test_table1).await;
+        // The rewrite is finished by the write worker, flush to wait until the
+        // commands sent before are handled.
+        test_ctx.flush_table(test_table1).await;
+
+        let opts = test_ctx.table(test_table1).options();
+        assert_eq!("COLUMNAR", opts[STORAGE_FORMAT]);
+        assert!(!opts.contains_key(REWRITE_STORAGE_FORMAT));
+
+        util::check_read(
+            &test_ctx,
+            &fixed_schema_table,
+            "Test read after rewrite",
+            test_table1,
+            &rows,
+        )
+        .await;
+
+        // The cleared flag is persisted.
+        test_ctx.reopen_with_tables(&[test_table1]).await;
+
+        let opts = test_ctx.table(test_table1).options();
+        assert!(!opts.contains_key(REWRITE_STORAGE_FORMAT));
+    });
+}
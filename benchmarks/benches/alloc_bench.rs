@@ -0,0 +1,111 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Benchmarks reporting the allocations.
+//!
+//! The counting allocator is registered as the global allocator of this target
+//! only, so it doesn't affect the other benchmarks.
+
+use std::sync::Once;
+
+use benchmarks::{
+    config::{self, BenchConfig},
+    util::CountingAllocator,
+    wal_read_bench::{WalBackend, WalReadBench},
+    wal_write_bench::WalEncodeBench,
+};
+use criterion::*;
+use log::info;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+static INIT_LOG: Once = Once::new();
+
+pub fn init_bench() -> BenchConfig {
+    INIT_LOG.call_once(|| {
+        env_logger::init();
+    });
+
+    config::bench_config_from_env()
+}
+
+fn bench_wal_encode_iter(b: &mut Bencher<'_>, bench: &WalEncodeBench) {
+    b.iter(|| bench.run_bench())
+}
+
+fn bench_wal_encode(c: &mut Criterion) {
+    let config = init_bench();
+
+    let mut group = c.benchmark_group("wal_encode");
+
+    group.measurement_time(config.wal_write_bench.bench_measurement_time.0);
+    group.sample_size(config.wal_write_bench.bench_sample_size);
+
+    let mut bench = WalEncodeBench::new(config.wal_write_bench);
+
+    for reuse_buf in [false, true] {
+        bench.init_for_bench(reuse_buf);
+        // Warm up once so the allocations of the lazy initializations are excluded.
+        bench.run_bench();
+        let num_allocations = bench.count_allocations();
+        info!(
+            "Wal encode allocations, reuse_buf:{}, values:{}, allocations:{}, allocations per value:{:.2}",
+            reuse_buf,
+            bench.num_values(),
+            num_allocations,
+            num_allocations as f64 / bench.num_values() as f64
+        );
+
+        let name = if reuse_buf { "reuse_buf" } else { "new_buf" };
+        group.bench_with_input(
+            BenchmarkId::new("wal_encode", name),
+            &bench,
+            bench_wal_encode_iter,
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_wal_read_iter(b: &mut Bencher<'_>, bench: &WalReadBench) {
+    b.iter(|| bench.run_bench())
+}
+
+fn bench_wal_read(c: &mut Criterion) {
+    let config = init_bench();
+
+    let mut group = c.benchmark_group("wal_read");
+
+    group.measurement_time(config.wal_read_bench.bench_measurement_time.0);
+    group.sample_size(config.wal_read_bench.bench_sample_size);
+
+    for (backend, name) in [
+        (WalBackend::MessageQueue, "message_queue"),
+        (WalBackend::TableKv, "table_kv"),
+    ] {
+        let bench = WalReadBench::new(config.wal_read_bench.clone(), backend);
+
+        // Warm up once so the allocations of the lazy initializations are excluded.
+        bench.run_bench();
+        let num_allocations = bench.count_allocations();
+        info!(
+            "Wal read allocations, backend:{}, entries:{}, allocations:{}, allocations per entry:{:.2}",
+            name,
+            bench.num_entries(),
+            num_allocations,
+            num_allocations as f64 / bench.num_entries() as f64
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("wal_read", name),
+            &bench,
+            bench_wal_read_iter,
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_wal_encode, bench_wal_read);
+
+criterion_main!(benches);
@@ -25,6 +25,7 @@ pub struct BenchConfig {
     pub scan_memtable_bench: ScanMemTableBenchConfig,
     pub merge_memtable_bench: MergeMemTableBenchConfig,
     pub wal_write_bench: WalWriteBenchConfig,
+    pub wal_read_bench: WalReadBenchConfig,
 }
 
 // TODO(yingwen): Maybe we can use layze static to load config first.
@@ -87,6 +88,10 @@ pub struct ScanMemTableBenchConfig {
     pub max_projections: usize,
 
     pub arena_block_size: ReadableSize,
+    pub read_batch_row_num: usize,
+    /// Only the rows of the row groups matching the predicate are loaded, all
+    /// the rows are loaded if it is not set.
+    pub predicate: Option<BenchPredicate>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -127,6 +132,10 @@ pub struct MergeMemTableBenchConfig {
     pub max_projections: usize,
 
     pub arena_block_size: ReadableSize,
+    pub read_batch_row_num: usize,
+    /// Only the rows of the row groups matching the predicate are loaded, all
+    /// the rows are loaded if it is not set.
+    pub predicate: Option<BenchPredicate>,
 }
 
 #[derive(Deserialize)]
@@ -136,3 +145,15 @@ pub struct WalWriteBenchConfig {
     pub batch_size: usize,
     pub value_size: usize,
 }
+
+#[derive(Clone, Deserialize)]
+pub struct WalReadBenchConfig {
+    pub bench_measurement_time: ReadableDuration,
+    pub bench_sample_size: usize,
+    /// Number of batches written before reading.
+    pub num_batches: usize,
+    pub batch_size: usize,
+    pub value_size: usize,
+    /// Batch size of each read.
+    pub read_batch_size: usize,
+}
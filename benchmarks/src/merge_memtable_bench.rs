@@ -24,7 +24,7 @@ use analytic_engine::{
         version::{MemTableState, MemTableVec},
     },
 };
-use arena::NoopCollector;
+use arena::{BlockGrowth, NoopCollector};
 use common_types::{
     projected_schema::ProjectedSchema, request_id::RequestId, schema::Schema, time::TimeRange,
 };
@@ -34,7 +34,10 @@ use object_store::{LocalFileSystem, ObjectStoreRef};
 use parquet_ext::DataCacheRef;
 use table_engine::{predicate::Predicate, table::TableId};
 
-use crate::{config::MergeMemTableBenchConfig, util};
+use crate::{
+    config::{BenchPredicate, MergeMemTableBenchConfig},
+    util,
+};
 
 pub struct MergeMemTableBench {
     store: ObjectStoreRef,
@@ -73,6 +76,10 @@ impl MergeMemTableBench {
         let projected_schema = ProjectedSchema::no_projection(schema.clone());
         let max_projections = cmp::min(config.max_projections, schema.num_columns());
 
+        let predicate = config
+            .predicate
+            .map(BenchPredicate::into_predicate)
+            .unwrap_or_else(|| Arc::new(Predicate::empty()));
         let mut memtables = Vec::with_capacity(config.sst_file_ids.len());
         for id in &config.sst_file_ids {
             let sst_path = sst_util::new_sst_file_path(space_id, table_id, *id);
@@ -82,6 +89,7 @@ impl MergeMemTableBench {
                 collector: Arc::new(NoopCollector {}),
                 schema: schema.clone(),
                 arena_block_size: config.arena_block_size.0 as u32,
+                arena_growth: BlockGrowth::Fixed,
                 creation_sequence: crate::INIT_SEQUENCE,
             };
             let memtable = memtable_factory.create_memtable(memtable_opts).unwrap();
@@ -91,6 +99,8 @@ impl MergeMemTableBench {
                 &sst_path,
                 &schema,
                 &memtable,
+                config.read_batch_row_num,
+                predicate.clone(),
                 runtime.clone(),
             ));
 
@@ -105,7 +115,11 @@ impl MergeMemTableBench {
                 id: *id,
             });
         }
-        let sst_reader_options = mock_sst_reader_options(projected_schema.clone(), runtime.clone());
+        let sst_reader_options = mock_sst_reader_options(
+            projected_schema.clone(),
+            config.read_batch_row_num,
+            runtime.clone(),
+        );
 
         MergeMemTableBench {
             store,
@@ -195,12 +209,14 @@ impl MergeMemTableBench {
 
 fn mock_sst_reader_options(
     projected_schema: ProjectedSchema,
+    read_batch_row_num: usize,
     runtime: Arc<Runtime>,
 ) -> SstReaderOptions {
     SstReaderOptions {
         sst_type: SstType::Parquet,
-        read_batch_row_num: 500,
+        read_batch_row_num,
         reverse: false,
+        use_page_index: false,
         projected_schema,
         predicate: Arc::new(Predicate::empty()),
         meta_cache: None,
@@ -69,6 +69,7 @@ impl MergeSstBench {
             sst_type: SstType::Parquet,
             read_batch_row_num: config.read_batch_row_num,
             reverse: false,
+            use_page_index: false,
             projected_schema,
             predicate,
             meta_cache: meta_cache.clone(),
@@ -12,13 +12,17 @@ use analytic_engine::{
     },
     sst::meta_cache::MetaCacheRef,
 };
-use arena::NoopCollector;
+use arena::{BlockGrowth, NoopCollector};
 use common_types::projected_schema::ProjectedSchema;
 use log::info;
 use object_store::{LocalFileSystem, Path};
 use parquet_ext::DataCacheRef;
+use table_engine::predicate::Predicate;
 
-use crate::{config::ScanMemTableBenchConfig, util};
+use crate::{
+    config::{BenchPredicate, ScanMemTableBenchConfig},
+    util,
+};
 
 pub struct ScanMemTableBench {
     memtable: MemTableRef,
@@ -48,15 +52,22 @@ impl ScanMemTableBench {
             collector: Arc::new(NoopCollector {}),
             schema: schema.clone(),
             arena_block_size: config.arena_block_size.0 as u32,
+            arena_growth: BlockGrowth::Fixed,
             creation_sequence: crate::INIT_SEQUENCE,
         };
         let memtable = memtable_factory.create_memtable(memtable_opts).unwrap();
 
+        let predicate = config
+            .predicate
+            .map(BenchPredicate::into_predicate)
+            .unwrap_or_else(|| Arc::new(Predicate::empty()));
         runtime.block_on(util::load_sst_to_memtable(
             &store,
             &sst_path,
             &schema,
             &memtable,
+            config.read_batch_row_num,
+            predicate,
             runtime.clone(),
         ));
 
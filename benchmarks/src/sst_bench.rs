@@ -56,6 +56,7 @@ impl SstBench {
             sst_type: SstType::Parquet,
             read_batch_row_num: config.read_batch_row_num,
             reverse: config.reverse,
+            use_page_index: false,
             projected_schema,
             predicate,
             meta_cache,
@@ -22,7 +22,7 @@ use analytic_engine::{
         manager::FileId,
     },
     table::sst_util,
-    table_options::Compression,
+    table_options::{ColumnStatsOptions, Compression},
 };
 use common_types::{projected_schema::ProjectedSchema, request_id::RequestId};
 use common_util::runtime::Runtime;
@@ -50,6 +50,7 @@ async fn create_sst_from_stream(config: SstConfig, record_batch_stream: RecordBa
         sst_type: SstType::Parquet,
         num_rows_per_row_group: config.num_rows_per_row_group,
         compression: config.compression,
+        column_stats: ColumnStatsOptions::default(),
     };
 
     info!(
@@ -90,17 +91,10 @@ pub async fn rebuild_sst(config: RebuildSstConfig, runtime: Arc<Runtime>) {
 
     let sst_meta = util::meta_from_sst(&store, &input_path, &None, &None).await;
 
-    let projected_schema = ProjectedSchema::no_projection(sst_meta.schema.clone());
-    let sst_reader_options = SstReaderOptions {
-        sst_type: SstType::Parquet,
-        read_batch_row_num: config.read_batch_row_num,
-        reverse: false,
-        projected_schema,
-        predicate: config.predicate.into_predicate(),
-        meta_cache: None,
-        data_cache: None,
-        runtime,
-    };
+    let sst_reader_options = SstReaderOptions::builder(sst_meta.schema.clone(), runtime)
+        .with_read_batch_row_num(config.read_batch_row_num)
+        .with_predicate(config.predicate.into_predicate())
+        .build();
 
     let record_batch_stream =
         sst_to_record_batch_stream(&sst_reader_options, &input_path, &store).await;
@@ -192,16 +186,11 @@ pub async fn merge_sst(config: MergeSstConfig, runtime: Arc<Runtime>) {
         let table_id = config.table_id;
         let sequence = max_sequence + 1;
         let projected_schema = ProjectedSchema::no_projection(schema.clone());
-        let sst_reader_options = SstReaderOptions {
-            sst_type: SstType::Parquet,
-            read_batch_row_num: config.read_batch_row_num,
-            reverse: false,
-            projected_schema: projected_schema.clone(),
-            predicate: config.predicate.into_predicate(),
-            meta_cache: None,
-            data_cache: None,
-            runtime: runtime.clone(),
-        };
+        let sst_reader_options = SstReaderOptions::builder(schema.clone(), runtime.clone())
+            .with_read_batch_row_num(config.read_batch_row_num)
+            .with_projected_schema(projected_schema.clone())
+            .with_predicate(config.predicate.into_predicate())
+            .build();
 
         let sst_factory: SstFactoryRef = Arc::new(FactoryImpl::default());
         let mut builder = MergeBuilder::new(MergeConfig {
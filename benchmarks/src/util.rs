@@ -2,13 +2,20 @@
 
 //! Utilities.
 
-use std::sync::Arc;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use analytic_engine::{
     memtable::{key::KeySequence, MemTableRef, PutContext},
     space::SpaceId,
     sst::{
-        factory::{Factory, FactoryImpl, SstReaderOptions, SstType},
+        factory::{Factory, FactoryImpl, SstReaderOptions},
         file::{FileHandle, FileMeta, FilePurgeQueue, SstMetaData},
         manager::FileId,
         meta_cache::MetaCacheRef,
@@ -17,7 +24,7 @@ use analytic_engine::{
     table::sst_util,
 };
 use common_types::{
-    bytes::{BufMut, SafeBufMut},
+    bytes::{Buf, BufMut, SafeBufMut},
     projected_schema::ProjectedSchema,
     schema::{IndexInWriterSchema, Schema},
 };
@@ -27,11 +34,10 @@ use common_util::{
 };
 use futures::stream::StreamExt;
 use object_store::{ObjectStoreRef, Path};
-use parquet::file::footer;
 use parquet_ext::DataCacheRef;
 use snafu::{ResultExt, Snafu};
-use table_engine::{predicate::Predicate, table::TableId};
-use wal::log_batch::Payload;
+use table_engine::{predicate::PredicateRef, table::TableId};
+use wal::log_batch::{Payload, PayloadDecoder};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -59,10 +65,9 @@ pub async fn meta_from_sst(
     _meta_cache: &Option<MetaCacheRef>,
     _data_cache: &Option<DataCacheRef>,
 ) -> SstMetaData {
-    let chunk_reader = reader::make_sst_chunk_reader(store, sst_path)
+    let metadata = reader::fetch_parquet_meta_data(store, sst_path)
         .await
         .unwrap();
-    let metadata = footer::parse_metadata(&chunk_reader).unwrap();
     reader::read_sst_meta(&metadata).unwrap()
 }
 
@@ -96,18 +101,14 @@ pub async fn load_sst_to_memtable(
     sst_path: &Path,
     schema: &Schema,
     memtable: &MemTableRef,
+    read_batch_row_num: usize,
+    predicate: PredicateRef,
     runtime: Arc<Runtime>,
 ) {
-    let sst_reader_options = SstReaderOptions {
-        sst_type: SstType::Parquet,
-        read_batch_row_num: 500,
-        reverse: false,
-        projected_schema: ProjectedSchema::no_projection(schema.clone()),
-        predicate: Arc::new(Predicate::empty()),
-        meta_cache: None,
-        data_cache: None,
-        runtime,
-    };
+    let sst_reader_options = SstReaderOptions::builder(schema.clone(), runtime)
+        .with_read_batch_row_num(read_batch_row_num)
+        .with_predicate(predicate)
+        .build();
     let sst_factory = FactoryImpl;
     let mut sst_reader = sst_factory
         .new_sst_reader(&sst_reader_options, sst_path, store)
@@ -203,3 +204,49 @@ impl<'a> From<&'a Vec<u8>> for WritePayload<'a> {
         Self(data)
     }
 }
+
+/// Decoder of the payloads written by [WritePayload], only the size of the
+/// payload is returned.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadPayloadDecoder;
+
+impl PayloadDecoder for ReadPayloadDecoder {
+    type Error = Infallible;
+    type Target = usize;
+
+    fn decode<B: Buf>(&self, buf: &mut B) -> std::result::Result<Self::Target, Self::Error> {
+        let size = buf.remaining();
+        buf.advance(size);
+        Ok(size)
+    }
+}
+
+static NUM_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocator counts the allocations, it must be registered as the global
+/// allocator by the bench binary to make [num_allocations] work.
+///
+/// Only the `alloc_bench` target registers it, so the other benches are not
+/// slowed down by the counting.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Number of allocations made since the process started.
+pub fn num_allocations() -> usize {
+    NUM_ALLOCATIONS.load(Ordering::Relaxed)
+}
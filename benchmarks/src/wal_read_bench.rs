@@ -0,0 +1,281 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! WalManager read bench, the wal is based on a message queue or a table kv
+//! in memory.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use common_types::table::Location;
+use common_util::runtime::Runtime;
+use message_queue::{
+    ConsumeIterator, Message, MessageAndOffset, MessageQueue, Offset, OffsetType, StartOffset,
+};
+use snafu::{OptionExt, Snafu};
+use table_kv::memory::MemoryImpl;
+use wal::{
+    manager::{ReadBoundary, ReadContext, ReadRequest, WalManager, WalManagerRef, WriteContext},
+    message_queue_impl::{config::Config, wal::MessageQueueImpl},
+    table_kv_impl::{model::NamespaceConfig, wal::WalNamespaceImpl, WalRuntimes},
+};
+
+use crate::{
+    config::WalReadBenchConfig,
+    util::{self, ReadPayloadDecoder, WritePayload},
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Topic not found, topic:{}.", topic))]
+    TopicNotFound { topic: String },
+
+    #[snafu(display("No message at offset, topic:{}, offset:{}.", topic, offset))]
+    NoMessage { topic: String, offset: Offset },
+}
+
+type Messages = Arc<RwLock<Vec<Message>>>;
+
+/// Message queue holding all the messages in memory, so the bench measures the
+/// read path of the wal instead of the message queue.
+///
+/// Deleted messages are never removed, the offset of a message is its index in
+/// the topic.
+#[derive(Debug, Default)]
+struct MemoryMessageQueue {
+    topics: RwLock<HashMap<String, Messages>>,
+}
+
+impl MemoryMessageQueue {
+    fn messages(&self, topic_name: &str) -> Result<Messages, Error> {
+        self.topics
+            .read()
+            .unwrap()
+            .get(topic_name)
+            .cloned()
+            .context(TopicNotFound { topic: topic_name })
+    }
+}
+
+#[async_trait]
+impl MessageQueue for MemoryMessageQueue {
+    type ConsumeIterator = MemoryConsumeIterator;
+    type Error = Error;
+
+    async fn create_topic_if_not_exist(&self, topic_name: &str) -> Result<(), Self::Error> {
+        self.topics
+            .write()
+            .unwrap()
+            .entry(topic_name.to_string())
+            .or_default();
+        Ok(())
+    }
+
+    async fn fetch_offset(
+        &self,
+        topic_name: &str,
+        offset_type: OffsetType,
+    ) -> Result<Offset, Self::Error> {
+        let messages = self.messages(topic_name)?;
+        let offset = match offset_type {
+            OffsetType::EarliestOffset => 0,
+            OffsetType::HighWaterMark => messages.read().unwrap().len() as Offset,
+        };
+
+        Ok(offset)
+    }
+
+    async fn produce(
+        &self,
+        topic_name: &str,
+        messages: Vec<Message>,
+    ) -> Result<Vec<Offset>, Self::Error> {
+        let topic_messages = self.messages(topic_name)?;
+        let mut topic_messages = topic_messages.write().unwrap();
+        let start = topic_messages.len() as Offset;
+        let offsets = (start..start + messages.len() as Offset).collect();
+        topic_messages.extend(messages);
+
+        Ok(offsets)
+    }
+
+    async fn consume(
+        &self,
+        topic_name: &str,
+        start_offset: StartOffset,
+    ) -> Result<Self::ConsumeIterator, Self::Error> {
+        let messages = self.messages(topic_name)?;
+        let offset = match start_offset {
+            StartOffset::Earliest => 0,
+            StartOffset::Latest => messages.read().unwrap().len() as Offset,
+            StartOffset::At(offset) => offset,
+        };
+
+        Ok(MemoryConsumeIterator {
+            topic: topic_name.to_string(),
+            messages,
+            offset,
+        })
+    }
+
+    async fn delete_to(&self, _topic_name: &str, _offset: Offset) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct MemoryConsumeIterator {
+    topic: String,
+    messages: Messages,
+    offset: Offset,
+}
+
+#[async_trait]
+impl ConsumeIterator for MemoryConsumeIterator {
+    type Error = Error;
+
+    async fn next_message(&mut self) -> Result<(MessageAndOffset, Offset), Self::Error> {
+        let messages = self.messages.read().unwrap();
+        let message = messages
+            .get(self.offset as usize)
+            .cloned()
+            .context(NoMessage {
+                topic: &self.topic,
+                offset: self.offset,
+            })?;
+        let message_and_offset = MessageAndOffset {
+            message,
+            offset: self.offset,
+        };
+        self.offset += 1;
+
+        Ok((message_and_offset, messages.len() as Offset))
+    }
+}
+
+/// Backend of the wal to read.
+#[derive(Debug, Clone, Copy)]
+pub enum WalBackend {
+    MessageQueue,
+    TableKv,
+}
+
+pub struct WalReadBench {
+    num_entries: usize,
+    read_batch_size: usize,
+    location: Location,
+    wal: WalManagerRef,
+    runtime: Arc<Runtime>,
+}
+
+impl WalReadBench {
+    pub fn new(config: WalReadBenchConfig, backend: WalBackend) -> Self {
+        let runtime = Arc::new(util::new_runtime(1));
+        let location = Location::new(1, 1);
+
+        let wal = runtime.block_on(async {
+            let wal: WalManagerRef = match backend {
+                WalBackend::MessageQueue => Arc::new(MessageQueueImpl::new(
+                    "ceresedb".to_string(),
+                    MemoryMessageQueue::default(),
+                    runtime.clone(),
+                    Config::default(),
+                )),
+                WalBackend::TableKv => {
+                    let runtimes = WalRuntimes {
+                        read_runtime: runtime.clone(),
+                        write_runtime: runtime.clone(),
+                        bg_runtime: runtime.clone(),
+                    };
+                    let wal = WalNamespaceImpl::open(
+                        MemoryImpl::default(),
+                        runtimes,
+                        "ceresedb",
+                        NamespaceConfig::default(),
+                    )
+                    .await
+                    .expect("should succeed to open WalNamespaceImpl(Memory)");
+                    Arc::new(wal)
+                }
+            };
+
+            let value = vec![0u8; config.value_size.max(128)];
+            let values = vec![value; config.batch_size];
+            let wal_encoder = wal
+                .encoder(location)
+                .expect("should succeed to create wal encoder");
+            let write_ctx = WriteContext::default();
+            for _ in 0..config.num_batches {
+                let log_batch = wal_encoder
+                    .encode_batch::<WritePayload, Vec<u8>>(values.as_slice())
+                    .expect("should succeed to encode payload batch");
+                wal.write(&write_ctx, &log_batch)
+                    .await
+                    .expect("should succeed to write wal");
+            }
+
+            wal
+        });
+
+        WalReadBench {
+            num_entries: config.num_batches * config.batch_size,
+            read_batch_size: config.read_batch_size,
+            location,
+            wal,
+            runtime,
+        }
+    }
+
+    /// Number of entries written to the wal.
+    pub fn num_entries(&self) -> usize {
+        self.num_entries
+    }
+
+    /// Read all the entries of the table, returns the number of entries read.
+    pub fn run_bench(&self) -> usize {
+        self.runtime.block_on(async {
+            let read_ctx = ReadContext {
+                batch_size: self.read_batch_size,
+                ..Default::default()
+            };
+            let read_req = ReadRequest {
+                location: self.location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            let mut iter = self
+                .wal
+                .read_batch(&read_ctx, &read_req)
+                .await
+                .expect("should succeed to read wal");
+
+            let mut num_read = 0;
+            let mut buffer = VecDeque::with_capacity(self.read_batch_size);
+            loop {
+                buffer = iter
+                    .next_log_entries(ReadPayloadDecoder, buffer)
+                    .await
+                    .expect("should succeed to fetch log entries");
+                if buffer.is_empty() {
+                    break;
+                }
+                num_read += buffer.len();
+            }
+
+            assert_eq!(self.num_entries, num_read);
+            num_read
+        })
+    }
+
+    /// Read all the entries once, returns the allocations made by the read.
+    ///
+    /// The [util::CountingAllocator] must be the global allocator, otherwise
+    /// zero is returned.
+    pub fn count_allocations(&self) -> usize {
+        let before = util::num_allocations();
+        self.run_bench();
+        util::num_allocations() - before
+    }
+}
@@ -4,7 +4,7 @@
 
 use std::sync::Arc;
 
-use common_types::table::Location;
+use common_types::{bytes::BytesMut, table::Location};
 use common_util::runtime::Runtime;
 use rand::prelude::*;
 use table_kv::memory::MemoryImpl;
@@ -88,3 +88,77 @@ impl WalWriteBench {
         });
     }
 }
+
+/// Bench of encoding every value into a log batch in a tight write loop, to
+/// compare the allocations with and without reusing the encoding buffer.
+pub struct WalEncodeBench {
+    values: Vec<Vec<u8>>,
+    wal: WalNamespaceImpl<MemoryImpl>,
+    reuse_buf: bool,
+    // Keep the runtime alive as long as the wal.
+    _runtime: Arc<Runtime>,
+}
+
+impl WalEncodeBench {
+    pub fn new(config: WalWriteBenchConfig) -> Self {
+        let write_bench = WalWriteBench::new(config);
+        let values = write_bench.build_value_vec();
+        let runtime = write_bench.runtime;
+        let runtimes = WalRuntimes {
+            read_runtime: runtime.clone(),
+            write_runtime: runtime.clone(),
+            bg_runtime: runtime.clone(),
+        };
+        let wal = runtime
+            .block_on(WalNamespaceImpl::open(
+                MemoryImpl::default(),
+                runtimes,
+                "ceresedb",
+                NamespaceConfig::default(),
+            ))
+            .expect("should succeed to open WalNamespaceImpl(Memory)");
+
+        WalEncodeBench {
+            values,
+            wal,
+            reuse_buf: false,
+            _runtime: runtime,
+        }
+    }
+
+    pub fn num_values(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn init_for_bench(&mut self, reuse_buf: bool) {
+        self.reuse_buf = reuse_buf;
+    }
+
+    pub fn run_bench(&self) {
+        let mut buf = BytesMut::new();
+        for value in &self.values {
+            let wal_encoder = self
+                .wal
+                .encoder(Location::new(1, 1))
+                .expect("should succeed to create wal encoder");
+            let payload = WritePayload(value);
+            let log_batch = if self.reuse_buf {
+                wal_encoder.encode_with_buf(&mut buf, &payload)
+            } else {
+                wal_encoder.encode(&payload)
+            }
+            .expect("should succeed to encode payload");
+            assert_eq!(1, log_batch.len());
+        }
+    }
+
+    /// Count the allocations of a [WalEncodeBench::run_bench].
+    ///
+    /// The [util::CountingAllocator] must be the global allocator, otherwise
+    /// zero is returned.
+    pub fn count_allocations(&self) -> usize {
+        let before = util::num_allocations();
+        self.run_bench();
+        util::num_allocations() - before
+    }
+}
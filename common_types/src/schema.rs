@@ -86,6 +86,18 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Unsupported column type, name:{}, type:{:?}.\nBacktrace:\n{}",
+        name,
+        kind,
+        backtrace
+    ))]
+    ColumnType {
+        name: String,
+        kind: DatumKind,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Timestamp key column already exists, timestamp_column:{}, given:{}.\nBacktrace:\n{}",
         timestamp_column,
@@ -156,6 +168,18 @@ pub enum Error {
     #[snafu(display("Failed to encode schema by protobuf, err:{}", source))]
     EncodeSchemaToPb { source: prost::EncodeError },
 
+    #[snafu(display(
+        "Failed to encode default value of column, column:{}, data_type:{}, err:{}",
+        column,
+        data_type,
+        source
+    ))]
+    EncodeColumnDefaultValue {
+        column: String,
+        data_type: DatumKind,
+        source: serde_json::Error,
+    },
+
     #[snafu(display("Encoded schema content is empty.\nBacktrace:\n{}", backtrace))]
     EmptyEncodedSchema { backtrace: Backtrace },
 
@@ -1038,13 +1062,21 @@ impl Builder {
         self.max_column_id = cmp::max(self.max_column_id, column.id);
     }
 
-    // TODO(yingwen): Do we need to support null data type?
     fn validate_column(&self, column: &ColumnSchema, is_key: bool) -> Result<()> {
         ensure!(
             !self.column_names.contains(&column.name),
             ColumnNameExists { name: &column.name }
         );
 
+        // A column of null type holds no data.
+        ensure!(
+            column.data_type != DatumKind::Null,
+            ColumnType {
+                name: &column.name,
+                kind: column.data_type,
+            }
+        );
+
         // Check datum kind if this is a key column
         if is_key {
             ensure!(
@@ -1217,6 +1249,10 @@ impl SchemaEncoder {
     }
 
     pub fn encode(&self, schema: &Schema) -> Result<Vec<u8>> {
+        for column in schema.columns() {
+            Self::ensure_column_encodable(column)?;
+        }
+
         let pb_schema = common_pb::TableSchema::from(schema);
         let mut buf = Vec::with_capacity(1 + pb_schema.encoded_len() as usize);
         buf.push(self.version);
@@ -1236,6 +1272,19 @@ impl SchemaEncoder {
         Schema::try_from(pb_schema)
     }
 
+    /// Ensure the `column` can be encoded, returns the error about the column
+    /// otherwise.
+    fn ensure_column_encodable(column: &ColumnSchema) -> Result<()> {
+        if let Some(default_value) = &column.default_value {
+            serde_json::to_vec(default_value).context(EncodeColumnDefaultValue {
+                column: &column.name,
+                data_type: column.data_type,
+            })?;
+        }
+
+        Ok(())
+    }
+
     fn ensure_version(&self, version: u8) -> Result<()> {
         ensure!(
             self.version == version,
@@ -1286,6 +1335,33 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn test_null_column_type() {
+        let builder = Builder::new()
+            .auto_increment_column_id(true)
+            .add_key_column(
+                column_schema::Builder::new("timestamp".to_string(), DatumKind::Timestamp)
+                    .build()
+                    .expect("should succeed build column schema"),
+            )
+            .unwrap();
+        let null_column = column_schema::Builder::new("null_field".to_string(), DatumKind::Null)
+            .build()
+            .expect("should succeed build column schema");
+
+        let err = builder.add_normal_column(null_column).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Error::ColumnType { name, kind: DatumKind::Null, .. } if name == "null_field"
+            ),
+            "err:{}",
+            err
+        );
+        let msg = err.to_string();
+        assert!(msg.contains("name:null_field"), "{}", msg);
+    }
+
     #[test]
     fn test_schema_encoding() {
         let schema = build_test_schema();
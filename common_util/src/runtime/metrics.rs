@@ -1,7 +1,12 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
+use std::{sync::Arc, time::Instant};
+
 use lazy_static::lazy_static;
-use prometheus::{register_int_gauge_vec, IntGauge, IntGaugeVec};
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_gauge_vec, Histogram, HistogramVec,
+    IntGauge, IntGaugeVec,
+};
 
 lazy_static! {
     // Gauges:
@@ -17,6 +22,28 @@ lazy_static! {
         &["name"]
     )
         .unwrap();
+    static ref RUNTIME_BLOCKING_QUEUED_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "runtime_blocking_queued_gauge",
+        "spawned but not started blocking task number for runtime",
+        &["name"]
+    )
+        .unwrap();
+    static ref RUNTIME_BLOCKING_RUNNING_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "runtime_blocking_running_gauge",
+        "running blocking task number for runtime",
+        &["name"]
+    )
+        .unwrap();
+
+    // Histograms:
+    // Buckets: 0.0001, .., 0.0001 * 4^9
+    static ref RUNTIME_BLOCKING_WAIT_HISTOGRAM: HistogramVec = register_histogram_vec!(
+        "runtime_blocking_wait_duration",
+        "Histogram for time of blocking tasks waiting to start in seconds",
+        &["name"],
+        exponential_buckets(0.0001, 4.0, 10).unwrap()
+    )
+        .unwrap();
 }
 
 /// Runtime metrics.
@@ -25,6 +52,11 @@ pub struct Metrics {
     // Gauges:
     pub thread_alive_gauge: IntGauge,
     pub thread_idle_gauge: IntGauge,
+    pub blocking_queued_gauge: IntGauge,
+    pub blocking_running_gauge: IntGauge,
+
+    // Histograms:
+    pub blocking_wait_histogram: Histogram,
 }
 
 impl Metrics {
@@ -32,6 +64,9 @@ impl Metrics {
         Self {
             thread_alive_gauge: RUNTIME_THREAD_ALIVE_GAUGE.with_label_values(&[name]),
             thread_idle_gauge: RUNTIME_THREAD_IDLE_GAUGE.with_label_values(&[name]),
+            blocking_queued_gauge: RUNTIME_BLOCKING_QUEUED_GAUGE.with_label_values(&[name]),
+            blocking_running_gauge: RUNTIME_BLOCKING_RUNNING_GAUGE.with_label_values(&[name]),
+            blocking_wait_histogram: RUNTIME_BLOCKING_WAIT_HISTOGRAM.with_label_values(&[name]),
         }
     }
 
@@ -55,3 +90,44 @@ impl Metrics {
         self.thread_idle_gauge.dec();
     }
 }
+
+/// Tracks a blocking task from being spawned to finished, the task is counted
+/// as queued until `on_start()` is called.
+pub struct BlockingTaskTracker {
+    metrics: Arc<Metrics>,
+    spawn_time: Instant,
+    started: bool,
+}
+
+impl BlockingTaskTracker {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        metrics.blocking_queued_gauge.inc();
+
+        Self {
+            metrics,
+            spawn_time: Instant::now(),
+            started: false,
+        }
+    }
+
+    pub fn on_start(&mut self) {
+        self.started = true;
+        self.metrics.blocking_queued_gauge.dec();
+        self.metrics.blocking_running_gauge.inc();
+        self.metrics
+            .blocking_wait_histogram
+            .observe(self.spawn_time.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for BlockingTaskTracker {
+    // The task may be dropped without being started if the runtime is shutting
+    // down.
+    fn drop(&mut self) {
+        if self.started {
+            self.metrics.blocking_running_gauge.dec();
+        } else {
+            self.metrics.blocking_queued_gauge.dec();
+        }
+    }
+}
@@ -15,7 +15,7 @@ use tokio::{
     task::{JoinError, JoinHandle as TokioJoinHandle},
 };
 mod metrics;
-use metrics::Metrics;
+use metrics::{BlockingTaskTracker, Metrics};
 
 // TODO(yingwen): Use opaque error type
 #[derive(Debug, Snafu)]
@@ -67,13 +67,20 @@ impl Runtime {
 
     /// Run the provided function on an executor dedicated to blocking
     /// operations.
+    ///
+    /// The time waiting for a thread to run the function is recorded, see
+    /// [RuntimeStats].
     pub fn spawn_blocking<F, R>(&self, func: F) -> JoinHandle<R>
     where
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
+        let mut tracker = BlockingTaskTracker::new(self.metrics.clone());
         JoinHandle {
-            inner: self.rt.spawn_blocking(func),
+            inner: self.rt.spawn_blocking(move || {
+                tracker.on_start();
+                func()
+            }),
         }
     }
 
@@ -84,9 +91,14 @@ impl Runtime {
 
     /// Returns the runtime stats
     pub fn stats(&self) -> RuntimeStats {
+        let wait_histogram = &self.metrics.blocking_wait_histogram;
         RuntimeStats {
             alive_thread_num: self.metrics.thread_alive_gauge.get(),
             idle_thread_num: self.metrics.thread_idle_gauge.get(),
+            blocking_queued_num: self.metrics.blocking_queued_gauge.get(),
+            blocking_running_num: self.metrics.blocking_running_gauge.get(),
+            blocking_started_num: wait_histogram.get_sample_count(),
+            blocking_wait_secs: wait_histogram.get_sample_sum(),
         }
     }
 }
@@ -112,9 +124,19 @@ impl<T> Future for JoinHandle<T> {
 }
 
 /// Runtime statistics
+#[derive(Debug, Clone)]
 pub struct RuntimeStats {
     pub alive_thread_num: i64,
     pub idle_thread_num: i64,
+    /// Number of blocking tasks spawned but not started, a growing number
+    /// means the runtime is saturated.
+    pub blocking_queued_num: i64,
+    /// Number of blocking tasks running.
+    pub blocking_running_num: i64,
+    /// Number of blocking tasks started.
+    pub blocking_started_num: u64,
+    /// Total time of the started blocking tasks waiting to start in seconds.
+    pub blocking_wait_secs: f64,
 }
 
 pub struct Builder {
@@ -150,6 +172,12 @@ impl Builder {
         self
     }
 
+    /// Sets the max number of threads spawned to run the blocking tasks.
+    pub fn max_blocking_threads(&mut self, val: usize) -> &mut Self {
+        self.builder.max_blocking_threads(val);
+        self
+    }
+
     /// Sets name of threads spawned by the Runtime thread pool
     pub fn thread_name(&mut self, val: impl Into<String>) -> &mut Self {
         self.thread_name = val.into();
@@ -232,6 +260,47 @@ mod tests {
         assert_eq!(4, s.idle_thread_num);
     }
 
+    #[test]
+    fn test_blocking_stats() {
+        let rt = Builder::default()
+            .worker_threads(1)
+            .max_blocking_threads(1)
+            .thread_name("test_blocking_stats")
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // Only one blocking thread, the tasks spawned later are queued.
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let first = rt.spawn_blocking(move || {
+            rx.recv().unwrap();
+        });
+        let others: Vec<_> = (0..3)
+            .map(|_| rt.spawn_blocking(|| thread::sleep(Duration::from_millis(10))))
+            .collect();
+
+        thread::sleep(Duration::from_millis(50));
+        let s = rt.stats();
+        assert_eq!(1, s.blocking_running_num);
+        assert_eq!(3, s.blocking_queued_num);
+        assert_eq!(1, s.blocking_started_num);
+
+        tx.send(()).unwrap();
+        rt.block_on(async move {
+            first.await.unwrap();
+            for handle in others {
+                handle.await.unwrap();
+            }
+        });
+
+        let s = rt.stats();
+        assert_eq!(0, s.blocking_running_num);
+        assert_eq!(0, s.blocking_queued_num);
+        assert_eq!(4, s.blocking_started_num);
+        // The queued tasks waited for the first task.
+        assert!(s.blocking_wait_secs >= 0.05, "stats:{:?}", s);
+    }
+
     #[test]
     fn block_on_async() {
         let rt = rt();
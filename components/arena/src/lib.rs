@@ -8,4 +8,4 @@ mod mono_inc;
 
 pub use arena_trait::{Arena, BasicStats, Collector, CollectorRef};
 pub use fixed_size::FixedSizeArena;
-pub use mono_inc::{MonoIncArena, NoopCollector};
+pub use mono_inc::{BlockGrowth, MonoIncArena, NoopCollector};
@@ -23,6 +23,19 @@ impl Collector for NoopCollector {
 
 const DEFAULT_ALIGN: usize = 8;
 
+/// Growth policy of the size of the regular blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockGrowth {
+    /// All the regular blocks are of the initial block size.
+    Fixed,
+    /// Every new regular block doubles the size of the last one, up to
+    /// `max_block_size`.
+    ///
+    /// Allocations larger than the current regular block still go to the
+    /// special blocks, so the threshold grows with the block size.
+    Geometric { max_block_size: usize },
+}
+
 /// A thread-safe arena. All allocated memory is aligned to 8. Organizes its
 /// allocated memory as blocks.
 #[derive(Clone)]
@@ -35,14 +48,29 @@ impl MonoIncArena {
         Self {
             core: Arc::new(Mutex::new(ArenaCore::new(
                 regular_block_size,
+                BlockGrowth::Fixed,
                 Arc::new(NoopCollector {}),
             ))),
         }
     }
 
     pub fn with_collector(regular_block_size: usize, collector: CollectorRef) -> Self {
+        Self::with_growth(regular_block_size, BlockGrowth::Fixed, collector)
+    }
+
+    /// Create an arena whose regular blocks start from `regular_block_size`
+    /// and grow by the `growth` policy.
+    pub fn with_growth(
+        regular_block_size: usize,
+        growth: BlockGrowth,
+        collector: CollectorRef,
+    ) -> Self {
         Self {
-            core: Arc::new(Mutex::new(ArenaCore::new(regular_block_size, collector))),
+            core: Arc::new(Mutex::new(ArenaCore::new(
+                regular_block_size,
+                growth,
+                collector,
+            ))),
         }
     }
 }
@@ -66,6 +94,7 @@ impl Arena for MonoIncArena {
 struct ArenaCore {
     collector: CollectorRef,
     regular_layout: Layout,
+    growth: BlockGrowth,
     regular_blocks: Vec<Block>,
     special_blocks: Vec<Block>,
     stats: BasicStats,
@@ -74,7 +103,7 @@ struct ArenaCore {
 impl ArenaCore {
     /// # Safety
     /// Required property is tested in debug assertions.
-    fn new(regular_block_size: usize, collector: CollectorRef) -> Self {
+    fn new(regular_block_size: usize, growth: BlockGrowth, collector: CollectorRef) -> Self {
         debug_assert_ne!(DEFAULT_ALIGN, 0);
         debug_assert_eq!(DEFAULT_ALIGN & (DEFAULT_ALIGN - 1), 0);
         // TODO(yingwen): Avoid panic.
@@ -87,6 +116,7 @@ impl ArenaCore {
         Self {
             collector,
             regular_layout,
+            growth,
             regular_blocks,
             special_blocks,
             stats: BasicStats {
@@ -122,6 +152,7 @@ impl ArenaCore {
         if let Some(ptr) = self.try_alloc(layout) {
             ptr
         } else {
+            self.grow_regular_layout();
             Self::add_new_block(
                 self.regular_layout,
                 &mut self.regular_blocks,
@@ -132,6 +163,19 @@ impl ArenaCore {
         }
     }
 
+    /// Enlarge the layout of the next regular block by the growth policy.
+    fn grow_regular_layout(&mut self) {
+        if let BlockGrowth::Geometric { max_block_size } = self.growth {
+            let size = self.regular_layout.size();
+            let new_size = size.saturating_mul(2).min(max_block_size).max(size);
+            if new_size != size {
+                // The size is bounded by `max_block_size`, which is far from overflow
+                // after aligned.
+                self.regular_layout = Layout::from_size_align(new_size, DEFAULT_ALIGN).unwrap();
+            }
+        }
+    }
+
     /// # Safety
     /// `regular_blocks` vector is guaranteed to contains at least one element.
     fn try_alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
@@ -327,6 +371,34 @@ mod test {
         assert_eq!(total_used, arena.stats().bytes_used());
     }
 
+    #[test]
+    fn alloc_with_geometric_growth() {
+        let arena = MonoIncArena::with_growth(
+            128,
+            BlockGrowth::Geometric {
+                max_block_size: 512,
+            },
+            Arc::new(NoopCollector {}),
+        );
+
+        // Size is 80, fill the blocks of 128, 256, 512 and 512.
+        let layout_slice = Layout::new::<[u64; 10]>().align_to(8).unwrap();
+        for _ in 0..16 {
+            let buf = arena.alloc(layout_slice);
+            consume_buf_as_u64_slice(buf, 10);
+        }
+
+        assert_eq!(128 + 256 + 512 + 512, arena.stats().bytes_allocated());
+        assert_eq!(1280, arena.stats().bytes_used());
+
+        // Fixed growth keeps allocating the blocks of the initial size.
+        let arena = MonoIncArena::with_growth(128, BlockGrowth::Fixed, Arc::new(NoopCollector {}));
+        for _ in 0..16 {
+            arena.alloc(layout_slice);
+        }
+        assert_eq!(2048, arena.stats().bytes_allocated());
+    }
+
     #[test]
     fn unaligned_alloc_request() {
         let arena = MonoIncArena::new(1024);
@@ -37,6 +37,13 @@ enum Error {
     #[snafu(display("Failed to delete object at path:{}, err:{}", path, source))]
     DeleteObject { path: String, source: AliyunError },
 
+    #[snafu(display("Failed to copy object from:{} to:{}, err:{}", from, to, source))]
+    CopyObject {
+        from: String,
+        to: String,
+        source: AliyunError,
+    },
+
     #[snafu(display("Operation {} is not implemented", op))]
     Unimplemented { op: String },
 }
@@ -103,10 +110,8 @@ impl ObjectStore for AliyunOSS {
         &self,
         _location: &Path,
     ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
-        Err(Error::Unimplemented {
-            op: "put_multipart".to_string(),
-        }
-        .into())
+        // Callers may fall back to `put` on this error.
+        Err(OssError::NotImplemented)
     }
 
     async fn abort_multipart(&self, _location: &Path, _multipart_id: &MultipartId) -> Result<()> {
@@ -189,11 +194,21 @@ impl ObjectStore for AliyunOSS {
         .into())
     }
 
-    async fn copy(&self, _from: &Path, _to: &Path) -> Result<()> {
-        Err(Error::Unimplemented {
-            op: "copy".to_string(),
-        }
-        .into())
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.oss
+            .copy_object_from_object(
+                &from.to_string(),
+                &to.to_string(),
+                None::<HashMap<String, String>>,
+                None,
+            )
+            .await
+            .with_context(|| CopyObject {
+                from: from.to_string(),
+                to: to.to_string(),
+            })?;
+
+        Ok(())
     }
 
     async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> Result<()> {
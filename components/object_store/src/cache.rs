@@ -5,8 +5,8 @@
 //!
 //! - Local Store: a local file system cache
 //! - Remote Store: a remote OSS service
-//! - LRU Manager: a cache eviction policy (LRU) to keep the local store size
-//!   under control.
+//! - Eviction Manager: a cache eviction policy (LRU by default, or LFU/FIFO) to
+//!   keep the local store size under control.
 //!
 //! ## Read
 //! On serving a read request, [CachedStore] will first check if the object
@@ -39,30 +39,100 @@
 //!
 //! ## Restart
 //! To suit some deploy scenarios that aren't stateless, [ObjectStore] will try
-//! to load all existing entries from `LocalStore` to `LRU Manager`.
+//! to load all existing entries from `LocalStore` to `Eviction Manager`.
 //!
 //! ## Purge
 //! Both read and write operations may trigger purge on `LocalStore`. The purge
-//! policy is defined by the `LRU Manager`. It will get a delete list from `LRU
-//! Manager` and delete the list from `LocalStore`.
+//! policy is defined by the `Eviction Manager`, see [EvictionPolicyType]. It
+//! will get a delete list from `Eviction Manager` and delete the list from
+//! `LocalStore`.
 //!
 //! To ensure the total size of `LocalStore` is always less than the threshold,
 //! [CachedStore] will first purge enough space for the incoming new objects.
+//!
+//! ## Expiry
+//! If `ttl_secs` is configured, entries cached longer than the ttl are treated
+//! as missing on access and re-fetched from `RemoteStore`. Expired entries are
+//! also purged first when making space for new objects.
+//!
+//! ## Coherence
+//! The cached objects are keyed by path only, so an object overwritten in
+//! `RemoteStore` by others would be served stale. If `coherence_check` is
+//! enabled, the [ObjectMeta] (size and last modified time) of the remote object
+//! is recorded on caching, and revalidated by a `head` to `RemoteStore` on
+//! every hit. The mismatched entry is dropped and re-fetched. Entries loaded
+//! from `LocalStore` on restart have no recorded meta and are re-fetched on
+//! first access.
+//!
+//! ## Warm Up
+//! Hot objects can be fetched into `LocalStore` in background before serving
+//! traffic by [CachedStore::warm_up], e.g. after restart.
+//!
+//! ## Error Isolation
+//! `LocalStore` is only a cache, so its failures (e.g. the disk is full or
+//! broken) never fail the request. They are logged and counted in
+//! [CacheFailureStats], and the request is served by `RemoteStore` instead.
 
-use std::{fmt::Display, ops::Range, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use chrono::{DateTime, Utc};
-use futures::{future::try_join_all, lock::Mutex, stream::BoxStream, TryStreamExt};
-use lru::LruCache;
+use chrono::{DateTime, Duration, Utc};
+use futures::{
+    future::try_join_all,
+    lock::Mutex,
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use log::{debug, warn};
 use serde_derive::Deserialize;
-use tokio::io::AsyncWrite;
+use tokio::{
+    io::AsyncWrite,
+    task::{JoinError, JoinHandle},
+};
 use upstream::{path::Path, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result};
 
+use crate::{
+    eviction::{EvictionPolicyRef, EvictionPolicyType},
+    util,
+};
+
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub struct CachedStoreConfig {
     pub max_cache_size: usize,
+    /// Entries cached longer than this duration (in seconds) are expired, no
+    /// expiry if it is not set.
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// Policy to choose the entries to purge when the cache is full.
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicyType,
+    /// Revalidate the cached objects against remote store on every hit, to
+    /// avoid serving objects overwritten in remote store.
+    #[serde(default)]
+    pub coherence_check: bool,
+}
+
+/// Clock to decide whether an entry is expired.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
 }
 
 #[derive(Debug)]
@@ -70,6 +140,16 @@ pub struct CachedStore {
     local_store: Arc<dyn ObjectStore>,
     remote_store: Arc<dyn ObjectStore>,
     state: Mutex<CacheState>,
+    coherence_check: bool,
+    read_failures: AtomicUsize,
+    write_failures: AtomicUsize,
+}
+
+/// Statistics of the failures of local store, which are bypassed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheFailureStats {
+    pub read_failures: usize,
+    pub write_failures: usize,
 }
 
 impl CachedStore {
@@ -77,9 +157,24 @@ impl CachedStore {
         local_store: Arc<dyn ObjectStore>,
         remote_store: Arc<dyn ObjectStore>,
         config: CachedStoreConfig,
+    ) -> Result<Self> {
+        Self::init_with_clock(local_store, remote_store, config, Arc::new(SystemClock)).await
+    }
+
+    pub async fn init_with_clock(
+        local_store: Arc<dyn ObjectStore>,
+        remote_store: Arc<dyn ObjectStore>,
+        config: CachedStoreConfig,
+        clock: Arc<dyn Clock>,
     ) -> Result<Self> {
         let local_list: Vec<ObjectMeta> = local_store.list(None).await?.try_collect().await?;
-        let mut state = CacheState::new(config.max_cache_size, local_list);
+        let mut state = CacheState::new(config.max_cache_size, local_list, config.eviction_policy);
+        if let Some(ttl) = config
+            .ttl_secs
+            .and_then(|v| Duration::from_std(std::time::Duration::from_secs(v)).ok())
+        {
+            state.set_ttl(ttl, clock);
+        }
         let result = state.reserve(0);
         if !result.removed_path.is_empty() {
             Self::remove_paths(local_store.as_ref(), &result.removed_path).await?;
@@ -89,12 +184,47 @@ impl CachedStore {
             local_store,
             remote_store,
             state: Mutex::new(state),
+            coherence_check: config.coherence_check,
+            read_failures: AtomicUsize::new(0),
+            write_failures: AtomicUsize::new(0),
         })
     }
 
+    pub fn failure_stats(&self) -> CacheFailureStats {
+        CacheFailureStats {
+            read_failures: self.read_failures.load(Ordering::Relaxed),
+            write_failures: self.write_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    fn on_read_failure(&self, location: &Path, err: &upstream::Error) {
+        warn!(
+            "Failed to read from local store, bypass it, location:{}, err:{}",
+            location, err
+        );
+        self.read_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_write_failure(&self, location: &Path, err: &upstream::Error) {
+        warn!(
+            "Failed to write to local store, bypass it, location:{}, err:{}",
+            location, err
+        );
+        self.write_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Try putting object to local store. If local store cannot make enough
-    /// space for the object, this function will skip putting it and return Ok.
-    async fn try_put_local(&self, location: &Path, bytes: Bytes) -> Result<()> {
+    /// space for the object, this function will skip putting it and return
+    /// Ok(false).
+    ///
+    /// `remote_meta` is the meta of the object in remote store, used by the
+    /// coherence check.
+    async fn try_put_local(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        remote_meta: Option<ObjectMeta>,
+    ) -> Result<bool> {
         let required_size = bytes.len();
 
         let guard = {
@@ -108,7 +238,7 @@ impl CachedStore {
 
         // cannot reserve enough space, skip to put local store
         if !guard.is_success() {
-            return Ok(());
+            return Ok(false);
         }
 
         let result = self.local_store.put(location, bytes.clone()).await;
@@ -116,9 +246,188 @@ impl CachedStore {
             let _ = self.local_store.delete(location).await;
             self.state.lock().await.cancel_reserve(guard);
         } else {
-            self.state.lock().await.confirm_reserve(guard, location);
+            self.state
+                .lock()
+                .await
+                .confirm_reserve(guard, location, remote_meta);
         }
-        result
+        result.map(|_| true)
+    }
+
+    /// Put the object fetched from remote store into local store, returns
+    /// whether it is cached. The failure of local store is bypassed.
+    async fn put_local_or_bypass(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        remote_meta: Option<ObjectMeta>,
+    ) -> bool {
+        match self.try_put_local(location, bytes, remote_meta).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                self.on_write_failure(location, &e);
+                false
+            }
+        }
+    }
+
+    /// Returns whether the object at `location` can be served by local store,
+    /// the failure of local store is treated as not cached.
+    async fn is_cached_or_bypass(&self, location: &Path) -> bool {
+        match self.is_cached(location).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                self.on_read_failure(location, &e);
+                false
+            }
+        }
+    }
+
+    /// Returns whether the object at `location` can be served by local store,
+    /// the cached object is revalidated against remote store if coherence
+    /// check is enabled.
+    async fn check_cached(&self, location: &Path) -> Result<bool> {
+        if !self.is_cached_or_bypass(location).await {
+            return Ok(false);
+        }
+        if !self.coherence_check {
+            return Ok(true);
+        }
+
+        self.revalidate(location).await
+    }
+
+    /// Returns whether the cached object at `location` is the same as the one
+    /// in remote store, the stale entry is dropped.
+    async fn revalidate(&self, location: &Path) -> Result<bool> {
+        let current_meta = match self.remote_store.head(location).await {
+            Ok(v) => Some(v),
+            Err(e) if crate::is_not_found(&e) => None,
+            Err(e) => return Err(e),
+        };
+        let coherent = {
+            let state = self.state.lock().await;
+            match (state.remote_meta(location), &current_meta) {
+                (Some(cached), Some(current)) => !util::is_modified(cached, current),
+                _ => false,
+            }
+        };
+
+        if !coherent {
+            debug!(
+                "Cached object is stale, drop it, location:{}, remote meta:{:?}",
+                location, current_meta
+            );
+            if let Err(e) = self.invalidate(location).await {
+                self.on_write_failure(location, &e);
+            }
+        }
+
+        Ok(coherent)
+    }
+
+    /// Drop the entry of `location` as local store fails to serve it.
+    async fn drop_broken_entry(&self, location: &Path, err: &upstream::Error) {
+        self.on_read_failure(location, err);
+        if self.state.lock().await.try_remove(location) {
+            let _ = self.local_store.delete(location).await;
+        }
+    }
+
+    /// Fetch the whole object from remote store and try to cache it.
+    async fn fetch_remote(&self, location: &Path) -> Result<Bytes> {
+        // The meta is fetched before the object, so an overwrite in between is
+        // detected by the next revalidation.
+        let remote_meta = if self.coherence_check {
+            Some(self.remote_store.head(location).await?)
+        } else {
+            None
+        };
+        let remote_obj = self.remote_store.get(location).await?;
+        let bytes = remote_obj.bytes().await?;
+        self.put_local_or_bypass(location, bytes.clone(), remote_meta)
+            .await;
+
+        Ok(bytes)
+    }
+
+    /// Fetch the hot `blocks` into local store in background, at most
+    /// `concurrency` blocks are fetched at the same time.
+    ///
+    /// The returned [WarmUpHandle] can be used to cancel the warm up or wait
+    /// for its result.
+    pub fn warm_up(
+        self: &Arc<Self>,
+        blocks: Vec<(Path, Range<usize>)>,
+        concurrency: usize,
+    ) -> WarmUpHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let store = self.clone();
+        let task_cancelled = cancelled.clone();
+
+        let handle = tokio::spawn(async move {
+            stream::iter(blocks)
+                .map(|(location, range)| {
+                    let store = store.clone();
+                    let cancelled = task_cancelled.clone();
+                    async move {
+                        if cancelled.load(Ordering::Relaxed) {
+                            return WarmUpOutcome::Cancelled;
+                        }
+
+                        match store.get_range(&location, range).await {
+                            Ok(_) => WarmUpOutcome::Succeeded,
+                            Err(_) => WarmUpOutcome::Failed,
+                        }
+                    }
+                })
+                .buffer_unordered(concurrency.max(1))
+                .fold(WarmUpStats::default(), |mut stats, outcome| async move {
+                    stats.record(outcome);
+                    stats
+                })
+                .await
+        });
+
+        WarmUpHandle { cancelled, handle }
+    }
+
+    /// Returns true if the object at `location` is cached in local store. The
+    /// expired object is removed from local store.
+    async fn is_cached(&self, location: &Path) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        match state.lookup(location) {
+            Lookup::Hit => Ok(true),
+            Lookup::Miss => Ok(false),
+            Lookup::Expired => {
+                Self::remove_paths(self.local_store.as_ref(), &[location.clone()]).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Drop the cached object at `location` from local store, the remote store
+    /// is untouched. Returns whether the object was cached.
+    pub async fn invalidate(&self, location: &Path) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        if !state.try_remove(location) {
+            return Ok(false);
+        }
+        Self::remove_paths(self.local_store.as_ref(), &[location.clone()]).await?;
+
+        Ok(true)
+    }
+
+    /// Drop all cached objects under `prefix` from local store, the remote
+    /// store is untouched. The prefix is matched by path segments as
+    /// [ObjectStore::list] does, e.g. `a/b` matches `a/b/c` but not `a/bc`.
+    /// Returns the number of invalidated objects.
+    pub async fn invalidate_prefix(&self, prefix: &Path) -> Result<usize> {
+        let mut state = self.state.lock().await;
+        let removed = state.remove_prefix(prefix);
+        Self::remove_paths(self.local_store.as_ref(), &removed).await?;
+
+        Ok(removed.len())
     }
 
     async fn remove_paths(store: &dyn ObjectStore, paths: &[Path]) -> Result<()> {
@@ -134,9 +443,23 @@ impl CachedStore {
 #[async_trait]
 impl ObjectStore for CachedStore {
     async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
-        let _ = self.try_put_local(location, bytes.clone()).await;
+        self.put_local_or_bypass(location, bytes.clone(), None)
+            .await;
 
-        self.remote_store.put(location, bytes).await
+        self.remote_store.put(location, bytes).await?;
+        if self.coherence_check {
+            // Record the meta of the new object, otherwise the entry is treated as
+            // stale on next hit.
+            match self.remote_store.head(location).await {
+                Ok(meta) => self.state.lock().await.set_remote_meta(location, meta),
+                Err(e) => warn!(
+                    "Failed to head the object just put, location:{}, err:{}",
+                    location, e
+                ),
+            }
+        }
+
+        Ok(())
     }
 
     async fn put_multipart(
@@ -153,24 +476,33 @@ impl ObjectStore for CachedStore {
     }
 
     async fn get(&self, location: &Path) -> Result<GetResult> {
-        if self.state.lock().await.contains(location) {
-            self.local_store.get(location).await
-        } else {
-            let remote_obj = self.remote_store.get(location).await?;
-            let bytes = remote_obj.bytes().await?;
-            let _ = self.try_put_local(location, bytes).await?;
-            self.local_store.get(location).await
+        if self.check_cached(location).await? {
+            match self.local_store.get(location).await {
+                Ok(v) => return Ok(v),
+                Err(e) => self.drop_broken_entry(location, &e).await,
+            }
         }
+
+        let bytes = self.fetch_remote(location).await?;
+        Ok(GetResult::Stream(Box::pin(stream::once(async move {
+            Ok(bytes)
+        }))))
     }
 
     async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
-        if self.state.lock().await.contains(location) {
-            self.local_store.get_range(location, range).await
+        if self.check_cached(location).await? {
+            match self.local_store.get_range(location, range.clone()).await {
+                Ok(v) => return Ok(v),
+                Err(e) => self.drop_broken_entry(location, &e).await,
+            }
+        }
+
+        let bytes = self.fetch_remote(location).await?;
+        if range.start <= range.end && range.end <= bytes.len() {
+            Ok(bytes.slice(range))
         } else {
-            let remote_obj = self.remote_store.get(location).await?;
-            let bytes = remote_obj.bytes().await?;
-            let _ = self.try_put_local(location, bytes).await?;
-            self.local_store.get_range(location, range).await
+            // Let the remote store report the invalid range.
+            self.remote_store.get_range(location, range).await
         }
     }
 
@@ -180,15 +512,17 @@ impl ObjectStore for CachedStore {
     }
 
     async fn delete(&self, location: &Path) -> Result<()> {
-        let mut tasks = Vec::with_capacity(2);
-        if self.state.lock().await.try_remove(location) {
-            tasks.push(self.local_store.delete(location));
-        }
-        tasks.push(self.remote_store.delete(location));
-        let result = futures::future::join_all(tasks.into_iter()).await;
-        result.into_iter().collect::<Result<Vec<_>>>()?;
+        let cached = self.state.lock().await.try_remove(location);
+        let delete_local = async {
+            if cached {
+                if let Err(e) = self.local_store.delete(location).await {
+                    self.on_write_failure(location, &e);
+                }
+            }
+        };
+        let (_, result) = futures::join!(delete_local, self.remote_store.delete(location));
 
-        Ok(())
+        result
     }
 
     async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
@@ -218,40 +552,187 @@ impl Display for CachedStore {
     }
 }
 
+/// Statistics of a warm up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUpStats {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Blocks skipped as the warm up is cancelled.
+    pub cancelled: usize,
+}
+
+impl WarmUpStats {
+    fn record(&mut self, outcome: WarmUpOutcome) {
+        match outcome {
+            WarmUpOutcome::Succeeded => self.succeeded += 1,
+            WarmUpOutcome::Failed => self.failed += 1,
+            WarmUpOutcome::Cancelled => self.cancelled += 1,
+        }
+    }
+}
+
+enum WarmUpOutcome {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Handle of a background warm up.
+pub struct WarmUpHandle {
+    cancelled: Arc<AtomicBool>,
+    handle: JoinHandle<WarmUpStats>,
+}
+
+impl WarmUpHandle {
+    /// Cancel the warm up, blocks not fetched yet will be skipped.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the warm up to finish.
+    pub async fn wait(self) -> std::result::Result<WarmUpStats, JoinError> {
+        self.handle.await
+    }
+}
+
 struct CacheState {
     max_size: usize,
     total_size: usize,
-    cached_entries: LruCache<String, ObjectMeta>,
+    cached_entries: HashMap<String, ObjectMeta>,
+    /// Metas of the cached objects in remote store, only recorded if coherence
+    /// check is enabled.
+    remote_metas: HashMap<String, ObjectMeta>,
+    policy: EvictionPolicyRef,
+    /// Ttl of the cached entries and the clock to check it, `last_modified` of
+    /// the cached [ObjectMeta] is the time it was cached.
+    expiry: Option<(Duration, Arc<dyn Clock>)>,
+}
+
+/// Result of looking up an entry in [CacheState].
+enum Lookup {
+    Hit,
+    Miss,
+    /// The entry is expired and has been removed.
+    Expired,
 }
 
 impl CacheState {
-    fn new(max_size: usize, metas: Vec<ObjectMeta>) -> Self {
+    fn new(max_size: usize, metas: Vec<ObjectMeta>, policy_type: EvictionPolicyType) -> Self {
         let total_size = metas.iter().map(|m| m.size).sum();
-        let mut cached_entries = LruCache::unbounded();
+        let mut cached_entries = HashMap::with_capacity(metas.len());
+        let mut policy = policy_type.build();
         for m in metas {
-            cached_entries.put(m.location.to_string(), m);
+            let key = m.location.to_string();
+            policy.on_insert(&key);
+            cached_entries.insert(key, m);
         }
 
         Self {
             max_size,
             total_size,
             cached_entries,
+            remote_metas: HashMap::new(),
+            policy,
+            expiry: None,
+        }
+    }
+
+    fn set_ttl(&mut self, ttl: Duration, clock: Arc<dyn Clock>) {
+        self.expiry = Some((ttl, clock));
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        match &self.expiry {
+            Some((_, clock)) => clock.now(),
+            None => Utc::now(),
         }
     }
 
+    /// Entries cached no later than the returned time are expired, returns
+    /// None if no entry can be expired.
+    fn expire_deadline(&self) -> Option<DateTime<Utc>> {
+        self.expiry
+            .as_ref()
+            .and_then(|(ttl, clock)| clock.now().checked_sub_signed(*ttl))
+    }
+
     /// Try to remove an entry. Returns whether the entry was removed.
     fn try_remove(&mut self, location: &Path) -> bool {
-        let removed = self.cached_entries.pop(&location.to_string());
+        let key = location.to_string();
+        let removed = self.cached_entries.remove(&key);
         if let Some(removed) = &removed {
             self.total_size -= removed.size;
+            self.policy.on_remove(&key);
         }
+        self.remote_metas.remove(&key);
         removed.is_some()
     }
 
-    /// Check whether the local storage contains the given location. This will
-    /// be treat as a cache read operation.
-    fn contains(&mut self, location: &Path) -> bool {
-        self.cached_entries.get(&location.to_string()).is_some()
+    fn remote_meta(&self, location: &Path) -> Option<&ObjectMeta> {
+        self.remote_metas.get(location.as_ref())
+    }
+
+    /// Record the meta of the cached object in remote store, ignored if the
+    /// object is not cached.
+    fn set_remote_meta(&mut self, location: &Path, meta: ObjectMeta) {
+        let key = location.to_string();
+        if self.cached_entries.contains_key(&key) {
+            self.remote_metas.insert(key, meta);
+        }
+    }
+
+    /// Look up the entry of given location, the expired entry is removed. This
+    /// will be treat as a cache read operation.
+    fn lookup(&mut self, location: &Path) -> Lookup {
+        let deadline = self.expire_deadline();
+        let key = location.to_string();
+        let expired = match self.cached_entries.get(&key) {
+            Some(meta) => deadline.map_or(false, |v| meta.last_modified <= v),
+            None => return Lookup::Miss,
+        };
+
+        if expired {
+            self.try_remove(location);
+            Lookup::Expired
+        } else {
+            self.policy.on_access(&key);
+            Lookup::Hit
+        }
+    }
+
+    /// Remove all expired entries and returns their paths.
+    fn purge_expired(&mut self) -> Vec<Path> {
+        let deadline = match self.expire_deadline() {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+
+        let expired = self
+            .cached_entries
+            .iter()
+            .filter(|(_, meta)| meta.last_modified <= deadline)
+            .map(|(_, meta)| meta.location.clone())
+            .collect::<Vec<_>>();
+        for location in &expired {
+            self.try_remove(location);
+        }
+
+        expired
+    }
+
+    /// Remove all entries under the `prefix` and returns their paths.
+    fn remove_prefix(&mut self, prefix: &Path) -> Vec<Path> {
+        let matched = self
+            .cached_entries
+            .iter()
+            .filter(|(_, meta)| meta.location.prefix_match(prefix).is_some())
+            .map(|(_, meta)| meta.location.clone())
+            .collect::<Vec<_>>();
+        for location in &matched {
+            self.try_remove(location);
+        }
+
+        matched
     }
 
     /// Reserve space for a new entry. Returns paths that need to be removed and
@@ -270,12 +751,17 @@ impl CacheState {
             };
         }
 
-        let mut removed = Vec::new();
+        // Expired entries are purged before the victims of the eviction policy.
+        let mut removed = self.purge_expired();
         while self.total_size + size > self.max_size {
             // try to pop a cached entry.
-            let popped = self.cached_entries.pop_lru();
-            if let Some((_, meta)) = popped {
+            let popped = self
+                .policy
+                .pop_victim()
+                .and_then(|key| self.cached_entries.remove(&key));
+            if let Some(meta) = popped {
                 self.total_size -= meta.size;
+                self.remote_metas.remove(meta.location.as_ref());
                 removed.push(meta.location);
             } else {
                 return ReserveResult {
@@ -299,20 +785,31 @@ impl CacheState {
         }
     }
 
-    fn confirm_reserve(&mut self, guard: ReserveResult, location: &Path) {
+    fn confirm_reserve(
+        &mut self,
+        guard: ReserveResult,
+        location: &Path,
+        remote_meta: Option<ObjectMeta>,
+    ) {
         if guard.size.is_none() {
             return;
         }
         let size = guard.size.unwrap();
-        let prev_cache = self.cached_entries.push(
-            location.to_string(),
+        let key = location.to_string();
+        self.policy.on_insert(&key);
+        match remote_meta {
+            Some(meta) => self.remote_metas.insert(key.clone(), meta),
+            None => self.remote_metas.remove(&key),
+        };
+        let prev_cache = self.cached_entries.insert(
+            key,
             ObjectMeta {
                 location: location.to_owned(),
-                last_modified: DateTime::<Utc>::MIN_UTC,
+                last_modified: self.now(),
                 size,
             },
         );
-        if let Some((_, meta)) = prev_cache {
+        if let Some(meta) = prev_cache {
             self.total_size -= meta.size;
         }
     }
@@ -344,12 +841,24 @@ mod test {
     use super::*;
 
     async fn prepare_cache(max_cache_size: usize) -> CachedStore {
+        prepare_cache_with_policy(max_cache_size, EvictionPolicyType::Lru).await
+    }
+
+    async fn prepare_cache_with_policy(
+        max_cache_size: usize,
+        eviction_policy: EvictionPolicyType,
+    ) -> CachedStore {
         let local_path = tempdir().unwrap();
         let remote_path = tempdir().unwrap();
 
         let local_store = Arc::new(LocalFileSystem::new_with_prefix(local_path.path()).unwrap());
         let remote_store = Arc::new(LocalFileSystem::new_with_prefix(remote_path.path()).unwrap());
-        let config = CachedStoreConfig { max_cache_size };
+        let config = CachedStoreConfig {
+            max_cache_size,
+            ttl_secs: None,
+            eviction_policy,
+            coherence_check: false,
+        };
 
         CachedStore::init(local_store, remote_store, config)
             .await
@@ -370,6 +879,55 @@ mod test {
         assert!(result.is_err());
     }
 
+    /// Put 4 objects to fill the cache, read `0.bin` twice and the others once,
+    /// then put a new object, returns the objects left in the local store.
+    async fn cached_after_eviction(eviction_policy: EvictionPolicyType) -> Vec<String> {
+        let store = prepare_cache_with_policy(4096, eviction_policy).await;
+        for i in 0..4 {
+            let location = Path::from(format!("{}.bin", i));
+            store
+                .put(&location, Bytes::from_static(&[0; 1024]))
+                .await
+                .unwrap();
+        }
+        for i in [0, 0, 1, 2, 3] {
+            let location = Path::from(format!("{}.bin", i));
+            store.get(&location).await.unwrap();
+        }
+        store
+            .put(&Path::from("4.bin"), Bytes::from_static(&[0; 1024]))
+            .await
+            .unwrap();
+
+        let mut cached = store
+            .state
+            .lock()
+            .await
+            .cached_entries
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        cached.sort_unstable();
+        cached
+    }
+
+    #[tokio::test]
+    async fn evict_by_policy() {
+        assert_eq!(
+            vec!["1.bin", "2.bin", "3.bin", "4.bin"],
+            cached_after_eviction(EvictionPolicyType::Lru).await
+        );
+        // `0.bin` is the most frequently used.
+        assert_eq!(
+            vec!["0.bin", "2.bin", "3.bin", "4.bin"],
+            cached_after_eviction(EvictionPolicyType::Lfu).await
+        );
+        assert_eq!(
+            vec!["1.bin", "2.bin", "3.bin", "4.bin"],
+            cached_after_eviction(EvictionPolicyType::Fifo).await
+        );
+    }
+
     #[tokio::test]
     async fn large_than_capacity() {
         let store = prepare_cache(4096).await;
@@ -383,7 +941,7 @@ mod test {
 
     #[test]
     fn reserve_but_not_consumed() {
-        let mut state = CacheState::new(40960, Vec::new());
+        let mut state = CacheState::new(40960, Vec::new(), EvictionPolicyType::Lru);
         let mut guards = Vec::with_capacity(10);
         for _ in 0..10 {
             let guard = state.reserve(4096);
@@ -392,7 +950,7 @@ mod test {
         }
         assert_eq!(state.total_size, 4096 * 10);
         for guard in guards {
-            state.confirm_reserve(guard, &Path::from("object.bin"));
+            state.confirm_reserve(guard, &Path::from("object.bin"), None);
         }
         assert_eq!(state.total_size, 4096);
     }
@@ -431,6 +989,9 @@ mod test {
         let remote_store = Arc::new(LocalFileSystem::new_with_prefix(remote_path.path()).unwrap());
         let config = CachedStoreConfig {
             max_cache_size: 4096,
+            ttl_secs: None,
+            eviction_policy: EvictionPolicyType::Lru,
+            coherence_check: false,
         };
 
         for i in 0..5 {
@@ -448,4 +1009,363 @@ mod test {
         assert_eq!(store.state.lock().await.cached_entries.len(), 4);
         assert_eq!(store.local_store.list(None).await.unwrap().count().await, 4);
     }
+
+    #[tokio::test]
+    async fn warm_up_hot_blocks() {
+        let store = Arc::new(prepare_cache(40960).await);
+        let mut blocks = Vec::new();
+        for i in 0..4 {
+            let location = Path::from(format!("{}.bin", i));
+            store
+                .remote_store
+                .put(&location, Bytes::from_static(&[0; 1024]))
+                .await
+                .unwrap();
+            blocks.push((location, 0..512));
+        }
+        // Object doesn't exist.
+        blocks.push((Path::from("not_exist.bin"), 0..512));
+
+        let stats = store.warm_up(blocks, 2).wait().await.unwrap();
+        assert_eq!(
+            WarmUpStats {
+                succeeded: 4,
+                failed: 1,
+                cancelled: 0,
+            },
+            stats
+        );
+        assert_eq!(store.state.lock().await.cached_entries.len(), 4);
+        assert_eq!(store.state.lock().await.total_size, 4096);
+    }
+
+    #[tokio::test]
+    async fn cancel_warm_up() {
+        let store = Arc::new(prepare_cache(40960).await);
+        let location = Path::from("0.bin");
+        store
+            .remote_store
+            .put(&location, Bytes::from_static(&[0; 1024]))
+            .await
+            .unwrap();
+
+        let handle = store.warm_up(vec![(location.clone(), 0..512)], 1);
+        // The task is not polled yet on current thread runtime.
+        handle.cancel();
+        let stats = handle.wait().await.unwrap();
+        assert_eq!(1, stats.cancelled);
+        assert!(matches!(
+            store.state.lock().await.lookup(&location),
+            Lookup::Miss
+        ));
+    }
+
+    #[derive(Debug)]
+    struct MockClock {
+        now: std::sync::Mutex<DateTime<Utc>>,
+    }
+
+    impl MockClock {
+        fn advance(&self, duration: Duration) {
+            let mut now = self.now.lock().unwrap();
+            *now = *now + duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_entry_is_refetched() {
+        let local_path = tempdir().unwrap();
+        let remote_path = tempdir().unwrap();
+        let local_store = Arc::new(LocalFileSystem::new_with_prefix(local_path.path()).unwrap());
+        let remote_store = Arc::new(LocalFileSystem::new_with_prefix(remote_path.path()).unwrap());
+        let config = CachedStoreConfig {
+            max_cache_size: 4096,
+            ttl_secs: Some(60),
+            eviction_policy: EvictionPolicyType::Lru,
+            coherence_check: false,
+        };
+        let clock = Arc::new(MockClock {
+            now: std::sync::Mutex::new(Utc::now()),
+        });
+        let store = CachedStore::init_with_clock(local_store, remote_store, config, clock.clone())
+            .await
+            .unwrap();
+
+        let location = Path::from("object.bin");
+        store
+            .put(&location, Bytes::from_static(&[0; 1024]))
+            .await
+            .unwrap();
+        // Overwrite the remote object.
+        store
+            .remote_store
+            .put(&location, Bytes::from_static(&[1; 1024]))
+            .await
+            .unwrap();
+
+        // Not expired, read from local store.
+        let bytes = store.get_range(&location, 0..4).await.unwrap();
+        assert_eq!(&[0; 4], &bytes[..]);
+
+        clock.advance(Duration::seconds(61));
+        // Expired, re-fetch from remote store.
+        let bytes = store.get_range(&location, 0..4).await.unwrap();
+        assert_eq!(&[1; 4], &bytes[..]);
+        assert_eq!(store.state.lock().await.total_size, 1024);
+        assert_eq!(store.state.lock().await.cached_entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalidate_path() {
+        let store = prepare_cache(4096).await;
+        let location = Path::from("sst/1.sst");
+        store
+            .put(&location, Bytes::from_static(&[0; 1024]))
+            .await
+            .unwrap();
+
+        assert!(store.invalidate(&location).await.unwrap());
+        assert!(!store.invalidate(&location).await.unwrap());
+        assert!(store.local_store.get(&location).await.is_err());
+        assert!(store.remote_store.get(&location).await.is_ok());
+        assert_eq!(store.state.lock().await.total_size, 0);
+    }
+
+    #[tokio::test]
+    async fn invalidate_path_prefix() {
+        let store = prepare_cache(4096).await;
+        let paths = ["sst/1/a.sst", "sst/1/b.sst", "sst/10/a.sst", "sst/2/a.sst"];
+        for path in paths {
+            store
+                .put(&Path::from(path), Bytes::from_static(&[0; 512]))
+                .await
+                .unwrap();
+        }
+
+        // `sst/1` must not match `sst/10`.
+        let removed = store.invalidate_prefix(&Path::from("sst/1")).await.unwrap();
+        assert_eq!(2, removed);
+        for path in &paths[..2] {
+            assert!(store.local_store.get(&Path::from(*path)).await.is_err());
+        }
+        for path in &paths[2..] {
+            assert!(store.local_store.get(&Path::from(*path)).await.is_ok());
+        }
+        assert_eq!(store.state.lock().await.total_size, 1024);
+
+        let removed = store.invalidate_prefix(&Path::from("sst")).await.unwrap();
+        assert_eq!(2, removed);
+        assert_eq!(store.state.lock().await.total_size, 0);
+    }
+
+    /// Local store whose operations fail after `broken` is set.
+    #[derive(Debug)]
+    struct BrokenStore {
+        broken: AtomicBool,
+        inner: LocalFileSystem,
+    }
+
+    impl BrokenStore {
+        fn check(&self) -> Result<()> {
+            if self.broken.load(Ordering::Relaxed) {
+                return Err(upstream::Error::Generic {
+                    store: "BrokenStore",
+                    source: "disk is broken".into(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl Display for BrokenStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "BrokenStore({})", self.inner)
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for BrokenStore {
+        async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+            self.check()?;
+            self.inner.put(location, bytes).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+            self.check()?;
+            self.inner.put_multipart(location).await
+        }
+
+        async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+            self.check()?;
+            self.inner.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get(&self, location: &Path) -> Result<GetResult> {
+            self.check()?;
+            self.inner.get(location).await
+        }
+
+        async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+            self.check()?;
+            self.inner.get_range(location, range).await
+        }
+
+        async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+            self.check()?;
+            self.inner.head(location).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.check()?;
+            self.inner.delete(location).await
+        }
+
+        async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+            self.check()?;
+            self.inner.list(prefix).await
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+            self.check()?;
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+            self.check()?;
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+            self.check()?;
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    #[tokio::test]
+    async fn bypass_broken_local_store() {
+        let local_path = tempdir().unwrap();
+        let remote_path = tempdir().unwrap();
+        let local_store = Arc::new(BrokenStore {
+            broken: AtomicBool::new(false),
+            inner: LocalFileSystem::new_with_prefix(local_path.path()).unwrap(),
+        });
+        let remote_store = Arc::new(LocalFileSystem::new_with_prefix(remote_path.path()).unwrap());
+        let config = CachedStoreConfig {
+            max_cache_size: 4096,
+            ttl_secs: None,
+            eviction_policy: EvictionPolicyType::Lru,
+            coherence_check: false,
+        };
+        let store = CachedStore::init(local_store.clone(), remote_store, config)
+            .await
+            .unwrap();
+
+        let location = Path::from("1.sst");
+        let data = Bytes::from((0..255).collect::<Vec<u8>>());
+        store.put(&location, data.clone()).await.unwrap();
+        assert_eq!(CacheFailureStats::default(), store.failure_stats());
+
+        // The cached object can't be read from the broken local store, serve it
+        // from remote store.
+        local_store.broken.store(true, Ordering::Relaxed);
+        let bytes = store.get_range(&location, 10..20).await.unwrap();
+        assert_eq!(data.slice(10..20), bytes);
+        assert_eq!(
+            CacheFailureStats {
+                read_failures: 1,
+                write_failures: 1,
+            },
+            store.failure_stats()
+        );
+        assert_eq!(store.state.lock().await.total_size, 0);
+
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(data, bytes);
+
+        let location = Path::from("2.sst");
+        store.put(&location, data.clone()).await.unwrap();
+        let bytes = store.get_range(&location, 0..255).await.unwrap();
+        assert_eq!(data, bytes);
+        assert_eq!(
+            CacheFailureStats {
+                read_failures: 1,
+                write_failures: 4,
+            },
+            store.failure_stats()
+        );
+
+        store.delete(&location).await.unwrap();
+        assert!(store.get_range(&location, 0..1).await.is_err());
+
+        // Local store recovers.
+        local_store.broken.store(false, Ordering::Relaxed);
+        let location = Path::from("1.sst");
+        let bytes = store.get_range(&location, 0..10).await.unwrap();
+        assert_eq!(data.slice(0..10), bytes);
+        assert!(store.local_store.get(&location).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn refetch_overwritten_object() {
+        let local_path = tempdir().unwrap();
+        let remote_path = tempdir().unwrap();
+        let local_store = Arc::new(LocalFileSystem::new_with_prefix(local_path.path()).unwrap());
+        let remote_store = Arc::new(LocalFileSystem::new_with_prefix(remote_path.path()).unwrap());
+        let config = CachedStoreConfig {
+            max_cache_size: 4096,
+            ttl_secs: None,
+            eviction_policy: EvictionPolicyType::Lru,
+            coherence_check: true,
+        };
+        let store = CachedStore::init(local_store.clone(), remote_store.clone(), config)
+            .await
+            .unwrap();
+
+        let location = Path::from("1.sst");
+        remote_store
+            .put(&location, Bytes::from_static(b"old"))
+            .await
+            .unwrap();
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(b"old", bytes.as_ref());
+        assert!(store.revalidate(&location).await.unwrap());
+
+        // Overwrite the object behind the cache.
+        remote_store
+            .put(&location, Bytes::from_static(b"new object"))
+            .await
+            .unwrap();
+        let bytes = store.get_range(&location, 0..10).await.unwrap();
+        assert_eq!(b"new object", bytes.as_ref());
+        let bytes = local_store
+            .get(&location)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(b"new object", bytes.as_ref());
+
+        // The object put through the cache is coherent.
+        store
+            .put(&location, Bytes::from_static(b"newer"))
+            .await
+            .unwrap();
+        assert!(store.revalidate(&location).await.unwrap());
+
+        // Delete the object behind the cache.
+        remote_store.delete(&location).await.unwrap();
+        assert!(store.get(&location).await.is_err());
+        assert!(store.state.lock().await.cached_entries.is_empty());
+        assert!(local_store.get(&location).await.is_err());
+    }
 }
@@ -0,0 +1,436 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! An implementation of ObjectStore, which guarantees the integrity of the
+//! whole object by checksum.
+//!
+//! The crc32 checksum and the length of the object is computed on `put` and
+//! stored in a sidecar object at `{location}.checksum`:
+//!
+//! ```text
+//! +---------------+---------------+
+//! | length(u64)   | crc32(u32)    |
+//! +---------------+---------------+
+//! ```
+//!
+//! If `verify_on_read` is enabled, the object is verified against its checksum
+//! on the first read (the whole object will be fetched), and the verified
+//! state is cached so following reads are not affected. Truncated or
+//! corrupted objects are reported by the [Error::Corrupted] error, which can be
+//! checked by [is_corrupted].
+//!
+//! NOTICE: Objects written by `put_multipart` and objects without checksum
+//! (e.g. written before the [ChecksumStore] is used) are not verified.
+
+use std::{collections::HashSet, fmt::Display, ops::Range, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{stream::BoxStream, StreamExt};
+use snafu::{ensure, Backtrace, Snafu};
+use tokio::io::AsyncWrite;
+use upstream::{
+    path::Path, Error as ObjectStoreError, GetResult, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, Result,
+};
+
+use crate::is_not_found;
+
+/// Name of the store in the [ObjectStoreError::Generic] errors raised by the
+/// [ChecksumStore].
+pub(crate) const STORE_NAME: &str = "Checksum";
+const CHECKSUM_SUFFIX: &str = ".checksum";
+/// Size of the encoded checksum, length(u64) + crc32(u32).
+const CHECKSUM_SIZE: usize = 12;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Object is corrupted, location:{}, expect length:{}, actual length:{}, expect crc:{}, actual crc:{}.\nBacktrace:\n{}",
+        location,
+        expect_len,
+        actual_len,
+        expect_crc,
+        actual_crc,
+        backtrace
+    ))]
+    Corrupted {
+        location: String,
+        expect_len: u64,
+        actual_len: u64,
+        expect_crc: u32,
+        actual_crc: u32,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Invalid checksum of object, location:{}, size:{}.\nBacktrace:\n{}",
+        location,
+        size,
+        backtrace
+    ))]
+    InvalidChecksum {
+        location: String,
+        size: usize,
+        backtrace: Backtrace,
+    },
+}
+
+impl From<Error> for ObjectStoreError {
+    fn from(source: Error) -> Self {
+        Self::Generic {
+            store: STORE_NAME,
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Returns true if the `err` is caused by a corrupted object.
+pub fn is_corrupted(err: &ObjectStoreError) -> bool {
+    match err {
+        ObjectStoreError::Generic { store, source } if *store == STORE_NAME => matches!(
+            source.downcast_ref::<Error>(),
+            Some(Error::Corrupted { .. })
+        ),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Checksum {
+    len: u64,
+    crc: u32,
+}
+
+impl Checksum {
+    fn compute(bytes: &[u8]) -> Self {
+        Self {
+            len: bytes.len() as u64,
+            crc: crc32fast::hash(bytes),
+        }
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(CHECKSUM_SIZE);
+        buf.put_u64(self.len);
+        buf.put_u32(self.crc);
+        buf.freeze()
+    }
+
+    fn decode(location: &Path, mut buf: &[u8]) -> std::result::Result<Self, Error> {
+        ensure!(
+            buf.len() == CHECKSUM_SIZE,
+            InvalidChecksum {
+                location: location.to_string(),
+                size: buf.len(),
+            }
+        );
+
+        Ok(Self {
+            len: buf.get_u64(),
+            crc: buf.get_u32(),
+        })
+    }
+}
+
+fn ensure_checksum_match(
+    location: &Path,
+    expect: Checksum,
+    actual: Checksum,
+) -> std::result::Result<(), Error> {
+    ensure!(
+        expect == actual,
+        Corrupted {
+            location: location.to_string(),
+            expect_len: expect.len,
+            actual_len: actual.len,
+            expect_crc: expect.crc,
+            actual_crc: actual.crc,
+        }
+    );
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct ChecksumStore {
+    underlying_store: Arc<dyn ObjectStore>,
+    verify_on_read: bool,
+    /// Locations of the verified objects.
+    verified: std::sync::Mutex<HashSet<String>>,
+}
+
+impl ChecksumStore {
+    pub fn new(underlying_store: Arc<dyn ObjectStore>, verify_on_read: bool) -> Self {
+        Self {
+            underlying_store,
+            verify_on_read,
+            verified: Default::default(),
+        }
+    }
+
+    fn checksum_path(location: &Path) -> Path {
+        Path::from(format!("{}{}", location, CHECKSUM_SUFFIX))
+    }
+
+    fn is_checksum_path(location: &Path) -> bool {
+        location.as_ref().ends_with(CHECKSUM_SUFFIX)
+    }
+
+    fn is_verified(&self, location: &Path) -> bool {
+        self.verified.lock().unwrap().contains(location.as_ref())
+    }
+
+    fn set_verified(&self, location: &Path, verified: bool) {
+        let mut verified_set = self.verified.lock().unwrap();
+        if verified {
+            verified_set.insert(location.to_string());
+        } else {
+            verified_set.remove(location.as_ref());
+        }
+    }
+
+    async fn maybe_verify(&self, location: &Path) -> Result<()> {
+        if !self.verify_on_read || self.is_verified(location) {
+            return Ok(());
+        }
+
+        let checksum_path = Self::checksum_path(location);
+        let expect = match self.underlying_store.get(&checksum_path).await {
+            Ok(v) => Checksum::decode(location, &v.bytes().await?)?,
+            // Object without checksum can't be verified.
+            Err(e) if is_not_found(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let bytes = self.underlying_store.get(location).await?.bytes().await?;
+        let actual = Checksum::compute(&bytes);
+        ensure_checksum_match(location, expect, actual)?;
+
+        self.set_verified(location, true);
+        Ok(())
+    }
+
+    async fn delete_checksum(&self, location: &Path) -> Result<()> {
+        let checksum_path = Self::checksum_path(location);
+        // Not all stores return `NotFound` when deleting a missing object, so check
+        // its existence first.
+        match self.underlying_store.head(&checksum_path).await {
+            Ok(_) => self.underlying_store.delete(&checksum_path).await,
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Copy the checksum of `from` to `to`, the stale checksum of `to` is
+    /// removed if `from` has no checksum.
+    async fn copy_checksum(&self, from: &Path, to: &Path) -> Result<()> {
+        let from_checksum_path = Self::checksum_path(from);
+        match self.underlying_store.head(&from_checksum_path).await {
+            Ok(_) => {
+                self.underlying_store
+                    .copy(&from_checksum_path, &Self::checksum_path(to))
+                    .await
+            }
+            Err(e) if is_not_found(&e) => self.delete_checksum(to).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    fn filter_checksum_objects(objects: Vec<ObjectMeta>) -> Vec<ObjectMeta> {
+        objects
+            .into_iter()
+            .filter(|meta| !Self::is_checksum_path(&meta.location))
+            .collect()
+    }
+}
+
+impl Display for ChecksumStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ChecksumStore with underlying storage {}",
+            self.underlying_store
+        )
+    }
+}
+
+#[async_trait]
+impl ObjectStore for ChecksumStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        let checksum = Checksum::compute(&bytes);
+        self.set_verified(location, false);
+        self.underlying_store.put(location, bytes).await?;
+        self.underlying_store
+            .put(&Self::checksum_path(location), checksum.encode())
+            .await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        // The checksum of the old object is stale, and the object written by
+        // multipart won't be verified.
+        self.set_verified(location, false);
+        self.delete_checksum(location).await?;
+        self.underlying_store.put_multipart(location).await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.underlying_store
+            .abort_multipart(location, multipart_id)
+            .await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.maybe_verify(location).await?;
+        self.underlying_store.get(location).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.maybe_verify(location).await?;
+        self.underlying_store.get_range(location, range).await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.underlying_store.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.set_verified(location, false);
+        self.underlying_store.delete(location).await?;
+        self.delete_checksum(location).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let stream = self.underlying_store.list(prefix).await?;
+        let stream = stream.filter(|res| {
+            let is_checksum = matches!(res, Ok(meta) if Self::is_checksum_path(&meta.location));
+            futures::future::ready(!is_checksum)
+        });
+
+        Ok(stream.boxed())
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        let mut result = self.underlying_store.list_with_delimiter(prefix).await?;
+        result.objects = Self::filter_checksum_objects(result.objects);
+
+        Ok(result)
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.set_verified(to, false);
+        self.underlying_store.copy(from, to).await?;
+        self.copy_checksum(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy_if_not_exists(from, to).await?;
+        self.set_verified(to, false);
+        self.copy_checksum(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use tempfile::tempdir;
+    use upstream::local::LocalFileSystem;
+
+    use super::*;
+
+    fn prepare_store(dir: &std::path::Path, verify_on_read: bool) -> ChecksumStore {
+        let local_store = Arc::new(LocalFileSystem::new_with_prefix(dir).unwrap());
+        ChecksumStore::new(local_store, verify_on_read)
+    }
+
+    #[tokio::test]
+    async fn detect_truncated_object() {
+        let dir = tempdir().unwrap();
+        let store = prepare_store(dir.path(), true);
+        let location = Path::from("1.sst");
+        store
+            .put(&location, Bytes::from_static(&[1; 1024]))
+            .await
+            .unwrap();
+
+        // Truncate the stored object.
+        store
+            .underlying_store
+            .put(&location, Bytes::from_static(&[1; 512]))
+            .await
+            .unwrap();
+
+        let err = store.get_range(&location, 0..16).await.unwrap_err();
+        assert!(is_corrupted(&err), "{}", err);
+        let err = store.get(&location).await.unwrap_err();
+        assert!(is_corrupted(&err), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn cache_verified_state() {
+        let dir = tempdir().unwrap();
+        let store = prepare_store(dir.path(), true);
+        let location = Path::from("1.sst");
+        store
+            .put(&location, Bytes::from_static(&[1; 1024]))
+            .await
+            .unwrap();
+
+        let bytes = store.get_range(&location, 0..16).await.unwrap();
+        assert_eq!(&[1; 16], &bytes[..]);
+        assert!(store.is_verified(&location));
+
+        // Verified objects won't be verified again.
+        store
+            .underlying_store
+            .put(&location, Bytes::from_static(&[2; 512]))
+            .await
+            .unwrap();
+        assert!(store.get_range(&location, 0..16).await.is_ok());
+
+        // Overwrite by the store resets the verified state.
+        store
+            .put(&location, Bytes::from_static(&[3; 256]))
+            .await
+            .unwrap();
+        assert!(!store.is_verified(&location));
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(&[3; 256], &bytes[..]);
+    }
+
+    #[tokio::test]
+    async fn skip_verify_if_disabled() {
+        let dir = tempdir().unwrap();
+        let store = prepare_store(dir.path(), false);
+        let location = Path::from("1.sst");
+        store
+            .put(&location, Bytes::from_static(&[1; 1024]))
+            .await
+            .unwrap();
+        store
+            .underlying_store
+            .put(&location, Bytes::from_static(&[1; 512]))
+            .await
+            .unwrap();
+
+        assert!(store.get_range(&location, 0..16).await.is_ok());
+
+        // Checksum objects are hidden from listing.
+        let objects: Vec<_> = store.list(None).await.unwrap().try_collect().await.unwrap();
+        assert_eq!(1, objects.len());
+        assert_eq!(location, objects[0].location);
+
+        store.delete(&location).await.unwrap();
+        let objects: Vec<_> = store
+            .underlying_store
+            .list(None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(objects.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Compression codecs of objects.
+//!
+//! Every object encoded by the [CodecRegistry] is prefixed with a one byte tag
+//! of the codec id, so objects written by different codecs can be decoded by
+//! the same registry and the codec can be switched without rewriting stored
+//! objects:
+//!
+//! ```text
+//! +---------------+-----------------+
+//! | codec_id(u8)  | encoded payload |
+//! +---------------+-----------------+
+//! ```
+
+use std::{collections::HashMap, fmt::Debug, io, sync::Arc};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use upstream::{path::Path, Error as ObjectStoreError, ObjectStore};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unknown codec, id:{}.\nBacktrace:\n{}", id, backtrace))]
+    UnknownCodec { id: CodecId, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Codec already registered, id:{}, name:{}.\nBacktrace:\n{}",
+        id,
+        name,
+        backtrace
+    ))]
+    DuplicateCodec {
+        id: CodecId,
+        name: &'static str,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to encode object, codec:{}, err:{}", name, source))]
+    Encode {
+        name: &'static str,
+        source: io::Error,
+    },
+
+    #[snafu(display("Failed to decode object, codec:{}, err:{}", name, source))]
+    Decode {
+        name: &'static str,
+        source: io::Error,
+    },
+
+    #[snafu(display("Missing codec tag of object.\nBacktrace:\n{}", backtrace))]
+    MissingCodecTag { backtrace: Backtrace },
+}
+
+impl From<Error> for ObjectStoreError {
+    fn from(source: Error) -> Self {
+        Self::Generic {
+            store: "Codec",
+            source: Box::new(source),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Id of the codec, persisted along with the encoded object.
+pub type CodecId = u8;
+
+pub const CODEC_ID_NONE: CodecId = 0;
+pub const CODEC_ID_LZ4: CodecId = 1;
+pub const CODEC_ID_ZSTD: CodecId = 2;
+
+/// Default compression level of zstd.
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Compression codec of objects.
+pub trait Codec: Debug + Send + Sync {
+    /// Id of the codec, must be unique in a [CodecRegistry].
+    fn id(&self) -> CodecId;
+
+    fn name(&self) -> &'static str;
+
+    fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+
+    fn decode(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+pub type CodecRef = Arc<dyn Codec>;
+
+/// Codec keeps the data as it is.
+#[derive(Debug, Default)]
+pub struct NoneCodec;
+
+impl Codec for NoneCodec {
+    fn id(&self) -> CodecId {
+        CODEC_ID_NONE
+    }
+
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn decode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+}
+
+/// Lz4 block codec, the size of the uncompressed data is prepended.
+#[derive(Debug, Default)]
+pub struct Lz4Codec;
+
+impl Codec for Lz4Codec {
+    fn id(&self) -> CodecId {
+        CODEC_ID_LZ4
+    }
+
+    fn name(&self) -> &'static str {
+        "lz4"
+    }
+
+    fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        lz4::block::compress(data, None, true)
+    }
+
+    fn decode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        lz4::block::decompress(data, None)
+    }
+}
+
+#[derive(Debug)]
+pub struct ZstdCodec {
+    level: i32,
+}
+
+impl ZstdCodec {
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+impl Default for ZstdCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_ZSTD_LEVEL)
+    }
+}
+
+impl Codec for ZstdCodec {
+    fn id(&self) -> CodecId {
+        CODEC_ID_ZSTD
+    }
+
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::bulk::compress(data, self.level)
+    }
+
+    fn decode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::stream::decode_all(data)
+    }
+}
+
+/// Registry maps the codec id to the [Codec].
+#[derive(Debug, Clone)]
+pub struct CodecRegistry {
+    codecs: HashMap<CodecId, CodecRef>,
+}
+
+impl Default for CodecRegistry {
+    /// Registry with all builtin codecs registered.
+    fn default() -> Self {
+        let builtin_codecs: [CodecRef; 3] = [
+            Arc::new(NoneCodec),
+            Arc::new(Lz4Codec),
+            Arc::new(ZstdCodec::default()),
+        ];
+        let codecs = builtin_codecs
+            .into_iter()
+            .map(|codec| (codec.id(), codec))
+            .collect();
+
+        Self { codecs }
+    }
+}
+
+impl CodecRegistry {
+    /// Create an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            codecs: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, codec: CodecRef) -> Result<()> {
+        let id = codec.id();
+        ensure!(
+            !self.codecs.contains_key(&id),
+            DuplicateCodec {
+                id,
+                name: codec.name(),
+            }
+        );
+
+        self.codecs.insert(id, codec);
+        Ok(())
+    }
+
+    pub fn get(&self, id: CodecId) -> Option<&CodecRef> {
+        self.codecs.get(&id)
+    }
+
+    /// Encode `data` by the codec with given `id`, the codec id is prefixed to
+    /// the encoded data.
+    pub fn encode(&self, id: CodecId, data: &[u8]) -> Result<Bytes> {
+        let codec = self.get(id).context(UnknownCodec { id })?;
+        let encoded = codec.encode(data).context(Encode { name: codec.name() })?;
+
+        let mut buf = BytesMut::with_capacity(1 + encoded.len());
+        buf.put_u8(id);
+        buf.put_slice(&encoded);
+        Ok(buf.freeze())
+    }
+
+    /// Decode data encoded by [CodecRegistry::encode], the codec is chosen by
+    /// the tag of `data`.
+    pub fn decode(&self, data: &[u8]) -> Result<Bytes> {
+        let (id, payload) = data.split_first().context(MissingCodecTag)?;
+        let codec = self.get(*id).context(UnknownCodec { id: *id })?;
+        let decoded = codec
+            .decode(payload)
+            .context(Decode { name: codec.name() })?;
+
+        Ok(Bytes::from(decoded))
+    }
+
+    /// Encode `bytes` by the codec with given `id` and put it to `store`.
+    pub async fn put(
+        &self,
+        store: &dyn ObjectStore,
+        location: &Path,
+        id: CodecId,
+        bytes: &[u8],
+    ) -> upstream::Result<()> {
+        let encoded = self.encode(id, bytes)?;
+        store.put(location, encoded).await
+    }
+
+    /// Get the object from `store` and decode it by the codec it was encoded
+    /// with.
+    pub async fn get_decoded(
+        &self,
+        store: &dyn ObjectStore,
+        location: &Path,
+    ) -> upstream::Result<Bytes> {
+        let encoded = store.get(location).await?.bytes().await?;
+        let decoded = self.decode(&encoded)?;
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    fn test_data() -> Vec<u8> {
+        (0..4096).map(|i| (i % 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_builtin_codecs() {
+        let registry = CodecRegistry::default();
+        let data = test_data();
+        for id in [CODEC_ID_NONE, CODEC_ID_LZ4, CODEC_ID_ZSTD] {
+            let encoded = registry.encode(id, &data).unwrap();
+            assert_eq!(id, encoded[0]);
+
+            let decoded = registry.decode(&encoded).unwrap();
+            assert_eq!(&data[..], &decoded[..]);
+        }
+
+        assert!(registry.encode(100, &data).is_err());
+        assert!(registry.decode(&[100, 1, 2]).is_err());
+        assert!(registry.decode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_register_codec() {
+        let mut registry = CodecRegistry::empty();
+        assert!(registry.get(CODEC_ID_LZ4).is_none());
+
+        registry.register(Arc::new(Lz4Codec)).unwrap();
+        assert!(registry.get(CODEC_ID_LZ4).is_some());
+        assert!(registry.register(Arc::new(Lz4Codec)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_get_by_codec() {
+        let store = InMemory::new();
+        let registry = CodecRegistry::default();
+        let data = test_data();
+
+        let lz4_path = Path::from("lz4.sst");
+        let zstd_path = Path::from("zstd.sst");
+        registry
+            .put(&store, &lz4_path, CODEC_ID_LZ4, &data)
+            .await
+            .unwrap();
+        registry
+            .put(&store, &zstd_path, CODEC_ID_ZSTD, &data)
+            .await
+            .unwrap();
+
+        // Objects are compressed in the store.
+        let stored = store.get(&lz4_path).await.unwrap().bytes().await.unwrap();
+        assert!(stored.len() < data.len());
+
+        // Objects are decoded by the codec they were written with.
+        for path in [lz4_path, zstd_path] {
+            let decoded = registry.get_decoded(&store, &path).await.unwrap();
+            assert_eq!(&data[..], &decoded[..]);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Eviction policies deciding which cached entry to drop when the cache is
+//! full.
+//!
+//! The cache owns the entries, and the policy only tracks their keys: the
+//! cache notifies the policy about insertions, accesses and removals, and asks
+//! it for a victim when space is needed for an insertion.
+
+use std::collections::{BTreeSet, HashMap};
+
+use lru::LruCache;
+use serde_derive::Deserialize;
+
+pub trait EvictionPolicy: Send {
+    /// Track a newly inserted `key`, a re-inserted key is treated as new.
+    fn on_insert(&mut self, key: &str);
+
+    /// Record an access of the `key`.
+    fn on_access(&mut self, key: &str);
+
+    /// Stop tracking the `key`, which is removed from the cache.
+    fn on_remove(&mut self, key: &str);
+
+    /// Pick the entry to evict and stop tracking it, returns None if there is
+    /// no entry.
+    fn pop_victim(&mut self) -> Option<String>;
+
+    /// Number of the tracked keys.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub type EvictionPolicyRef = Box<dyn EvictionPolicy>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicyType {
+    /// Evict the least recently used entry.
+    Lru,
+    /// Evict the least frequently used entry, the least recently used one is
+    /// chosen among entries with the same frequency.
+    Lfu,
+    /// Evict the earliest inserted entry, accesses are ignored.
+    Fifo,
+}
+
+impl Default for EvictionPolicyType {
+    fn default() -> Self {
+        Self::Lru
+    }
+}
+
+impl EvictionPolicyType {
+    pub fn build(self) -> EvictionPolicyRef {
+        match self {
+            Self::Lru => Box::new(LruPolicy::default()),
+            Self::Lfu => Box::new(LfuPolicy::default()),
+            Self::Fifo => Box::new(FifoPolicy::default()),
+        }
+    }
+}
+
+pub struct LruPolicy {
+    keys: LruCache<String, ()>,
+}
+
+impl Default for LruPolicy {
+    fn default() -> Self {
+        Self {
+            keys: LruCache::unbounded(),
+        }
+    }
+}
+
+impl EvictionPolicy for LruPolicy {
+    fn on_insert(&mut self, key: &str) {
+        self.keys.put(key.to_string(), ());
+    }
+
+    fn on_access(&mut self, key: &str) {
+        // Promote the key.
+        self.keys.get(&key.to_string());
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.keys.pop(&key.to_string());
+    }
+
+    fn pop_victim(&mut self) -> Option<String> {
+        self.keys.pop_lru().map(|(key, _)| key)
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+pub struct FifoPolicy {
+    /// Keys are never promoted after insertion, so the least recently used key
+    /// is the earliest inserted one.
+    keys: LruCache<String, ()>,
+}
+
+impl Default for FifoPolicy {
+    fn default() -> Self {
+        Self {
+            keys: LruCache::unbounded(),
+        }
+    }
+}
+
+impl EvictionPolicy for FifoPolicy {
+    fn on_insert(&mut self, key: &str) {
+        self.keys.put(key.to_string(), ());
+    }
+
+    fn on_access(&mut self, _key: &str) {}
+
+    fn on_remove(&mut self, key: &str) {
+        self.keys.pop(&key.to_string());
+    }
+
+    fn pop_victim(&mut self) -> Option<String> {
+        self.keys.pop_lru().map(|(key, _)| key)
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// Access frequency and the time of last access of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Usage {
+    frequency: u64,
+    tick: u64,
+}
+
+#[derive(Default)]
+pub struct LfuPolicy {
+    /// Logical clock incremented on every insertion and access.
+    tick: u64,
+    usages: HashMap<String, Usage>,
+    /// Keys ordered by usage, the first one is the victim.
+    ordered: BTreeSet<(Usage, String)>,
+}
+
+impl LfuPolicy {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+impl EvictionPolicy for LfuPolicy {
+    fn on_insert(&mut self, key: &str) {
+        self.on_remove(key);
+
+        let usage = Usage {
+            frequency: 0,
+            tick: self.next_tick(),
+        };
+        self.usages.insert(key.to_string(), usage);
+        self.ordered.insert((usage, key.to_string()));
+    }
+
+    fn on_access(&mut self, key: &str) {
+        let tick = self.next_tick();
+        if let Some(usage) = self.usages.get_mut(key) {
+            let key = key.to_string();
+            self.ordered.remove(&(*usage, key.clone()));
+            usage.frequency += 1;
+            usage.tick = tick;
+            self.ordered.insert((*usage, key));
+        }
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        if let Some(usage) = self.usages.remove(key) {
+            self.ordered.remove(&(usage, key.to_string()));
+        }
+    }
+
+    fn pop_victim(&mut self) -> Option<String> {
+        let victim = self.ordered.iter().next().cloned()?;
+        self.ordered.remove(&victim);
+        self.usages.remove(&victim.1);
+        Some(victim.1)
+    }
+
+    fn len(&self) -> usize {
+        self.usages.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Insert `a`, `b`, `c` and access them by `b, b, a, a, c`, so `a` is the
+    /// earliest inserted, `b` is the least recently used and `c` is the least
+    /// frequently used.
+    fn crafted_policy(policy_type: EvictionPolicyType) -> EvictionPolicyRef {
+        let mut policy = policy_type.build();
+        for key in ["a", "b", "c"] {
+            policy.on_insert(key);
+        }
+        for key in ["b", "b", "a", "a", "c"] {
+            policy.on_access(key);
+        }
+        policy
+    }
+
+    fn drain_victims(mut policy: EvictionPolicyRef) -> Vec<String> {
+        let mut victims = Vec::new();
+        while let Some(key) = policy.pop_victim() {
+            victims.push(key);
+        }
+        assert!(policy.is_empty());
+        victims
+    }
+
+    #[test]
+    fn test_lru_policy() {
+        let policy = crafted_policy(EvictionPolicyType::Lru);
+        assert_eq!(vec!["b", "a", "c"], drain_victims(policy));
+    }
+
+    #[test]
+    fn test_lfu_policy() {
+        let policy = crafted_policy(EvictionPolicyType::Lfu);
+        // `a` and `b` have the same frequency, and `b` is less recently used.
+        assert_eq!(vec!["c", "b", "a"], drain_victims(policy));
+    }
+
+    #[test]
+    fn test_fifo_policy() {
+        let policy = crafted_policy(EvictionPolicyType::Fifo);
+        assert_eq!(vec!["a", "b", "c"], drain_victims(policy));
+    }
+
+    #[test]
+    fn test_policy_remove_and_reinsert() {
+        for policy_type in [
+            EvictionPolicyType::Lru,
+            EvictionPolicyType::Lfu,
+            EvictionPolicyType::Fifo,
+        ] {
+            let mut policy = crafted_policy(policy_type);
+            policy.on_remove("a");
+            policy.on_remove("not_exist");
+            assert_eq!(2, policy.len());
+
+            // The re-inserted key is the newest one with no access.
+            policy.on_insert("b");
+            assert_eq!(2, policy.len());
+            let victims = drain_victims(policy);
+            match policy_type {
+                EvictionPolicyType::Lru | EvictionPolicyType::Fifo => {
+                    assert_eq!(vec!["c", "b"], victims)
+                }
+                EvictionPolicyType::Lfu => assert_eq!(vec!["b", "c"], victims),
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Build [ObjectStoreRef] from a storage uri.
+//!
+//! Supported uri formats:
+//! - `file://<path>`: local file system rooted at `<path>`;
+//! - `memory://`: in-memory store, mainly for tests;
+//! - `oss://<bucket>?endpoint=<endpoint>&key_id=<id>&key_secret=<secret>`:
+//!   aliyun oss.
+
+use std::{collections::HashMap, sync::Arc};
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use upstream::{local::LocalFileSystem, memory::InMemory};
+
+use crate::{aliyun::AliyunOSS, ObjectStoreError, ObjectStoreRef};
+
+const SCHEME_SEPARATOR: &str = "://";
+
+pub const SCHEME_FILE: &str = "file";
+pub const SCHEME_MEMORY: &str = "memory";
+pub const SCHEME_OSS: &str = "oss";
+
+/// Parameters of the uri never shown in the errors.
+const SECRET_PARAMS: [&str; 1] = ["key_secret"];
+const REDACTED: &str = "******";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Invalid storage uri, missing scheme, uri:{}", uri))]
+    MissingScheme { uri: String },
+
+    #[snafu(display("Unknown scheme of storage uri, scheme:{}, uri:{}", scheme, uri))]
+    UnknownScheme { scheme: String, uri: String },
+
+    #[snafu(display("Invalid storage uri, uri:{}, msg:{}", uri, msg))]
+    InvalidUri { uri: String, msg: String },
+
+    #[snafu(display("Missing parameter of storage uri, param:{}, uri:{}", param, uri))]
+    MissingParam { param: String, uri: String },
+
+    #[snafu(display("Failed to open object store, uri:{}, err:{}", uri, source))]
+    OpenStore {
+        uri: String,
+        source: ObjectStoreError,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Replace the values of the secret parameters in the `uri`, so the uri can be
+/// shown in the errors.
+fn redact_uri(uri: &str) -> String {
+    let (prefix, query) = match uri.split_once('?') {
+        Some(v) => v,
+        None => return uri.to_string(),
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_PARAMS.contains(&key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", prefix, query)
+}
+
+/// Parsed form of a storage uri.
+struct StorageUri<'a> {
+    scheme: &'a str,
+    location: &'a str,
+    params: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> StorageUri<'a> {
+    /// Parse the `uri`, the errors only contain the `redacted_uri`.
+    fn parse(uri: &'a str, redacted_uri: &str) -> Result<Self> {
+        let (scheme, rest) = uri
+            .split_once(SCHEME_SEPARATOR)
+            .context(MissingScheme { uri: redacted_uri })?;
+        ensure!(!scheme.is_empty(), MissingScheme { uri: redacted_uri });
+
+        let (location, query) = match rest.split_once('?') {
+            Some((location, query)) => (location, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut params = HashMap::new();
+        for pair in query.into_iter().flat_map(|q| q.split('&')) {
+            if pair.is_empty() {
+                continue;
+            }
+            let (key, value) = pair.split_once('=').context(InvalidUri {
+                uri: redacted_uri,
+                msg: format!("invalid query parameter:{}", pair),
+            })?;
+            params.insert(key, value);
+        }
+
+        Ok(Self {
+            scheme,
+            location,
+            params,
+        })
+    }
+
+    fn param(&self, name: &str, redacted_uri: &str) -> Result<&'a str> {
+        self.params
+            .get(name)
+            .copied()
+            .filter(|v| !v.is_empty())
+            .context(MissingParam {
+                param: name,
+                uri: redacted_uri,
+            })
+    }
+}
+
+/// Build an object store from the `uri`, the backend is chosen by the scheme
+/// of the uri.
+///
+/// The secrets in the uri are redacted in the returned errors.
+pub fn build_object_store(uri: &str) -> Result<ObjectStoreRef> {
+    let redacted_uri = redact_uri(uri);
+    let parsed = StorageUri::parse(uri, &redacted_uri)?;
+    // Only the redacted uri is shown in the errors below.
+    let uri = redacted_uri.as_str();
+
+    match parsed.scheme {
+        SCHEME_FILE => {
+            ensure!(
+                !parsed.location.is_empty(),
+                InvalidUri {
+                    uri,
+                    msg: "empty path",
+                }
+            );
+            let store =
+                LocalFileSystem::new_with_prefix(parsed.location).context(OpenStore { uri })?;
+            Ok(Arc::new(store))
+        }
+        SCHEME_MEMORY => Ok(Arc::new(InMemory::new())),
+        SCHEME_OSS => {
+            ensure!(
+                !parsed.location.is_empty(),
+                InvalidUri {
+                    uri,
+                    msg: "empty bucket",
+                }
+            );
+            let endpoint = parsed.param("endpoint", uri)?;
+            let key_id = parsed.param("key_id", uri)?;
+            let key_secret = parsed.param("key_secret", uri)?;
+            Ok(Arc::new(AliyunOSS::new(
+                key_id,
+                key_secret,
+                endpoint,
+                parsed.location,
+            )))
+        }
+        scheme => UnknownScheme { scheme, uri }.fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+    use upstream::path::Path;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_build_file_store() {
+        let dir = tempdir().unwrap();
+        let uri = format!("file://{}", dir.path().to_str().unwrap());
+        let store = build_object_store(&uri).unwrap();
+        assert!(store.to_string().starts_with("LocalFileSystem"));
+
+        let path = Path::from("a");
+        store.put(&path, "hello".into()).await.unwrap();
+        assert!(dir.path().join("a").exists());
+    }
+
+    #[tokio::test]
+    async fn test_build_memory_store() {
+        let store = build_object_store("memory://").unwrap();
+        assert_eq!("InMemory", store.to_string());
+
+        let path = Path::from("a");
+        store.put(&path, "hello".into()).await.unwrap();
+        let bytes = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(b"hello".as_slice(), bytes.as_ref());
+    }
+
+    #[test]
+    fn test_build_oss_store() {
+        let store =
+            build_object_store("oss://bucket?endpoint=oss.aliyuncs.com&key_id=id&key_secret=sk")
+                .unwrap();
+        assert_eq!("AliyunOSS(bucket)", store.to_string());
+
+        let err = build_object_store("oss://bucket?endpoint=oss.aliyuncs.com&key_id=id")
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::MissingParam { .. }));
+
+        let err = build_object_store("oss://?endpoint=e&key_id=id&key_secret=sk")
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::InvalidUri { .. }));
+    }
+
+    #[test]
+    fn test_redact_secret_in_errors() {
+        let secret = "my_secret";
+        let uris = [
+            // Missing endpoint.
+            format!("oss://bucket?key_id=id&key_secret={}", secret),
+            // Empty bucket.
+            format!("oss://?endpoint=e&key_id=id&key_secret={}", secret),
+            // Invalid query parameter.
+            format!("oss://bucket?key_secret={}&key_id", secret),
+            // Unknown scheme.
+            format!("s3://bucket?key_secret={}", secret),
+        ];
+        for uri in uris {
+            let err = build_object_store(&uri).err().unwrap();
+            let msg = err.to_string();
+            assert!(!msg.contains(secret), "{}", msg);
+            assert!(msg.contains("key_secret=******"), "{}", msg);
+        }
+
+        assert_eq!("file:///a/b", redact_uri("file:///a/b"));
+        assert_eq!(
+            "oss://b?endpoint=e&key_secret=******&key_id=id",
+            redact_uri("oss://b?endpoint=e&key_secret=sk&key_id=id")
+        );
+    }
+
+    #[test]
+    fn test_build_unknown_scheme() {
+        let err = build_object_store("s3://bucket/path").err().unwrap();
+        assert!(matches!(err, Error::UnknownScheme { .. }));
+
+        let err = build_object_store("/no/scheme").err().unwrap();
+        assert!(matches!(err, Error::MissingScheme { .. }));
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Health check of the object store.
+//!
+//! The check puts a tiny probe object under [PROBE_DIR], reads it back and
+//! deletes it, to verify the store is reachable and writable, e.g. at startup
+//! or in a readiness endpoint.
+
+use std::{
+    process,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use log::warn;
+use snafu::{ensure, ResultExt, Snafu};
+use upstream::{path::Path, Error as ObjectStoreError, ObjectStore};
+
+/// Directory reserved for the probe objects.
+pub const PROBE_DIR: &str = ".ceresdb_health_check";
+
+const PROBE_CONTENT: &[u8] = b"ceresdb health check";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to put probe object, path:{}, err:{}", path, source))]
+    PutProbe {
+        path: Path,
+        source: ObjectStoreError,
+    },
+
+    #[snafu(display("Failed to get probe object, path:{}, err:{}", path, source))]
+    GetProbe {
+        path: Path,
+        source: ObjectStoreError,
+    },
+
+    #[snafu(display("Probe object is corrupted, path:{}", path))]
+    CorruptedProbe { path: Path },
+
+    #[snafu(display("Failed to delete probe object, path:{}, err:{}", path, source))]
+    DeleteProbe {
+        path: Path,
+        source: ObjectStoreError,
+    },
+}
+
+/// Result of a health check.
+#[derive(Debug)]
+pub struct HealthStatus {
+    /// Time spent on the whole check, including the clean up.
+    pub latency: Duration,
+    /// Error of the check, None if the store is healthy.
+    pub error: Option<Error>,
+}
+
+impl HealthStatus {
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Check whether the `store` is reachable and writable.
+///
+/// The probe object is always deleted, even if the check fails halfway.
+pub async fn health_check(store: &dyn ObjectStore) -> HealthStatus {
+    let begin = Instant::now();
+    let location = probe_path();
+
+    let probe_res = probe(store, &location).await;
+    // The probe object may exist even if the put fails, so always clean it up.
+    let delete_res = match store.delete(&location).await {
+        Err(e) if crate::is_not_found(&e) => Ok(()),
+        res => res.context(DeleteProbe {
+            path: location.clone(),
+        }),
+    };
+
+    let error = match (probe_res, delete_res) {
+        (Err(e), delete_res) => {
+            if let Err(delete_err) = delete_res {
+                warn!(
+                    "Failed to clean up probe object after health check failed, err:{}",
+                    delete_err
+                );
+            }
+            Some(e)
+        }
+        (Ok(()), delete_res) => delete_res.err(),
+    };
+
+    HealthStatus {
+        latency: begin.elapsed(),
+        error,
+    }
+}
+
+/// Path of the probe object, unique among the processes sharing the store.
+fn probe_path() -> Path {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Path::from(format!("{}/{}-{}", PROBE_DIR, process::id(), nanos))
+}
+
+async fn probe(store: &dyn ObjectStore, location: &Path) -> Result<(), Error> {
+    store
+        .put(location, Bytes::from_static(PROBE_CONTENT))
+        .await
+        .context(PutProbe {
+            path: location.clone(),
+        })?;
+
+    let bytes = match store.get(location).await {
+        Ok(res) => res.bytes().await,
+        Err(e) => Err(e),
+    }
+    .context(GetProbe {
+        path: location.clone(),
+    })?;
+    ensure!(
+        bytes.as_ref() == PROBE_CONTENT,
+        CorruptedProbe {
+            path: location.clone()
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt::{self, Display},
+        io,
+        ops::Range,
+    };
+
+    use async_trait::async_trait;
+    use futures::{stream::BoxStream, TryStreamExt};
+    use tempfile::tempdir;
+    use tokio::io::AsyncWrite;
+    use upstream::{
+        local::LocalFileSystem, GetResult, ListResult, MultipartId, ObjectMeta, Result,
+    };
+
+    use super::*;
+
+    /// A [LocalFileSystem] whose operations can be denied.
+    #[derive(Debug)]
+    struct DenyingStore {
+        inner: LocalFileSystem,
+        deny_put: bool,
+        deny_get: bool,
+    }
+
+    impl DenyingStore {
+        fn check_denied(denied: bool) -> Result<()> {
+            if denied {
+                return Err(ObjectStoreError::Generic {
+                    store: "DenyingStore",
+                    source: Box::new(io::Error::from(io::ErrorKind::PermissionDenied)),
+                });
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Display for DenyingStore {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "DenyingStore({})", self.inner)
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for DenyingStore {
+        async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+            Self::check_denied(self.deny_put)?;
+            self.inner.put(location, bytes).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+            Self::check_denied(self.deny_put)?;
+            self.inner.put_multipart(location).await
+        }
+
+        async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+            self.inner.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get(&self, location: &Path) -> Result<GetResult> {
+            Self::check_denied(self.deny_get)?;
+            self.inner.get(location).await
+        }
+
+        async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+            Self::check_denied(self.deny_get)?;
+            self.inner.get_range(location, range).await
+        }
+
+        async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+            self.inner.head(location).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.inner.delete(location).await
+        }
+
+        async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+            self.inner.list(prefix).await
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    async fn num_probe_objects(store: &dyn ObjectStore) -> usize {
+        store
+            .list(Some(&Path::from(PROBE_DIR)))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .len()
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+
+        let status = health_check(&store).await;
+        assert!(status.is_healthy(), "{:?}", status);
+        assert!(status.latency > Duration::ZERO);
+        assert_eq!(0, num_probe_objects(&store).await);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_permission_denied() {
+        let dir = tempdir().unwrap();
+        let new_store = |deny_put, deny_get| DenyingStore {
+            inner: LocalFileSystem::new_with_prefix(dir.path()).unwrap(),
+            deny_put,
+            deny_get,
+        };
+
+        let store = new_store(true, false);
+        let status = health_check(&store).await;
+        assert!(!status.is_healthy());
+        assert!(
+            matches!(status.error, Some(Error::PutProbe { .. })),
+            "{:?}",
+            status
+        );
+        assert_eq!(0, num_probe_objects(&store).await);
+
+        // The probe object is cleaned up even if it can't be read back.
+        let store = new_store(false, true);
+        let status = health_check(&store).await;
+        assert!(
+            matches!(status.error, Some(Error::GetProbe { .. })),
+            "{:?}",
+            status
+        );
+        assert_eq!(0, num_probe_objects(&store).await);
+    }
+}
@@ -11,6 +11,118 @@ pub use upstream::{
 
 pub mod aliyun;
 pub mod cache;
+pub mod checksum;
+pub mod codec;
+pub mod eviction;
+pub mod factory;
+pub mod health;
 pub mod mem_cache;
+pub mod prefetch;
+pub mod sharded;
+pub mod timeout;
+pub mod trace;
+pub mod util;
+
+pub use factory::build_object_store;
+pub use util::{
+    copy, delete_many, get_if_modified, get_ranges, get_suffix, list_prefixes, rename,
+    ConditionalGetResult, MultiDeleteResult, MultiListResult, SuffixGetResult,
+};
 
 pub type ObjectStoreRef = Arc<dyn ObjectStore>;
+
+/// Returns true if the `err` means the object doesn't exist.
+///
+/// The wrappers should always classify errors by this instead of matching the
+/// error variants, so they behave consistently.
+pub fn is_not_found(err: &ObjectStoreError) -> bool {
+    matches!(err, ObjectStoreError::NotFound { .. })
+}
+
+/// Returns true if the operation failed with `err` may succeed by retrying.
+///
+/// Only generic failures (e.g. io or network errors) are retryable, except the
+/// deterministic ones raised by the wrappers, e.g. the corrupted objects
+/// detected by the [checksum::ChecksumStore]. Errors about the object itself
+/// (e.g. not found, already exists), invalid requests and unsupported
+/// operations will fail again.
+pub fn is_retryable(err: &ObjectStoreError) -> bool {
+    match err {
+        ObjectStoreError::Generic { store, .. } => *store != checksum::STORE_NAME,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tempfile::tempdir;
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    fn generic_error(store: &'static str) -> ObjectStoreError {
+        ObjectStoreError::Generic {
+            store,
+            source: "io error".into(),
+        }
+    }
+
+    #[test]
+    fn test_classify_errors() {
+        let not_found = ObjectStoreError::NotFound {
+            path: "a/b".to_string(),
+            source: "not found".into(),
+        };
+        assert!(is_not_found(&not_found));
+        assert!(!is_retryable(&not_found));
+
+        let already_exists = ObjectStoreError::AlreadyExists {
+            path: "a/b".to_string(),
+            source: "already exists".into(),
+        };
+        let not_supported = ObjectStoreError::NotSupported {
+            source: "not supported".into(),
+        };
+        for err in [
+            already_exists,
+            not_supported,
+            ObjectStoreError::NotImplemented,
+        ] {
+            assert!(!is_not_found(&err), "{:?}", err);
+            assert!(!is_retryable(&err), "{:?}", err);
+        }
+
+        for store in ["LocalFileSystem", "Aliyun"] {
+            let err = generic_error(store);
+            assert!(!is_not_found(&err));
+            assert!(is_retryable(&err));
+        }
+        // Errors of the checksum store are deterministic.
+        assert!(!is_retryable(&generic_error(checksum::STORE_NAME)));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_of_stores() {
+        let dir = tempdir().unwrap();
+        let stores: Vec<ObjectStoreRef> = vec![
+            Arc::new(LocalFileSystem::new_with_prefix(dir.path()).unwrap()),
+            Arc::new(InMemory::new()),
+        ];
+        let location = Path::from("not_exist");
+        for store in stores {
+            let checksum_store = checksum::ChecksumStore::new(store.clone(), true);
+            for store in [store, Arc::new(checksum_store) as _] {
+                assert!(is_not_found(&store.get(&location).await.unwrap_err()));
+                assert!(is_not_found(&store.head(&location).await.unwrap_err()));
+
+                store
+                    .put(&location, Bytes::from_static(b"0"))
+                    .await
+                    .unwrap();
+                store.delete(&location).await.unwrap();
+                assert!(is_not_found(&store.get(&location).await.unwrap_err()));
+            }
+        }
+    }
+}
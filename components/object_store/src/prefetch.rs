@@ -0,0 +1,373 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! An implementation of ObjectStore, which prefetches the following ranges of
+//! an object when sequential access is detected.
+//!
+//! Every `get_range` on an object is compared with the previous one on the
+//! same object, and a read starting exactly at the end of the previous read is
+//! regarded as sequential. After `sequential_threshold` sequential reads, the
+//! next `window` ranges (with the same length as the current read) are fetched
+//! from the underlying store in background, so the consumer can take them
+//! without waiting for the io later.
+//!
+//! Once a non-sequential read happens, all the inflight prefetches of the
+//! object are dropped and prefetching stops until the access becomes
+//! sequential again, so random access won't waste bandwidth.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{
+    future::{BoxFuture, Shared},
+    stream::BoxStream,
+    FutureExt,
+};
+use lru::LruCache;
+use serde_derive::Deserialize;
+use tokio::io::AsyncWrite;
+use upstream::{path::Path, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct PrefetchConfig {
+    /// Max number of ranges prefetched ahead of the consumer.
+    pub window: usize,
+    /// Number of sequential reads required before prefetching starts.
+    pub sequential_threshold: usize,
+    /// Max number of objects whose access pattern is tracked.
+    pub max_tracked_objects: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            window: 4,
+            sequential_threshold: 2,
+            max_tracked_objects: 1024,
+        }
+    }
+}
+
+/// Accounting of the prefetches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefetchMetrics {
+    /// Number of ranges prefetched.
+    pub prefetched: u64,
+    /// Number of reads served by prefetched ranges.
+    pub hits: u64,
+    /// Number of reads not served by prefetched ranges.
+    pub misses: u64,
+    /// Number of prefetched ranges dropped without being read.
+    pub wasted: u64,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    prefetched: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    wasted: AtomicU64,
+}
+
+type PrefetchFuture = Shared<BoxFuture<'static, Option<Bytes>>>;
+
+/// Access state of one object.
+#[derive(Default)]
+struct AccessState {
+    /// End offset of the last read.
+    last_end: usize,
+    /// Number of consecutive sequential reads.
+    sequential_reads: usize,
+    /// Prefetched ranges in the order of offset.
+    inflight: VecDeque<(Range<usize>, PrefetchFuture)>,
+}
+
+impl AccessState {
+    /// Record the read of `range`, and take the prefetched result of it if
+    /// any.
+    fn on_read(&mut self, range: &Range<usize>, stats: &Stats) -> Option<PrefetchFuture> {
+        if range.start == self.last_end {
+            self.sequential_reads += 1;
+        } else {
+            self.sequential_reads = 0;
+            self.discard_inflight(stats);
+        }
+        self.last_end = range.end;
+
+        match self.inflight.front() {
+            Some((prefetched, _)) if prefetched == range => {
+                self.inflight.pop_front().map(|(_, fut)| fut)
+            }
+            Some(_) => {
+                // The consumer changes the read size, prefetched ranges can't be used.
+                self.discard_inflight(stats);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Ranges to prefetch after reading `range`.
+    fn ranges_to_prefetch(
+        &self,
+        range: &Range<usize>,
+        config: &PrefetchConfig,
+    ) -> Vec<Range<usize>> {
+        let len = range.end - range.start;
+        if len == 0 || self.sequential_reads < config.sequential_threshold {
+            return Vec::new();
+        }
+
+        let mut start = self
+            .inflight
+            .back()
+            .map(|(r, _)| r.end)
+            .unwrap_or(range.end);
+        let num = config.window.saturating_sub(self.inflight.len());
+        (0..num)
+            .map(|_| {
+                let r = start..start + len;
+                start = r.end;
+                r
+            })
+            .collect()
+    }
+
+    fn discard_inflight(&mut self, stats: &Stats) {
+        stats
+            .wasted
+            .fetch_add(self.inflight.len() as u64, Ordering::Relaxed);
+        self.inflight.clear();
+    }
+}
+
+pub struct PrefetchStore {
+    config: PrefetchConfig,
+    states: Mutex<LruCache<Path, AccessState>>,
+    stats: Stats,
+    underlying_store: Arc<dyn ObjectStore>,
+}
+
+impl PrefetchStore {
+    pub fn new(config: PrefetchConfig, underlying_store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            config,
+            states: Mutex::new(LruCache::new(config.max_tracked_objects.max(1))),
+            stats: Stats::default(),
+            underlying_store,
+        }
+    }
+
+    pub fn metrics(&self) -> PrefetchMetrics {
+        PrefetchMetrics {
+            prefetched: self.stats.prefetched.load(Ordering::Relaxed),
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            misses: self.stats.misses.load(Ordering::Relaxed),
+            wasted: self.stats.wasted.load(Ordering::Relaxed),
+        }
+    }
+
+    fn spawn_prefetch(&self, location: &Path, range: Range<usize>) -> PrefetchFuture {
+        let store = self.underlying_store.clone();
+        let location = location.clone();
+        let handle = tokio::spawn(async move {
+            // The prefetched range may exceed the end of the object, just ignore
+            // the failure and the consumer will read it from the underlying store.
+            store.get_range(&location, range).await.ok()
+        });
+
+        handle.map(|res| res.ok().flatten()).boxed().shared()
+    }
+
+    /// Take the prefetched result of the `range` and issue new prefetches.
+    fn on_read(&self, location: &Path, range: &Range<usize>) -> Option<PrefetchFuture> {
+        let mut states = self.states.lock().unwrap();
+        if !states.contains(location) {
+            states.put(location.clone(), AccessState::default());
+        }
+        let state = states.get_mut(location).unwrap();
+
+        let prefetched = state.on_read(range, &self.stats);
+        for r in state.ranges_to_prefetch(range, &self.config) {
+            let fut = self.spawn_prefetch(location, r.clone());
+            state.inflight.push_back((r, fut));
+            self.stats.prefetched.fetch_add(1, Ordering::Relaxed);
+        }
+
+        prefetched
+    }
+}
+
+impl Display for PrefetchStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PrefetchStore({}, window:{})",
+            self.underlying_store, self.config.window
+        )
+    }
+}
+
+impl std::fmt::Debug for PrefetchStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefetchStore")
+            .field("config", &self.config)
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ObjectStore for PrefetchStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.underlying_store.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.underlying_store.put_multipart(location).await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.underlying_store
+            .abort_multipart(location, multipart_id)
+            .await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.underlying_store.get(location).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        if let Some(prefetched) = self.on_read(location, &range) {
+            if let Some(bytes) = prefetched.await {
+                self.stats.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(bytes);
+            }
+        }
+
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        self.underlying_store.get_range(location, range).await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.underlying_store.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.states.lock().unwrap().pop(location);
+        self.underlying_store.delete(location).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        self.underlying_store.list(prefix).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.underlying_store.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    async fn prepare_store(config: PrefetchConfig, location: &Path) -> (PrefetchStore, Bytes) {
+        let data: Bytes = (0..2048)
+            .map(|v| (v % 256) as u8)
+            .collect::<Vec<_>>()
+            .into();
+        let underlying = Arc::new(InMemory::new());
+        underlying.put(location, data.clone()).await.unwrap();
+
+        (PrefetchStore::new(config, underlying), data)
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_sequential_read() {
+        let location = Path::from("sequential.sst");
+        let config = PrefetchConfig {
+            window: 2,
+            sequential_threshold: 2,
+            max_tracked_objects: 8,
+        };
+        let (store, data) = prepare_store(config, &location).await;
+
+        // Consume the object sequentially in 8 reads.
+        for i in 0..8 {
+            let range = i * 128..(i + 1) * 128;
+            let bytes = store.get_range(&location, range.clone()).await.unwrap();
+            assert_eq!(data.slice(range), bytes);
+        }
+
+        // Prefetching starts after the 2nd read, so the last 6 reads are hits,
+        // and the 2 ranges prefetched beyond the consumed part are left inflight.
+        let metrics = store.metrics();
+        assert_eq!(2, metrics.misses);
+        assert_eq!(6, metrics.hits);
+        assert_eq!(8, metrics.prefetched);
+        assert_eq!(0, metrics.wasted);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_random_read() {
+        let location = Path::from("random.sst");
+        let (store, data) = prepare_store(PrefetchConfig::default(), &location).await;
+
+        for start in [512, 0, 768, 128, 896, 256] {
+            let range = start..start + 64;
+            let bytes = store.get_range(&location, range.clone()).await.unwrap();
+            assert_eq!(data.slice(range), bytes);
+        }
+
+        let metrics = store.metrics();
+        assert_eq!(0, metrics.prefetched);
+        assert_eq!(0, metrics.hits);
+        assert_eq!(6, metrics.misses);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_discard_on_seek() {
+        let location = Path::from("seek.sst");
+        let config = PrefetchConfig {
+            window: 2,
+            sequential_threshold: 1,
+            max_tracked_objects: 8,
+        };
+        let (store, data) = prepare_store(config, &location).await;
+
+        // Sequential reads trigger prefetching of [128, 192) and [192, 256).
+        store.get_range(&location, 0..64).await.unwrap();
+        store.get_range(&location, 64..128).await.unwrap();
+        // Seek away, the prefetched ranges are wasted.
+        let bytes = store.get_range(&location, 512..576).await.unwrap();
+        assert_eq!(data.slice(512..576), bytes);
+
+        let metrics = store.metrics();
+        assert_eq!(1, metrics.hits);
+        assert_eq!(2, metrics.misses);
+        assert_eq!(3, metrics.prefetched);
+        assert_eq!(2, metrics.wasted);
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! An implementation of ObjectStore, which spreads the objects across several
+//! equivalent underlying stores to scale the bandwidth.
+//!
+//! Each object is routed to a store by consistent hashing of its path, so all
+//! the operations on the same path always reach the same store, and adding a
+//! store only moves a fraction of the objects. Listing fans out to all the
+//! stores and merges their results.
+
+use std::{collections::BTreeMap, fmt::Display, ops::Range};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::io::AsyncWrite;
+use upstream::{
+    path::Path, Error, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result,
+};
+
+use crate::ObjectStoreRef;
+
+/// Number of virtual nodes of each store on the hash ring, more nodes spread
+/// the objects more evenly.
+const VIRTUAL_NODES_PER_SHARD: usize = 64;
+
+#[derive(Debug)]
+pub struct ShardedObjectStore {
+    shards: Vec<ObjectStoreRef>,
+    /// Hash ring, maps the hash of a virtual node to the index of its store.
+    ring: BTreeMap<u32, usize>,
+}
+
+impl ShardedObjectStore {
+    /// Create a store sharding objects across `shards`, which must not be
+    /// empty.
+    ///
+    /// The routing only depends on the index of the stores, so the stores
+    /// should always be given in the same order.
+    pub fn new(shards: Vec<ObjectStoreRef>) -> Self {
+        assert!(!shards.is_empty());
+
+        let mut ring = BTreeMap::new();
+        for shard_idx in 0..shards.len() {
+            for node in 0..VIRTUAL_NODES_PER_SHARD {
+                let node_key = format!("shard-{}-node-{}", shard_idx, node);
+                ring.insert(crc32fast::hash(node_key.as_bytes()), shard_idx);
+            }
+        }
+
+        Self { shards, ring }
+    }
+
+    /// Index of the store holding the object at `location`.
+    pub fn shard_index(&self, location: &Path) -> usize {
+        let hash = crc32fast::hash(location.as_ref().as_bytes());
+        // Take the first virtual node after the hash, wrapping around the ring.
+        self.ring
+            .range(hash..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, shard_idx)| *shard_idx)
+            .unwrap()
+    }
+
+    #[inline]
+    fn shard(&self, location: &Path) -> &ObjectStoreRef {
+        &self.shards[self.shard_index(location)]
+    }
+
+    #[inline]
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+}
+
+impl Display for ShardedObjectStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ShardedObjectStore(")?;
+        for (i, shard) in self.shards.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", shard)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[async_trait]
+impl ObjectStore for ShardedObjectStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.shard(location).put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.shard(location).put_multipart(location).await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.shard(location)
+            .abort_multipart(location, multipart_id)
+            .await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.shard(location).get(location).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.shard(location).get_range(location, range).await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        self.shard(location).get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.shard(location).head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.shard(location).delete(location).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let mut streams = Vec::with_capacity(self.shards.len());
+        for shard in &self.shards {
+            streams.push(shard.list(prefix).await?);
+        }
+
+        Ok(stream::select_all(streams).boxed())
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        let mut common_prefixes = Vec::new();
+        let mut objects = Vec::new();
+        for shard in &self.shards {
+            let result = shard.list_with_delimiter(prefix).await?;
+            common_prefixes.extend(result.common_prefixes);
+            objects.extend(result.objects);
+        }
+        // Objects under the same prefix may be spread across the stores.
+        common_prefixes.sort_unstable();
+        common_prefixes.dedup();
+
+        Ok(ListResult {
+            common_prefixes,
+            objects,
+        })
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from_shard, to_shard) = (self.shard_index(from), self.shard_index(to));
+        if from_shard == to_shard {
+            return self.shards[from_shard].copy(from, to).await;
+        }
+
+        let bytes = self.shards[from_shard].get(from).await?.bytes().await?;
+        self.shards[to_shard].put(to, bytes).await
+    }
+
+    /// The check and the copy is not atomic if `from` and `to` are in different
+    /// stores.
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from_shard, to_shard) = (self.shard_index(from), self.shard_index(to));
+        if from_shard == to_shard {
+            return self.shards[from_shard].copy_if_not_exists(from, to).await;
+        }
+
+        match self.shards[to_shard].head(to).await {
+            Ok(_) => {
+                return Err(Error::AlreadyExists {
+                    path: to.to_string(),
+                    source: "object already exists in the target store".into(),
+                })
+            }
+            Err(e) if crate::is_not_found(&e) => (),
+            Err(e) => return Err(e),
+        }
+
+        let bytes = self.shards[from_shard].get(from).await?.bytes().await?;
+        self.shards[to_shard].put(to, bytes).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, sync::Arc};
+
+    use futures::TryStreamExt;
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    fn new_shards(num: usize) -> Vec<ObjectStoreRef> {
+        (0..num)
+            .map(|_| Arc::new(InMemory::new()) as ObjectStoreRef)
+            .collect()
+    }
+
+    fn test_paths() -> Vec<Path> {
+        (0..100)
+            .map(|i| Path::from(format!("space/{}/table/{}.sst", i % 4, i)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sharded_routing() {
+        let shards = new_shards(2);
+        let store = ShardedObjectStore::new(shards.clone());
+        let paths = test_paths();
+        for path in &paths {
+            store
+                .put(path, Bytes::from(path.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let mut num_objects = vec![0; 2];
+        for path in &paths {
+            // Each object is in the store it routes to only.
+            let shard_idx = store.shard_index(path);
+            num_objects[shard_idx] += 1;
+            assert!(shards[shard_idx].head(path).await.is_ok());
+            assert!(shards[1 - shard_idx].head(path).await.is_err());
+
+            let bytes = store.get(path).await.unwrap().bytes().await.unwrap();
+            assert_eq!(path.to_string().as_bytes(), bytes.as_ref());
+        }
+        // Both stores are used.
+        assert!(num_objects.iter().all(|v| *v > 0), "{:?}", num_objects);
+
+        // The routing is stable across instances.
+        let reopened = ShardedObjectStore::new(shards.clone());
+        for path in &paths {
+            assert_eq!(store.shard_index(path), reopened.shard_index(path));
+            assert!(reopened.head(path).await.is_ok());
+        }
+
+        // Adding a store only moves objects to the new store.
+        let mut grown_shards = shards.clone();
+        grown_shards.extend(new_shards(1));
+        let grown = ShardedObjectStore::new(grown_shards);
+        let mut num_moved = 0;
+        for path in &paths {
+            let shard_idx = grown.shard_index(path);
+            if shard_idx != store.shard_index(path) {
+                assert_eq!(2, shard_idx);
+                num_moved += 1;
+            }
+        }
+        assert!(num_moved < paths.len());
+
+        // Copy across stores.
+        let from = &paths[0];
+        let to = (1..)
+            .map(|i| Path::from(format!("copied/{}", i)))
+            .find(|to| store.shard_index(to) != store.shard_index(from))
+            .unwrap();
+        store.copy_if_not_exists(from, &to).await.unwrap();
+        assert!(store.head(&to).await.is_ok());
+        assert!(store.copy_if_not_exists(from, &to).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sharded_list() {
+        let store = ShardedObjectStore::new(new_shards(2));
+        let paths = test_paths();
+        for path in &paths {
+            store.put(path, Bytes::from_static(b"0")).await.unwrap();
+        }
+
+        let listed = store
+            .list(None)
+            .await
+            .unwrap()
+            .map_ok(|meta| meta.location)
+            .try_collect::<BTreeSet<_>>()
+            .await
+            .unwrap();
+        assert_eq!(paths.iter().cloned().collect::<BTreeSet<_>>(), listed);
+
+        let prefix = Path::from("space/1/table");
+        let listed = store
+            .list(Some(&prefix))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(25, listed.len());
+
+        // Prefixes spread across the stores are merged.
+        let result = store
+            .list_with_delimiter(Some(&Path::from("space")))
+            .await
+            .unwrap();
+        let expect_prefixes = (0..4)
+            .map(|i| Path::from(format!("space/{}", i)))
+            .collect::<Vec<_>>();
+        assert_eq!(expect_prefixes, result.common_prefixes);
+        assert!(result.objects.is_empty());
+    }
+}
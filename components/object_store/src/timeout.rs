@@ -0,0 +1,363 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! An implementation of ObjectStore, which bounds the time of the operations
+//! by the timeout of their classes.
+//!
+//! Operations of different classes take quite different time, e.g. putting a
+//! large sst may take minutes while getting a small meta object should return
+//! in seconds, so a single timeout is either too short for the former or too
+//! long for the latter. The classes are:
+//! - get: `get` and `head`;
+//! - get_range: `get_range` and `get_ranges`;
+//! - put: `put` and `put_multipart`;
+//! - list: `list` and `list_with_delimiter`;
+//! - delete: `delete`.
+//!
+//! Operations of a class without timeout, and other operations (e.g. `copy`),
+//! are not bounded. Only the request is bounded for the operations returning a
+//! stream (`get`, `list` and `put_multipart`), consuming the stream is not.
+//!
+//! Timed out operations fail with the [Error::Timeout] error, which can be
+//! checked by [is_timeout].
+
+use std::{fmt::Display, future::Future, ops::Range, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use serde_derive::Deserialize;
+use snafu::{Backtrace, Snafu};
+use tokio::io::AsyncWrite;
+use upstream::{
+    path::Path, Error as ObjectStoreError, GetResult, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, Result,
+};
+
+/// Name of the store in the [ObjectStoreError::Generic] errors raised by the
+/// [TimeoutStore].
+const STORE_NAME: &str = "Timeout";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Object store operation timeout, operation:{}, location:{}, timeout:{:?}.\nBacktrace:\n{}",
+        operation,
+        location,
+        timeout,
+        backtrace
+    ))]
+    Timeout {
+        operation: &'static str,
+        location: String,
+        timeout: Duration,
+        backtrace: Backtrace,
+    },
+}
+
+impl From<Error> for ObjectStoreError {
+    fn from(source: Error) -> Self {
+        Self::Generic {
+            store: STORE_NAME,
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Returns true if the `err` is caused by an operation timeout.
+pub fn is_timeout(err: &ObjectStoreError) -> bool {
+    match err {
+        ObjectStoreError::Generic { store, source } if *store == STORE_NAME => {
+            matches!(source.downcast_ref::<Error>(), Some(Error::Timeout { .. }))
+        }
+        _ => false,
+    }
+}
+
+/// Timeouts (in milliseconds) of the operation classes, no timeout if not set.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub get_timeout_ms: Option<u64>,
+    pub get_range_timeout_ms: Option<u64>,
+    pub put_timeout_ms: Option<u64>,
+    pub list_timeout_ms: Option<u64>,
+    pub delete_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct TimeoutStore {
+    underlying_store: Arc<dyn ObjectStore>,
+    get_timeout: Option<Duration>,
+    get_range_timeout: Option<Duration>,
+    put_timeout: Option<Duration>,
+    list_timeout: Option<Duration>,
+    delete_timeout: Option<Duration>,
+}
+
+impl TimeoutStore {
+    pub fn new(underlying_store: Arc<dyn ObjectStore>, config: TimeoutConfig) -> Self {
+        Self {
+            underlying_store,
+            get_timeout: config.get_timeout_ms.map(Duration::from_millis),
+            get_range_timeout: config.get_range_timeout_ms.map(Duration::from_millis),
+            put_timeout: config.put_timeout_ms.map(Duration::from_millis),
+            list_timeout: config.list_timeout_ms.map(Duration::from_millis),
+            delete_timeout: config.delete_timeout_ms.map(Duration::from_millis),
+        }
+    }
+}
+
+/// Run the `fut` of `operation` on `location`, it fails if not finished within
+/// the `timeout`.
+async fn run_with_timeout<T>(
+    operation: &'static str,
+    location: Option<&Path>,
+    timeout: Option<Duration>,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let timeout = match timeout {
+        Some(v) => v,
+        None => return fut.await,
+    };
+
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(res) => res,
+        Err(_) => Timeout {
+            operation,
+            location: location.map(|v| v.to_string()).unwrap_or_default(),
+            timeout,
+        }
+        .fail()
+        .map_err(Into::into),
+    }
+}
+
+impl Display for TimeoutStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TimeoutStore({})", self.underlying_store)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for TimeoutStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        run_with_timeout(
+            "put",
+            Some(location),
+            self.put_timeout,
+            self.underlying_store.put(location, bytes),
+        )
+        .await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        run_with_timeout(
+            "put_multipart",
+            Some(location),
+            self.put_timeout,
+            self.underlying_store.put_multipart(location),
+        )
+        .await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.underlying_store
+            .abort_multipart(location, multipart_id)
+            .await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        run_with_timeout(
+            "get",
+            Some(location),
+            self.get_timeout,
+            self.underlying_store.get(location),
+        )
+        .await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        run_with_timeout(
+            "get_range",
+            Some(location),
+            self.get_range_timeout,
+            self.underlying_store.get_range(location, range),
+        )
+        .await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        run_with_timeout(
+            "get_ranges",
+            Some(location),
+            self.get_range_timeout,
+            self.underlying_store.get_ranges(location, ranges),
+        )
+        .await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        run_with_timeout(
+            "head",
+            Some(location),
+            self.get_timeout,
+            self.underlying_store.head(location),
+        )
+        .await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        run_with_timeout(
+            "delete",
+            Some(location),
+            self.delete_timeout,
+            self.underlying_store.delete(location),
+        )
+        .await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        run_with_timeout(
+            "list",
+            prefix,
+            self.list_timeout,
+            self.underlying_store.list(prefix),
+        )
+        .await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        run_with_timeout(
+            "list_with_delimiter",
+            prefix,
+            self.list_timeout,
+            self.underlying_store.list_with_delimiter(prefix),
+        )
+        .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    /// Store whose `put`, `get`, `head` and `get_range` take `delay` before
+    /// reaching the [InMemory] store.
+    #[derive(Debug)]
+    struct SlowStore {
+        inner: InMemory,
+        delay: Duration,
+    }
+
+    impl Display for SlowStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "SlowStore")
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for SlowStore {
+        async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.put(location, bytes).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+            self.inner.put_multipart(location).await
+        }
+
+        async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+            self.inner.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get(&self, location: &Path) -> Result<GetResult> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.get(location).await
+        }
+
+        async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.get_range(location, range).await
+        }
+
+        async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.head(location).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.inner.delete(location).await
+        }
+
+        async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+            self.inner.list(prefix).await
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_per_operation_class() {
+        let slow_store = Arc::new(SlowStore {
+            inner: InMemory::new(),
+            delay: Duration::from_millis(200),
+        });
+        let config = TimeoutConfig {
+            get_timeout_ms: Some(20),
+            put_timeout_ms: Some(10_000),
+            list_timeout_ms: Some(20),
+            ..Default::default()
+        };
+        let store = TimeoutStore::new(slow_store, config);
+        let location = Path::from("meta/1");
+
+        // The slow put is allowed by the long timeout of put.
+        store
+            .put(&location, Bytes::from_static(b"meta"))
+            .await
+            .unwrap();
+
+        // The slow get and head are bounded by the short timeout of get.
+        let err = store.get(&location).await.unwrap_err();
+        assert!(is_timeout(&err), "{:?}", err);
+        assert!(err.to_string().contains("operation:get, location:meta/1"));
+        assert!(is_timeout(&store.head(&location).await.unwrap_err()));
+
+        // Operations without timeout are not bounded.
+        let bytes = store.get_range(&location, 0..2).await.unwrap();
+        assert_eq!(b"me", bytes.as_ref());
+        // The fast list finishes within the timeout.
+        assert!(store.list_with_delimiter(None).await.is_ok());
+        store.delete(&location).await.unwrap();
+
+        // Timeouts may not happen again.
+        assert!(crate::is_retryable(&err));
+        assert!(!crate::checksum::is_corrupted(&err));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! An implementation of ObjectStore, which logs the operations with the trace
+//! id of the originating request, to correlate slow object store operations
+//! with the slow queries.
+//!
+//! The trace id is passed by running the request in [with_trace_id] instead of
+//! a parameter of the [ObjectStore] methods, so it reaches the store through
+//! all the layers between them. Operations issued by spawned tasks are logged
+//! without trace id.
+//!
+//! Nothing is measured or logged if the debug level of the log is disabled.
+
+use std::{
+    fmt::Display,
+    future::Future,
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use log::{debug, log_enabled, Level};
+use tokio::io::AsyncWrite;
+use upstream::{path::Path, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result};
+
+/// Placeholder of the trace id in the logs of untraced operations.
+const NO_TRACE_ID: &str = "-";
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// Run the `fut` with the `trace_id`, the [TracingStore] operations issued by
+/// the `fut` are logged with the `trace_id`.
+pub async fn with_trace_id<F: Future>(trace_id: impl Into<String>, fut: F) -> F::Output {
+    TRACE_ID.scope(trace_id.into(), fut).await
+}
+
+/// Returns the trace id of current task, None if the task is not traced.
+pub fn current_trace_id() -> Option<String> {
+    TRACE_ID.try_with(|trace_id| trace_id.clone()).ok()
+}
+
+fn log_operation(
+    operation: &str,
+    location: &Path,
+    range: &Range<usize>,
+    cost: Duration,
+    succeeded: bool,
+) {
+    let trace_id = current_trace_id();
+    debug!(
+        "Object store {}, trace_id:{}, path:{}, range:{:?}, cost:{:?}, succeeded:{}",
+        operation,
+        trace_id.as_deref().unwrap_or(NO_TRACE_ID),
+        location,
+        range,
+        cost,
+        succeeded
+    );
+}
+
+/// Run the `fut` and log it as `operation` on the `range` of `location`.
+async fn trace_operation<T>(
+    operation: &str,
+    location: &Path,
+    range: Range<usize>,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    if !log_enabled!(Level::Debug) {
+        return fut.await;
+    }
+
+    let begin = Instant::now();
+    let res = fut.await;
+    log_operation(operation, location, &range, begin.elapsed(), res.is_ok());
+
+    res
+}
+
+#[derive(Debug)]
+pub struct TracingStore {
+    underlying_store: Arc<dyn ObjectStore>,
+}
+
+impl TracingStore {
+    pub fn new(underlying_store: Arc<dyn ObjectStore>) -> Self {
+        Self { underlying_store }
+    }
+}
+
+impl Display for TracingStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TracingStore({})", self.underlying_store)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for TracingStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        let range = 0..bytes.len();
+        trace_operation(
+            "put",
+            location,
+            range,
+            self.underlying_store.put(location, bytes),
+        )
+        .await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.underlying_store.put_multipart(location).await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.underlying_store
+            .abort_multipart(location, multipart_id)
+            .await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.underlying_store.get(location).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        trace_operation(
+            "get_range",
+            location,
+            range.clone(),
+            self.underlying_store.get_range(location, range),
+        )
+        .await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        let range = ranges.iter().map(|v| v.start).min().unwrap_or(0)
+            ..ranges.iter().map(|v| v.end).max().unwrap_or(0);
+        trace_operation(
+            "get_ranges",
+            location,
+            range,
+            self.underlying_store.get_ranges(location, ranges),
+        )
+        .await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.underlying_store.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.underlying_store.delete(location).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        self.underlying_store.list(prefix).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.underlying_store.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.underlying_store.copy_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, Once};
+
+    use log::{LevelFilter, Log, Metadata, Record};
+    use upstream::memory::InMemory;
+
+    use super::*;
+
+    /// Logger capturing the messages of the records.
+    struct CaptureLogger;
+
+    static CAPTURED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static LOGGER: CaptureLogger = CaptureLogger;
+    static INIT_LOGGER: Once = Once::new();
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Debug
+        }
+
+        fn log(&self, record: &Record) {
+            CAPTURED.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn captured_with(pattern: &str) -> Vec<String> {
+        CAPTURED
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.contains(pattern))
+            .cloned()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_trace_operations() {
+        INIT_LOGGER.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(LevelFilter::Debug);
+        });
+
+        let store = TracingStore::new(Arc::new(InMemory::new()));
+        let location = Path::from("traced.sst");
+        with_trace_id("query-1234", async {
+            assert_eq!(Some("query-1234".to_string()), current_trace_id());
+            store
+                .put(&location, Bytes::from_static(&[1; 64]))
+                .await
+                .unwrap();
+            store.get_range(&location, 8..16).await.unwrap();
+        })
+        .await;
+
+        let records = captured_with("trace_id:query-1234");
+        assert_eq!(2, records.len(), "records:{:?}", records);
+        assert!(records[0].starts_with("Object store put"));
+        assert!(records[0].contains("path:traced.sst, range:0..64"));
+        assert!(records[1].starts_with("Object store get_range"));
+        assert!(records[1].contains("path:traced.sst, range:8..16"));
+
+        // Operations out of the scope are not traced.
+        assert!(current_trace_id().is_none());
+        store.get_range(&location, 16..32).await.unwrap();
+        let records = captured_with("range:16..32");
+        assert_eq!(1, records.len(), "records:{:?}", records);
+        assert!(records[0].contains(&format!("trace_id:{},", NO_TRACE_ID)));
+    }
+}
@@ -0,0 +1,853 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Helpers built on top of [ObjectStore].
+
+use std::{ops::Range, pin::Pin};
+
+use bytes::{Bytes, BytesMut};
+use futures::{
+    pin_mut,
+    stream::{self, BoxStream, Peekable},
+    Stream, StreamExt, TryStreamExt,
+};
+use tokio::io::AsyncWriteExt;
+use upstream::{path::Path, Error as ObjectStoreError, ObjectMeta, ObjectStore, Result};
+
+fn io_error(source: std::io::Error) -> ObjectStoreError {
+    ObjectStoreError::Generic {
+        store: "Stream",
+        source: Box::new(source),
+    }
+}
+
+/// Put the object at `location` with the content drained from `stream`.
+///
+/// The chunks are written by multipart upload so the whole object is never
+/// held in memory. If the store doesn't support multipart upload, the chunks
+/// are buffered and put at once.
+pub async fn put_streaming(
+    store: &dyn ObjectStore,
+    location: &Path,
+    stream: impl Stream<Item = Bytes> + Send,
+) -> Result<()> {
+    pin_mut!(stream);
+
+    let (multipart_id, mut writer) = match store.put_multipart(location).await {
+        Ok(v) => v,
+        Err(ObjectStoreError::NotImplemented) => {
+            let mut buf = BytesMut::new();
+            while let Some(chunk) = stream.next().await {
+                buf.extend_from_slice(&chunk);
+            }
+
+            return store.put(location, buf.freeze()).await;
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut write_res = Ok(());
+    while let Some(chunk) = stream.next().await {
+        write_res = writer.write_all(&chunk).await;
+        if write_res.is_err() {
+            break;
+        }
+    }
+    if write_res.is_ok() {
+        write_res = writer.shutdown().await;
+    }
+
+    if let Err(e) = write_res {
+        // Ignore the error of abort, the error of writing is more important.
+        let _ = store.abort_multipart(location, &multipart_id).await;
+        return Err(io_error(e));
+    }
+
+    Ok(())
+}
+
+/// Copy the object at `from` to `to`, the object at `to` will be overwritten.
+///
+/// The native copy of the store is used if supported, which is atomic and done
+/// on the server side for remote stores (e.g. S3, Aliyun OSS). Otherwise it
+/// falls back to get and put, which is NOT atomic and transfers the whole
+/// object through the client.
+pub async fn copy(store: &dyn ObjectStore, from: &Path, to: &Path) -> Result<()> {
+    match store.copy(from, to).await {
+        Err(ObjectStoreError::NotImplemented) => {
+            let bytes = store.get(from).await?.bytes().await?;
+            store.put(to, bytes).await
+        }
+        res => res,
+    }
+}
+
+/// Move the object at `from` to `to`, the object at `to` will be overwritten.
+///
+/// The native rename of the store is used if supported, e.g. it is atomic for
+/// the local file system, but is implemented as copy and delete by most remote
+/// stores. Otherwise it falls back to [copy] and delete, which is NOT atomic,
+/// and both objects may exist if it fails halfway.
+pub async fn rename(store: &dyn ObjectStore, from: &Path, to: &Path) -> Result<()> {
+    match store.rename(from, to).await {
+        Err(ObjectStoreError::NotImplemented) => {
+            copy(store, from, to).await?;
+            store.delete(from).await
+        }
+        res => res,
+    }
+}
+
+/// Result of [get_if_modified].
+#[derive(Debug)]
+pub enum ConditionalGetResult {
+    /// The object is the same as the cached version.
+    NotModified,
+    /// The object is changed or not cached, along with its meta and content.
+    Modified { meta: ObjectMeta, bytes: Bytes },
+}
+
+/// Returns true if the object described by `latest` may be different from the
+/// `cached` one.
+///
+/// [ObjectMeta] has no ETag, so the object is considered unchanged only if both
+/// its last modified time and size are the same.
+pub fn is_modified(cached: &ObjectMeta, latest: &ObjectMeta) -> bool {
+    cached.location != latest.location
+        || cached.last_modified != latest.last_modified
+        || cached.size != latest.size
+}
+
+/// Read the object at `location` only if it is modified since the `cached`
+/// version, which avoids downloading frequently polled but seldom changed
+/// objects (e.g. manifest), and always read it if `cached` is None.
+///
+/// The underlying [ObjectStore] doesn't support conditional requests yet, so
+/// the latest meta is fetched by [ObjectStore::head] for all stores, which
+/// costs a round trip but no transfer of the content.
+///
+/// NOTICE: The object may be overwritten between the head and the read, and
+/// the returned meta may be older than the content in such case. It is safe
+/// for polling, as the next poll will read the object again.
+pub async fn get_if_modified(
+    store: &dyn ObjectStore,
+    location: &Path,
+    cached: Option<&ObjectMeta>,
+) -> Result<ConditionalGetResult> {
+    let meta = store.head(location).await?;
+    if let Some(cached) = cached {
+        if !is_modified(cached, &meta) {
+            return Ok(ConditionalGetResult::NotModified);
+        }
+    }
+
+    let bytes = store.get(location).await?.bytes().await?;
+    Ok(ConditionalGetResult::Modified { meta, bytes })
+}
+
+/// Ranges separated by a gap no larger than this are merged into one request
+/// by [get_ranges], as reading the gap is cheaper than another round trip.
+pub const GET_RANGES_COALESCE_GAP: usize = 1024 * 1024;
+
+/// Merge the `ranges` sorted by start into disjoint ranges, ranges overlapped
+/// or separated by at most `gap` bytes are merged.
+fn coalesce_ranges(sorted_ranges: &[Range<usize>], gap: usize) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(sorted_ranges.len());
+    for range in sorted_ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(gap) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range.clone()),
+        }
+    }
+
+    merged
+}
+
+/// Read multiple `ranges` of the object at `location`, the returned bytes are
+/// in the same order as the `ranges`.
+///
+/// The ranges are coalesced by [GET_RANGES_COALESCE_GAP] first, and then read
+/// by [ObjectStore::get_ranges], which uses the native vectored read of the
+/// store if supported (e.g. the [LocalFileSystem] reads all ranges from one
+/// opened file), and otherwise reads the coalesced ranges one by one by
+/// [ObjectStore::get_range]. The results are sliced from the coalesced ranges
+/// without copying.
+///
+/// [LocalFileSystem]: upstream::local::LocalFileSystem
+pub async fn get_ranges(
+    store: &dyn ObjectStore,
+    location: &Path,
+    ranges: &[Range<usize>],
+) -> Result<Vec<Bytes>> {
+    if ranges.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut sorted_ranges = ranges.to_vec();
+    sorted_ranges.sort_unstable_by_key(|range| range.start);
+    let coalesced = coalesce_ranges(&sorted_ranges, GET_RANGES_COALESCE_GAP);
+    let fetched = store.get_ranges(location, &coalesced).await?;
+
+    let results = ranges
+        .iter()
+        .map(|range| {
+            // The coalesced ranges are sorted and disjoint, so the last one
+            // starting no later than the range contains it.
+            let idx = coalesced.partition_point(|v| v.start <= range.start) - 1;
+            let offset = coalesced[idx].start;
+            fetched[idx].slice(range.start - offset..range.end - offset)
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Result of [get_suffix].
+#[derive(Debug)]
+pub struct SuffixGetResult {
+    /// Meta of the object, whose size is discovered by [ObjectStore::head].
+    pub meta: ObjectMeta,
+    /// The last bytes of the object, the whole object if it is shorter than
+    /// the requested length.
+    pub bytes: Bytes,
+}
+
+/// Read at most the last `len` bytes of the object at `location`, e.g. the
+/// footer of a parquet file, without reading the whole object.
+///
+/// The size of the object is discovered by [ObjectStore::head] first, then the
+/// suffix is read by [ObjectStore::get_range]. The returned meta can be used to
+/// read more bytes before the suffix if `len` turns out to be too short.
+pub async fn get_suffix(
+    store: &dyn ObjectStore,
+    location: &Path,
+    len: usize,
+) -> Result<SuffixGetResult> {
+    let meta = store.head(location).await?;
+    let range = meta.size.saturating_sub(len)..meta.size;
+    let bytes = if range.is_empty() {
+        Bytes::new()
+    } else {
+        store.get_range(location, range).await?
+    };
+
+    Ok(SuffixGetResult { meta, bytes })
+}
+
+/// Opaque token to continue a paginated listing by [ListPager].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuationToken {
+    /// Number of objects listed before.
+    offset: usize,
+}
+
+/// A page of the listed objects.
+#[derive(Debug)]
+pub struct ListPage {
+    pub objects: Vec<ObjectMeta>,
+    /// Token to list the next page, None if this is the last page.
+    pub next_token: Option<ContinuationToken>,
+}
+
+/// List objects in bounded pages instead of collecting all of them at once.
+///
+/// The pages are chunked from the stream returned by [ObjectStore::list], so
+/// the remote stores which list objects by pages natively (e.g. S3) only
+/// request the next page on demand, and the [LocalFileSystem] walks the
+/// directory lazily.
+///
+/// NOTICE: Resuming from a [ContinuationToken] assumes the listing order is
+/// stable, i.e. the objects under the prefix are not changed between two
+/// listings.
+///
+/// [LocalFileSystem]: upstream::local::LocalFileSystem
+pub struct ListPager<'a> {
+    stream: Peekable<BoxStream<'a, Result<ObjectMeta>>>,
+    page_size: usize,
+    offset: usize,
+}
+
+impl<'a> ListPager<'a> {
+    /// Create a pager listing objects under `prefix` with at most `page_size`
+    /// objects per page, starting from `token` if provided.
+    pub async fn new(
+        store: &'a dyn ObjectStore,
+        prefix: Option<&Path>,
+        page_size: usize,
+        token: Option<ContinuationToken>,
+    ) -> Result<ListPager<'a>> {
+        let mut stream = store.list(prefix).await?.peekable();
+        let offset = token.map(|v| v.offset).unwrap_or(0);
+        for _ in 0..offset {
+            match stream.next().await {
+                Some(res) => {
+                    res?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(Self {
+            stream,
+            page_size: page_size.max(1),
+            offset,
+        })
+    }
+
+    /// Fetch the next page, returns None if all objects have been listed.
+    pub async fn next_page(&mut self) -> Result<Option<ListPage>> {
+        let mut objects = Vec::with_capacity(self.page_size);
+        while objects.len() < self.page_size {
+            match self.stream.next().await {
+                Some(meta) => objects.push(meta?),
+                None => break,
+            }
+        }
+        if objects.is_empty() {
+            return Ok(None);
+        }
+
+        self.offset += objects.len();
+        let has_more = Pin::new(&mut self.stream).peek().await.is_some();
+        let next_token = has_more.then_some(ContinuationToken {
+            offset: self.offset,
+        });
+
+        Ok(Some(ListPage {
+            objects,
+            next_token,
+        }))
+    }
+}
+
+/// Default number of prefixes listed concurrently by [list_prefixes].
+pub const DEFAULT_LIST_PREFIXES_CONCURRENCY: usize = 8;
+
+/// Merged result of listing several prefixes by [list_prefixes].
+#[derive(Debug)]
+pub struct MultiListResult {
+    /// Objects under all the listed prefixes, sorted and deduplicated by
+    /// location.
+    pub objects: Vec<ObjectMeta>,
+    /// Prefixes failed to be listed and their errors, in the order of the given
+    /// prefixes.
+    pub errors: Vec<(Path, ObjectStoreError)>,
+}
+
+impl MultiListResult {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// List the objects under all the `prefixes`, with at most `concurrency`
+/// prefixes listed at the same time.
+///
+/// The objects under overlapped prefixes are only returned once. A failed
+/// prefix doesn't stop listing the others, its error is reported in
+/// [MultiListResult::errors] and the objects listed from it are discarded.
+pub async fn list_prefixes(
+    store: &dyn ObjectStore,
+    prefixes: &[Path],
+    concurrency: usize,
+) -> MultiListResult {
+    let results = stream::iter(prefixes)
+        .map(|prefix| async move {
+            let res = match store.list(Some(prefix)).await {
+                Ok(objects) => objects.try_collect::<Vec<_>>().await,
+                Err(e) => Err(e),
+            };
+            (prefix, res)
+        })
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut objects = Vec::new();
+    let mut errors = Vec::new();
+    for (prefix, res) in results {
+        match res {
+            Ok(listed) => objects.extend(listed),
+            Err(e) => errors.push((prefix.clone(), e)),
+        }
+    }
+    objects.sort_unstable_by(|a, b| a.location.cmp(&b.location));
+    objects.dedup_by(|a, b| a.location == b.location);
+
+    MultiListResult { objects, errors }
+}
+
+/// Default number of objects deleted concurrently by [delete_many].
+pub const DEFAULT_DELETE_MANY_CONCURRENCY: usize = 16;
+
+/// Result of deleting several objects by [delete_many].
+#[derive(Debug)]
+pub struct MultiDeleteResult {
+    /// Objects deleted, in the order of the given paths.
+    pub deleted: Vec<Path>,
+    /// Objects failed to be deleted and their errors, in the order of the
+    /// given paths.
+    pub errors: Vec<(Path, ObjectStoreError)>,
+}
+
+impl MultiDeleteResult {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Delete the objects at `paths`, with at most `concurrency` objects deleted at
+/// the same time.
+///
+/// The [ObjectStore] of current version provides no bulk delete (e.g. the
+/// multi-object delete of S3/OSS), so the objects are deleted by concurrent
+/// single deletes. A failed delete doesn't stop deleting the others, its error
+/// is reported in [MultiDeleteResult::errors].
+pub async fn delete_many(
+    store: &dyn ObjectStore,
+    paths: &[Path],
+    concurrency: usize,
+) -> MultiDeleteResult {
+    let results = stream::iter(paths)
+        .map(|path| async move { (path, store.delete(path).await) })
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut deleted = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for (path, res) in results {
+        match res {
+            Ok(()) => deleted.push(path.clone()),
+            Err(e) => errors.push((path.clone(), e)),
+        }
+    }
+
+    MultiDeleteResult { deleted, errors }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tempfile::tempdir;
+    use upstream::{local::LocalFileSystem, memory::InMemory};
+
+    use super::*;
+
+    async fn check_put_streaming(store: Arc<dyn ObjectStore>) {
+        let chunks = (0..5u8)
+            .map(|i| Bytes::from(vec![i; 1024 * (i as usize + 1)]))
+            .collect::<Vec<_>>();
+        let expect = chunks.iter().flatten().copied().collect::<Vec<_>>();
+
+        let location = Path::from("stream/1.sst");
+        put_streaming(store.as_ref(), &location, stream::iter(chunks))
+            .await
+            .unwrap();
+
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(expect, bytes.to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_put_streaming_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        check_put_streaming(Arc::new(store)).await;
+    }
+
+    #[tokio::test]
+    async fn test_put_streaming_memory() {
+        check_put_streaming(Arc::new(InMemory::new())).await;
+    }
+
+    async fn collect_pages(pager: &mut ListPager<'_>) -> Vec<ListPage> {
+        let mut pages = Vec::new();
+        while let Some(page) = pager.next_page().await.unwrap() {
+            pages.push(page);
+        }
+        pages
+    }
+
+    #[tokio::test]
+    async fn test_list_pages() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        for i in 0..25 {
+            let location = Path::from(format!("manifest/{}.snapshot", i));
+            store
+                .put(&location, Bytes::from_static(b"x"))
+                .await
+                .unwrap();
+        }
+        store
+            .put(&Path::from("other/0.sst"), Bytes::from_static(b"x"))
+            .await
+            .unwrap();
+
+        let prefix = Path::from("manifest");
+        let mut pager = ListPager::new(&store, Some(&prefix), 10, None)
+            .await
+            .unwrap();
+        let pages = collect_pages(&mut pager).await;
+        let page_sizes: Vec<_> = pages.iter().map(|page| page.objects.len()).collect();
+        assert_eq!(vec![10, 10, 5], page_sizes);
+        assert!(pages[0].next_token.is_some());
+        assert!(pages[1].next_token.is_some());
+        assert!(pages[2].next_token.is_none());
+
+        let mut listed: Vec<_> = pages
+            .iter()
+            .flat_map(|page| page.objects.iter().map(|meta| meta.location.to_string()))
+            .collect();
+        listed.sort();
+        listed.dedup();
+        assert_eq!(25, listed.len());
+        assert!(listed.iter().all(|v| v.starts_with("manifest/")));
+
+        // Resume from the token of the first page.
+        let mut pager = ListPager::new(&store, Some(&prefix), 10, pages[0].next_token)
+            .await
+            .unwrap();
+        let resumed = collect_pages(&mut pager).await;
+        assert_eq!(2, resumed.len());
+        for (resumed, page) in resumed.iter().zip(&pages[1..]) {
+            assert_eq!(resumed.objects, page.objects);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_pages_exact_boundary() {
+        let store = InMemory::new();
+        for i in 0..4 {
+            let location = Path::from(format!("{}.sst", i));
+            store
+                .put(&location, Bytes::from_static(b"x"))
+                .await
+                .unwrap();
+        }
+
+        let mut pager = ListPager::new(&store, None, 2, None).await.unwrap();
+        let pages = collect_pages(&mut pager).await;
+        assert_eq!(2, pages.len());
+        assert!(pages[0].next_token.is_some());
+        assert!(pages[1].next_token.is_none());
+    }
+
+    /// Store without native copy and rename.
+    #[derive(Debug)]
+    struct NoCopyStore(InMemory);
+
+    impl std::fmt::Display for NoCopyStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "NoCopyStore")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStore for NoCopyStore {
+        async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+            self.0.put(location, bytes).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> Result<(
+            upstream::MultipartId,
+            Box<dyn tokio::io::AsyncWrite + Unpin + Send>,
+        )> {
+            self.0.put_multipart(location).await
+        }
+
+        async fn abort_multipart(
+            &self,
+            location: &Path,
+            multipart_id: &upstream::MultipartId,
+        ) -> Result<()> {
+            self.0.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get(&self, location: &Path) -> Result<upstream::GetResult> {
+            self.0.get(location).await
+        }
+
+        async fn get_range(&self, location: &Path, range: std::ops::Range<usize>) -> Result<Bytes> {
+            self.0.get_range(location, range).await
+        }
+
+        async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+            self.0.head(location).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.0.delete(location).await
+        }
+
+        async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+            self.0.list(prefix).await
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<upstream::ListResult> {
+            self.0.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, _from: &Path, _to: &Path) -> Result<()> {
+            Err(ObjectStoreError::NotImplemented)
+        }
+
+        async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> Result<()> {
+            Err(ObjectStoreError::NotImplemented)
+        }
+
+        async fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+            Err(ObjectStoreError::NotImplemented)
+        }
+    }
+
+    async fn check_copy_and_rename(store: &dyn ObjectStore) {
+        let from = Path::from("manifest/1.snapshot");
+        let to = Path::from("manifest/2.snapshot");
+        store
+            .put(&from, Bytes::from_static(b"snapshot"))
+            .await
+            .unwrap();
+
+        copy(store, &from, &to).await.unwrap();
+        let bytes = store.get(&to).await.unwrap().bytes().await.unwrap();
+        assert_eq!(b"snapshot", &bytes[..]);
+        assert!(store.head(&from).await.is_ok());
+
+        let renamed = Path::from("manifest/3.snapshot");
+        rename(store, &from, &renamed).await.unwrap();
+        let bytes = store.get(&renamed).await.unwrap().bytes().await.unwrap();
+        assert_eq!(b"snapshot", &bytes[..]);
+        assert!(matches!(
+            store.head(&from).await,
+            Err(ObjectStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_rename_native() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        check_copy_and_rename(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_if_modified_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        let location = Path::from("manifest/current");
+        store
+            .put(&location, Bytes::from_static(b"version-1"))
+            .await
+            .unwrap();
+
+        // Always read if not cached.
+        let cached = match get_if_modified(&store, &location, None).await.unwrap() {
+            ConditionalGetResult::Modified { meta, bytes } => {
+                assert_eq!(b"version-1", &bytes[..]);
+                meta
+            }
+            ConditionalGetResult::NotModified => panic!("Object is not cached"),
+        };
+        assert!(matches!(
+            get_if_modified(&store, &location, Some(&cached))
+                .await
+                .unwrap(),
+            ConditionalGetResult::NotModified
+        ));
+
+        store
+            .put(&location, Bytes::from_static(b"version-22"))
+            .await
+            .unwrap();
+        match get_if_modified(&store, &location, Some(&cached))
+            .await
+            .unwrap()
+        {
+            ConditionalGetResult::Modified { meta, bytes } => {
+                assert_eq!(b"version-22", &bytes[..]);
+                assert!(is_modified(&cached, &meta));
+            }
+            ConditionalGetResult::NotModified => panic!("Object is overwritten"),
+        }
+
+        assert!(matches!(
+            get_if_modified(&store, &Path::from("manifest/not_exist"), Some(&cached)).await,
+            Err(ObjectStoreError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_coalesce_ranges() {
+        let gap = 10;
+        assert!(coalesce_ranges(&[], gap).is_empty());
+        assert_eq!(
+            vec![0..30, 50..60],
+            coalesce_ranges(&[0..10, 10..20, 5..8, 25..30, 50..60], gap)
+        );
+        assert_eq!(vec![0..60], coalesce_ranges(&[0..10, 20..30, 40..60], gap));
+    }
+
+    async fn check_get_ranges(
+        store: &dyn ObjectStore,
+        location: &Path,
+        data: &Bytes,
+        ranges: Vec<Range<usize>>,
+    ) {
+        let results = get_ranges(store, location, &ranges).await.unwrap();
+        assert_eq!(ranges.len(), results.len());
+        for (range, bytes) in ranges.into_iter().zip(results) {
+            assert_eq!(data.slice(range), bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_ranges_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        let location = Path::from("data.sst");
+        let size = 3 * GET_RANGES_COALESCE_GAP;
+        let data: Bytes = (0..size)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>()
+            .into();
+        store.put(&location, data.clone()).await.unwrap();
+
+        // Disjoint ranges too far to be coalesced, in reversed order.
+        check_get_ranges(&store, &location, &data, vec![size - 100..size, 10..20]).await;
+        // Adjacent and overlapped ranges.
+        let ranges = vec![100..200, 0..100, 200..300, 150..250];
+        check_get_ranges(&store, &location, &data, ranges).await;
+        // Mixed ranges with an empty one.
+        let far = 2 * GET_RANGES_COALESCE_GAP;
+        let ranges = vec![far..far + 8, 0..8, 8..8, 8..16];
+        check_get_ranges(&store, &location, &data, ranges).await;
+        assert!(get_ranges(&store, &location, &[]).await.unwrap().is_empty());
+
+        assert!(matches!(
+            get_ranges(&store, &Path::from("not_exist"), &[0..8]).await,
+            Err(ObjectStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_suffix_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        let location = Path::from("data.sst");
+        let data = Bytes::from_static(b"row-groups...metadata\x15\x00\x00\x00PAR1");
+        store.put(&location, data.clone()).await.unwrap();
+
+        // Footer of the object.
+        let suffix = get_suffix(&store, &location, 8).await.unwrap();
+        assert_eq!(data.len(), suffix.meta.size);
+        assert_eq!(b"\x15\x00\x00\x00PAR1", &suffix.bytes[..]);
+        let suffix = get_suffix(&store, &location, 16).await.unwrap();
+        assert_eq!(data.slice(data.len() - 16..), suffix.bytes);
+
+        // The whole object is returned if it is shorter than the length.
+        let suffix = get_suffix(&store, &location, 1024).await.unwrap();
+        assert_eq!(data, suffix.bytes);
+        assert!(get_suffix(&store, &location, 0)
+            .await
+            .unwrap()
+            .bytes
+            .is_empty());
+
+        assert!(matches!(
+            get_suffix(&store, &Path::from("not_exist"), 8).await,
+            Err(ObjectStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_rename_fallback() {
+        let store = NoCopyStore(InMemory::new());
+        assert!(matches!(
+            store.copy(&Path::from("a"), &Path::from("b")).await,
+            Err(ObjectStoreError::NotImplemented)
+        ));
+        check_copy_and_rename(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_list_prefixes_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        let paths = ["manifest/1/a", "manifest/1/b/c", "manifest/2/d", "wal/e"];
+        for path in paths {
+            store
+                .put(&Path::from(path), Bytes::from_static(b"x"))
+                .await
+                .unwrap();
+        }
+
+        let prefixes = [
+            "manifest/1",
+            "manifest/1/b",
+            "manifest/2",
+            "manifest/1",
+            "empty",
+        ]
+        .into_iter()
+        .map(Path::from)
+        .collect::<Vec<_>>();
+        for concurrency in [0, 1, 3, 16] {
+            let result = list_prefixes(&store, &prefixes, concurrency).await;
+            assert!(result.is_ok(), "{:?}", result.errors);
+            let listed = result
+                .objects
+                .iter()
+                .map(|meta| meta.location.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(paths[..3].to_vec(), listed);
+        }
+
+        // A file can't be walked as a directory, which fails this prefix only.
+        let prefixes = vec![Path::from("manifest/1/a/x"), Path::from("wal")];
+        let result = list_prefixes(&store, &prefixes, 2).await;
+        assert_eq!(1, result.errors.len());
+        assert_eq!(prefixes[0], result.errors[0].0);
+        assert_eq!(1, result.objects.len());
+        assert_eq!(Path::from("wal/e"), result.objects[0].location);
+    }
+
+    #[tokio::test]
+    async fn test_delete_many_local() {
+        let dir = tempdir().unwrap();
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        let mut paths = (0..100)
+            .map(|i| Path::from(format!("sst/{}.sst", i)))
+            .collect::<Vec<_>>();
+        for path in &paths {
+            store.put(path, Bytes::from_static(b"x")).await.unwrap();
+        }
+        // A directory can't be deleted as an object, which fails this path only.
+        store
+            .put(&Path::from("sst/dir/0.sst"), Bytes::from_static(b"x"))
+            .await
+            .unwrap();
+        paths.insert(42, Path::from("sst/dir"));
+
+        let result = delete_many(&store, &paths, DEFAULT_DELETE_MANY_CONCURRENCY).await;
+        assert!(!result.is_ok());
+        assert_eq!(1, result.errors.len());
+        assert_eq!(Path::from("sst/dir"), result.errors[0].0);
+        assert_eq!(100, result.deleted.len());
+        for path in &result.deleted {
+            assert!(crate::is_not_found(&store.head(path).await.unwrap_err()));
+        }
+
+        let remaining = list_prefixes(&store, &[Path::from("sst")], 1).await;
+        assert_eq!(1, remaining.objects.len());
+        assert_eq!(Path::from("sst/dir/0.sst"), remaining.objects[0].location);
+    }
+}
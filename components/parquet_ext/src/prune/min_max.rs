@@ -140,7 +140,14 @@ macro_rules! get_min_max_values {
             .collect();
 
         // ignore errors converting to arrays (e.g. different types)
-        ScalarValue::iter_to_array(scalar_values).ok()
+        let values = ScalarValue::iter_to_array(scalar_values).ok()?;
+        // The statistics are in the physical type, e.g. int64 for timestamp, cast
+        // them to the type of the column to compare with the literals.
+        if values.data_type() == data_type {
+            Some(values)
+        } else {
+            Some(arrow::compute::cast(&values, data_type).unwrap_or(values))
+        }
     }};
 }
 
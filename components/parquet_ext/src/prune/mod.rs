@@ -2,3 +2,4 @@
 
 pub mod equal;
 pub mod min_max;
+pub mod page;
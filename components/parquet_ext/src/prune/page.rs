@@ -0,0 +1,346 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Prune the pages of the row groups by the page index.
+//!
+//! Pages of different columns are not aligned, so every column is pruned on
+//! its own: the predicates are evaluated on the page statistics of one column,
+//! while the statistics of other columns are unknown, and the selected rows of
+//! all the columns are intersected.
+
+use std::{ops::Range, sync::Arc};
+
+use arrow::{
+    array::ArrayRef,
+    datatypes::{Field, Schema as ArrowSchema},
+};
+use datafusion::{
+    physical_optimizer::pruning::{PruningPredicate, PruningStatistics},
+    prelude::{Column, Expr},
+    scalar::ScalarValue,
+};
+use log::{error, trace};
+use parquet::{
+    arrow::arrow_reader::{RowSelection, RowSelector},
+    file::{metadata::ParquetMetaData, page_index::index::Index},
+};
+
+/// Select the rows of the `row_groups` whose pages may match the `exprs`
+/// according to the page index in the `meta_data`.
+///
+/// The selection covers the rows of the `row_groups` in order, which should be
+/// the row groups to read. Returns None if the page index is not loaded, then
+/// all the rows of the row groups should be read.
+pub fn select_rows(
+    schema: Arc<ArrowSchema>,
+    exprs: &[Expr],
+    meta_data: &ParquetMetaData,
+    row_groups: &[usize],
+) -> Option<RowSelection> {
+    let column_indexes = meta_data.page_indexes()?;
+    let offset_indexes = meta_data.offset_indexes()?;
+
+    let predicates = exprs
+        .iter()
+        .filter_map(
+            |expr| match PruningPredicate::try_new(expr.clone(), schema.clone()) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    // Ignore the predicate, so the pages are not pruned by it.
+                    error!("fail to build pruning predicate, err:{}", e);
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let schema_descr = meta_data.file_metadata().schema_descr();
+    // Leaf column index of the fields, only the top level primitive columns have
+    // page statistics to prune.
+    let leaves = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            schema_descr.columns().iter().position(|column| {
+                column.max_rep_level() == 0 && column.path().string() == *field.name()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut selectors = Vec::new();
+    for &row_group_idx in row_groups {
+        let num_rows = meta_data.row_group(row_group_idx).num_rows() as usize;
+        let mut selected = vec![0..num_rows];
+        for (field, leaf) in schema.fields().iter().zip(&leaves) {
+            let leaf = match leaf {
+                Some(v) => *v,
+                None => continue,
+            };
+            let (index, page_locations) = match (
+                column_indexes.get(row_group_idx).and_then(|v| v.get(leaf)),
+                offset_indexes.get(row_group_idx).and_then(|v| v.get(leaf)),
+            ) {
+                (Some(index), Some(page_locations)) => (index, page_locations),
+                _ => continue,
+            };
+            let page_first_rows = page_locations
+                .iter()
+                .map(|v| v.first_row_index as usize)
+                .collect::<Vec<_>>();
+            let stats = PagePruningStatistics {
+                field,
+                index,
+                num_pages: page_first_rows.len(),
+            };
+
+            for predicate in &predicates {
+                match predicate.prune(&stats) {
+                    Ok(pages) => {
+                        let ranges = page_rows(&page_first_rows, &pages, num_rows);
+                        selected = intersect_ranges(&selected, &ranges);
+                    }
+                    Err(e) => trace!("fail to prune pages, column:{}, err:{}", field.name(), e),
+                }
+            }
+        }
+
+        push_selectors(&mut selectors, &selected, num_rows);
+    }
+
+    Some(RowSelection::from(selectors))
+}
+
+/// Rows of the pages to read, the `i`th page starts from `page_first_rows[i]`
+/// and ends before the next page or `num_rows`.
+fn page_rows(page_first_rows: &[usize], pages: &[bool], num_rows: usize) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, _) in pages.iter().enumerate().filter(|(_, read)| **read) {
+        let start = page_first_rows[i];
+        let end = page_first_rows.get(i + 1).copied().unwrap_or(num_rows);
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+
+    ranges
+}
+
+/// Intersect the two sorted and non-overlapping lists of row ranges.
+fn intersect_ranges(ranges0: &[Range<usize>], ranges1: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut intersect = Vec::new();
+
+    let (mut i0, mut i1) = (0, 0);
+    while i0 < ranges0.len() && i1 < ranges1.len() {
+        let (range0, range1) = (&ranges0[i0], &ranges1[i1]);
+        let start = range0.start.max(range1.start);
+        let end = range0.end.min(range1.end);
+        if start < end {
+            intersect.push(start..end);
+        }
+
+        if range0.end < range1.end {
+            i0 += 1;
+        } else {
+            i1 += 1;
+        }
+    }
+
+    intersect
+}
+
+/// Append the selectors of a row group of `num_rows` rows, only the rows in
+/// `ranges` are selected.
+fn push_selectors(selectors: &mut Vec<RowSelector>, ranges: &[Range<usize>], num_rows: usize) {
+    let mut next_row = 0;
+    for range in ranges {
+        if range.start > next_row {
+            selectors.push(RowSelector::skip(range.start - next_row));
+        }
+        selectors.push(RowSelector::select(range.end - range.start));
+        next_row = range.end;
+    }
+    if num_rows > next_row {
+        selectors.push(RowSelector::skip(num_rows - next_row));
+    }
+}
+
+/// Wraps the page index of a column in a way that implements
+/// [`PruningStatistics`], every page is a container.
+struct PagePruningStatistics<'a> {
+    field: &'a Field,
+    index: &'a Index,
+    num_pages: usize,
+}
+
+impl<'a> PagePruningStatistics<'a> {
+    fn page_values(&self, column: &Column, is_min: bool) -> Option<ArrayRef> {
+        if column.name != *self.field.name() {
+            return None;
+        }
+
+        macro_rules! collect_values {
+            ($index:expr, $scalar:ident) => {
+                $index
+                    .indexes
+                    .iter()
+                    .map(|page| ScalarValue::$scalar(if is_min { page.min } else { page.max }))
+                    .collect::<Vec<_>>()
+            };
+        }
+        let scalar_values = match self.index {
+            Index::INT32(v) => collect_values!(v, Int32),
+            Index::INT64(v) => collect_values!(v, Int64),
+            Index::FLOAT(v) => collect_values!(v, Float32),
+            Index::DOUBLE(v) => collect_values!(v, Float64),
+            // Type not supported yet.
+            _ => return None,
+        };
+        if scalar_values.len() != self.num_pages {
+            return None;
+        }
+
+        // Ignore errors converting to arrays (e.g. different types).
+        let values = ScalarValue::iter_to_array(scalar_values).ok()?;
+        // The statistics are in the physical type, e.g. int64 for timestamp, cast
+        // them to the type of the column to compare with the literals.
+        let data_type = self.field.data_type();
+        if values.data_type() == data_type {
+            Some(values)
+        } else {
+            Some(arrow::compute::cast(&values, data_type).unwrap_or(values))
+        }
+    }
+}
+
+impl<'a> PruningStatistics for PagePruningStatistics<'a> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.page_values(column, true)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.page_values(column, false)
+    }
+
+    fn num_containers(&self) -> usize {
+        self.num_pages
+    }
+
+    // TODO: support this.
+    fn null_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Int64Array, StringArray},
+        datatypes::DataType,
+        record_batch::RecordBatch,
+    };
+    use bytes::Bytes;
+    use datafusion::logical_expr::{expr_fn::col, lit};
+    use parquet::{
+        arrow::{
+            arrow_reader::{ArrowReaderOptions, ParquetRecordBatchReaderBuilder},
+            ArrowWriter,
+        },
+        file::properties::WriterProperties,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_intersect_ranges() {
+        let test_cases = vec![
+            (vec![0..10], vec![2..4, 6..12], vec![2..4, 6..10]),
+            (vec![0..3, 5..8], vec![2..6], vec![2..3, 5..6]),
+            (vec![], vec![0..3], vec![]),
+            (vec![0..3], vec![3..6], vec![]),
+        ];
+
+        for (ranges0, ranges1, expect) in test_cases {
+            assert_eq!(expect, intersect_ranges(&ranges0, &ranges1));
+        }
+    }
+
+    /// Write `num_rows` rows of increasing `ts` into a row group of small
+    /// pages.
+    fn write_small_pages(num_rows: i64) -> (Arc<ArrowSchema>, Bytes) {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("ts", DataType::Int64, false),
+            Field::new("tag", DataType::Utf8, false),
+        ]));
+        let ts = Int64Array::from_iter_values(0..num_rows);
+        let tags = StringArray::from_iter_values((0..num_rows).map(|v| format!("tag{}", v % 7)));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(ts), Arc::new(tags)]).unwrap();
+
+        let props = WriterProperties::builder()
+            .set_dictionary_enabled(false)
+            .set_data_page_size_limit(256)
+            .set_write_batch_size(32)
+            .build();
+        let mut buf = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buf, schema.clone(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        (schema, Bytes::from(buf))
+    }
+
+    #[test]
+    fn test_select_single_page() {
+        let num_rows = 1000;
+        let (schema, bytes) = write_small_pages(num_rows);
+        let options = ArrowReaderOptions::new().with_page_index(true);
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new_with_options(bytes.clone(), options).unwrap();
+        let meta_data = builder.metadata().clone();
+        assert_eq!(1, meta_data.num_row_groups());
+
+        let page_first_rows = meta_data.offset_indexes().unwrap()[0][0]
+            .iter()
+            .map(|v| v.first_row_index as usize)
+            .collect::<Vec<_>>();
+        assert!(page_first_rows.len() > 3, "{:?}", page_first_rows);
+
+        // Matches the rows of the second page of `ts` only.
+        let second_page = page_first_rows[1]..page_first_rows[2];
+        let exprs = [col("ts").eq(lit(second_page.start as i64 + 1))];
+        let selection = select_rows(schema.clone(), &exprs, &meta_data, &[0]).unwrap();
+        let expect = vec![
+            RowSelector::skip(second_page.start),
+            RowSelector::select(second_page.len()),
+            RowSelector::skip(num_rows as usize - second_page.end),
+        ];
+        assert_eq!(RowSelection::from(expect.clone()), selection);
+
+        // Other pages are skipped by the reader.
+        let reader = builder.with_row_selection(selection).build().unwrap();
+        let mut read_ts = Vec::new();
+        for batch in reader {
+            let batch = batch.unwrap();
+            let ts = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            read_ts.extend(ts.iter().map(Option::unwrap));
+        }
+        let expect_ts = second_page.map(|v| v as i64).collect::<Vec<_>>();
+        assert_eq!(expect_ts, read_ts);
+
+        // Predicates on the columns without supported page statistics select all rows.
+        let exprs = [col("tag").eq(lit("tag1"))];
+        let selection = select_rows(schema.clone(), &exprs, &meta_data, &[0]).unwrap();
+        assert_eq!(
+            RowSelection::from(vec![RowSelector::select(num_rows as usize)]),
+            selection
+        );
+
+        // No selection without the page index.
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+        assert!(select_rows(schema, &exprs, builder.metadata(), &[0]).is_none());
+    }
+}
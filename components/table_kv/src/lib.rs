@@ -10,9 +10,24 @@ pub mod obkv;
 #[cfg(test)]
 mod tests;
 
+/// Durability level of a write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// The write is persisted before it returns.
+    #[default]
+    Durable,
+    /// The write may be buffered by the backend and lost on crash, but is
+    /// faster than a durable write.
+    ///
+    /// A backend that can't buffer writes treats it as [Durability::Durable].
+    Buffered,
+}
+
 /// Context during write.
-#[derive(Default)]
-pub struct WriteContext {}
+#[derive(Debug, Clone, Default)]
+pub struct WriteContext {
+    pub durability: Durability,
+}
 
 /// Write operations.
 pub trait WriteBatch: Default {
@@ -147,6 +162,28 @@ pub trait ScanIter: fmt::Debug {
 pub trait TableError: std::error::Error {
     /// Is it primary key duplicate error.
     fn is_primary_key_duplicate(&self) -> bool;
+
+    /// Is it a transient error, so the failed operation can be retried.
+    ///
+    /// No error is retryable by default.
+    fn is_retryable(&self) -> bool {
+        false
+    }
+
+    /// Is it a timeout error, the operation may succeed with a longer timeout.
+    ///
+    /// No error is a timeout by default.
+    fn is_timeout(&self) -> bool {
+        false
+    }
+
+    /// Is it table not found error.
+    ///
+    /// Returns false by default, so missing tables are reported as other
+    /// failures.
+    fn is_table_not_found(&self) -> bool {
+        false
+    }
 }
 
 /// Kv service provided by a relational database.
@@ -33,6 +33,10 @@ impl TableError for Error {
     fn is_primary_key_duplicate(&self) -> bool {
         matches!(self, Self::PrimaryKeyDuplicate { .. })
     }
+
+    fn is_table_not_found(&self) -> bool {
+        matches!(self, Self::TableNotFound { .. })
+    }
 }
 
 enum WriteOp {
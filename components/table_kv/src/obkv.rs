@@ -193,6 +193,29 @@ impl TableError for Error {
             code == obkv::ResultCodes::OB_ERR_PRIMARY_KEY_DUPLICATE
         })
     }
+
+    fn is_retryable(&self) -> bool {
+        self.obkv_result_code().map_or(false, |code| {
+            matches!(
+                code,
+                obkv::ResultCodes::OB_TIMEOUT | obkv::ResultCodes::OB_TRANS_TIMEOUT
+            )
+        })
+    }
+
+    fn is_timeout(&self) -> bool {
+        self.obkv_result_code().map_or(false, |code| {
+            matches!(
+                code,
+                obkv::ResultCodes::OB_TIMEOUT | obkv::ResultCodes::OB_TRANS_TIMEOUT
+            )
+        })
+    }
+
+    fn is_table_not_found(&self) -> bool {
+        self.obkv_result_code()
+            .map_or(false, |code| code == obkv::ResultCodes::OB_TABLE_NOT_EXIST)
+    }
 }
 
 const KEY_COLUMN_NAME: &str = "k";
@@ -449,6 +472,12 @@ impl TableKv for ObkvImpl {
             .context(TruncateTable { table_name })
     }
 
+    /// Obkv has no option to buffer writes, the batch is always committed
+    /// durably before returning, so [Durability::Buffered] is treated as
+    /// [Durability::Durable].
+    ///
+    /// [Durability::Buffered]: crate::Durability::Buffered
+    /// [Durability::Durable]: crate::Durability::Durable
     fn write(
         &self,
         _ctx: WriteContext,
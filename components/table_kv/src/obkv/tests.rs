@@ -8,6 +8,7 @@ use log::{error, info};
 use rand::prelude::*;
 
 use super::*;
+use crate::Durability;
 
 const MAX_TABLE_ID: u32 = 30;
 
@@ -32,12 +33,21 @@ impl ObkvTester {
     }
 
     fn try_insert_batch(&self, table_name: &str, pairs: &[(&[u8], &[u8])]) -> Result<()> {
+        self.try_insert_batch_with_ctx(WriteContext::default(), table_name, pairs)
+    }
+
+    fn try_insert_batch_with_ctx(
+        &self,
+        ctx: WriteContext,
+        table_name: &str,
+        pairs: &[(&[u8], &[u8])],
+    ) -> Result<()> {
         let mut batch = ObkvWriteBatch::with_capacity(pairs.len());
         for pair in pairs {
             batch.insert(pair.0, pair.1);
         }
 
-        self.obkv.write(WriteContext::default(), table_name, batch)
+        self.obkv.write(ctx, table_name, batch)
     }
 
     fn insert_or_update_batch(&self, table_name: &str, pairs: &[(&[u8], &[u8])]) {
@@ -188,6 +198,8 @@ fn test_obkv() {
     test_min_max_scan(&tester, &table_name);
 
     test_reverse_scan(&tester, &table_name);
+
+    test_buffered_write(&tester, &table_name);
 }
 
 // This test does a full scan, need to truncate table.
@@ -536,3 +548,25 @@ fn test_prefix_scan(tester: &ObkvTester, table_name: &str) {
         check_scan_result(&data[1..4], &result);
     }
 }
+
+// Obkv always writes durably, so the buffered writes behave the same as the
+// durable ones: visible at once and never silently dropped.
+fn test_buffered_write(tester: &ObkvTester, table_name: &str) {
+    let data: [(&[u8], &[u8]); 2] = [(b"buffered:a1", b"value a1"), (b"buffered:b1", b"value b1")];
+    let ctx = WriteContext {
+        durability: Durability::Buffered,
+    };
+    tester
+        .try_insert_batch_with_ctx(ctx.clone(), table_name, &data)
+        .unwrap();
+
+    for pair in data {
+        let v = tester.get(table_name, pair.0).unwrap();
+        assert_eq!(pair.1, v);
+    }
+
+    // Duplicate keys are still rejected by the buffered writes.
+    assert!(tester
+        .try_insert_batch_with_ctx(ctx, table_name, &data[..1])
+        .is_err());
+}
@@ -2,7 +2,10 @@
 
 //! Interpreter trait
 
+use std::collections::HashMap;
+
 use async_trait::async_trait;
+use common_types::table::TableId;
 use query_engine::executor::RecordBatchVec;
 use snafu::Snafu;
 
@@ -53,6 +56,31 @@ pub enum Output {
     AffectedRows(usize),
     /// A vec of RecordBatch
     Records(RecordBatchVec),
+    /// The table is created, or already exists if `IF NOT EXISTS` is specified
+    TableCreated(CreatedTable),
+}
+
+impl Output {
+    /// Returns the affected rows number, no rows are affected by ddl.
+    pub fn affected_rows(&self) -> Option<usize> {
+        match self {
+            Output::AffectedRows(n) => Some(*n),
+            Output::TableCreated(_) => Some(0),
+            Output::Records(_) => None,
+        }
+    }
+}
+
+/// Info of the table returned by create table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedTable {
+    pub table_id: TableId,
+    pub table_name: String,
+    pub schema_name: String,
+    /// Options of the table resolved by the table engine, i.e. the options in
+    /// the create request merged with the defaults, or the options of the
+    /// existing table.
+    pub options: HashMap<String, String>,
 }
 
 impl TryFrom<Output> for RecordBatchVec {
@@ -1,58 +1,228 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
 use async_trait::async_trait;
+use catalog::manager::ManagerRef;
 use common_types::schema::SchemaEncoder;
-use log::info;
+use futures::{stream, StreamExt};
+use log::{debug, info};
 use meta_client::{
-    types::{CreateTableRequest, DropTableRequest},
+    types::{CreateTableRequest, DropTableRequest, RouteTablesRequest},
     MetaClientRef,
 };
-use snafu::ResultExt;
+use snafu::{ensure, ResultExt};
 use sql::plan::{CreateTablePlan, DropTablePlan};
-use table_engine::engine::TableEngineRef;
+use table_engine::{
+    engine::TableEngineRef, table::TableRef, ANALYTIC_ENGINE_TYPE, MEMORY_ENGINE_TYPE,
+};
 
 use crate::{
     context::Context,
-    interpreter::Output,
-    table_manipulator::{CreateWithCause, DropWithCause, Result, TableManipulator},
+    interpreter::{CreatedTable, Output},
+    table_manipulator::{
+        CreateWithCause, DropWithCause, Result, TableExists, TableManipulator, TableNotExists,
+        UnknownEngine,
+    },
 };
 
+/// Preview of what a ddl plan would do, the plan is validated against the meta
+/// catalog but not executed.
+#[derive(Debug, Clone)]
+pub enum DdlPreview {
+    /// The table would be created by the request.
+    CreateTable(CreateTableRequest),
+    /// The table would be dropped by the request.
+    DropTable(DropTableRequest),
+    /// Nothing would happen, e.g. create an existing table with `IF NOT
+    /// EXISTS`.
+    Noop { reason: String },
+}
+
+/// Default max number of tables created concurrently by `create_tables`.
+const DEFAULT_CREATE_CONCURRENCY: usize = 16;
+
 pub struct TableManipulatorImpl {
     meta_client: MetaClientRef,
+    create_concurrency: usize,
+    /// Catalog of the tables opened locally.
+    catalog_manager: Option<ManagerRef>,
 }
 
 impl TableManipulatorImpl {
     pub fn new(meta_client: MetaClientRef) -> Self {
-        Self { meta_client }
+        Self {
+            meta_client,
+            create_concurrency: DEFAULT_CREATE_CONCURRENCY,
+            catalog_manager: None,
+        }
     }
-}
 
-#[async_trait]
-impl TableManipulator for TableManipulatorImpl {
-    async fn create_table(
+    /// Set max number of tables created concurrently by `create_tables`.
+    pub fn with_create_concurrency(mut self, create_concurrency: usize) -> Self {
+        self.create_concurrency = create_concurrency.max(1);
+        self
+    }
+
+    /// Set the catalog of the tables opened locally, then creating a table
+    /// with `IF NOT EXISTS` returns at once if the table is found in it,
+    /// without requesting the meta.
+    pub fn with_catalog_manager(mut self, catalog_manager: ManagerRef) -> Self {
+        self.catalog_manager = Some(catalog_manager);
+        self
+    }
+
+    /// Find the table in the local catalog, returns None if the catalog is not
+    /// set, the table is not opened locally or fails to be looked up, then the
+    /// meta should be requested.
+    fn find_local_table(&self, ctx: &Context, table_name: &str) -> Option<TableRef> {
+        let catalog = self
+            .catalog_manager
+            .as_ref()?
+            .catalog_by_name(ctx.default_catalog())
+            .ok()??;
+        let schema = catalog.schema_by_name(ctx.default_schema()).ok()??;
+
+        schema.table_by_name(table_name).ok()?
+    }
+
+    /// Validate the create table plan without creating the table: the schema
+    /// is encoded, the engine must be supported and the table name must be
+    /// available unless `IF NOT EXISTS` is specified.
+    pub async fn preview_create_table(
         &self,
-        ctx: Context,
+        ctx: &Context,
         plan: CreateTablePlan,
-        _table_engine: TableEngineRef,
-    ) -> Result<Output> {
+    ) -> Result<DdlPreview> {
+        ensure!(
+            matches!(
+                plan.engine.as_str(),
+                ANALYTIC_ENGINE_TYPE | MEMORY_ENGINE_TYPE
+            ),
+            UnknownEngine {
+                engine: plan.engine,
+            }
+        );
+
+        let req = Self::build_create_request(ctx, plan)?;
+        let exists = self
+            .table_exists(&req.schema_name, &req.name)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .with_context(|| CreateWithCause {
+                msg: format!("failed to route table by meta client, req:{:?}", req),
+            })?;
+        if !exists {
+            return Ok(DdlPreview::CreateTable(req));
+        }
+
+        ensure!(req.create_if_not_exist, TableExists { table: req.name });
+
+        Ok(DdlPreview::Noop {
+            reason: format!("table {} already exists", req.name),
+        })
+    }
+
+    /// Validate the drop table plan without dropping the table: the table must
+    /// exist unless `IF EXISTS` is specified.
+    pub async fn preview_drop_table(
+        &self,
+        ctx: &Context,
+        plan: DropTablePlan,
+    ) -> Result<DdlPreview> {
+        let req = DropTableRequest {
+            schema_name: ctx.default_schema().to_string(),
+            name: plan.table,
+        };
+        let exists = self
+            .table_exists(&req.schema_name, &req.name)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .with_context(|| DropWithCause {
+                msg: format!("failed to route table by meta client, req:{:?}", req),
+            })?;
+        if exists {
+            return Ok(DdlPreview::DropTable(req));
+        }
+
+        ensure!(plan.if_exists, TableNotExists { table: req.name });
+
+        Ok(DdlPreview::Noop {
+            reason: format!("table {} not exists", req.name),
+        })
+    }
+
+    fn build_create_request(ctx: &Context, plan: CreateTablePlan) -> Result<CreateTableRequest> {
+        // The encoding error tells which column is invalid, so the plan is not
+        // dumped into the message.
         let encoded_schema = SchemaEncoder::default()
             .encode(&plan.table_schema)
             .map_err(|e| Box::new(e) as _)
             .with_context(|| CreateWithCause {
                 msg: format!(
-                    "fail to encode table schema, ctx:{:?}, plan:{:?}",
-                    ctx, plan
+                    "fail to encode table schema, request_id:{}, table:{}",
+                    ctx.request_id(),
+                    plan.table
                 ),
             })?;
 
-        let req = CreateTableRequest {
+        Ok(CreateTableRequest {
             schema_name: ctx.default_schema().to_string(),
             name: plan.table,
             encoded_schema,
             engine: plan.engine,
             create_if_not_exist: plan.if_not_exists,
             options: plan.options,
+        })
+    }
+
+    async fn table_exists(&self, schema_name: &str, table_name: &str) -> meta_client::Result<bool> {
+        let req = RouteTablesRequest {
+            schema_name: schema_name.to_string(),
+            table_names: vec![table_name.to_string()],
         };
+        let resp = self.meta_client.route_tables(req).await?;
+
+        Ok(resp.entries.contains_key(table_name))
+    }
+}
+
+#[async_trait]
+impl TableManipulator for TableManipulatorImpl {
+    async fn create_table(
+        &self,
+        ctx: Context,
+        plan: CreateTablePlan,
+        table_engine: TableEngineRef,
+    ) -> Result<Output> {
+        if plan.if_not_exists {
+            if let Some(table) = self.find_local_table(&ctx, &plan.table) {
+                debug!(
+                    "Table to create already exists locally, request_id:{}, table:{}",
+                    ctx.request_id(),
+                    plan.table
+                );
+
+                return Ok(Output::TableCreated(CreatedTable {
+                    table_id: table.id().as_u64(),
+                    table_name: plan.table,
+                    schema_name: ctx.default_schema().to_string(),
+                    options: table.options(),
+                }));
+            }
+        }
+
+        // Resolve the options before requesting the meta, so the table is not
+        // created with invalid options.
+        let options = table_engine
+            .resolve_create_options(&plan.engine, &plan.table, &plan.options)
+            .map_err(|e| Box::new(e) as _)
+            .with_context(|| CreateWithCause {
+                msg: format!(
+                    "invalid table options, request_id:{}, table:{}",
+                    ctx.request_id(),
+                    plan.table
+                ),
+            })?;
+        let req = Self::build_create_request(&ctx, plan)?;
 
         let resp = self
             .meta_client
@@ -68,7 +238,31 @@ impl TableManipulator for TableManipulatorImpl {
             req, resp
         );
 
-        Ok(Output::AffectedRows(0))
+        // The id of the existing table is returned if the table already exists.
+        Ok(Output::TableCreated(CreatedTable {
+            table_id: resp.created_table.id,
+            table_name: resp.created_table.name,
+            schema_name: resp.created_table.schema_name,
+            options,
+        }))
+    }
+
+    /// Create tables concurrently, at most `create_concurrency` requests to the
+    /// meta are in flight at the same time.
+    ///
+    /// All the creates are polled on the current task, so the schemas are still
+    /// encoded one at a time, which is cheap compared to the requests.
+    async fn create_tables(
+        &self,
+        ctx: Context,
+        plans: Vec<CreateTablePlan>,
+        table_engine: TableEngineRef,
+    ) -> Vec<Result<Output>> {
+        stream::iter(plans)
+            .map(|plan| self.create_table(ctx.clone(), plan, table_engine.clone()))
+            .buffered(self.create_concurrency)
+            .collect()
+            .await
     }
 
     async fn drop_table(
@@ -99,3 +293,278 @@ impl TableManipulator for TableManipulatorImpl {
         Ok(Output::AffectedRows(0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use common_types::{request_id::RequestId, tests::build_schema};
+    use table_engine::{
+        engine::{
+            CloseTableRequest, CreateTableRequest as EngineCreateTableRequest,
+            DropTableRequest as EngineDropTableRequest, OpenTableRequest, TableEngine,
+        },
+        memory::MemoryTable,
+        table::TableId,
+    };
+
+    use super::*;
+    use crate::table_manipulator::{
+        tests::{MockCatalog, MockMetaClient},
+        Error,
+    };
+
+    /// Table engine only resolves the options for the meta based manipulator,
+    /// the default ttl "7d" is added if not set.
+    struct MockTableEngine;
+
+    #[async_trait]
+    impl TableEngine for MockTableEngine {
+        fn engine_type(&self) -> &str {
+            ANALYTIC_ENGINE_TYPE
+        }
+
+        async fn close(&self) -> table_engine::engine::Result<()> {
+            unimplemented!()
+        }
+
+        async fn create_table(
+            &self,
+            _request: EngineCreateTableRequest,
+        ) -> table_engine::engine::Result<TableRef> {
+            unimplemented!()
+        }
+
+        async fn drop_table(
+            &self,
+            _request: EngineDropTableRequest,
+        ) -> table_engine::engine::Result<bool> {
+            unimplemented!()
+        }
+
+        async fn open_table(
+            &self,
+            _request: OpenTableRequest,
+        ) -> table_engine::engine::Result<Option<TableRef>> {
+            unimplemented!()
+        }
+
+        async fn close_table(
+            &self,
+            _request: CloseTableRequest,
+        ) -> table_engine::engine::Result<()> {
+            unimplemented!()
+        }
+
+        fn resolve_create_options(
+            &self,
+            _engine: &str,
+            _table: &str,
+            options: &HashMap<String, String>,
+        ) -> table_engine::engine::Result<HashMap<String, String>> {
+            let mut options = options.clone();
+            options
+                .entry("ttl".to_string())
+                .or_insert_with(|| "7d".to_string());
+            Ok(options)
+        }
+    }
+
+    fn new_create_plan(table: &str) -> CreateTablePlan {
+        CreateTablePlan {
+            engine: ANALYTIC_ENGINE_TYPE.to_string(),
+            if_not_exists: false,
+            table: table.to_string(),
+            table_schema: build_schema(),
+            options: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_tables_partial_failure() {
+        let meta_client = Arc::new(MockMetaClient::new(100));
+        let manipulator = TableManipulatorImpl::new(meta_client).with_create_concurrency(2);
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "public".to_string())
+            .build();
+
+        let tables = ["t0", "bad1", "t2", "bad3", "t4"];
+        let plans = tables.iter().map(|t| new_create_plan(t)).collect();
+        let results = manipulator
+            .create_tables(ctx, plans, Arc::new(MockTableEngine))
+            .await;
+
+        assert_eq!(tables.len(), results.len());
+        let mut created_tables = Vec::new();
+        for (table, result) in tables.iter().zip(results) {
+            if table.starts_with("bad") {
+                assert!(result.is_err(), "table:{}", table);
+                continue;
+            }
+
+            match result.unwrap() {
+                Output::TableCreated(created) => {
+                    assert_eq!(*table, created.table_name);
+                    assert_eq!("public", created.schema_name);
+                    assert_eq!("7d", created.options["ttl"]);
+                    created_tables.push(created.table_id);
+                }
+                _ => panic!("unexpected output, table:{}", table),
+            }
+        }
+        created_tables.sort_unstable();
+        assert_eq!(vec![100, 101, 102], created_tables);
+    }
+
+    #[tokio::test]
+    async fn test_create_if_not_exists_locally() {
+        let catalog = MockCatalog::default();
+        catalog.add_table(Arc::new(MemoryTable::new(
+            "t0".to_string(),
+            TableId::from(42),
+            build_schema(),
+            ANALYTIC_ENGINE_TYPE.to_string(),
+        )));
+        let meta_client = Arc::new(MockMetaClient::new(100));
+        let manipulator =
+            TableManipulatorImpl::new(meta_client.clone()).with_catalog_manager(Arc::new(catalog));
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "public".to_string())
+            .build();
+        let create_table = |table: &str, if_not_exists: bool| {
+            let plan = CreateTablePlan {
+                if_not_exists,
+                ..new_create_plan(table)
+            };
+            manipulator.create_table(ctx.clone(), plan, Arc::new(MockTableEngine))
+        };
+        let created_table_id = |output: Output| match output {
+            Output::TableCreated(created) => created.table_id,
+            _ => panic!("unexpected output"),
+        };
+
+        // The existing table is returned without requesting the meta.
+        let output = create_table("t0", true).await.unwrap();
+        assert_eq!(42, created_table_id(output));
+        assert_eq!(0, meta_client.num_create_requests());
+
+        // Falls back to the meta if the table is not opened locally or `IF NOT EXISTS`
+        // is not specified.
+        let output = create_table("t1", true).await.unwrap();
+        assert_eq!(100, created_table_id(output));
+        let output = create_table("t0", false).await.unwrap();
+        assert_eq!(101, created_table_id(output));
+        assert_eq!(2, meta_client.num_create_requests());
+
+        // Falls back to the meta if the schema is not found locally.
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "other".to_string())
+            .build();
+        let plan = CreateTablePlan {
+            if_not_exists: true,
+            ..new_create_plan("t0")
+        };
+        manipulator
+            .create_table(ctx, plan, Arc::new(MockTableEngine))
+            .await
+            .unwrap();
+        assert_eq!(3, meta_client.num_create_requests());
+    }
+
+    #[tokio::test]
+    async fn test_preview_create_table() {
+        let meta_client = Arc::new(MockMetaClient::new(100).with_tables(&[("t0", 42)]));
+        let manipulator = TableManipulatorImpl::new(meta_client.clone());
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "public".to_string())
+            .build();
+        let preview = |plan| manipulator.preview_create_table(&ctx, plan);
+
+        match preview(new_create_plan("t1")).await.unwrap() {
+            DdlPreview::CreateTable(req) => {
+                assert_eq!("t1", req.name);
+                assert_eq!("public", req.schema_name);
+                assert!(!req.create_if_not_exist);
+                let schema = SchemaEncoder::default()
+                    .decode(&req.encoded_schema)
+                    .unwrap();
+                assert_eq!(build_schema(), schema);
+            }
+            v => panic!("unexpected preview:{:?}", v),
+        }
+
+        let err = preview(new_create_plan("t0")).await.unwrap_err();
+        assert!(matches!(err, Error::TableExists { .. }), "{}", err);
+
+        let plan = CreateTablePlan {
+            if_not_exists: true,
+            ..new_create_plan("t0")
+        };
+        let preview_output = preview(plan).await.unwrap();
+        assert!(
+            matches!(preview_output, DdlPreview::Noop { .. }),
+            "{:?}",
+            preview_output
+        );
+
+        let plan = CreateTablePlan {
+            engine: "unknown".to_string(),
+            ..new_create_plan("t1")
+        };
+        let err = preview(plan).await.unwrap_err();
+        assert!(matches!(err, Error::UnknownEngine { .. }), "{}", err);
+
+        // Nothing is created by the preview.
+        assert_eq!(0, meta_client.num_create_requests());
+    }
+
+    #[tokio::test]
+    async fn test_preview_drop_table() {
+        let meta_client = Arc::new(MockMetaClient::new(100).with_tables(&[("t0", 42)]));
+        let manipulator = TableManipulatorImpl::new(meta_client);
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "public".to_string())
+            .build();
+        let preview = |table: &str, if_exists: bool| {
+            let plan = DropTablePlan {
+                engine: ANALYTIC_ENGINE_TYPE.to_string(),
+                if_exists,
+                table: table.to_string(),
+            };
+            manipulator.preview_drop_table(&ctx, plan)
+        };
+
+        match preview("t0", false).await.unwrap() {
+            DdlPreview::DropTable(req) => {
+                assert_eq!("t0", req.name);
+                assert_eq!("public", req.schema_name);
+            }
+            v => panic!("unexpected preview:{:?}", v),
+        }
+
+        let err = preview("t1", false).await.unwrap_err();
+        assert!(matches!(err, Error::TableNotExists { .. }), "{}", err);
+
+        let preview_output = preview("t1", true).await.unwrap();
+        assert!(
+            matches!(preview_output, DdlPreview::Noop { .. }),
+            "{:?}",
+            preview_output
+        );
+
+        // Tables of other schemas are not routed.
+        let ctx = Context::builder(RequestId::next_id())
+            .default_catalog_and_schema("ceresdb".to_string(), "other".to_string())
+            .build();
+        let plan = DropTablePlan {
+            engine: ANALYTIC_ENGINE_TYPE.to_string(),
+            if_exists: false,
+            table: "t0".to_string(),
+        };
+        let err = manipulator
+            .preview_drop_table(&ctx, plan)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::TableNotExists { .. }), "{}", err);
+    }
+}
@@ -11,6 +11,8 @@ use crate::{context::Context, interpreter::Output};
 
 pub mod catalog_based;
 pub mod meta_based;
+#[cfg(any(test, feature = "test"))]
+pub mod tests;
 
 pub type TableManipulatorRef = Arc<dyn TableManipulator + Send + Sync>;
 
@@ -64,6 +66,18 @@ pub enum Error {
         msg: String,
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+
+    #[snafu(display("Table already exists, table:{}.\nBacktrace:\n{}", table, backtrace))]
+    TableExists { table: String, backtrace: Backtrace },
+
+    #[snafu(display("Table not exists, table:{}.\nBacktrace:\n{}", table, backtrace))]
+    TableNotExists { table: String, backtrace: Backtrace },
+
+    #[snafu(display("Unknown engine type, engine:{}.\nBacktrace:\n{}", engine, backtrace))]
+    UnknownEngine {
+        engine: String,
+        backtrace: Backtrace,
+    },
 }
 
 define_result!(Error);
@@ -77,6 +91,27 @@ pub trait TableManipulator {
         table_engine: TableEngineRef,
     ) -> Result<Output>;
 
+    /// Create a batch of tables, the result of each table is returned in the
+    /// same order as `plans`, so failures are reported per table.
+    ///
+    /// Tables are created one by one by default.
+    async fn create_tables(
+        &self,
+        ctx: Context,
+        plans: Vec<CreateTablePlan>,
+        table_engine: TableEngineRef,
+    ) -> Vec<Result<Output>> {
+        let mut results = Vec::with_capacity(plans.len());
+        for plan in plans {
+            results.push(
+                self.create_table(ctx.clone(), plan, table_engine.clone())
+                    .await,
+            );
+        }
+
+        results
+    }
+
     async fn drop_table(
         &self,
         ctx: Context,
@@ -0,0 +1,282 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Mocks of the meta client and the local catalog for testing the table
+//! manipulators.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use async_trait::async_trait;
+use catalog::{
+    manager::{self, Manager},
+    schema::{
+        CloseOptions, CloseTableRequest, CreateOptions,
+        CreateTableRequest as SchemaCreateTableRequest, DropOptions,
+        DropTableRequest as SchemaDropTableRequest, NameRef, OpenOptions, OpenTableRequest, Schema,
+        SchemaRef,
+    },
+    Catalog, CatalogRef,
+};
+use common_types::schema::SchemaEncoder;
+use meta_client::{types::*, MetaClient};
+use table_engine::{
+    memory::MemoryTable,
+    table::{SchemaId, TableRef},
+};
+
+type Tables = Arc<RwLock<HashMap<String, TableRef>>>;
+
+/// Local catalog "ceresdb" with the schema "public" only.
+#[derive(Clone, Default)]
+pub struct MockCatalog {
+    tables: Tables,
+}
+
+impl MockCatalog {
+    pub fn add_table(&self, table: TableRef) {
+        self.tables
+            .write()
+            .unwrap()
+            .insert(table.name().to_string(), table);
+    }
+}
+
+impl Manager for MockCatalog {
+    fn default_catalog_name(&self) -> NameRef {
+        "ceresdb"
+    }
+
+    fn default_schema_name(&self) -> NameRef {
+        "public"
+    }
+
+    fn catalog_by_name(&self, name: NameRef) -> manager::Result<Option<CatalogRef>> {
+        Ok((name == "ceresdb").then(|| Arc::new(self.clone()) as CatalogRef))
+    }
+
+    fn all_catalogs(&self) -> manager::Result<Vec<CatalogRef>> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl Catalog for MockCatalog {
+    fn name(&self) -> NameRef {
+        "ceresdb"
+    }
+
+    fn schema_by_name(&self, name: NameRef) -> catalog::Result<Option<SchemaRef>> {
+        let schema = MockSchema {
+            tables: self.tables.clone(),
+        };
+        Ok((name == "public").then(|| Arc::new(schema) as SchemaRef))
+    }
+
+    async fn create_schema<'a>(&'a self, _name: NameRef<'a>) -> catalog::Result<()> {
+        unimplemented!()
+    }
+
+    fn all_schemas(&self) -> catalog::Result<Vec<SchemaRef>> {
+        unimplemented!()
+    }
+}
+
+struct MockSchema {
+    tables: Tables,
+}
+
+#[async_trait]
+impl Schema for MockSchema {
+    fn name(&self) -> NameRef {
+        "public"
+    }
+
+    fn id(&self) -> SchemaId {
+        SchemaId::from_u32(0)
+    }
+
+    fn table_by_name(&self, name: NameRef) -> catalog::schema::Result<Option<TableRef>> {
+        Ok(self.tables.read().unwrap().get(name).cloned())
+    }
+
+    async fn create_table(
+        &self,
+        _request: SchemaCreateTableRequest,
+        _opts: CreateOptions,
+    ) -> catalog::schema::Result<TableRef> {
+        unimplemented!()
+    }
+
+    async fn drop_table(
+        &self,
+        _request: SchemaDropTableRequest,
+        _opts: DropOptions,
+    ) -> catalog::schema::Result<bool> {
+        unimplemented!()
+    }
+
+    async fn open_table(
+        &self,
+        _request: OpenTableRequest,
+        _opts: OpenOptions,
+    ) -> catalog::schema::Result<Option<TableRef>> {
+        unimplemented!()
+    }
+
+    async fn close_table(
+        &self,
+        _request: CloseTableRequest,
+        _opts: CloseOptions,
+    ) -> catalog::schema::Result<()> {
+        unimplemented!()
+    }
+
+    fn all_tables(&self) -> catalog::schema::Result<Vec<TableRef>> {
+        unimplemented!()
+    }
+}
+
+/// Meta client fails to create tables whose name starts with "bad", and
+/// routes the tables of the schema "public" it holds.
+///
+/// The created tables are also opened in the local catalog if it is set, as if
+/// they are opened on this node at once.
+pub struct MockMetaClient {
+    next_table_id: AtomicU64,
+    create_requests: AtomicUsize,
+    tables: HashMap<String, TableInfo>,
+    catalog: Option<MockCatalog>,
+}
+
+impl MockMetaClient {
+    pub fn new(next_table_id: u64) -> Self {
+        Self {
+            next_table_id: AtomicU64::new(next_table_id),
+            create_requests: AtomicUsize::new(0),
+            tables: HashMap::new(),
+            catalog: None,
+        }
+    }
+
+    pub fn with_tables(mut self, tables: &[(&str, u64)]) -> Self {
+        self.tables = tables
+            .iter()
+            .map(|(name, id)| {
+                let table = TableInfo {
+                    id: *id,
+                    name: name.to_string(),
+                    schema_id: 0,
+                    schema_name: "public".to_string(),
+                };
+                (name.to_string(), table)
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_catalog(mut self, catalog: MockCatalog) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Number of the create table requests received, including the failed
+    /// ones.
+    pub fn num_create_requests(&self) -> usize {
+        self.create_requests.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl MetaClient for MockMetaClient {
+    async fn alloc_schema_id(
+        &self,
+        _req: AllocSchemaIdRequest,
+    ) -> meta_client::Result<AllocSchemaIdResponse> {
+        unimplemented!()
+    }
+
+    async fn create_table(
+        &self,
+        req: CreateTableRequest,
+    ) -> meta_client::Result<CreateTableResponse> {
+        self.create_requests.fetch_add(1, Ordering::Relaxed);
+        if req.name.starts_with("bad") {
+            return meta_client::MissingTableInfo {
+                msg: format!("mock failure, table:{}", req.name),
+            }
+            .fail();
+        }
+
+        let table_id = self.next_table_id.fetch_add(1, Ordering::Relaxed);
+        if let Some(catalog) = &self.catalog {
+            let schema = SchemaEncoder::default()
+                .decode(&req.encoded_schema)
+                .unwrap();
+            let table = MemoryTable::new(
+                req.name.clone(),
+                table_id.into(),
+                schema,
+                req.engine.clone(),
+            );
+            catalog.add_table(Arc::new(table));
+        }
+
+        Ok(CreateTableResponse {
+            created_table: TableInfo {
+                id: table_id,
+                name: req.name,
+                schema_id: 0,
+                schema_name: req.schema_name,
+            },
+            shard_info: ShardInfo::default(),
+        })
+    }
+
+    async fn drop_table(&self, _req: DropTableRequest) -> meta_client::Result<DropTableResponse> {
+        unimplemented!()
+    }
+
+    async fn get_tables_of_shards(
+        &self,
+        _req: GetTablesOfShardsRequest,
+    ) -> meta_client::Result<GetTablesOfShardsResponse> {
+        unimplemented!()
+    }
+
+    async fn route_tables(
+        &self,
+        req: RouteTablesRequest,
+    ) -> meta_client::Result<RouteTablesResponse> {
+        let entries = req
+            .table_names
+            .iter()
+            .filter_map(|name| self.tables.get(name))
+            .filter(|table| table.schema_name == req.schema_name)
+            .map(|table| {
+                let entry = RouteEntry {
+                    table: table.clone(),
+                    node_shards: Vec::new(),
+                };
+                (table.name.clone(), entry)
+            })
+            .collect();
+
+        Ok(RouteTablesResponse {
+            cluster_topology_version: 0,
+            entries,
+        })
+    }
+
+    async fn get_nodes(&self, _req: GetNodesRequest) -> meta_client::Result<GetNodesResponse> {
+        unimplemented!()
+    }
+
+    async fn send_heartbeat(&self, _req: Vec<ShardInfo>) -> meta_client::Result<()> {
+        unimplemented!()
+    }
+}
@@ -174,9 +174,9 @@ pub async fn fetch_query_output<Q: QueryExecutor + 'static>(
 fn convert_output(output: &Output) -> Result<QueryResponse> {
     match output {
         Output::Records(records) => convert_records(records),
-        Output::AffectedRows(rows) => {
+        Output::AffectedRows(_) | Output::TableCreated(_) => {
             let mut resp = empty_ok_resp();
-            resp.affected_rows = *rows as u32;
+            resp.affected_rows = output.affected_rows().unwrap_or_default() as u32;
             Ok(resp)
         }
     }
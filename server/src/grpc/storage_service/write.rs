@@ -227,17 +227,24 @@ async fn create_table<Q: QueryExecutor + 'static>(
     );
     let interpreter = interpreter_factory.create(interpreter_ctx, plan);
 
-    let _ = match interpreter
+    let output = interpreter
         .execute()
         .await
         .map_err(|e| Box::new(e) as _)
         .context(ErrWithCause {
             code: StatusCode::INTERNAL_SERVER_ERROR,
             msg: "failed to execute interpreter",
-        })? {
-        Output::AffectedRows(n) => n,
-        _ => unreachable!(),
-    };
+        })?;
+    match output {
+        Output::TableCreated(created_table) => {
+            debug!(
+                "Grpc handle create table end, table:{}, table_id:{}",
+                created_table.table_name, created_table.table_id
+            );
+        }
+        Output::AffectedRows(_) => (),
+        Output::Records(_) => unreachable!(),
+    }
 
     Ok(())
 }
@@ -454,14 +461,31 @@ fn convert_proto_value_to_datum(
 
 #[cfg(test)]
 mod test {
-    use ceresdbproto::storage::{Field, FieldGroup, Tag, Value};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use ceresdbproto::storage::{Field, FieldGroup, Route, RouteRequest, Tag, Value};
+    use cluster::config::SchemaConfig;
     use common_types::{
         column_schema::{self, ColumnSchema},
         schema::Builder,
     };
+    use df_operator::registry::FunctionRegistryImpl;
+    use interpreters::table_manipulator::{
+        meta_based::TableManipulatorImpl,
+        tests::{MockCatalog, MockMetaClient},
+    };
+    use query_engine::executor::ExecutorImpl;
     use system_catalog::sys_catalog_table::TIMESTAMP_COLUMN_NAME;
 
     use super::*;
+    use crate::{
+        grpc::storage_service::{HandlerContext, RequestHeader},
+        instance::Instance,
+        limiter::Limiter,
+        route::{self, Router},
+        table_engine::MemoryTableEngine,
+    };
 
     const TAG_K: &str = "tagk";
     const TAG_V: &str = "tagv";
@@ -618,4 +642,68 @@ mod test {
         ];
         assert_eq!(rows, expect_rows);
     }
+
+    struct MockRouter;
+
+    #[async_trait]
+    impl Router for MockRouter {
+        async fn route(&self, _schema: &str, _req: RouteRequest) -> route::Result<Vec<Route>> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_auto_create_table() {
+        let catalog = MockCatalog::default();
+        let meta_client = Arc::new(MockMetaClient::new(100).with_catalog(catalog.clone()));
+        let table_manipulator = TableManipulatorImpl::new(meta_client.clone())
+            .with_catalog_manager(Arc::new(catalog.clone()));
+        let instance = Arc::new(Instance {
+            catalog_manager: Arc::new(catalog.clone()),
+            query_executor: ExecutorImpl::new(Default::default()),
+            table_engine: Arc::new(MemoryTableEngine),
+            function_registry: Arc::new(FunctionRegistryImpl::new()),
+            limiter: Limiter::default(),
+            table_manipulator: Arc::new(table_manipulator),
+        });
+        let schema_config = SchemaConfig {
+            auto_create_tables: true,
+            default_timestamp_column_name: TIMESTAMP_COLUMN_NAME.to_string(),
+            ..Default::default()
+        };
+        let ctx = HandlerContext {
+            header: RequestHeader::default(),
+            router: Arc::new(MockRouter),
+            instance,
+            catalog: "ceresdb".to_string(),
+            schema: "public".to_string(),
+            schema_config: Some(&schema_config),
+        };
+
+        let (_, tag_names, field_names, write_entry) = generate_write_entry();
+        let write_metric = WriteMetric {
+            metric: "auto_created".to_string(),
+            tag_names,
+            field_names,
+            entries: vec![write_entry],
+        };
+        let mut req = WriteRequest::default();
+        req.metrics.push(write_metric.clone());
+
+        // The missing table is created by the meta before written.
+        let plans = write_request_to_insert_plan(&ctx, req, RequestId::next_id())
+            .await
+            .unwrap();
+        assert_eq!(1, plans.len());
+        assert_eq!("auto_created", plans[0].table.name());
+        assert_eq!(100, plans[0].table.id().as_u64());
+        assert_eq!(3, plans[0].rows.num_rows());
+        assert_eq!(1, meta_client.num_create_requests());
+
+        // Creating the existing table returns at once by the local catalog.
+        create_table(&ctx, &write_metric, RequestId::next_id())
+            .await
+            .unwrap();
+        assert_eq!(1, meta_client.num_create_requests());
+    }
 }
@@ -198,6 +198,7 @@ pub async fn handle_sql<Q: QueryExecutor + 'static>(
 fn convert_output(output: Output) -> ArrowResult<Response> {
     match output {
         Output::AffectedRows(n) => Ok(Response::AffectedRows(n)),
+        Output::TableCreated(_) => Ok(Response::AffectedRows(0)),
         Output::Records(records) => convert_records(records),
     }
 }
@@ -2,7 +2,7 @@
 
 //! Table engine implementation
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 use table_engine::{
@@ -106,4 +106,17 @@ impl TableEngine for TableEngineProxy {
             engine_type => UnknownEngineType { engine_type }.fail(),
         }
     }
+
+    fn resolve_create_options(
+        &self,
+        engine: &str,
+        table: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        match engine {
+            MEMORY_ENGINE_TYPE => self.memory.resolve_create_options(engine, table, options),
+            ANALYTIC_ENGINE_TYPE => self.analytic.resolve_create_options(engine, table, options),
+            engine_type => UnknownEngineType { engine_type }.fail(),
+        }
+    }
 }
@@ -175,7 +175,10 @@ async fn build_in_cluster_mode<Q: Executor + 'static>(
         shard_tables_cache,
         meta_client.clone(),
     ));
-    let table_manipulator = Arc::new(meta_based::TableManipulatorImpl::new(meta_client));
+    let table_manipulator = Arc::new(
+        meta_based::TableManipulatorImpl::new(meta_client)
+            .with_catalog_manager(catalog_manager.clone()),
+    );
     let router = Arc::new(ClusterBasedRouter::new(cluster.clone()));
     let schema_config_provider = Arc::new(ClusterBasedProvider::new(cluster.clone()));
     builder
@@ -281,6 +281,18 @@ pub trait TableEngine: Send + Sync {
 
     /// Close table
     async fn close_table(&self, request: CloseTableRequest) -> Result<()>;
+
+    /// Resolve the `options` to create the `table` of `engine` with, i.e. the
+    /// options after merged with the defaults of the engine. The `options` are
+    /// returned as they are by default.
+    fn resolve_create_options(
+        &self,
+        _engine: &str,
+        _table: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        Ok(options.clone())
+    }
 }
 
 /// A reference counted pointer to table engine
@@ -56,6 +56,17 @@ impl Predicate {
         self.time_range
     }
 
+    /// Exprs to prune the row groups of ssts by, which consist of the `exprs`
+    /// and the restriction of the time range if it is bounded.
+    pub fn pushdown_exprs(&self, time_column_name: impl AsRef<str>) -> Vec<Expr> {
+        let mut exprs = self.exprs.clone();
+        if self.time_range != TimeRange::min_to_max() {
+            exprs.push(self.time_range.to_df_expr(time_column_name));
+        }
+
+        exprs
+    }
+
     /// Return a DataFusion [`Expr`] predicate representing the
     /// combination of AND'ing all (`exprs`) and timestamp restriction
     /// in this Predicate.
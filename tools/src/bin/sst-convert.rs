@@ -6,15 +6,14 @@ use std::{error::Error, sync::Arc};
 
 use analytic_engine::{
     sst::factory::{Factory, FactoryImpl, SstBuilderOptions, SstReaderOptions, SstType},
-    table_options::{Compression, StorageFormat, StorageFormatOptions},
+    table_options::{ColumnStatsOptions, Compression, StorageFormat, StorageFormatOptions},
 };
 use anyhow::{Context, Result};
 use clap::Parser;
-use common_types::{projected_schema::ProjectedSchema, request_id::RequestId};
+use common_types::request_id::RequestId;
 use common_util::runtime::{self, Runtime};
 use futures::stream::StreamExt;
 use object_store::{LocalFileSystem, Path};
-use table_engine::predicate::Predicate;
 use tools::sst_util;
 
 #[derive(Parser, Debug)]
@@ -32,7 +31,8 @@ struct Args {
     #[clap(short, long, required(true))]
     output: String,
 
-    /// Compression of new sst file(values: uncompressed/lz4/snappy/zstd)
+    /// Compression of new sst file(values:
+    /// uncompressed/lz4/lz4_raw/snappy/zstd)
     #[clap(short, long, default_value = "zstd")]
     compression: String,
 
@@ -71,16 +71,7 @@ async fn run(args: Args, runtime: Arc<Runtime>) -> Result<()> {
     let input_path = Path::from(args.input);
     let mut sst_meta = sst_util::meta_from_sst(&storage, &input_path).await;
     let factory = FactoryImpl;
-    let reader_opts = SstReaderOptions {
-        sst_type: SstType::Parquet,
-        read_batch_row_num: 8192,
-        reverse: false,
-        projected_schema: ProjectedSchema::no_projection(sst_meta.schema.clone()),
-        predicate: Arc::new(Predicate::empty()),
-        meta_cache: None,
-        data_cache: None,
-        runtime,
-    };
+    let reader_opts = SstReaderOptions::builder(sst_meta.schema.clone(), runtime).build();
     let mut reader = factory
         .new_sst_reader(&reader_opts, &input_path, &storage)
         .expect("no sst reader found");
@@ -90,6 +81,7 @@ async fn run(args: Args, runtime: Arc<Runtime>) -> Result<()> {
         num_rows_per_row_group: args.batch_size,
         compression: Compression::parse_from(&args.compression)
             .with_context(|| format!("invalid compression:{}", args.compression))?,
+        column_stats: ColumnStatsOptions::default(),
     };
     let output = Path::from(args.output);
     let mut builder = factory
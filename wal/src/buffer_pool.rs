@@ -0,0 +1,109 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Pool of reusable byte buffers.
+//!
+//! A pool is owned by a single iterator, so no synchronization is needed.
+//! Buffers returned to the pool are cleared but keep their capacity, so
+//! replaying logs of similar sizes stops allocating after warming up.
+
+/// Default max number of idle buffers kept by a pool.
+pub const DEFAULT_POOL_CAPACITY: usize = 4;
+
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Vec<Vec<u8>>,
+    /// Max number of idle buffers to keep.
+    capacity: usize,
+    /// Number of buffers allocated by the pool.
+    num_allocations: usize,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_POOL_CAPACITY)
+    }
+}
+
+impl BufferPool {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffers: Vec::with_capacity(capacity),
+            capacity,
+            num_allocations: 0,
+        }
+    }
+
+    /// Take an empty buffer from the pool, allocates a new one if the pool is
+    /// empty.
+    pub fn get(&mut self) -> Vec<u8> {
+        match self.buffers.pop() {
+            Some(buf) => buf,
+            None => {
+                self.num_allocations += 1;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Take an empty buffer and fill it with `data`.
+    pub fn get_filled(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut buf = self.get();
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    /// Return `buf` to the pool, it is dropped if the pool is full or it has
+    /// never been allocated.
+    pub fn put(&mut self, mut buf: Vec<u8>) {
+        if buf.capacity() == 0 || self.buffers.len() >= self.capacity {
+            return;
+        }
+
+        buf.clear();
+        self.buffers.push(buf);
+    }
+
+    #[inline]
+    pub fn num_allocations(&self) -> usize {
+        self.num_allocations
+    }
+
+    #[inline]
+    pub fn num_idle(&self) -> usize {
+        self.buffers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_pool_reuse() {
+        let mut pool = BufferPool::with_capacity(2);
+        let buf = pool.get_filled(b"hello");
+        assert_eq!(b"hello", buf.as_slice());
+        assert_eq!(1, pool.num_allocations());
+
+        let capacity = buf.capacity();
+        pool.put(buf);
+        assert_eq!(1, pool.num_idle());
+
+        // The returned buffer is cleared and reused.
+        let buf = pool.get();
+        assert!(buf.is_empty());
+        assert_eq!(capacity, buf.capacity());
+        assert_eq!(1, pool.num_allocations());
+        assert_eq!(0, pool.num_idle());
+
+        // Buffers never allocated are not pooled.
+        pool.put(Vec::new());
+        assert_eq!(0, pool.num_idle());
+
+        // Buffers exceeding the capacity are dropped.
+        pool.put(buf);
+        pool.put(vec![1]);
+        pool.put(vec![2]);
+        assert_eq!(2, pool.num_idle());
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Stream decoding the payloads of a [SyncLogIterator] in parallel.
+//!
+//! The logs are read by a blocking task and decoded by at most `num_workers`
+//! blocking tasks at the same time, so reading the logs from the storage
+//! overlaps decoding the payloads, which is CPU-bound during recovery. The
+//! decoded entries are still yielded in the order of the iterator.
+
+use std::sync::Arc;
+
+use common_util::runtime::Runtime;
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use snafu::ResultExt;
+use tokio::sync::mpsc::{self, Sender};
+
+use crate::{
+    log_batch::{LogEntry, PayloadDecoder},
+    manager::{self, Result, SyncLogIterator},
+};
+
+/// Convert `iter` into a stream of the log entries whose payloads are decoded
+/// by `decoder` on at most `num_workers` blocking tasks of `runtime`.
+///
+/// The entries are yielded in the order of `iter`. The stream ends after the
+/// first error, which is yielded in order too, i.e. after all the entries
+/// before the failed one. Dropping the stream stops reading `iter`.
+pub fn parallel_decode_stream<I, D>(
+    iter: I,
+    decoder: D,
+    runtime: Arc<Runtime>,
+    num_workers: usize,
+) -> BoxStream<'static, Result<LogEntry<D::Target>>>
+where
+    I: SyncLogIterator + 'static,
+    D: PayloadDecoder + 'static,
+    D::Target: 'static,
+{
+    let num_workers = num_workers.max(1);
+    // Read ahead as many entries as the workers can decode.
+    let (tx, rx) = mpsc::channel(num_workers);
+    runtime.spawn_blocking(move || read_log_entries(iter, tx));
+
+    let raw_entries = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|raw_entry| (raw_entry, rx))
+    });
+    let decoder = Arc::new(decoder);
+    raw_entries
+        .map(move |raw_entry| {
+            let decoder = decoder.clone();
+            let runtime = runtime.clone();
+            async move {
+                let raw_entry = raw_entry?;
+                runtime
+                    .spawn_blocking(move || decode_log_entry(&*decoder, raw_entry))
+                    .await
+                    .context(manager::RuntimeExec)?
+            }
+        })
+        .buffered(num_workers)
+        .scan(false, |failed, res| {
+            if *failed {
+                return future::ready(None);
+            }
+            *failed = res.is_err();
+
+            future::ready(Some(res))
+        })
+        .boxed()
+}
+
+/// Send the entries of `iter` to `tx` until the iterator is exhausted, an
+/// error is encountered or the receiver is dropped.
+fn read_log_entries<I: SyncLogIterator>(iter: I, tx: Sender<Result<LogEntry<Vec<u8>>>>) {
+    for raw_entry in iter.into_owned_iter() {
+        let failed = raw_entry.is_err();
+        if tx.blocking_send(raw_entry).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn decode_log_entry<D: PayloadDecoder>(
+    decoder: &D,
+    raw_entry: LogEntry<Vec<u8>>,
+) -> Result<LogEntry<D::Target>> {
+    let payload = decoder
+        .decode(&mut raw_entry.payload.as_slice())
+        .map_err(|e| Box::new(e) as _)
+        .context(manager::Decoding)?;
+
+    Ok(LogEntry {
+        table_id: raw_entry.table_id,
+        sequence: raw_entry.sequence,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use common_types::bytes::Buf;
+    use common_util::runtime;
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    /// Iterator over `payloads`, records the number of entries read and whether
+    /// it is dropped.
+    #[derive(Debug)]
+    struct VecLogIterator {
+        payloads: Vec<Vec<u8>>,
+        cursor: usize,
+        num_read: Arc<AtomicUsize>,
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl VecLogIterator {
+        fn new(num_entries: u32) -> Self {
+            Self {
+                payloads: (0..num_entries).map(|v| v.to_be_bytes().to_vec()).collect(),
+                cursor: 0,
+                num_read: Arc::new(AtomicUsize::new(0)),
+                dropped: Arc::new(AtomicBool::new(false)),
+            }
+        }
+    }
+
+    impl Drop for VecLogIterator {
+        fn drop(&mut self) {
+            self.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl SyncLogIterator for VecLogIterator {
+        fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>> {
+            if self.cursor >= self.payloads.len() {
+                return Ok(None);
+            }
+
+            self.cursor += 1;
+            self.num_read.store(self.cursor, Ordering::SeqCst);
+            Ok(Some(LogEntry {
+                table_id: 0,
+                sequence: self.cursor as u64,
+                payload: self.payloads[self.cursor - 1].as_slice(),
+            }))
+        }
+    }
+
+    /// Decoder sleeping `delay` on each payload, and failing on payload
+    /// `fail_at`.
+    ///
+    /// The number of payloads being decoded at the same time is tracked by
+    /// `in_flight`, and the max of it is kept in `max_in_flight`.
+    #[derive(Default)]
+    struct SlowDecoder {
+        delay: Duration,
+        fail_at: Option<u32>,
+        in_flight: AtomicUsize,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl PayloadDecoder for SlowDecoder {
+        type Error = io::Error;
+        type Target = u32;
+
+        fn decode<B: Buf>(&self, buf: &mut B) -> std::result::Result<u32, io::Error> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            thread::sleep(self.delay);
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            let val = buf.get_u32();
+            if self.fail_at == Some(val) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupted"));
+            }
+
+            Ok(val)
+        }
+    }
+
+    fn new_runtime() -> Arc<Runtime> {
+        let runtime = runtime::Builder::default()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        Arc::new(runtime)
+    }
+
+    /// Wait until the iterator is dropped by the reader task.
+    fn wait_dropped(dropped: &AtomicBool) {
+        while !dropped.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_parallel_decode_in_order() {
+        let runtime = new_runtime();
+        let num_entries = 32;
+        let num_workers = 4;
+        let decoder = SlowDecoder {
+            delay: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let max_in_flight = decoder.max_in_flight.clone();
+
+        let entries = runtime.block_on(async {
+            parallel_decode_stream(
+                VecLogIterator::new(num_entries),
+                decoder,
+                runtime.clone(),
+                num_workers,
+            )
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+        });
+
+        let expect_payloads = (0..num_entries).collect::<Vec<_>>();
+        assert_eq!(
+            expect_payloads,
+            entries.iter().map(|e| e.payload).collect::<Vec<_>>()
+        );
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(i as u64 + 1, entry.sequence);
+        }
+        // Payloads are decoded in parallel, but by no more than `num_workers`.
+        let max_in_flight = max_in_flight.load(Ordering::SeqCst);
+        assert!(
+            max_in_flight > 1 && max_in_flight <= num_workers,
+            "max_in_flight:{}",
+            max_in_flight
+        );
+    }
+
+    #[test]
+    fn test_parallel_decode_error() {
+        let runtime = new_runtime();
+        let decoder = SlowDecoder {
+            delay: Duration::from_millis(1),
+            fail_at: Some(10),
+            ..Default::default()
+        };
+        let iter = VecLogIterator::new(32);
+        let dropped = iter.dropped.clone();
+
+        runtime.block_on(async {
+            let mut stream = parallel_decode_stream(iter, decoder, runtime.clone(), 4);
+            for expect in 0..10 {
+                let entry = stream.next().await.unwrap().unwrap();
+                assert_eq!(expect, entry.payload);
+            }
+            assert!(stream.next().await.unwrap().is_err());
+            assert!(stream.next().await.is_none());
+        });
+
+        // The reader stops once the stream is dropped.
+        wait_dropped(&dropped);
+    }
+
+    #[test]
+    fn test_drop_parallel_decode_stream() {
+        let runtime = new_runtime();
+        let num_entries = 1024;
+        let num_workers = 2;
+        let iter = VecLogIterator::new(num_entries);
+        let num_read = iter.num_read.clone();
+        let dropped = iter.dropped.clone();
+
+        runtime.block_on(async {
+            let mut stream =
+                parallel_decode_stream(iter, SlowDecoder::default(), runtime.clone(), num_workers);
+            assert_eq!(0, stream.next().await.unwrap().unwrap().payload);
+        });
+
+        // Dropping the stream early releases the iterator without reading all the
+        // entries, the reader is blocked by the bounded channel before it.
+        wait_dropped(&dropped);
+        let num_read = num_read.load(Ordering::SeqCst);
+        assert!(num_read < num_entries as usize, "num_read:{}", num_read);
+    }
+}
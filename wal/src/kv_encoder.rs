@@ -22,13 +22,23 @@ pub const LOG_KEY_ENCODING_V0: u8 = 0;
 pub const NEWEST_LOG_KEY_ENCODING_VERSION: u8 = LOG_KEY_ENCODING_V0;
 
 pub const LOG_VALUE_ENCODING_V0: u8 = 0;
-pub const NEWEST_LOG_VALUE_ENCODING_VERSION: u8 = LOG_VALUE_ENCODING_V0;
+/// The payload is prefixed by its length since this version.
+pub const LOG_VALUE_ENCODING_V1: u8 = 1;
+pub const NEWEST_LOG_VALUE_ENCODING_VERSION: u8 = LOG_VALUE_ENCODING_V1;
 
 pub const META_KEY_ENCODING_V0: u8 = 0;
 pub const NEWEST_META_KEY_ENCODING_VERSION: u8 = META_KEY_ENCODING_V0;
 
 pub const META_VALUE_ENCODING_V0: u8 = 0;
-pub const NEWEST_META_VALUE_ENCODING_VERSION: u8 = META_VALUE_ENCODING_V0;
+/// Meta value with a crc32 checksum of the content.
+pub const META_VALUE_ENCODING_V1: u8 = 1;
+pub const NEWEST_META_VALUE_ENCODING_VERSION: u8 = META_VALUE_ENCODING_V1;
+
+/// Id of the tenant whose wal shares the same storage with others.
+pub type TenantId = u8;
+/// Keys of the default tenant are the same as keys before tenant is introduced.
+pub const DEFAULT_TENANT_ID: TenantId = 0;
+pub const MAX_TENANT_ID: TenantId = 127;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -52,6 +62,30 @@ pub enum Error {
     #[snafu(display("Failed to decode log value header, err:{}", source))]
     DecodeLogValueHeader { source: bytes::Error },
 
+    #[snafu(display(
+        "Log value is truncated, expect payload len:{}, given:{}.\nBacktrace:\n{}",
+        expect,
+        given,
+        backtrace
+    ))]
+    TruncatedValue {
+        expect: usize,
+        given: usize,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Log value has invalid payload length, expect:{}, given:{}.\nBacktrace:\n{}",
+        expect,
+        given,
+        backtrace
+    ))]
+    InvalidValueLength {
+        expect: usize,
+        given: usize,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to decode log value payload, err:{}", source))]
     DecodeLogValuePayload {
         source: Box<dyn std::error::Error + Send + Sync>,
@@ -72,6 +106,18 @@ pub enum Error {
     #[snafu(display("Failed to decode meta value, err:{}", source))]
     DecodeMetaValue { source: bytes::Error },
 
+    #[snafu(display(
+        "Checksum of meta value mismatch, expect:{}, actual:{}.\nBacktrace:\n{}",
+        expect,
+        actual,
+        backtrace
+    ))]
+    MetaValueChecksumMismatch {
+        expect: u32,
+        actual: u32,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Found invalid meta key type, expect:{:?}, given:{}.\nBacktrace:\n{}",
         expect,
@@ -96,6 +142,29 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Found invalid tenant id, expect:{}, given:{}.\nBacktrace:\n{}",
+        expect,
+        given,
+        backtrace
+    ))]
+    InvalidTenant {
+        expect: TenantId,
+        given: TenantId,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Tenant id is out of range, tenant_id:{}, max:{}.\nBacktrace:\n{}",
+        tenant_id,
+        MAX_TENANT_ID,
+        backtrace
+    ))]
+    TenantIdOutOfRange {
+        tenant_id: TenantId,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Found invalid version, expect:{}, given:{}.\nBacktrace:\n{}",
         expect,
@@ -111,12 +180,56 @@ pub enum Error {
 
 define_result!(Error);
 
+impl Error {
+    /// Returns true if the log value is truncated, e.g. by a crash in the
+    /// middle of writing, so the recovery can stop at the last complete
+    /// log.
+    pub fn is_truncated_value(&self) -> bool {
+        matches!(self, Error::TruncatedValue { .. })
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum Namespace {
     Meta = 0,
     Log = 1,
 }
 
+impl Namespace {
+    /// Returns the first byte of the key, the lowest bit is the namespace and
+    /// the other bits are the tenant id.
+    #[inline]
+    fn key_prefix(self, tenant_id: TenantId) -> u8 {
+        (tenant_id << 1) | self as u8
+    }
+
+    /// Check the first byte of the key matches the namespace and `tenant_id`.
+    fn check_key_prefix(self, tenant_id: TenantId, prefix: u8) -> Result<()> {
+        ensure!(
+            prefix & 1 == self as u8,
+            InvalidNamespace {
+                expect: self,
+                given: prefix & 1,
+            }
+        );
+        ensure!(
+            prefix >> 1 == tenant_id,
+            InvalidTenant {
+                expect: tenant_id,
+                given: prefix >> 1,
+            }
+        );
+
+        Ok(())
+    }
+}
+
+fn validate_tenant_id(tenant_id: TenantId) -> Result<()> {
+    ensure!(tenant_id <= MAX_TENANT_ID, TenantIdOutOfRange { tenant_id });
+
+    Ok(())
+}
+
 /// Log key in old wal design, map the `TableId` to `RegionId`
 #[allow(unused)]
 pub type LogKey = (RegionId, SequenceNumber);
@@ -126,6 +239,7 @@ pub type LogKey = (RegionId, SequenceNumber);
 pub struct LogKeyEncoder {
     pub version: u8,
     pub namespace: Namespace,
+    pub tenant_id: TenantId,
 }
 
 #[allow(unused)]
@@ -135,13 +249,24 @@ impl LogKeyEncoder {
         Self {
             version: NEWEST_LOG_KEY_ENCODING_VERSION,
             namespace: Namespace::Log,
+            tenant_id: DEFAULT_TENANT_ID,
         }
     }
 
+    /// Create newest version encoder of the tenant.
+    pub fn newest_with_tenant(tenant_id: TenantId) -> Result<Self> {
+        validate_tenant_id(tenant_id)?;
+
+        Ok(Self {
+            tenant_id,
+            ..Self::newest()
+        })
+    }
+
     /// Determine whether the raw bytes is a log key.
     pub fn is_valid<B: Buf>(&self, buf: &mut B) -> Result<bool> {
-        let namespace = buf.try_get_u8().context(DecodeLogKey)?;
-        Ok(namespace == self.namespace as u8)
+        let prefix = buf.try_get_u8().context(DecodeLogKey)?;
+        Ok(prefix == self.namespace.key_prefix(self.tenant_id))
     }
 }
 
@@ -156,9 +281,15 @@ impl Encoder<LogKey> for LogKeyEncoder {
     /// +---------------+----------------+-------------------+--------------------+
     /// ```
     ///
+    /// The `namespace` byte also carries the tenant id in its higher 7 bits.
+    ///
+    /// The integers are encoded in big-endian, so the encoded keys of the same
+    /// tenant sort in the same order as the keys, which the scans rely on.
+    ///
     /// More information can be extended after the incremented `version header`.
     fn encode<B: BufMut>(&self, buf: &mut B, log_key: &LogKey) -> Result<()> {
-        buf.try_put_u8(self.namespace as u8).context(EncodeLogKey)?;
+        buf.try_put_u8(self.namespace.key_prefix(self.tenant_id))
+            .context(EncodeLogKey)?;
         buf.try_put_u64(log_key.0).context(EncodeLogKey)?;
         buf.try_put_u64(log_key.1).context(EncodeLogKey)?;
         buf.try_put_u8(self.version).context(EncodeLogKey)?;
@@ -177,14 +308,8 @@ impl Decoder<LogKey> for LogKeyEncoder {
 
     fn decode<B: Buf>(&self, buf: &mut B) -> Result<LogKey> {
         // check namespace
-        let namespace = buf.try_get_u8().context(DecodeLogKey)?;
-        ensure!(
-            namespace == self.namespace as u8,
-            InvalidNamespace {
-                expect: self.namespace,
-                given: namespace
-            }
-        );
+        let prefix = buf.try_get_u8().context(DecodeLogKey)?;
+        self.namespace.check_key_prefix(self.tenant_id, prefix)?;
 
         let log_key = (
             buf.try_get_u64().context(DecodeLogKey)?,
@@ -224,12 +349,21 @@ impl LogValueEncoder {
 impl<T: Payload> Encoder<T> for LogValueEncoder {
     type Error = Error;
 
-    /// Value format:
+    /// Value format of v1:
+    /// +--------------------+------------------+---------+
+    /// | version_header(u8) | payload_len(u32) | payload |
+    /// +--------------------+------------------+---------+
+    ///
+    /// Value format of v0:
     /// +--------------------+---------+
     /// | version_header(u8) | payload |
     /// +--------------------+---------+
     fn encode<B: BufMut>(&self, buf: &mut B, payload: &T) -> Result<()> {
         buf.try_put_u8(self.version).context(EncodeLogValueHeader)?;
+        if self.version >= LOG_VALUE_ENCODING_V1 {
+            buf.try_put_u32(payload.encode_size() as u32)
+                .context(EncodeLogValueHeader)?;
+        }
 
         payload
             .encode_to(buf)
@@ -239,27 +373,70 @@ impl<T: Payload> Encoder<T> for LogValueEncoder {
 
     fn estimate_encoded_size(&self, payload: &T) -> usize {
         // Refer to value format.
-        1 + payload.encode_size()
+        if self.version >= LOG_VALUE_ENCODING_V1 {
+            1 + 4 + payload.encode_size()
+        } else {
+            1 + payload.encode_size()
+        }
     }
 }
 
 #[allow(unused)]
 pub struct LogValueDecoder {
     pub version: u8,
+    /// Accept values encoded by newer versions, which are assumed to keep the
+    /// layout of this version and only append fields after the payload. The
+    /// appended fields are ignored.
+    pub lenient: bool,
 }
 
 #[allow(unused)]
 impl LogValueDecoder {
+    /// Decode the payload from the value, values encoded by elder versions are
+    /// also supported.
     pub fn decode<'a>(&self, mut buf: &'a [u8]) -> Result<&'a [u8]> {
         let version = buf.try_get_u8().context(DecodeLogValueHeader)?;
+        // Newer versions can be read only if the payload length is known.
+        let accept_newer = self.lenient && self.version >= LOG_VALUE_ENCODING_V1;
         ensure!(
-            version == self.version,
+            version <= self.version || accept_newer,
             InvalidVersion {
                 expect: self.version,
                 given: version
             }
         );
 
+        if version == LOG_VALUE_ENCODING_V0 {
+            return Ok(buf);
+        }
+
+        ensure!(
+            buf.len() >= 4,
+            TruncatedValue {
+                expect: 4_usize,
+                given: buf.len(),
+            }
+        );
+        let payload_len = buf.try_get_u32().context(DecodeLogValueHeader)? as usize;
+        ensure!(
+            buf.len() >= payload_len,
+            TruncatedValue {
+                expect: payload_len,
+                given: buf.len(),
+            }
+        );
+        if version > self.version {
+            // Skip the unknown fields appended by the newer version.
+            return Ok(&buf[..payload_len]);
+        }
+        ensure!(
+            buf.len() == payload_len,
+            InvalidValueLength {
+                expect: payload_len,
+                given: buf.len(),
+            }
+        );
+
         Ok(buf)
     }
 }
@@ -274,6 +451,7 @@ pub struct MetaKeyEncoder {
     version: u8,
     key_type: MetaKeyType,
     namespace: Namespace,
+    tenant_id: TenantId,
 }
 
 #[derive(Clone, Debug)]
@@ -284,9 +462,9 @@ pub struct MetaKey {
 impl MetaKeyEncoder {
     /// Determine whether the raw bytes is a valid meta key.
     pub fn is_valid<B: Buf>(&self, buf: &mut B) -> Result<bool> {
-        let namespace = buf.try_get_u8().context(DecodeMetaKey)?;
+        let prefix = buf.try_get_u8().context(DecodeMetaKey)?;
         let key_type = buf.try_get_u8().context(DecodeMetaKey)?;
-        Ok(namespace == self.namespace as u8 && key_type == self.key_type as u8)
+        Ok(prefix == self.namespace.key_prefix(self.tenant_id) && key_type == self.key_type as u8)
     }
 }
 
@@ -301,9 +479,11 @@ impl Encoder<MetaKey> for MetaKeyEncoder {
     /// +---------------+--------------+----------------+--------------------+
     /// ```
     ///
+    /// The `namespace` byte also carries the tenant id in its higher 7 bits.
+    ///
     /// More information can be extended after the incremented `version header`.
     fn encode<B: BufMut>(&self, buf: &mut B, meta_key: &MetaKey) -> Result<()> {
-        buf.try_put_u8(self.namespace as u8)
+        buf.try_put_u8(self.namespace.key_prefix(self.tenant_id))
             .context(EncodeMetaKey)?;
         buf.try_put_u8(self.key_type as u8).context(EncodeMetaKey)?;
         buf.try_put_u64(meta_key.region_id).context(EncodeMetaKey)?;
@@ -323,14 +503,8 @@ impl Decoder<MetaKey> for MetaKeyEncoder {
 
     fn decode<B: Buf>(&self, buf: &mut B) -> Result<MetaKey> {
         // check namespace
-        let namespace = buf.try_get_u8().context(DecodeMetaKey)?;
-        ensure!(
-            namespace == self.namespace as u8,
-            InvalidNamespace {
-                expect: self.namespace,
-                given: namespace
-            }
-        );
+        let prefix = buf.try_get_u8().context(DecodeMetaKey)?;
+        self.namespace.check_key_prefix(self.tenant_id, prefix)?;
 
         let key_type = buf.try_get_u8().context(DecodeMetaKey)?;
         ensure!(
@@ -362,6 +536,12 @@ pub struct MaxSeqMetaValue {
     pub max_seq: SequenceNumber,
 }
 
+impl MaxSeqMetaValue {
+    fn checksum(&self) -> u32 {
+        crc32fast::hash(&self.max_seq.to_be_bytes())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MaxSeqMetaValueEncoder {
     version: u8,
@@ -373,23 +553,33 @@ impl Encoder<MaxSeqMetaValue> for MaxSeqMetaValueEncoder {
     /// Value format:
     ///
     /// ```text
-    /// +--------------------+--------------+
-    /// | version header(u8) | max_seq(u64) |
-    /// +--------------------+--------------+
+    /// +--------------------+--------------+-------------+
+    /// | version header(u8) | max_seq(u64) | crc32(u32)  |
+    /// +--------------------+--------------+-------------+
     /// ```
     ///
+    /// The crc32 covers the `max_seq` and is absent in version 0.
+    ///
     /// More information can be extended after the incremented `version header`.
     fn encode<B: BufMut>(&self, buf: &mut B, meta_value: &MaxSeqMetaValue) -> Result<()> {
         buf.try_put_u8(self.version).context(EncodeMetaValue)?;
         buf.try_put_u64(meta_value.max_seq)
             .context(EncodeMetaValue)?;
+        if self.version >= META_VALUE_ENCODING_V1 {
+            buf.try_put_u32(meta_value.checksum())
+                .context(EncodeMetaValue)?;
+        }
 
         Ok(())
     }
 
     fn estimate_encoded_size(&self, _meta_value: &MaxSeqMetaValue) -> usize {
         // Refer to value format.
-        1 + 8
+        if self.version >= META_VALUE_ENCODING_V1 {
+            1 + 8 + 4
+        } else {
+            1 + 8
+        }
     }
 }
 
@@ -397,10 +587,10 @@ impl Decoder<MaxSeqMetaValue> for MaxSeqMetaValueEncoder {
     type Error = Error;
 
     fn decode<B: Buf>(&self, buf: &mut B) -> Result<MaxSeqMetaValue> {
-        // check version
+        // check version, values written by older versions are still readable.
         let version = buf.try_get_u8().context(DecodeMetaValue)?;
         ensure!(
-            version == self.version,
+            version <= self.version,
             InvalidVersion {
                 expect: self.version,
                 given: version
@@ -408,7 +598,17 @@ impl Decoder<MaxSeqMetaValue> for MaxSeqMetaValueEncoder {
         );
 
         let max_seq = buf.try_get_u64().context(DecodeMetaValue)?;
-        Ok(MaxSeqMetaValue { max_seq })
+        let meta_value = MaxSeqMetaValue { max_seq };
+        if version >= META_VALUE_ENCODING_V1 {
+            let expect = buf.try_get_u32().context(DecodeMetaValue)?;
+            let actual = meta_value.checksum();
+            ensure!(
+                expect == actual,
+                MetaValueChecksumMismatch { expect, actual }
+            );
+        }
+
+        Ok(meta_value)
     }
 }
 
@@ -425,6 +625,7 @@ impl MaxSeqMetaEncoding {
                 version: NEWEST_META_KEY_ENCODING_VERSION,
                 key_type: MetaKeyType::MaxSeq,
                 namespace: Namespace::Meta,
+                tenant_id: DEFAULT_TENANT_ID,
             },
             value_enc: MaxSeqMetaValueEncoder {
                 version: NEWEST_META_VALUE_ENCODING_VERSION,
@@ -432,6 +633,16 @@ impl MaxSeqMetaEncoding {
         }
     }
 
+    #[allow(unused)]
+    pub fn newest_with_tenant(tenant_id: TenantId) -> Result<Self> {
+        validate_tenant_id(tenant_id)?;
+
+        let mut encoding = Self::newest();
+        encoding.key_enc.tenant_id = tenant_id;
+
+        Ok(encoding)
+    }
+
     pub fn is_max_seq_meta_key(&self, mut buf: &[u8]) -> manager::Result<bool> {
         self.key_enc
             .is_valid(&mut buf)
@@ -485,6 +696,7 @@ pub struct LogEncoding {
     value_enc: LogValueEncoder,
     // value decoder is created dynamically from the version,
     value_enc_version: u8,
+    lenient_value_decoding: bool,
 }
 
 #[allow(unused)]
@@ -494,9 +706,24 @@ impl LogEncoding {
             key_enc: LogKeyEncoder::newest(),
             value_enc: LogValueEncoder::newest(),
             value_enc_version: NEWEST_LOG_VALUE_ENCODING_VERSION,
+            lenient_value_decoding: false,
         }
     }
 
+    pub fn newest_with_tenant(tenant_id: TenantId) -> Result<Self> {
+        Ok(Self {
+            key_enc: LogKeyEncoder::newest_with_tenant(tenant_id)?,
+            ..Self::newest()
+        })
+    }
+
+    /// Accept values encoded by newer versions while decoding, the unknown
+    /// fields are ignored. Useful during rolling upgrades.
+    pub fn with_lenient_value_decoding(mut self, lenient: bool) -> Self {
+        self.lenient_value_decoding = lenient;
+        self
+    }
+
     /// Encode [LogKey] into `buf` and caller should knows that the keys are
     /// ordered by ([RegionId], [SequenceNum]) so the caller can use this
     /// method to generate min/max key in specific scope(global or in some
@@ -526,6 +753,7 @@ impl LogEncoding {
     pub fn decode_value<'a>(&self, buf: &'a [u8]) -> Result<&'a [u8]> {
         let value_dec = LogValueDecoder {
             version: self.value_enc_version,
+            lenient: self.lenient_value_decoding,
         };
 
         value_dec.decode(buf)
@@ -550,16 +778,22 @@ impl LogBatchEncoder {
 
     /// Consume LogBatchEncoder and encode single payload to LogWriteBatch.
     pub fn encode(self, payload: &impl Payload) -> manager::Result<LogWriteBatch> {
-        let mut write_batch = LogWriteBatch::new(self.location);
-        let mut buf = BytesMut::new();
-        self.log_encoding
-            .encode_value(&mut buf, payload)
-            .map_err(|e| Box::new(e) as _)
-            .context(Encoding)?;
+        self.encode_with_buf(&mut BytesMut::new(), payload)
+    }
 
-        write_batch.push(LogWriteEntry {
-            payload: buf.to_vec(),
-        });
+    /// Same as [LogBatchEncoder::encode], but the payload is encoded in the
+    /// reusable `buf`, so callers encoding in a tight loop can avoid
+    /// allocating the buffer on every call.
+    ///
+    /// The `buf` is cleared before encoding, and its content after encoding is
+    /// unspecified.
+    pub fn encode_with_buf(
+        self,
+        buf: &mut BytesMut,
+        payload: &impl Payload,
+    ) -> manager::Result<LogWriteBatch> {
+        let mut write_batch = LogWriteBatch::with_capacity(self.location, 1);
+        self.encode_entry(buf, payload, &mut write_batch)?;
 
         Ok(write_batch)
     }
@@ -574,21 +808,50 @@ impl LogBatchEncoder {
     where
         &'a I: Into<P>,
     {
-        let mut write_batch = LogWriteBatch::new(self.location);
-        let mut buf = BytesMut::new();
+        self.encode_batch_with_buf(&mut BytesMut::new(), raw_payload_batch)
+    }
+
+    /// Same as [LogBatchEncoder::encode_batch], but the payloads are encoded
+    /// in the reusable `buf`, see [LogBatchEncoder::encode_with_buf].
+    pub fn encode_batch_with_buf<'a, P: Payload, I>(
+        self,
+        buf: &mut BytesMut,
+        raw_payload_batch: &'a [I],
+    ) -> manager::Result<LogWriteBatch>
+    where
+        &'a I: Into<P>,
+    {
+        let mut write_batch = LogWriteBatch::with_capacity(self.location, raw_payload_batch.len());
         for raw_payload in raw_payload_batch.iter() {
-            self.log_encoding
-                .encode_value(&mut buf, &raw_payload.into())
-                .map_err(|e| Box::new(e) as _)
-                .context(Encoding)?;
-
-            write_batch.push(LogWriteEntry {
-                payload: buf.to_vec(),
-            });
+            self.encode_entry(buf, &raw_payload.into(), &mut write_batch)?;
         }
 
         Ok(write_batch)
     }
+
+    /// Encode the `payload` in `buf` and push the encoded entry to
+    /// `write_batch`.
+    ///
+    /// The `buf` is cleared by [LogEncoding::encode_value] and the entry takes
+    /// a copy of the encoded bytes, so `buf` can be reused for the next
+    /// entry.
+    fn encode_entry(
+        &self,
+        buf: &mut BytesMut,
+        payload: &impl Payload,
+        write_batch: &mut LogWriteBatch,
+    ) -> manager::Result<()> {
+        self.log_encoding
+            .encode_value(buf, payload)
+            .map_err(|e| Box::new(e) as _)
+            .context(Encoding)?;
+
+        write_batch.push(LogWriteEntry {
+            payload: buf.to_vec(),
+        });
+
+        Ok(())
+    }
 }
 
 /// Common log key used in multiple wal implementation
@@ -618,6 +881,7 @@ impl CommonLogKey {
 pub struct CommonLogKeyEncoder {
     pub version: u8,
     pub namespace: Namespace,
+    pub tenant_id: TenantId,
 }
 
 #[allow(unused)]
@@ -627,13 +891,24 @@ impl CommonLogKeyEncoder {
         Self {
             version: NEWEST_LOG_KEY_ENCODING_VERSION,
             namespace: Namespace::Log,
+            tenant_id: DEFAULT_TENANT_ID,
         }
     }
 
+    /// Create newest version encoder of the tenant.
+    pub fn newest_with_tenant(tenant_id: TenantId) -> Result<Self> {
+        validate_tenant_id(tenant_id)?;
+
+        Ok(Self {
+            tenant_id,
+            ..Self::newest()
+        })
+    }
+
     /// Determine whether the raw bytes is a log key.
     pub fn is_valid<B: Buf>(&self, buf: &mut B) -> Result<bool> {
-        let namespace = buf.try_get_u8().context(DecodeLogKey)?;
-        Ok(namespace == self.namespace as u8)
+        let prefix = buf.try_get_u8().context(DecodeLogKey)?;
+        Ok(prefix == self.namespace.key_prefix(self.tenant_id))
     }
 }
 
@@ -648,9 +923,15 @@ impl Encoder<CommonLogKey> for CommonLogKeyEncoder {
     /// +---------------+----------------+---------------+-------------------+--------------------+
     /// ```
     ///
+    /// The `namespace` byte also carries the tenant id in its higher 7 bits.
+    ///
+    /// The integers are encoded in big-endian, so the encoded keys of the same
+    /// tenant sort in the same order as the keys, which the scans rely on.
+    ///
     /// More information can be extended after the incremented `version header`.
     fn encode<B: BufMut>(&self, buf: &mut B, log_key: &CommonLogKey) -> Result<()> {
-        buf.try_put_u8(self.namespace as u8).context(EncodeLogKey)?;
+        buf.try_put_u8(self.namespace.key_prefix(self.tenant_id))
+            .context(EncodeLogKey)?;
         buf.try_put_u64(log_key.region_id).context(EncodeLogKey)?;
         buf.try_put_u64(log_key.table_id).context(EncodeLogKey)?;
         buf.try_put_u64(log_key.sequence_num)
@@ -671,14 +952,8 @@ impl Decoder<CommonLogKey> for CommonLogKeyEncoder {
 
     fn decode<B: Buf>(&self, buf: &mut B) -> Result<CommonLogKey> {
         // Check namespace
-        let namespace = buf.try_get_u8().context(DecodeLogKey)?;
-        ensure!(
-            namespace == self.namespace as u8,
-            InvalidNamespace {
-                expect: self.namespace,
-                given: namespace
-            }
-        );
+        let prefix = buf.try_get_u8().context(DecodeLogKey)?;
+        self.namespace.check_key_prefix(self.tenant_id, prefix)?;
 
         let log_key = CommonLogKey {
             region_id: buf.try_get_u64().context(DecodeLogKey)?,
@@ -707,6 +982,7 @@ pub struct CommonLogEncoding {
     value_enc: LogValueEncoder,
     // value decoder is created dynamically from the version,
     value_enc_version: u8,
+    lenient_value_decoding: bool,
 }
 
 #[allow(unused)]
@@ -716,9 +992,24 @@ impl CommonLogEncoding {
             key_enc: CommonLogKeyEncoder::newest(),
             value_enc: LogValueEncoder::newest(),
             value_enc_version: NEWEST_LOG_VALUE_ENCODING_VERSION,
+            lenient_value_decoding: false,
         }
     }
 
+    pub fn newest_with_tenant(tenant_id: TenantId) -> Result<Self> {
+        Ok(Self {
+            key_enc: CommonLogKeyEncoder::newest_with_tenant(tenant_id)?,
+            ..Self::newest()
+        })
+    }
+
+    /// Accept values encoded by newer versions while decoding, the unknown
+    /// fields are ignored. Useful during rolling upgrades.
+    pub fn with_lenient_value_decoding(mut self, lenient: bool) -> Self {
+        self.lenient_value_decoding = lenient;
+        self
+    }
+
     /// Encode [LogKey] into `buf` and caller should knows that the keys are
     /// ordered by ([RegionId], [SequenceNum]) so the caller can use this
     /// method to generate min/max key in specific scope(global or in some
@@ -748,6 +1039,7 @@ impl CommonLogEncoding {
     pub fn decode_value<'a>(&self, buf: &'a [u8]) -> Result<&'a [u8]> {
         let value_dec = LogValueDecoder {
             version: self.value_enc_version,
+            lenient: self.lenient_value_decoding,
         };
 
         value_dec.decode(buf)
@@ -758,9 +1050,8 @@ impl CommonLogEncoding {
 mod tests {
     use common_types::bytes::BytesMut;
 
-    use super::{CommonLogEncoding, LogEncoding};
+    use super::*;
     use crate::{
-        kv_encoder::CommonLogKey,
         log_batch::PayloadDecoder,
         tests::util::{TestPayload, TestPayloadDecoder},
     };
@@ -795,6 +1086,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_log_batch_encoder_with_buf() {
+        let location = Location::new(1, 2);
+        let raw_payloads = [1_u32, 20, 300];
+        let expect = LogBatchEncoder::create(location)
+            .encode_batch::<TestPayload, u32>(&raw_payloads)
+            .unwrap();
+
+        // The stale content of the reused buffer doesn't affect the entries.
+        let mut buf = BytesMut::from(&b"stale content"[..]);
+        for _ in 0..3 {
+            let batch = LogBatchEncoder::create(location)
+                .encode_batch_with_buf::<TestPayload, u32>(&mut buf, &raw_payloads)
+                .unwrap();
+            assert_eq!(location, batch.location);
+            assert_eq!(expect.len(), batch.len());
+            for (expect_entry, entry) in expect.entries.iter().zip(batch.entries.iter()) {
+                assert_eq!(expect_entry.payload, entry.payload);
+            }
+        }
+
+        let payload = TestPayload { val: 300 };
+        let batch = LogBatchEncoder::create(location)
+            .encode_with_buf(&mut buf, &payload)
+            .unwrap();
+        assert_eq!(1, batch.len());
+        assert_eq!(expect.entries[2].payload, batch.entries[0].payload);
+    }
+
     #[test]
     fn test_common_log_key_encoding() {
         let region_id = 1234;
@@ -818,4 +1138,249 @@ mod tests {
             assert_eq!(common_log_key, decoded_key);
         }
     }
+
+    /// Integers whose big-endian and little-endian bytes sort differently.
+    const ORDER_TEST_INTEGERS: [u64; 9] = [
+        0,
+        1,
+        255,
+        256,
+        257,
+        u32::MAX as u64,
+        u32::MAX as u64 + 1,
+        u64::MAX - 1,
+        u64::MAX,
+    ];
+
+    #[test]
+    fn test_log_key_order() {
+        let encoding = LogEncoding::newest();
+        let mut keys = Vec::new();
+        for region_id in ORDER_TEST_INTEGERS {
+            for seq in ORDER_TEST_INTEGERS {
+                keys.push((region_id, seq));
+            }
+        }
+
+        let mut encoded_keys = keys
+            .iter()
+            .map(|key| {
+                let mut buf = BytesMut::new();
+                encoding.encode_key(&mut buf, key).unwrap();
+                (buf.to_vec(), *key)
+            })
+            .collect::<Vec<_>>();
+        encoded_keys.sort_unstable();
+        let sorted_keys = encoded_keys.into_iter().map(|v| v.1).collect::<Vec<_>>();
+
+        keys.sort_unstable();
+        assert_eq!(keys, sorted_keys);
+    }
+
+    #[test]
+    fn test_common_log_key_order() {
+        for tenant_id in [DEFAULT_TENANT_ID, MAX_TENANT_ID] {
+            let encoding = CommonLogEncoding::newest_with_tenant(tenant_id).unwrap();
+            let mut keys = Vec::new();
+            for region_id in ORDER_TEST_INTEGERS {
+                for table_id in [0, 256, u64::MAX] {
+                    for seq in ORDER_TEST_INTEGERS {
+                        keys.push(CommonLogKey::new(region_id, table_id, seq));
+                    }
+                }
+            }
+
+            let encode = |key: &CommonLogKey| {
+                let mut buf = BytesMut::new();
+                encoding.encode_key(&mut buf, key).unwrap();
+                buf.to_vec()
+            };
+            let mut encoded_keys = keys
+                .iter()
+                .map(|key| (encode(key), *key))
+                .collect::<Vec<_>>();
+            encoded_keys.sort_unstable();
+            let sorted_keys = encoded_keys.iter().map(|v| v.1).collect::<Vec<_>>();
+
+            keys.sort_unstable();
+            assert_eq!(keys, sorted_keys);
+
+            // The keys with min and max sequence bound all the logs of the table.
+            for key in &keys {
+                let min_key = CommonLogKey::new(
+                    key.region_id,
+                    key.table_id,
+                    common_types::MIN_SEQUENCE_NUMBER,
+                );
+                let max_key = CommonLogKey::new(
+                    key.region_id,
+                    key.table_id,
+                    common_types::MAX_SEQUENCE_NUMBER,
+                );
+                let encoded_key = encode(key);
+                assert!(encode(&min_key) <= encoded_key);
+                assert!(encoded_key <= encode(&max_key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated_log_value() {
+        let encoding = LogEncoding::newest();
+        let payload = TestPayload { val: 1234 };
+        let mut buf = BytesMut::new();
+        encoding.encode_value(&mut buf, &payload).unwrap();
+
+        // Complete value.
+        let mut value = encoding.decode_value(&buf).unwrap();
+        assert_eq!(payload, TestPayloadDecoder.decode(&mut value).unwrap());
+
+        // Truncated in the middle of the payload or the length.
+        for len in [buf.len() - 1, 3] {
+            let err = encoding.decode_value(&buf[..len]).unwrap_err();
+            assert!(err.is_truncated_value(), "err:{}", err);
+        }
+
+        // Trailing garbage is not a truncation.
+        let mut corrupted = buf.to_vec();
+        corrupted.push(0);
+        let err = encoding.decode_value(&corrupted).unwrap_err();
+        assert!(matches!(err, Error::InvalidValueLength { .. }));
+    }
+
+    #[test]
+    fn test_decode_v0_log_value() {
+        let encoder = LogValueEncoder {
+            version: LOG_VALUE_ENCODING_V0,
+        };
+        let payload = TestPayload { val: 1234 };
+        let mut buf = BytesMut::new();
+        encoder.encode(&mut buf, &payload).unwrap();
+
+        let mut value = LogEncoding::newest().decode_value(&buf).unwrap();
+        assert_eq!(payload, TestPayloadDecoder.decode(&mut value).unwrap());
+    }
+
+    fn encode_future_log_value(payload: &TestPayload) -> Vec<u8> {
+        let mut payload_buf = BytesMut::new();
+        payload.encode_to(&mut payload_buf).unwrap();
+
+        // A future version appends an unknown field after the payload.
+        let mut buf = BytesMut::new();
+        buf.put_u8(NEWEST_LOG_VALUE_ENCODING_VERSION + 1);
+        buf.put_u32(payload_buf.len() as u32);
+        buf.put_slice(&payload_buf);
+        buf.put_u64(5678);
+        buf.to_vec()
+    }
+
+    #[test]
+    fn test_decode_future_log_value_strict() {
+        let payload = TestPayload { val: 1234 };
+        let value = encode_future_log_value(&payload);
+
+        let err = LogEncoding::newest().decode_value(&value).unwrap_err();
+        assert!(matches!(err, Error::InvalidVersion { .. }));
+        let err = CommonLogEncoding::newest()
+            .with_lenient_value_decoding(false)
+            .decode_value(&value)
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidVersion { .. }));
+    }
+
+    #[test]
+    fn test_decode_future_log_value_lenient() {
+        let payload = TestPayload { val: 1234 };
+        let value = encode_future_log_value(&payload);
+
+        let encoding = LogEncoding::newest().with_lenient_value_decoding(true);
+        let mut decoded = encoding.decode_value(&value).unwrap();
+        assert_eq!(payload, TestPayloadDecoder.decode(&mut decoded).unwrap());
+
+        let encoding = CommonLogEncoding::newest().with_lenient_value_decoding(true);
+        let mut decoded = encoding.decode_value(&value).unwrap();
+        assert_eq!(payload, TestPayloadDecoder.decode(&mut decoded).unwrap());
+
+        // Truncation is still detected.
+        let err = encoding
+            .decode_value(&value[..value.len() - 10])
+            .unwrap_err();
+        assert!(err.is_truncated_value(), "err:{}", err);
+
+        // Values of the known versions are decoded as before.
+        let mut buf = BytesMut::new();
+        encoding.encode_value(&mut buf, &payload).unwrap();
+        let mut decoded = encoding.decode_value(&buf).unwrap();
+        assert_eq!(payload, TestPayloadDecoder.decode(&mut decoded).unwrap());
+    }
+
+    #[test]
+    fn test_tenant_log_key_encoding() {
+        let log_key = CommonLogKey::new(1234, 8910, 1000);
+        let default_encoding = CommonLogEncoding::newest();
+        let encoding1 = CommonLogEncoding::newest_with_tenant(1).unwrap();
+        let encoding2 = CommonLogEncoding::newest_with_tenant(MAX_TENANT_ID).unwrap();
+
+        let (mut buf1, mut buf2) = (BytesMut::new(), BytesMut::new());
+        encoding1.encode_key(&mut buf1, &log_key).unwrap();
+        encoding2.encode_key(&mut buf2, &log_key).unwrap();
+        assert_ne!(buf1, buf2);
+
+        // Each tenant only decodes its own keys.
+        assert_eq!(log_key, encoding1.decode_key(&buf1).unwrap());
+        assert_eq!(log_key, encoding2.decode_key(&buf2).unwrap());
+        assert!(encoding1.is_log_key(&buf1).unwrap());
+        assert!(!encoding1.is_log_key(&buf2).unwrap());
+        assert!(!default_encoding.is_log_key(&buf1).unwrap());
+        assert!(matches!(
+            encoding1.decode_key(&buf2).unwrap_err(),
+            Error::InvalidTenant { .. }
+        ));
+
+        // Meta keys never collide with log keys of another tenant.
+        let meta_encoding = MaxSeqMetaEncoding::newest_with_tenant(1).unwrap();
+        let mut meta_buf = BytesMut::new();
+        meta_encoding
+            .encode_key(&mut meta_buf, &MetaKey { region_id: 1234 })
+            .unwrap();
+        assert!(!encoding1.is_log_key(&meta_buf).unwrap());
+        assert!(!default_encoding.is_log_key(&meta_buf).unwrap());
+        assert!(meta_encoding.is_max_seq_meta_key(&meta_buf).unwrap());
+        assert!(!MaxSeqMetaEncoding::newest()
+            .is_max_seq_meta_key(&meta_buf)
+            .unwrap());
+
+        assert!(CommonLogEncoding::newest_with_tenant(MAX_TENANT_ID + 1).is_err());
+    }
+
+    #[test]
+    fn test_max_seq_meta_value_checksum() {
+        let encoding = MaxSeqMetaEncoding::newest();
+        let meta_value = MaxSeqMetaValue { max_seq: 1234 };
+        let mut buf = BytesMut::new();
+        encoding.encode_value(&mut buf, &meta_value).unwrap();
+        assert_eq!(1 + 8 + 4, buf.len());
+        assert_eq!(1234, encoding.decode_value(&buf).unwrap().max_seq);
+
+        // Flip a bit of the max_seq.
+        let mut corrupted = buf.to_vec();
+        corrupted[8] ^= 1;
+        let mut value = corrupted.as_slice();
+        let err = encoding.value_enc.decode(&mut value).unwrap_err();
+        assert!(
+            matches!(err, Error::MetaValueChecksumMismatch { .. }),
+            "err:{}",
+            err
+        );
+        assert!(encoding.decode_value(&corrupted).is_err());
+
+        // Values of version 0 have no checksum.
+        let v0_encoder = MaxSeqMetaValueEncoder {
+            version: META_VALUE_ENCODING_V0,
+        };
+        let mut buf = BytesMut::new();
+        v0_encoder.encode(&mut buf, &meta_value).unwrap();
+        assert_eq!(1 + 8, buf.len());
+        assert_eq!(1234, encoding.decode_value(&buf).unwrap().max_seq);
+    }
 }
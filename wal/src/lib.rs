@@ -2,6 +2,8 @@
 
 //! Write Ahead Log
 
+pub mod buffer_pool;
+pub mod decode_stream;
 mod kv_encoder;
 pub mod log_batch;
 pub mod manager;
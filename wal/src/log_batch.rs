@@ -2,18 +2,56 @@
 
 //! Log entries definition.
 
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug, marker::PhantomData, sync::Arc};
 
 use common_types::{
-    bytes::{Buf, BufMut},
+    bytes::{Buf, BufMut, SafeBuf, SafeBufMut},
     table::{Location, TableId},
     SequenceNumber,
 };
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to encode payload format, err:{}", source))]
+    EncodeFormat { source: common_types::bytes::Error },
+
+    #[snafu(display("Failed to encode payload, format:{:?}, err:{}", format, source))]
+    EncodeBody {
+        format: PayloadFormat,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[snafu(display("Failed to decode payload format, err:{}", source))]
+    DecodeFormat { source: common_types::bytes::Error },
+
+    #[snafu(display("Unknown payload format, value:{}.\nBacktrace:\n{}", value, backtrace))]
+    UnknownFormat { value: u8, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Codec of payload format not found, format:{:?}.\nBacktrace:\n{}",
+        format,
+        backtrace
+    ))]
+    CodecNotFound {
+        format: PayloadFormat,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to decode payload, format:{:?}, err:{}", format, source))]
+    DecodeBody {
+        format: PayloadFormat,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
 
 pub trait Payload: Send + Sync + Debug {
     type Error: std::error::Error + Send + Sync + 'static;
 
     /// Compute size of the encoded payload.
+    ///
+    /// The size must be exact as it is written as the payload length of the
+    /// log value.
     fn encode_size(&self) -> usize;
     /// Append the encoded payload to the `buf`.
     fn encode_to<B: BufMut>(&self, buf: &mut B) -> Result<(), Self::Error>;
@@ -26,6 +64,17 @@ pub struct LogEntry<P> {
     pub payload: P,
 }
 
+impl LogEntry<&'_ [u8]> {
+    /// Copy the payload to make an entry not borrowing the iterator.
+    pub fn into_owned(self) -> LogEntry<Vec<u8>> {
+        LogEntry {
+            table_id: self.table_id,
+            sequence: self.sequence,
+            payload: self.payload.to_vec(),
+        }
+    }
+}
+
 /// An encoded entry to be written into the Wal.
 #[derive(Debug)]
 pub struct LogWriteEntry {
@@ -78,3 +127,244 @@ pub trait PayloadDecoder: Send + Sync {
     /// Decode `Target` from the `bytes`.
     fn decode<B: Buf>(&self, buf: &mut B) -> Result<Self::Target, Self::Error>;
 }
+
+/// Format of the payload body, it is written as the first byte of the payload
+/// encoded by [FormattedPayload].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadFormat {
+    Protobuf = 1,
+    Bincode = 2,
+}
+
+impl PayloadFormat {
+    pub fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            value if value == Self::Protobuf as u8 => Some(Self::Protobuf),
+            value if value == Self::Bincode as u8 => Some(Self::Bincode),
+            _ => None,
+        }
+    }
+}
+
+/// Size of the format header in bytes.
+const FORMAT_HEADER_SIZE: usize = 1;
+
+/// Codec to encode/decode the payload body of type `T` in a specific
+/// [PayloadFormat].
+pub trait PayloadCodec<T>: Send + Sync + Debug {
+    /// Format of the encoded body.
+    fn format(&self) -> PayloadFormat;
+
+    /// Compute size of the encoded body.
+    fn encode_size(&self, item: &T) -> usize;
+
+    /// Append the encoded body to the `buf`.
+    fn encode_to(
+        &self,
+        item: &T,
+        buf: &mut dyn BufMut,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Decode the body from the `buf`.
+    fn decode(
+        &self,
+        buf: &mut dyn Buf,
+    ) -> std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+pub type PayloadCodecRef<T> = Arc<dyn PayloadCodec<T>>;
+
+/// Codec encoding the payload body by protobuf.
+#[derive(Debug, Default)]
+pub struct ProtobufCodec<T> {
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: prost::Message + Default> PayloadCodec<T> for ProtobufCodec<T> {
+    fn format(&self) -> PayloadFormat {
+        PayloadFormat::Protobuf
+    }
+
+    fn encode_size(&self, item: &T) -> usize {
+        item.encoded_len()
+    }
+
+    fn encode_to(
+        &self,
+        item: &T,
+        mut buf: &mut dyn BufMut,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        item.encode(&mut buf).map_err(|e| Box::new(e) as _)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut dyn Buf,
+    ) -> std::result::Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        T::decode(buf).map_err(|e| Box::new(e) as _)
+    }
+}
+
+/// [Payload] whose body is encoded by a [PayloadCodec], the format of the
+/// codec is written before the body so it can be decoded by
+/// [FormattedPayloadDecoder].
+#[derive(Debug)]
+pub struct FormattedPayload<'a, T> {
+    codec: &'a dyn PayloadCodec<T>,
+    item: &'a T,
+}
+
+impl<'a, T> FormattedPayload<'a, T> {
+    pub fn new(codec: &'a dyn PayloadCodec<T>, item: &'a T) -> Self {
+        Self { codec, item }
+    }
+}
+
+impl<'a, T: Send + Sync + Debug> Payload for FormattedPayload<'a, T> {
+    type Error = Error;
+
+    fn encode_size(&self) -> usize {
+        FORMAT_HEADER_SIZE + self.codec.encode_size(self.item)
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) -> Result<(), Self::Error> {
+        let format = self.codec.format();
+        buf.try_put_u8(format.to_u8()).context(EncodeFormat)?;
+        self.codec
+            .encode_to(self.item, buf)
+            .context(EncodeBody { format })
+    }
+}
+
+/// Decoder of [FormattedPayload], dispatch the decoding to the registered codec
+/// according to the format of payload.
+#[derive(Debug)]
+pub struct FormattedPayloadDecoder<T> {
+    codecs: HashMap<PayloadFormat, PayloadCodecRef<T>>,
+}
+
+impl<T> Default for FormattedPayloadDecoder<T> {
+    fn default() -> Self {
+        Self {
+            codecs: HashMap::new(),
+        }
+    }
+}
+
+impl<T> FormattedPayloadDecoder<T> {
+    /// Register the `codec`, the old codec of the same format will be
+    /// replaced.
+    pub fn register(mut self, codec: PayloadCodecRef<T>) -> Self {
+        self.codecs.insert(codec.format(), codec);
+        self
+    }
+}
+
+impl<T: Send + Sync> PayloadDecoder for FormattedPayloadDecoder<T> {
+    type Error = Error;
+    type Target = T;
+
+    fn decode<B: Buf>(&self, buf: &mut B) -> Result<Self::Target, Self::Error> {
+        let value = buf.try_get_u8().context(DecodeFormat)?;
+        let format = PayloadFormat::from_u8(value).context(UnknownFormat { value })?;
+        let codec = self.codecs.get(&format).context(CodecNotFound { format })?;
+
+        codec.decode(buf).context(DecodeBody { format })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_types::bytes::BytesMut;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct TestMessage {
+        #[prost(uint32, tag = "1")]
+        val: u32,
+    }
+
+    /// Codec encoding the value in fixed 4 bytes, registered as bincode format
+    /// for test.
+    #[derive(Debug)]
+    struct FixedCodec;
+
+    impl PayloadCodec<TestMessage> for FixedCodec {
+        fn format(&self) -> PayloadFormat {
+            PayloadFormat::Bincode
+        }
+
+        fn encode_size(&self, _item: &TestMessage) -> usize {
+            4
+        }
+
+        fn encode_to(
+            &self,
+            item: &TestMessage,
+            buf: &mut dyn BufMut,
+        ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            buf.put_u32(item.val);
+            Ok(())
+        }
+
+        fn decode(
+            &self,
+            buf: &mut dyn Buf,
+        ) -> std::result::Result<TestMessage, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(TestMessage { val: buf.get_u32() })
+        }
+    }
+
+    fn encode(codec: &dyn PayloadCodec<TestMessage>, item: &TestMessage) -> BytesMut {
+        let payload = FormattedPayload::new(codec, item);
+        let mut buf = BytesMut::new();
+        payload.encode_to(&mut buf).unwrap();
+        assert_eq!(payload.encode_size(), buf.len());
+        buf
+    }
+
+    #[test]
+    fn test_decode_dispatch() {
+        let protobuf_codec = Arc::new(ProtobufCodec::<TestMessage>::default());
+        let fixed_codec = Arc::new(FixedCodec);
+        let decoder = FormattedPayloadDecoder::<TestMessage>::default()
+            .register(protobuf_codec.clone())
+            .register(fixed_codec.clone());
+
+        let item = TestMessage { val: 42 };
+        let protobuf_buf = encode(protobuf_codec.as_ref(), &item);
+        assert_eq!(PayloadFormat::Protobuf.to_u8(), protobuf_buf[0]);
+        let fixed_buf = encode(fixed_codec.as_ref(), &item);
+        assert_eq!(PayloadFormat::Bincode.to_u8(), fixed_buf[0]);
+        assert_ne!(protobuf_buf, fixed_buf);
+
+        for buf in [protobuf_buf, fixed_buf] {
+            let decoded = decoder.decode(&mut buf.as_ref()).unwrap();
+            assert_eq!(item, decoded);
+        }
+    }
+
+    #[test]
+    fn test_decode_unknown_format() {
+        let decoder = FormattedPayloadDecoder::<TestMessage>::default()
+            .register(Arc::new(ProtobufCodec::<TestMessage>::default()));
+
+        let item = TestMessage { val: 42 };
+        let buf = encode(&FixedCodec, &item);
+        assert!(matches!(
+            decoder.decode(&mut buf.as_ref()),
+            Err(Error::CodecNotFound { .. })
+        ));
+
+        let buf = [0_u8, 1, 2];
+        assert!(matches!(
+            decoder.decode(&mut &buf[..]),
+            Err(Error::UnknownFormat { .. })
+        ));
+    }
+}
@@ -126,17 +126,36 @@ pub mod error {
 pub type RegionId = u64;
 pub const MAX_REGION_ID: RegionId = u64::MAX;
 
+/// Default number (100) of the deleted logs to clean in a batch, it only takes
+/// effect when the Wal cleans the deleted logs in batches.
+pub const DEFAULT_CLEAN_BATCH_SIZE: usize = 100;
+
+/// Durability level of a wal write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// The logs are persisted before the write returns.
+    #[default]
+    Durable,
+    /// The logs may be buffered and lost on crash, it is suitable for bulk
+    /// writes that can be redone, such as backfilling.
+    Buffered,
+}
+
 #[derive(Debug, Clone)]
 pub struct WriteContext {
     /// Timeout to write wal and it only takes effect when writing to a Wal on a
     /// remote machine (writing to the local disk does not have timeout).
     pub timeout: Duration,
+    /// Durability of the write, it only takes effect if the Wal supports
+    /// buffered writes, otherwise all writes are durable.
+    pub durability: Durability,
 }
 
 impl Default for WriteContext {
     fn default() -> Self {
         Self {
             timeout: Duration::from_secs(1),
+            durability: Durability::default(),
         }
     }
 }
@@ -147,7 +166,8 @@ pub struct ReadContext {
     /// Wal on a remote machine (reading from the local disk does not have
     /// timeout).
     pub timeout: Duration,
-    /// Batch size to read log entries.
+    /// Batch size to read log entries, it is also the batch size of each scan
+    /// when reading from a remote table kv.
     pub batch_size: usize,
 }
 
@@ -241,6 +261,36 @@ pub trait SyncLogIterator: Send + fmt::Debug {
     ///
     /// NOTE that this operation may **SYNC** caller thread now.
     fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>>;
+
+    /// Fetch next log entry owning its payload, which can be sent to other
+    /// threads, e.g. to decode the payloads in parallel.
+    ///
+    /// The default implementation copies the payload, implementations holding
+    /// an owned payload should override it to avoid the copy.
+    fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
+        Ok(self.next_log_entry()?.map(LogEntry::into_owned))
+    }
+
+    /// Convert into an [Iterator] of the owned log entries.
+    fn into_owned_iter(self) -> OwnedLogIter<Self>
+    where
+        Self: Sized,
+    {
+        OwnedLogIter(self)
+    }
+}
+
+/// [Iterator] of the log entries owning their payloads, see
+/// [SyncLogIterator::next_owned_log_entry].
+#[derive(Debug)]
+pub struct OwnedLogIter<I>(I);
+
+impl<I: SyncLogIterator> Iterator for OwnedLogIter<I> {
+    type Item = Result<LogEntry<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_owned_log_entry().transpose()
+    }
 }
 
 /// Vectorwise log entry iterator.
@@ -248,6 +298,12 @@ pub trait SyncLogIterator: Send + fmt::Debug {
 pub trait AsyncLogIterator: Send + fmt::Debug {
     /// Async fetch next log entry from the iterator.
     async fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>>;
+
+    /// Async fetch next log entry owning its payload, see
+    /// [SyncLogIterator::next_owned_log_entry].
+    async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
+        Ok(self.next_log_entry().await?.map(LogEntry::into_owned))
+    }
 }
 
 /// Management of multi-region Wals.
@@ -434,7 +490,10 @@ mod tests {
     use common_util::runtime::{self, Runtime};
 
     use super::{AsyncLogIterator, BatchLogIteratorAdapter, SyncLogIterator};
-    use crate::{log_batch::LogEntry, tests::util::TestPayloadDecoder};
+    use crate::{
+        log_batch::{LogEntry, PayloadDecoder},
+        tests::util::TestPayloadDecoder,
+    };
 
     #[derive(Debug, Clone)]
     struct TestIterator {
@@ -509,6 +568,56 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_owned_log_entries() {
+        let test_data = vec![1_u32, 2, 3, 4, 5, 6];
+        let test_iterator = TestIterator {
+            test_logs: test_data.iter().map(|u| u.to_be_bytes().to_vec()).collect(),
+            cursor: 0,
+            terminate: test_data.len(),
+        };
+        let runtime = Arc::new(runtime::Builder::default().enable_all().build().unwrap());
+
+        runtime.block_on(async {
+            // Decode the owned entries in a spawned task.
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<LogEntry<Vec<u8>>>();
+            let decoder = tokio::spawn(async move {
+                let mut res = Vec::new();
+                while let Some(log_entry) = rx.recv().await {
+                    let mut payload = log_entry.payload.as_slice();
+                    res.push(TestPayloadDecoder.decode(&mut payload).unwrap().val);
+                }
+                res
+            });
+
+            let mut iter = test_iterator.clone();
+            while let Some(log_entry) = AsyncLogIterator::next_owned_log_entry(&mut iter)
+                .await
+                .unwrap()
+            {
+                tx.send(log_entry).unwrap();
+            }
+            drop(tx);
+            assert_eq!(test_data, decoder.await.unwrap());
+
+            let decoded = tokio::spawn(async move {
+                test_iterator
+                    .into_owned_iter()
+                    .map(|log_entry| {
+                        let log_entry = log_entry.unwrap();
+                        TestPayloadDecoder
+                            .decode(&mut log_entry.payload.as_slice())
+                            .unwrap()
+                            .val
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap();
+            assert_eq!(test_data, decoded);
+        });
+    }
+
     async fn test_async_iterator_adapting(test_iterator: TestIterator) -> Vec<u32> {
         let mut res = Vec::new();
         let mut iter = BatchLogIteratorAdapter::new_with_async(Box::new(test_iterator), 3);
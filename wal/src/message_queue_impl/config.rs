@@ -10,12 +10,17 @@ use serde_derive::{Deserialize, Serialize};
 #[serde(default)]
 pub struct Config {
     pub clean_period: ReadableDuration,
+    /// Max entries of the start sequence to offset mapping of each table, the
+    /// mapping will be coarsened if exceeding it and the safe delete offset
+    /// becomes less precise (more logs will be kept). No bound if not set.
+    pub max_offset_mapping_entries: Option<usize>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             clean_period: ReadableDuration::millis(3600 * 1000),
+            max_offset_mapping_entries: None,
         }
     }
 }
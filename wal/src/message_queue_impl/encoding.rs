@@ -17,11 +17,12 @@ use snafu::{ensure, Backtrace, ResultExt, Snafu};
 use crate::{
     kv_encoder::Namespace,
     manager::{self, RegionId},
-    message_queue_impl::region_context::{RegionMetaSnapshot, TableMetaData},
+    message_queue_impl::region_context::{RegionMetaDelta, RegionMetaSnapshot, TableMetaData},
 };
 
 const NEWEST_MQ_META_KEY_ENCODING_VERSION: u8 = 0;
 const NEWEST_MQ_META_VALUE_ENCODING_VERSION: u8 = 0;
+const NEWEST_MQ_META_DELTA_ENCODING_VERSION: u8 = 0;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -53,6 +54,18 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[snafu(display(
+        "Failed to encode meta deltas of message queue implementation, err:{}",
+        source
+    ))]
+    EncodeMetaDelta { source: bytes::Error },
+
+    #[snafu(display(
+        "Failed to decode meta deltas of message queue implementation, err:{}",
+        source
+    ))]
+    DecodeMetaDelta { source: bytes::Error },
+
     #[snafu(display(
         "Found invalid meta key magic of message queue implementation, expect:{}, given:{}.\nBacktrace:\n{}",
         expect,
@@ -109,6 +122,7 @@ pub fn format_wal_meta_topic_name(namespace: &str, region_id: RegionId) -> Strin
 pub struct MetaEncoding {
     key_enc: MetaKeyEncoder,
     value_enc: MetaValueEncoder,
+    delta_enc: MetaDeltaEncoder,
 }
 
 #[allow(unused)]
@@ -156,6 +170,26 @@ impl MetaEncoding {
         Ok(meta_value.into())
     }
 
+    pub fn encode_deltas(
+        &self,
+        buf: &mut BytesMut,
+        region_meta_deltas: &[RegionMetaDelta],
+    ) -> manager::Result<()> {
+        buf.clear();
+        buf.reserve(self.delta_enc.estimate_encoded_size(region_meta_deltas));
+        self.delta_enc
+            .encode(buf, region_meta_deltas)
+            .map_err(|e| Box::new(e) as _)
+            .context(manager::Encoding)
+    }
+
+    pub fn decode_deltas(&self, mut buf: &[u8]) -> manager::Result<Vec<RegionMetaDelta>> {
+        self.delta_enc
+            .decode(&mut buf)
+            .map_err(|e| Box::new(e) as _)
+            .context(manager::Decoding)
+    }
+
     pub fn is_meta_key(&self, mut buf: &[u8]) -> manager::Result<bool> {
         self.key_enc
             .is_valid(&mut buf)
@@ -172,6 +206,9 @@ impl MetaEncoding {
             value_enc: MetaValueEncoder {
                 version: NEWEST_MQ_META_VALUE_ENCODING_VERSION,
             },
+            delta_enc: MetaDeltaEncoder {
+                version: NEWEST_MQ_META_DELTA_ENCODING_VERSION,
+            },
         }
     }
 }
@@ -268,7 +305,7 @@ pub struct MetaValueEncoder {
 impl Encoder<MetaValue> for MetaValueEncoder {
     type Error = Error;
 
-    /// Key format:
+    /// Value format:
     ///
     /// ```text
     /// +--------------------+----------------------+
@@ -319,6 +356,72 @@ impl Decoder<MetaValue> for MetaValueEncoder {
     }
 }
 
+/// Encoder of a batch of [RegionMetaDelta]s.
+#[derive(Clone, Debug)]
+pub struct MetaDeltaEncoder {
+    pub version: u8,
+}
+
+impl Encoder<[RegionMetaDelta]> for MetaDeltaEncoder {
+    type Error = Error;
+
+    /// Deltas format:
+    ///
+    /// ```text
+    /// +--------------------+-----------------+---------------+-------------------+-------------+-----+
+    /// | version header(u8) | delta num(u32)  | table id(u64) | sequence num(u64) | offset(i64) | ... |
+    /// +--------------------+-----------------+---------------+-------------------+-------------+-----+
+    /// ```
+    ///
+    /// More information can be extended after the incremented `version header`.
+    fn encode<B: BufMut>(&self, buf: &mut B, deltas: &[RegionMetaDelta]) -> Result<()> {
+        buf.try_put_u8(self.version).context(EncodeMetaDelta)?;
+        buf.try_put_u32(deltas.len() as u32)
+            .context(EncodeMetaDelta)?;
+        for delta in deltas {
+            buf.try_put_u64(delta.table_id()).context(EncodeMetaDelta)?;
+            buf.try_put_u64(delta.sequence_num())
+                .context(EncodeMetaDelta)?;
+            buf.try_put_u64(delta.offset() as u64)
+                .context(EncodeMetaDelta)?;
+        }
+
+        Ok(())
+    }
+
+    fn estimate_encoded_size(&self, deltas: &[RegionMetaDelta]) -> usize {
+        // Refer to deltas format.
+        1 + 4 + deltas.len() * (8 + 8 + 8)
+    }
+}
+
+impl Decoder<Vec<RegionMetaDelta>> for MetaDeltaEncoder {
+    type Error = Error;
+
+    fn decode<B: Buf>(&self, buf: &mut B) -> Result<Vec<RegionMetaDelta>> {
+        // Check version.
+        let version = buf.try_get_u8().context(DecodeMetaDelta)?;
+        ensure!(
+            version == self.version,
+            InvalidVersion {
+                expect: self.version,
+                given: version,
+            }
+        );
+
+        let num_deltas = buf.try_get_u32().context(DecodeMetaDelta)? as usize;
+        let mut deltas = Vec::with_capacity(num_deltas.min(buf.remaining() / (8 + 8 + 8)));
+        for _ in 0..num_deltas {
+            let table_id = buf.try_get_u64().context(DecodeMetaDelta)?;
+            let sequence_num = buf.try_get_u64().context(DecodeMetaDelta)?;
+            let offset = buf.try_get_u64().context(DecodeMetaDelta)? as i64;
+            deltas.push(RegionMetaDelta::new(table_id, sequence_num, offset));
+        }
+
+        Ok(deltas)
+    }
+}
+
 impl From<RegionMetaSnapshot> for MetaValue {
     fn from(region_meta_snapshot: RegionMetaSnapshot) -> Self {
         let entries_pb: Vec<_> = region_meta_snapshot
@@ -377,7 +480,9 @@ mod tests {
     use common_types::bytes::BytesMut;
 
     use super::{MetaEncoding, MetaKey};
-    use crate::message_queue_impl::region_context::{RegionMetaSnapshot, TableMetaData};
+    use crate::message_queue_impl::region_context::{
+        RegionMetaDelta, RegionMetaSnapshot, TableMetaData,
+    };
 
     #[test]
     fn test_meta_encoding() {
@@ -424,4 +529,59 @@ mod tests {
         assert_eq!(test_meta_key, decoded_key);
         assert_eq!(test_region_snapshot, decoded_value);
     }
+
+    #[test]
+    fn test_meta_delta_encoding() {
+        let meta_encoding = MetaEncoding::newest();
+        let mut buf = BytesMut::new();
+
+        let deltas = vec![
+            RegionMetaDelta::new(0, 42, 142),
+            RegionMetaDelta::new(1, 2, 143),
+            RegionMetaDelta::new(0, 43, 144),
+            RegionMetaDelta::new(u64::MAX, u64::MAX, i64::MAX),
+        ];
+        meta_encoding.encode_deltas(&mut buf, &deltas).unwrap();
+        let decoded_deltas = meta_encoding.decode_deltas(&buf).unwrap();
+        assert_eq!(deltas, decoded_deltas);
+
+        // Empty batch.
+        meta_encoding.encode_deltas(&mut buf, &[]).unwrap();
+        assert!(meta_encoding.decode_deltas(&buf).unwrap().is_empty());
+
+        // Truncated batch.
+        meta_encoding.encode_deltas(&mut buf, &deltas).unwrap();
+        assert!(meta_encoding.decode_deltas(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_meta_encoding_unknown_version() {
+        let meta_encoding = MetaEncoding::newest();
+
+        let snapshot = RegionMetaSnapshot {
+            entries: vec![TableMetaData {
+                table_id: 0,
+                next_sequence_num: 42,
+                latest_marked_deleted: 40,
+                current_high_watermark: 142,
+                safe_delete_offset: None,
+            }],
+        };
+        let mut value_buf = BytesMut::new();
+        meta_encoding
+            .encode_value(&mut value_buf, snapshot)
+            .unwrap();
+        // Bump the version header.
+        value_buf[0] += 1;
+        let err = meta_encoding.decode_value(&value_buf).unwrap_err();
+        assert!(err.to_string().contains("invalid version"), "{}", err);
+
+        let mut delta_buf = BytesMut::new();
+        meta_encoding
+            .encode_deltas(&mut delta_buf, &[RegionMetaDelta::new(0, 42, 142)])
+            .unwrap();
+        delta_buf[0] += 1;
+        let err = meta_encoding.decode_deltas(&delta_buf).unwrap_err();
+        assert!(err.to_string().contains("invalid version"), "{}", err);
+    }
 }
@@ -2,7 +2,7 @@
 
 //! Log cleaner
 
-use std::{cmp, sync::Arc};
+use std::sync::Arc;
 
 use common_util::define_result;
 use log::info;
@@ -112,22 +112,10 @@ impl<M: MessageQueue> LogCleaner<M> {
     }
 
     fn calc_safe_delete_offset(snapshot: &RegionMetaSnapshot) -> Offset {
-        let mut safe_delete_offset = Offset::MAX;
-        let mut high_watermark = 0;
-        // Calc the min offset in message queue.
-        for table_meta in &snapshot.entries {
-            if let Some(offset) = table_meta.safe_delete_offset {
-                safe_delete_offset = cmp::min(safe_delete_offset, offset);
-            }
-            high_watermark = cmp::max(high_watermark, table_meta.current_high_watermark);
-        }
-
-        if safe_delete_offset == Offset::MAX {
-            // All tables are in such states: after init/flush, but not written.
-            // So, we can directly delete it up to the high_watermark.
-            high_watermark
-        } else {
-            safe_delete_offset
-        }
+        // If all tables are in such states: after init/flush, but not written.
+        // So, we can directly delete it up to the high_watermark.
+        snapshot
+            .safe_delete_offset()
+            .unwrap_or_else(|| snapshot.high_watermark())
     }
 }
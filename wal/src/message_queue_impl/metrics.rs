@@ -0,0 +1,94 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Metrics of wal based on message queue.
+
+use lazy_static::lazy_static;
+use prometheus::{register_int_gauge_vec, IntGaugeVec};
+
+use crate::{manager::RegionId, message_queue_impl::region_context::RegionMetrics};
+
+lazy_static! {
+    // Gauges:
+    pub static ref REGION_TABLE_COUNT_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "wal_message_queue_region_table_count",
+        "Number of tables tracked by the region",
+        &["region"]
+    )
+        .unwrap();
+    pub static ref OFFSET_MAPPING_TOTAL_ENTRIES_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "wal_message_queue_offset_mapping_total_entries",
+        "Total entries of the sequence offset mappings of all tables in the region",
+        &["region"]
+    )
+        .unwrap();
+    pub static ref OFFSET_MAPPING_MAX_ENTRIES_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "wal_message_queue_offset_mapping_max_entries",
+        "Max entries of the sequence offset mapping of a table in the region",
+        &["region"]
+    )
+        .unwrap();
+}
+
+/// Report the `metrics` of the region with `region_id`.
+pub fn observe_region_metrics(region_id: RegionId, metrics: &RegionMetrics) {
+    let region = region_id.to_string();
+    REGION_TABLE_COUNT_GAUGE
+        .with_label_values(&[&region])
+        .set(metrics.table_count as i64);
+    OFFSET_MAPPING_TOTAL_ENTRIES_GAUGE
+        .with_label_values(&[&region])
+        .set(metrics.total_offset_mapping_entries as i64);
+    OFFSET_MAPPING_MAX_ENTRIES_GAUGE
+        .with_label_values(&[&region])
+        .set(metrics.max_offset_mapping_entries as i64);
+}
+
+/// Remove the metrics of the region with `region_id`, called once the region is
+/// closed.
+pub fn remove_region_metrics(region_id: RegionId) {
+    let region = region_id.to_string();
+    for gauge in [
+        &*REGION_TABLE_COUNT_GAUGE,
+        &*OFFSET_MAPPING_TOTAL_ENTRIES_GAUGE,
+        &*OFFSET_MAPPING_MAX_ENTRIES_GAUGE,
+    ] {
+        // The metrics may not be reported yet.
+        let _ = gauge.remove_label_values(&[&region]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_region_metrics() {
+        let region_id = 1000;
+        let region = region_id.to_string();
+        let metrics = RegionMetrics {
+            table_count: 2,
+            total_offset_mapping_entries: 10,
+            max_offset_mapping_entries: 8,
+        };
+        observe_region_metrics(region_id, &metrics);
+        assert_eq!(
+            2,
+            REGION_TABLE_COUNT_GAUGE
+                .get_metric_with_label_values(&[&region])
+                .unwrap()
+                .get()
+        );
+
+        remove_region_metrics(region_id);
+        for gauge in [
+            &*REGION_TABLE_COUNT_GAUGE,
+            &*OFFSET_MAPPING_TOTAL_ENTRIES_GAUGE,
+            &*OFFSET_MAPPING_MAX_ENTRIES_GAUGE,
+        ] {
+            assert!(gauge.remove_label_values(&[&region]).is_err());
+        }
+
+        // Removing the metrics of a region never reported is fine.
+        remove_region_metrics(region_id);
+    }
+}
@@ -10,6 +10,7 @@ use message_queue::Message;
 pub mod config;
 mod encoding;
 mod log_cleaner;
+mod metrics;
 mod namespace;
 mod region;
 mod region_context;
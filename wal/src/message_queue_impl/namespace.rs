@@ -2,7 +2,7 @@
 
 //! Namespace of wal on message queue
 
-use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+use std::{collections::HashMap, fmt, mem, sync::Arc, time::Duration};
 
 use common_types::{table::Location, SequenceNumber};
 use common_util::{define_result, runtime::Runtime};
@@ -156,7 +156,11 @@ impl<M: MessageQueue> Namespace<M> {
         bg_runtime: Arc<Runtime>,
         config: Config,
     ) -> Self {
-        let inner = Arc::new(NamespaceInner::new(namespace, message_queue));
+        let inner = Arc::new(NamespaceInner::new(
+            namespace,
+            message_queue,
+            config.max_offset_mapping_entries,
+        ));
         let cleaner_handle =
             start_log_cleaner(bg_runtime.as_ref(), config.clean_period.0, inner.clone());
 
@@ -169,10 +173,17 @@ impl<M: MessageQueue> Namespace<M> {
 
     /// Close namespace
     ///
-    /// Mainly clear the regions and wait logs cleaning routine to stop.
+    /// Mainly close the regions and wait logs cleaning routine to stop.
     pub async fn close(&self) -> Result<()> {
         let mut regions = self.inner.regions.write().await;
-        regions.clear();
+        for (region_id, region) in regions.drain() {
+            if let Err(e) = region.close().await {
+                error!(
+                    "Failed to close region, namespace:{}, region id:{}, err:{}",
+                    self.inner.namespace, region_id, e
+                );
+            }
+        }
 
         self.cleaner_handle.stop_task().await.context(Close {
             namespace: self.inner.namespace.clone(),
@@ -259,14 +270,20 @@ struct NamespaceInner<M: MessageQueue> {
     message_queue: Arc<M>,
     meta_encoding: MetaEncoding,
     log_encoding: LogEncoding,
+    max_offset_mapping_entries: Option<usize>,
 }
 
 impl<M: MessageQueue> NamespaceInner<M> {
-    pub fn new(namespace: String, message_queue: Arc<M>) -> Self {
+    pub fn new(
+        namespace: String,
+        message_queue: Arc<M>,
+        max_offset_mapping_entries: Option<usize>,
+    ) -> Self {
         Self {
             namespace,
             regions: Default::default(),
             message_queue,
+            max_offset_mapping_entries,
             meta_encoding: MetaEncoding::newest(),
             log_encoding: LogEncoding::newest(),
         }
@@ -304,8 +321,15 @@ impl<M: MessageQueue> NamespaceInner<M> {
             return Ok(region.clone());
         }
 
-        let region =
-            Arc::new(Region::open(&self.namespace, region_id, self.message_queue.clone()).await?);
+        let region = Arc::new(
+            Region::open(
+                &self.namespace,
+                region_id,
+                self.message_queue.clone(),
+                self.max_offset_mapping_entries,
+            )
+            .await?,
+        );
         regions.insert(region_id, region.clone());
 
         info!(
@@ -524,6 +548,23 @@ impl<C: ConsumeIterator> ScanRegionIterator<C> {
     }
 
     pub async fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>> {
+        let log_entry = match self.next_owned_log_entry().await? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let previous_payload = mem::replace(&mut self.current_log_payload, log_entry.payload);
+        if let Some(iter) = self.iter.as_mut() {
+            iter.recycle_payload(previous_payload);
+        }
+        Ok(Some(LogEntry {
+            table_id: log_entry.table_id,
+            sequence: log_entry.sequence,
+            payload: self.current_log_payload.as_slice(),
+        }))
+    }
+
+    pub async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
         // If terminated, just return.
         if self.is_terminated {
             return Ok(None);
@@ -531,27 +572,16 @@ impl<C: ConsumeIterator> ScanRegionIterator<C> {
 
         // If inner iter is `Some`, poll it to get required log entry.
         if let Some(iter) = self.iter.as_mut() {
-            iter.next_log_entry()
-                .await
-                .context(ScanWithCause {
-                    namespace: self.namespace.clone(),
-                    request: self.request.clone(),
-                    msg: "failed while polling log",
-                })
-                .map(|log_entry| {
-                    if log_entry.is_none() {
-                        self.is_terminated = true;
-                    }
+            let log_entry = iter.next_owned_log_entry().await.context(ScanWithCause {
+                namespace: self.namespace.clone(),
+                request: self.request.clone(),
+                msg: "failed while polling log",
+            })?;
+            if log_entry.is_none() {
+                self.is_terminated = true;
+            }
 
-                    log_entry.map(|log_entry| {
-                        self.current_log_payload = log_entry.payload.to_owned();
-                        LogEntry {
-                            table_id: log_entry.table_id,
-                            sequence: log_entry.sequence,
-                            payload: self.current_log_payload.as_slice(),
-                        }
-                    })
-                })
+            Ok(log_entry)
         } else {
             Ok(None)
         }
@@ -645,6 +675,23 @@ impl<C: ConsumeIterator> ReadTableIterator<C> {
     }
 
     pub async fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>> {
+        let log_entry = match self.next_owned_log_entry().await? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let previous_value = mem::replace(&mut self.previous_value, log_entry.payload);
+        if let Some(iter) = self.iter.as_mut() {
+            iter.recycle_payload(previous_value);
+        }
+        Ok(Some(LogEntry {
+            table_id: log_entry.table_id,
+            sequence: log_entry.sequence,
+            payload: &self.previous_value,
+        }))
+    }
+
+    pub async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
         // If terminated, just return.
         if self.is_terminated {
             return Ok(None);
@@ -655,7 +702,7 @@ impl<C: ConsumeIterator> ReadTableIterator<C> {
             let start_sequence = self.start;
             let end_sequence = self.end;
             loop {
-                let poll_result = iter.next_log_entry().await.context(ReadWithCause {
+                let poll_result = iter.next_owned_log_entry().await.context(ReadWithCause {
                     namespace: self.namespace.clone(),
                     request: self.request.clone(),
                     msg: "failed while polling log",
@@ -672,14 +719,12 @@ impl<C: ConsumeIterator> ReadTableIterator<C> {
                         && log_entry.sequence <= end_sequence
                     {
                         self.is_terminated = log_entry.sequence == self.end;
-                        self.previous_value = log_entry.payload.to_owned();
 
-                        return Ok(Some(LogEntry {
-                            table_id: log_entry.table_id,
-                            sequence: log_entry.sequence,
-                            payload: &self.previous_value,
-                        }));
+                        return Ok(Some(log_entry));
                     }
+
+                    // Reuse the buffer of the filtered log.
+                    iter.recycle_payload(log_entry.payload);
                 } else {
                     // While polling result is `None`, it represents has finished to poll inner
                     // iter.
@@ -2,7 +2,7 @@
 
 //! Region in wal on message queue
 
-use std::{cmp, sync::Arc};
+use std::{cmp, mem, sync::Arc};
 
 use common_types::{table::TableId, SequenceNumber};
 use common_util::define_result;
@@ -13,12 +13,14 @@ use tokio::sync::{Mutex, RwLock};
 use util::*;
 
 use crate::{
+    buffer_pool::BufferPool,
     kv_encoder::CommonLogEncoding,
     log_batch::{LogEntry, LogWriteBatch},
     manager::{self, RegionId},
     message_queue_impl::{
         encoding::{format_wal_data_topic_name, format_wal_meta_topic_name, MetaEncoding},
         log_cleaner::LogCleaner,
+        metrics,
         region_context::{
             self, RegionContext, RegionContextBuilder, RegionMetaDelta, RegionMetaSnapshot,
             TableMetaData, TableWriteContext,
@@ -102,6 +104,28 @@ pub enum Error {
         msg: String,
         backtrace: Backtrace,
     },
+
+    #[snafu(display(
+        "Failed to close region, region id:{}, msg:{}, \nBacktrace:\n{}",
+        region_id,
+        msg,
+        backtrace
+    ))]
+    CloseNoCause {
+        region_id: RegionId,
+        msg: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Region is closed, region id:{}, \nBacktrace:\n{}",
+        region_id,
+        backtrace
+    ))]
+    RegionClosed {
+        region_id: RegionId,
+        backtrace: Backtrace,
+    },
 }
 
 define_result!(Error);
@@ -126,7 +150,12 @@ pub struct Region<M: MessageQueue> {
 
 impl<M: MessageQueue> Region<M> {
     /// Init the region.
-    pub async fn open(namespace: &str, region_id: RegionId, message_queue: Arc<M>) -> Result<Self> {
+    pub async fn open(
+        namespace: &str,
+        region_id: RegionId,
+        message_queue: Arc<M>,
+        max_offset_mapping_entries: Option<usize>,
+    ) -> Result<Self> {
         info!(
             "Begin to open region in namespace, namespace:{}, region id:{}",
             namespace, region_id
@@ -158,7 +187,8 @@ impl<M: MessageQueue> Region<M> {
             })?;
 
         // Build region meta.
-        let mut region_meta_builder = RegionContextBuilder::new(region_id);
+        let mut region_meta_builder = RegionContextBuilder::new(region_id)
+            .with_max_offset_mapping_entries(max_offset_mapping_entries);
         let high_watermark_in_snapshot = Self::recover_region_meta_from_meta(
             namespace,
             region_id,
@@ -181,8 +211,16 @@ impl<M: MessageQueue> Region<M> {
         .await?;
 
         // Init region inner.
+        let region_context = region_meta_builder
+            .build()
+            .map_err(|e| Box::new(e) as _)
+            .context(OpenWithCause {
+                namespace,
+                region_id,
+                msg: "failed while building region meta",
+            })?;
         let inner = RwLock::new(RegionInner::new(
-            region_meta_builder.build(),
+            region_context,
             log_encoding,
             message_queue.clone(),
             log_topic.clone(),
@@ -445,6 +483,7 @@ impl<M: MessageQueue> Region<M> {
         log_batch: &LogWriteBatch,
     ) -> Result<SequenceNumber> {
         let inner = self.inner.read().await;
+        inner.ensure_not_closed()?;
 
         debug!(
             "Begin to write to wal region, ctx:{:?}, region id:{}, location:{:?}, log_entries_num:{}",
@@ -478,22 +517,12 @@ impl<M: MessageQueue> Region<M> {
             );
 
             let snapshot = inner.make_meta_snapshot().await;
-            let mut safe_delete_offset = Offset::MAX;
-            let mut high_watermark = 0;
+            let high_watermark = snapshot.high_watermark();
             // Calculate the min offset in message queue.
-            for table_meta in &snapshot.entries {
-                if let Some(offset) = table_meta.safe_delete_offset {
-                    safe_delete_offset = cmp::min(safe_delete_offset, offset);
-                }
-                high_watermark = cmp::max(high_watermark, table_meta.current_high_watermark);
-            }
-
-            if safe_delete_offset == Offset::MAX {
-                None
-            } else {
+            snapshot.safe_delete_offset().map(|safe_delete_offset| {
                 assert!(safe_delete_offset < high_watermark);
-                Some(ScanRange::new(safe_delete_offset, high_watermark))
-            }
+                ScanRange::new(safe_delete_offset, high_watermark)
+            })
         };
 
         match scan_range {
@@ -587,6 +616,7 @@ impl<M: MessageQueue> Region<M> {
     ) -> Result<()> {
         let (snapshot, synchronizer) = {
             let inner = self.inner.write().await;
+            inner.ensure_not_closed()?;
 
             debug!(
                 "Mark deleted entries to sequence num:{}, region id:{}, table id:{}",
@@ -615,7 +645,8 @@ impl<M: MessageQueue> Region<M> {
         inner.get_table_meta(table_id).await
     }
 
-    /// Clean outdated logs according to the information in region snapshot.
+    /// Clean outdated logs according to the information in region snapshot,
+    /// and report the metrics of the region.
     pub async fn clean_logs(&self) -> Result<()> {
         // Get current snapshot.
         let (snapshot, synchronizer) = {
@@ -626,6 +657,16 @@ impl<M: MessageQueue> Region<M> {
             )
         };
 
+        // Collect the metrics under the read lock, so the writes are not blocked.
+        let (region_id, region_metrics) = {
+            let inner = self.inner.read().await;
+            (
+                inner.region_context.region_id(),
+                inner.region_context.metrics().await,
+            )
+        };
+        metrics::observe_region_metrics(region_id, &region_metrics);
+
         // Check and maybe clean logs.
         let mut log_cleaner = self.log_cleaner.lock().await;
         log_cleaner
@@ -642,6 +683,49 @@ impl<M: MessageQueue> Region<M> {
             .context(CleanLogs)
     }
 
+    /// Close the region gracefully.
+    ///
+    /// The region is frozen by the write lock, so all the in-flight writes are
+    /// finished before closing. Then the meta snapshot is checked and
+    /// synchronized to the message queue, and the region is marked closed, the
+    /// later writes and mark deletes will fail with [Error::RegionClosed].
+    /// Closing a closed region does nothing.
+    pub async fn close(&self) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        if inner.closed {
+            return Ok(());
+        }
+
+        let region_id = inner.region_context.region_id();
+        info!("Begin to close region, region id:{}", region_id);
+
+        let snapshot = inner.make_meta_snapshot().await;
+        for table_meta in &snapshot.entries {
+            ensure!(
+                table_meta.latest_marked_deleted <= table_meta.next_sequence_num,
+                CloseNoCause {
+                    region_id,
+                    msg: format!(
+                        "marked deleted sequence exceeds next sequence, table meta:{:?}",
+                        table_meta
+                    ),
+                }
+            );
+        }
+        self.snapshot_synchronizer
+            .lock()
+            .await
+            .sync(snapshot)
+            .await
+            .context(SyncSnapshot)?;
+        inner.closed = true;
+        metrics::remove_region_metrics(region_id);
+
+        info!("Finish closing region, region id:{}", region_id);
+
+        Ok(())
+    }
+
     /// Return snapshot, just used for test.
     #[allow(unused)]
     async fn make_meta_snapshot(&self) -> RegionMetaSnapshot {
@@ -663,6 +747,9 @@ struct RegionInner<M> {
 
     /// Topic storing logs in message queue
     log_topic: String,
+
+    /// Whether the region is closed, see [Region::close]
+    closed: bool,
 }
 
 impl<M: MessageQueue> RegionInner<M> {
@@ -677,9 +764,21 @@ impl<M: MessageQueue> RegionInner<M> {
             log_encoding,
             message_queue,
             log_topic,
+            closed: false,
         }
     }
 
+    fn ensure_not_closed(&self) -> Result<()> {
+        ensure!(
+            !self.closed,
+            RegionClosed {
+                region_id: self.region_context.region_id(),
+            }
+        );
+
+        Ok(())
+    }
+
     async fn write(
         &self,
         ctx: &manager::WriteContext,
@@ -779,6 +878,9 @@ pub struct MessageQueueLogIterator<C: ConsumeIterator> {
 
     /// See the same problem in https://github.com/CeresDB/ceresdb/issues/120
     previous_value: Vec<u8>,
+
+    /// Buffers to hold the payloads, recycled after the payloads are consumed
+    buffer_pool: BufferPool,
     // TODO: timeout
 }
 
@@ -798,12 +900,37 @@ impl<C: ConsumeIterator> MessageQueueLogIterator<C> {
             is_terminated: false,
             log_encoding,
             previous_value: Vec::new(),
+            buffer_pool: BufferPool::default(),
         }
     }
 }
 
 impl<C: ConsumeIterator> MessageQueueLogIterator<C> {
     pub async fn next_log_entry(&mut self) -> Result<Option<LogEntry<&'_ [u8]>>> {
+        let log_entry = match self.next_owned_log_entry().await? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let previous_value = mem::replace(&mut self.previous_value, log_entry.payload);
+        self.buffer_pool.put(previous_value);
+        Ok(Some(LogEntry {
+            table_id: log_entry.table_id,
+            sequence: log_entry.sequence,
+            payload: self.previous_value.as_slice(),
+        }))
+    }
+
+    /// Return a payload fetched from this iterator, so its buffer can be reused
+    /// by the following entries.
+    #[inline]
+    pub fn recycle_payload(&mut self, payload: Vec<u8>) {
+        self.buffer_pool.put(payload);
+    }
+
+    /// Fetch next log entry owning its payload, the payload is copied from the
+    /// message only once, into a buffer taken from the pool of this iterator.
+    pub async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
         if self.is_terminated && self.terminate_offset.is_some() {
             debug!(
                 "Finished to poll all logs from message queue, region id:{}, terminate offset:{:?}",
@@ -871,12 +998,10 @@ impl<C: ConsumeIterator> MessageQueueLogIterator<C> {
                 msg: "failed while polling log",
             })?;
 
-        self.previous_value = payload.to_owned();
-
         Ok(Some(LogEntry {
             table_id: log_key.table_id,
             sequence: log_key.sequence_num,
-            payload: self.previous_value.as_slice(),
+            payload: self.buffer_pool.get_filled(payload),
         }))
     }
 }
@@ -913,7 +1038,7 @@ mod tests {
     use crate::{
         log_batch::PayloadDecoder,
         manager::{ReadContext, WriteContext},
-        message_queue_impl::{encoding::MetaEncoding, test_util::TestContext},
+        message_queue_impl::{encoding::MetaEncoding, region::Error, test_util::TestContext},
     };
 
     #[tokio::test]
@@ -939,6 +1064,8 @@ mod tests {
         test_mark_and_delete(test_datas.clone(), message_queue.clone()).await;
 
         test_recover_region(test_datas.clone(), message_queue.clone()).await;
+
+        test_close_region(test_datas.clone(), message_queue.clone()).await;
     }
 
     async fn test_read_write<M: MessageQueue>(
@@ -1165,4 +1292,49 @@ mod tests {
 
         assert_eq!(snapshot_from_recovered, snapshot_from_origin);
     }
+
+    async fn test_close_region<M: MessageQueue>(
+        test_datas: Vec<(TableId, Vec<u32>)>,
+        message_queue: Arc<M>,
+    ) {
+        let namespace = format!("test_{}", uuid::Uuid::new_v4());
+        let shard_id = 42;
+        let region_id = 42;
+
+        let test_context =
+            TestContext::new(namespace, region_id, shard_id, test_datas, message_queue).await;
+        let test_log_batch = &test_context.test_datas[0].1.test_log_batch;
+        test_context
+            .region
+            .write(&WriteContext::default(), test_log_batch)
+            .await
+            .unwrap();
+
+        let snapshot = test_context.region.make_meta_snapshot().await;
+        test_context.region.close().await.unwrap();
+        // Close again.
+        test_context.region.close().await.unwrap();
+        assert_eq!(snapshot, test_context.region.make_meta_snapshot().await);
+
+        let err = test_context
+            .region
+            .write(&WriteContext::default(), test_log_batch)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RegionClosed { .. }), "{:?}", err);
+        let err = test_context
+            .region
+            .mark_delete_to(test_context.test_datas[0].0, 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RegionClosed { .. }), "{:?}", err);
+
+        // Logs written before closing are still readable.
+        assert!(test_context
+            .region
+            .scan_region(&ReadContext::default())
+            .await
+            .unwrap()
+            .is_some());
+    }
 }
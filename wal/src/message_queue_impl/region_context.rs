@@ -83,6 +83,22 @@ pub enum Error {
         msg: String,
         backtrace: Backtrace,
     },
+
+    #[snafu(display(
+        "Offset range of table write should start from the high watermark or after, table id:{}, offset range:[{}, {}], high watermark:{}\nBacktrace:\n{}",
+        table_id,
+        start,
+        end,
+        high_watermark,
+        backtrace
+    ))]
+    OffsetRangeOutOfOrder {
+        table_id: TableId,
+        start: Offset,
+        end: Offset,
+        high_watermark: Offset,
+        backtrace: Backtrace,
+    },
 }
 
 define_result!(Error);
@@ -93,6 +109,10 @@ pub struct RegionContext {
     /// Id of region
     region_id: RegionId,
 
+    /// Max entries of each table's `start_sequence_offset_mapping`, see
+    /// [TableMetaInner::maybe_coarsen_mapping].
+    max_offset_mapping_entries: Option<usize>,
+
     /// Region context inner
     inner: RwLock<RegionContextInner>,
 }
@@ -117,10 +137,9 @@ impl RegionContext {
         };
 
         let mut inner = self.inner.write().await;
-        let table_context = inner
-            .table_contexts
-            .entry(table_id)
-            .or_insert_with(|| TableContext::new(TableMeta::new(table_id)));
+        let table_context = inner.table_contexts.entry(table_id).or_insert_with(|| {
+            TableContext::new(TableMeta::new(table_id, self.max_offset_mapping_entries))
+        });
 
         table_context
             .write_logs(ctx, self.region_id, table_id, log_batch, table_write_ctx)
@@ -170,6 +189,23 @@ impl RegionContext {
         RegionMetaSnapshot { entries }
     }
 
+    /// Get the offset up to which the logs of the region can be deleted, it is
+    /// the min `safe_delete_offset` of all tables.
+    ///
+    /// Tables without `safe_delete_offset` (no log written after init/flush)
+    /// are ignored, and None will be returned if all tables are in such state.
+    #[allow(unused)]
+    pub async fn safe_delete_offset(&self) -> Option<Offset> {
+        let inner = self.inner.read().await;
+        let mut safe_delete_offset = None;
+        for table_context in inner.table_contexts.values() {
+            let offset = table_context.get_meta_data().await.safe_delete_offset;
+            safe_delete_offset = min_offset(safe_delete_offset, offset);
+        }
+
+        safe_delete_offset
+    }
+
     /// Get table meta data by table id.
     pub async fn get_table_meta_data(&self, table_id: TableId) -> Result<Option<TableMetaData>> {
         let inner = self.inner.read().await;
@@ -181,11 +217,42 @@ impl RegionContext {
         }
     }
 
+    /// Get the metrics about the tables tracked by the region, see
+    /// [RegionMetrics].
+    pub async fn metrics(&self) -> RegionMetrics {
+        let inner = self.inner.read().await;
+        let mut metrics = RegionMetrics {
+            table_count: inner.table_contexts.len(),
+            ..Default::default()
+        };
+        for table_context in inner.table_contexts.values() {
+            let mapping_entries = table_context.meta.offset_mapping_entries().await;
+            metrics.total_offset_mapping_entries += mapping_entries;
+            metrics.max_offset_mapping_entries =
+                metrics.max_offset_mapping_entries.max(mapping_entries);
+        }
+
+        metrics
+    }
+
     pub fn region_id(&self) -> RegionId {
         self.region_id
     }
 }
 
+/// Metrics about the tables tracked by a region.
+///
+/// The offset mapping of a table only shrinks after flushing, so large
+/// mappings may indicate the tables are starved of flushing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegionMetrics {
+    pub table_count: usize,
+    /// Total entries of the `start_sequence_offset_mapping` of all tables.
+    pub total_offset_mapping_entries: usize,
+    /// Max entries of the `start_sequence_offset_mapping` of a table.
+    pub max_offset_mapping_entries: usize,
+}
+
 /// Region meta data.
 #[derive(Default, Debug)]
 struct RegionContextInner {
@@ -248,27 +315,48 @@ impl TableContext {
 #[derive(Debug)]
 struct TableMeta {
     table_id: TableId,
+    max_offset_mapping_entries: Option<usize>,
     /// The race condition may occur between writer thread
     /// and background flush thread.
     inner: Mutex<TableMetaInner>,
 }
 
 impl TableMeta {
-    fn new(table_id: TableId) -> Self {
+    fn new(table_id: TableId, max_offset_mapping_entries: Option<usize>) -> Self {
         Self {
             table_id,
+            max_offset_mapping_entries,
             inner: Mutex::new(TableMetaInner::default()),
         }
     }
 
+    #[inline]
+    async fn offset_mapping_entries(&self) -> usize {
+        self.inner.lock().await.start_sequence_offset_mapping.len()
+    }
+
     #[inline]
     async fn prepare_for_write(&self) -> SequenceNumber {
         self.get_meta_data().await.next_sequence_num
     }
 
-    async fn update_after_write(&self, write_offset_range: OffsetRange) {
+    /// Update the meta after writing logs to `write_offset_range`.
+    ///
+    /// The writes of a table are sequential, so the range must be after the
+    /// current high watermark, otherwise the offset mapping will be corrupted.
+    async fn update_after_write(&self, write_offset_range: OffsetRange) -> Result<()> {
         let updated_num = (write_offset_range.end - write_offset_range.start + 1) as u64;
         let mut inner = self.inner.lock().await;
+        ensure!(
+            write_offset_range.start >= inner.current_high_watermark,
+            OffsetRangeOutOfOrder {
+                table_id: self.table_id,
+                start: write_offset_range.start,
+                end: write_offset_range.end,
+                high_watermark: inner.current_high_watermark,
+            }
+        );
+
         let old_next_sequence_num = inner.next_sequence_num;
         let next_sequence_num = old_next_sequence_num + updated_num;
 
@@ -285,6 +373,9 @@ impl TableMeta {
             .extend(sequences.into_iter().zip(offsets.into_iter()));
 
         inner.current_high_watermark = write_offset_range.end + 1;
+        inner.maybe_coarsen_mapping(self.max_offset_mapping_entries);
+
+        Ok(())
     }
 
     async fn mark_delete_to(
@@ -309,6 +400,23 @@ impl TableMeta {
 
         inner.latest_marked_deleted = sequence_num;
 
+        // The mapping may be coarsened, so the exact entry of `sequence_num` may not
+        // exist, use the offset of the nearest smaller sequence number as its lower
+        // bound.
+        if sequence_num < inner.next_sequence_num {
+            let floor_offset = inner
+                .start_sequence_offset_mapping
+                .range(..=sequence_num)
+                .next_back()
+                .map(|(_, offset)| *offset);
+            if let Some(offset) = floor_offset {
+                inner
+                    .start_sequence_offset_mapping
+                    .entry(sequence_num)
+                    .or_insert(offset);
+            }
+        }
+
         // Update the mapping, keep the range in description.
         inner
             .start_sequence_offset_mapping
@@ -388,6 +496,86 @@ struct TableMetaInner {
     start_sequence_offset_mapping: BTreeMap<SequenceNumber, Offset>,
 }
 
+impl TableMetaInner {
+    /// Check the invariants assumed by [TableMeta::get_meta_data]:
+    /// + `latest_marked_deleted` <= `next_sequence_num`.
+    /// + the mapping has the entry of `latest_marked_deleted` if some logs
+    /// haven't been marked deleted.
+    /// + all the sequence numbers in mapping are in range
+    /// [`latest_marked_deleted`, `next_sequence_num`) and their offsets are
+    /// less than `current_high_watermark`.
+    fn validate(&self, table_id: TableId) -> Result<()> {
+        ensure!(
+            self.latest_marked_deleted <= self.next_sequence_num,
+            Build {
+                msg: format!(
+                    "latest marked deleted should be less than or equal to next sequence number, table id:{}, meta:{:?}",
+                    table_id, self
+                ),
+            }
+        );
+
+        if self.next_sequence_num > self.latest_marked_deleted {
+            ensure!(
+                self.start_sequence_offset_mapping
+                    .contains_key(&self.latest_marked_deleted),
+                Build {
+                    msg: format!(
+                        "offset of latest marked deleted not found in mapping, table id:{}, meta:{:?}",
+                        table_id, self
+                    ),
+                }
+            );
+        }
+
+        let invalid_entry =
+            self.start_sequence_offset_mapping
+                .iter()
+                .find(|(sequence_num, offset)| {
+                    **sequence_num < self.latest_marked_deleted
+                        || **sequence_num >= self.next_sequence_num
+                        || **offset >= self.current_high_watermark
+                });
+        ensure!(
+            invalid_entry.is_none(),
+            Build {
+                msg: format!(
+                    "invalid entry in offset mapping, table id:{}, entry:{:?}, meta:{:?}",
+                    table_id, invalid_entry, self
+                ),
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Coarsen the `start_sequence_offset_mapping` if its size exceeds
+    /// `max_entries` by keeping only every [OFFSET_MAPPING_COARSEN_STEP]th
+    /// entry, and the first entry is always kept.
+    ///
+    /// The offset of a removed sequence number will be replaced by the offset
+    /// of the nearest smaller one in the mapping when marking deleted to it, so
+    /// the `safe_delete_offset` is still a valid lower bound but less precise,
+    /// which means more logs than necessary are kept in message queue.
+    fn maybe_coarsen_mapping(&mut self, max_entries: Option<usize>) {
+        let max_entries = match max_entries {
+            Some(v) => v,
+            None => return,
+        };
+
+        if self.start_sequence_offset_mapping.len() <= max_entries {
+            return;
+        }
+
+        let mut idx = 0;
+        self.start_sequence_offset_mapping.retain(|_, _| {
+            let keep = idx % OFFSET_MAPPING_COARSEN_STEP == 0;
+            idx += 1;
+            keep
+        });
+    }
+}
+
 /// Self defined default implementation
 ///
 /// Because `SequenceNumber::MIN` is used as a special value, the normal value
@@ -403,6 +591,10 @@ impl Default for TableMetaInner {
     }
 }
 
+/// Only every `OFFSET_MAPPING_COARSEN_STEP`th entry is kept while coarsening
+/// the offset mapping.
+const OFFSET_MAPPING_COARSEN_STEP: usize = 2;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct TableMetaData {
     pub table_id: TableId,
@@ -420,6 +612,33 @@ pub struct RegionMetaSnapshot {
     pub entries: Vec<TableMetaData>,
 }
 
+impl RegionMetaSnapshot {
+    /// Get the min `safe_delete_offset` of all tables in snapshot, see
+    /// [RegionContext::safe_delete_offset].
+    pub fn safe_delete_offset(&self) -> Option<Offset> {
+        self.entries
+            .iter()
+            .fold(None, |acc, entry| min_offset(acc, entry.safe_delete_offset))
+    }
+
+    /// Get the max `current_high_watermark` of all tables in snapshot.
+    pub fn high_watermark(&self) -> Offset {
+        self.entries
+            .iter()
+            .map(|entry| entry.current_high_watermark)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[inline]
+fn min_offset(lhs: Option<Offset>, rhs: Option<Offset>) -> Option<Offset> {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+        (lhs, rhs) => lhs.or(rhs),
+    }
+}
+
 /// Message queue's offset range
 ///
 /// The range should be [start, end], and it will never be empty.
@@ -440,6 +659,7 @@ impl OffsetRange {
 #[derive(Debug)]
 pub struct RegionContextBuilder {
     region_id: RegionId,
+    max_offset_mapping_entries: Option<usize>,
     table_metas: HashMap<TableId, TableMetaInner>,
 }
 
@@ -448,10 +668,18 @@ impl RegionContextBuilder {
     pub fn new(region_id: RegionId) -> Self {
         Self {
             region_id,
+            max_offset_mapping_entries: None,
             table_metas: HashMap::default(),
         }
     }
 
+    /// Bound the offset mapping of each table to `max_entries`, no bound if it
+    /// is None.
+    pub fn with_max_offset_mapping_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_offset_mapping_entries = max_entries;
+        self
+    }
+
     pub fn apply_region_meta_snapshot(&mut self, snapshot: RegionMetaSnapshot) -> Result<()> {
         debug!("Apply region meta snapshot, snapshot:{:?}", snapshot);
 
@@ -496,18 +724,27 @@ impl RegionContextBuilder {
         Ok(())
     }
 
-    pub fn build(self) -> RegionContext {
+    /// Build the [RegionContext], the invariants of the recovered table metas
+    /// are validated, see [TableMetaInner::validate].
+    pub fn build(self) -> Result<RegionContext> {
         debug!(
             "Region meta data before building, region meta data:{:?}",
             self.table_metas
         );
 
+        for (table_id, table_meta_inner) in &self.table_metas {
+            table_meta_inner.validate(*table_id)?;
+        }
+
+        let max_offset_mapping_entries = self.max_offset_mapping_entries;
         let table_metas = self
             .table_metas
             .into_iter()
-            .map(|(table_id, table_meta_inner)| {
+            .map(|(table_id, mut table_meta_inner)| {
+                table_meta_inner.maybe_coarsen_mapping(max_offset_mapping_entries);
                 let table_meta = TableMeta {
                     table_id,
+                    max_offset_mapping_entries,
                     inner: Mutex::new(table_meta_inner),
                 };
 
@@ -515,17 +752,18 @@ impl RegionContextBuilder {
             })
             .collect();
 
-        RegionContext {
+        Ok(RegionContext {
             inner: RwLock::new(RegionContextInner {
                 table_contexts: table_metas,
             }),
             region_id: self.region_id,
-        }
+            max_offset_mapping_entries,
+        })
     }
 }
 
 #[allow(unused)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct RegionMetaDelta {
     table_id: TableId,
     sequence_num: SequenceNumber,
@@ -541,6 +779,21 @@ impl RegionMetaDelta {
             offset,
         }
     }
+
+    #[inline]
+    pub fn table_id(&self) -> TableId {
+        self.table_id
+    }
+
+    #[inline]
+    pub fn sequence_num(&self) -> SequenceNumber {
+        self.sequence_num
+    }
+
+    #[inline]
+    pub fn offset(&self) -> Offset {
+        self.offset
+    }
 }
 
 impl From<TableMetaData> for TableMetaInner {
@@ -649,7 +902,15 @@ impl TableWriter {
             }
         );
 
-        table_meta.update_after_write(offset_range).await;
+        table_meta
+            .update_after_write(offset_range)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .context(WriteWithCause {
+                region_id,
+                table_id,
+                msg: "failed to update table meta after write",
+            })?;
 
         Ok(next_sequence_num - 1)
     }
@@ -678,3 +939,225 @@ pub struct TableWriteContext<M: MessageQueue> {
     pub log_topic: String,
     pub message_queue: Arc<M>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_table_meta_data(
+        table_id: TableId,
+        safe_delete_offset: Option<Offset>,
+        current_high_watermark: Offset,
+    ) -> TableMetaData {
+        TableMetaData {
+            table_id,
+            next_sequence_num: 42,
+            latest_marked_deleted: if safe_delete_offset.is_some() { 40 } else { 42 },
+            current_high_watermark,
+            safe_delete_offset,
+        }
+    }
+
+    fn build_region_context(snapshot: RegionMetaSnapshot) -> RegionContext {
+        let mut builder = RegionContextBuilder::new(42);
+        builder.apply_region_meta_snapshot(snapshot).unwrap();
+        builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_safe_delete_offset() {
+        let snapshot = RegionMetaSnapshot {
+            entries: vec![
+                new_table_meta_data(0, Some(140), 142),
+                new_table_meta_data(1, None, 150),
+                new_table_meta_data(2, Some(10), 12),
+            ],
+        };
+        assert_eq!(Some(10), snapshot.safe_delete_offset());
+        assert_eq!(150, snapshot.high_watermark());
+
+        let region_context = build_region_context(snapshot);
+        assert_eq!(Some(10), region_context.safe_delete_offset().await);
+    }
+
+    #[tokio::test]
+    async fn test_safe_delete_offset_without_written_tables() {
+        let snapshot = RegionMetaSnapshot {
+            entries: vec![
+                new_table_meta_data(0, None, 142),
+                new_table_meta_data(1, None, 12),
+            ],
+        };
+        assert_eq!(None, snapshot.safe_delete_offset());
+        assert_eq!(142, snapshot.high_watermark());
+
+        let region_context = build_region_context(snapshot);
+        assert_eq!(None, region_context.safe_delete_offset().await);
+
+        let empty_context = build_region_context(RegionMetaSnapshot { entries: vec![] });
+        assert_eq!(None, empty_context.safe_delete_offset().await);
+    }
+
+    #[tokio::test]
+    async fn test_region_metrics() {
+        let mut builder = RegionContextBuilder::new(42);
+        builder
+            .apply_region_meta_snapshot(RegionMetaSnapshot {
+                entries: vec![new_table_meta_data(2, None, 0)],
+            })
+            .unwrap();
+        // Table 0 writes 3 logs and table 1 writes 10 logs, while table 2 writes
+        // nothing.
+        let mut offset = 0;
+        for (table_id, num_logs) in [(0, 3), (1, 10)] {
+            for sequence_num in 1..=num_logs {
+                builder
+                    .apply_region_meta_delta(RegionMetaDelta::new(table_id, sequence_num, offset))
+                    .unwrap();
+                offset += 1;
+            }
+        }
+        let region_context = builder.build().unwrap();
+
+        let expect = RegionMetrics {
+            table_count: 3,
+            total_offset_mapping_entries: 13,
+            max_offset_mapping_entries: 10,
+        };
+        assert_eq!(expect, region_context.metrics().await);
+
+        // Flushing table 1 shrinks its mapping.
+        region_context.mark_table_delete_to(1, 8).await.unwrap();
+        let expect = RegionMetrics {
+            table_count: 3,
+            total_offset_mapping_entries: 6,
+            max_offset_mapping_entries: 3,
+        };
+        assert_eq!(expect, region_context.metrics().await);
+
+        let empty_context = build_region_context(RegionMetaSnapshot { entries: vec![] });
+        assert_eq!(RegionMetrics::default(), empty_context.metrics().await);
+    }
+
+    #[tokio::test]
+    async fn test_update_after_write_out_of_order() {
+        let table_meta = TableMeta::new(0, None);
+        table_meta
+            .update_after_write(OffsetRange::new(10, 12))
+            .await
+            .unwrap();
+
+        // Overlapped with the last write.
+        assert!(matches!(
+            table_meta
+                .update_after_write(OffsetRange::new(11, 13))
+                .await,
+            Err(Error::OffsetRangeOutOfOrder {
+                start: 11,
+                high_watermark: 13,
+                ..
+            })
+        ));
+        // Before the last write.
+        assert!(matches!(
+            table_meta.update_after_write(OffsetRange::new(0, 1)).await,
+            Err(Error::OffsetRangeOutOfOrder { .. })
+        ));
+
+        // The failed updates take no effect.
+        let meta_data = table_meta.get_meta_data().await;
+        assert_eq!(SequenceNumber::MIN + 4, meta_data.next_sequence_num);
+        assert_eq!(13, meta_data.current_high_watermark);
+        assert_eq!(Some(10), meta_data.safe_delete_offset);
+
+        table_meta
+            .update_after_write(OffsetRange::new(13, 14))
+            .await
+            .unwrap();
+        let meta_data = table_meta.get_meta_data().await;
+        assert_eq!(SequenceNumber::MIN + 6, meta_data.next_sequence_num);
+        assert_eq!(15, meta_data.current_high_watermark);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_offset_mapping() {
+        let max_entries = 8;
+        let table_meta = TableMeta::new(0, Some(max_entries));
+        let first_sequence_num = SequenceNumber::MIN + 1;
+        // Offset of the sequence number is `sequence_num - first_sequence_num`.
+        for i in 0..100 {
+            table_meta
+                .update_after_write(OffsetRange::new(i * 3, i * 3 + 2))
+                .await
+                .unwrap();
+            let inner = table_meta.inner.lock().await;
+            assert!(inner.start_sequence_offset_mapping.len() <= max_entries);
+        }
+
+        for delta in [1, 37, 100, 299] {
+            let sequence_num = first_sequence_num + delta;
+            table_meta.mark_delete_to(sequence_num).await.unwrap();
+
+            let meta_data = table_meta.get_meta_data().await;
+            let safe_delete_offset = meta_data.safe_delete_offset.unwrap();
+            assert!(safe_delete_offset <= delta as Offset);
+            let inner = table_meta.inner.lock().await;
+            assert!(inner.start_sequence_offset_mapping.len() <= max_entries);
+        }
+    }
+
+    #[test]
+    fn test_build_with_invalid_meta() {
+        let invalid_metas = [
+            // Latest marked deleted is greater than next sequence number.
+            TableMetaData {
+                table_id: 0,
+                next_sequence_num: 40,
+                latest_marked_deleted: 42,
+                current_high_watermark: 142,
+                safe_delete_offset: None,
+            },
+            // Offset of latest marked deleted is missing.
+            TableMetaData {
+                table_id: 0,
+                next_sequence_num: 42,
+                latest_marked_deleted: 40,
+                current_high_watermark: 142,
+                safe_delete_offset: None,
+            },
+            // Safe delete offset is not less than the high watermark.
+            TableMetaData {
+                table_id: 0,
+                next_sequence_num: 42,
+                latest_marked_deleted: 40,
+                current_high_watermark: 142,
+                safe_delete_offset: Some(142),
+            },
+        ];
+
+        for meta in invalid_metas {
+            let mut builder = RegionContextBuilder::new(42);
+            builder
+                .apply_region_meta_snapshot(RegionMetaSnapshot {
+                    entries: vec![meta.clone()],
+                })
+                .unwrap();
+            assert!(
+                matches!(builder.build(), Err(Error::Build { .. })),
+                "{:?}",
+                meta
+            );
+        }
+
+        let mut builder = RegionContextBuilder::new(42);
+        builder
+            .apply_region_meta_snapshot(RegionMetaSnapshot {
+                entries: vec![new_table_meta_data(0, Some(140), 142)],
+            })
+            .unwrap();
+        builder
+            .apply_region_meta_delta(RegionMetaDelta::new(0, 42, 150))
+            .unwrap();
+        assert!(builder.build().is_ok());
+    }
+}
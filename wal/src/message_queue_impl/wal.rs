@@ -118,6 +118,13 @@ impl<C: ConsumeIterator> AsyncLogIterator for ScanRegionIterator<C> {
             .map_err(|e| Box::new(e) as _)
             .context(Read)
     }
+
+    async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
+        self.next_owned_log_entry()
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .context(Read)
+    }
 }
 
 #[async_trait]
@@ -128,4 +135,11 @@ impl<C: ConsumeIterator> AsyncLogIterator for ReadTableIterator<C> {
             .map_err(|e| Box::new(e) as _)
             .context(Read)
     }
+
+    async fn next_owned_log_entry(&mut self) -> Result<Option<LogEntry<Vec<u8>>>> {
+        self.next_owned_log_entry()
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .context(Read)
+    }
 }
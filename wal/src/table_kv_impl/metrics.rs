@@ -0,0 +1,26 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Metrics of wal based on TableKv.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, register_histogram, register_int_gauge, Histogram, IntGauge,
+};
+
+lazy_static! {
+    // Gauges:
+    pub static ref CLEAN_BATCH_SIZE_GAUGE: IntGauge = register_int_gauge!(
+        "wal_table_kv_clean_batch_size",
+        "Effective batch size of deleting logs when cleaning wal"
+    )
+        .unwrap();
+
+    // Histograms:
+    // Buckets: 0, 0.001, .., 0.001 * 4^9
+    pub static ref WRITE_THROTTLE_DURATION_HISTOGRAM: Histogram = register_histogram!(
+        "wal_table_kv_write_throttle_duration",
+        "Histogram for time of log writes waiting for the rate limit of regions in seconds",
+        exponential_buckets(0.001, 4.0, 10).unwrap()
+    )
+        .unwrap();
+}
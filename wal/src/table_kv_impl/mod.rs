@@ -4,16 +4,21 @@
 
 use std::sync::Arc;
 
-use common_util::runtime::Runtime;
+use common_util::runtime::{Runtime, RuntimeStats};
 
 pub mod encoding;
+mod metrics;
 pub mod model;
 mod namespace;
+mod rate_limiter;
 
 mod table_unit;
 pub mod timed_task;
 
 pub mod wal;
+pub mod write_coalescer;
+
+pub use table_unit::{MergedLogIterator, TableLogIterator, TableUnit};
 
 mod consts {
     /// Table name of the meta table.
@@ -29,3 +34,26 @@ pub struct WalRuntimes {
     pub write_runtime: Arc<Runtime>,
     pub bg_runtime: Arc<Runtime>,
 }
+
+impl WalRuntimes {
+    /// Snapshot of the stats of the runtimes.
+    ///
+    /// The blocking operations on table kv are run by these runtimes, so
+    /// growing queued tasks or wait time of a runtime means the wal is
+    /// bottlenecked on the capacity of the runtime instead of the table kv.
+    pub fn stats(&self) -> WalRuntimesStats {
+        WalRuntimesStats {
+            read: self.read_runtime.stats(),
+            write: self.write_runtime.stats(),
+            bg: self.bg_runtime.stats(),
+        }
+    }
+}
+
+/// Stats of [WalRuntimes].
+#[derive(Debug, Clone)]
+pub struct WalRuntimesStats {
+    pub read: RuntimeStats,
+    pub write: RuntimeStats,
+    pub bg: RuntimeStats,
+}
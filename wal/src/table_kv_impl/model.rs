@@ -11,8 +11,11 @@ use snafu::{ensure, Backtrace, ResultExt, Snafu};
 use table_kv::ScanContext;
 
 use crate::{
-    manager::SequenceNumber,
-    table_kv_impl::{consts, table_unit::CleanContext},
+    manager::{RegionId, SequenceNumber},
+    table_kv_impl::{
+        consts,
+        table_unit::{CleanContext, DEFAULT_SEQUENCE_WARN_RATIO},
+    },
 };
 
 #[derive(Debug, Snafu)]
@@ -259,6 +262,41 @@ pub struct NamespaceConfig {
     pub init_scan_batch_size: i32,
     pub clean_scan_timeout: ReadableDuration,
     pub clean_scan_batch_size: usize,
+    pub clean_min_batch_size: usize,
+    pub clean_max_batch_size: usize,
+    pub clean_slow_write_threshold: ReadableDuration,
+    /// Max number of buckets to clean concurrently.
+    pub clean_bucket_parallelism: usize,
+    /// Fraction of the max sequence number, a warning is logged once the
+    /// sequence of a table unit exceeds it.
+    pub sequence_warn_ratio: f64,
+    /// Delete the records of deleted logs immediately while deleting entries,
+    /// instead of waiting for the periodical log cleaner.
+    pub eager_clean_deleted_logs: bool,
+    /// Coalesce the log writes to the same wal shard table within this window
+    /// into one write, no coalescing if it is not set.
+    pub write_coalesce_window: Option<ReadableDuration>,
+    /// Write rate limits of regions, regions not listed are unlimited.
+    pub region_write_limits: Vec<RegionWriteLimit>,
+    /// Rewrite the live logs of a timed bucket into its next bucket and drop
+    /// it, if the ratio of its live logs is no larger than this, no compaction
+    /// if it is not set.
+    pub bucket_compaction_live_ratio: Option<f64>,
+    /// Rebuild the missing meta data of a table unit from its logs while
+    /// opening it, otherwise the table unit is treated as not existing.
+    pub repair_missing_table_unit_meta: bool,
+}
+
+/// Write rate limit of a region, it is unlimited if neither rate is set.
+///
+/// Writes of at most one second of the rate are allowed in a burst.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionWriteLimit {
+    pub region_id: RegionId,
+    /// Max bytes of log payloads written per second.
+    pub bytes_per_sec: Option<u64>,
+    /// Max log entries written per second.
+    pub entries_per_sec: Option<u64>,
 }
 
 impl NamespaceConfig {
@@ -266,6 +304,18 @@ impl NamespaceConfig {
         if self.init_scan_batch_size <= 0 {
             self.init_scan_batch_size = ScanContext::DEFAULT_BATCH_SIZE;
         }
+        if self.clean_min_batch_size == 0 {
+            self.clean_min_batch_size = 1;
+        }
+        if self.clean_max_batch_size < self.clean_min_batch_size {
+            self.clean_max_batch_size = self.clean_min_batch_size;
+        }
+        if self.clean_bucket_parallelism == 0 {
+            self.clean_bucket_parallelism = 1;
+        }
+        if self.sequence_warn_ratio <= 0.0 || self.sequence_warn_ratio > 1.0 {
+            self.sequence_warn_ratio = DEFAULT_SEQUENCE_WARN_RATIO;
+        }
     }
 
     pub fn new_namespace_entry(&self, namespace_name: &str) -> Result<NamespaceEntry> {
@@ -302,6 +352,11 @@ impl NamespaceConfig {
         CleanContext {
             scan_timeout: self.clean_scan_timeout.0,
             batch_size: self.clean_scan_batch_size,
+            min_batch_size: self.clean_min_batch_size,
+            max_batch_size: self.clean_max_batch_size,
+            slow_write_threshold: self.clean_slow_write_threshold.0,
+            bucket_parallelism: self.clean_bucket_parallelism,
+            progress_callback: None,
         }
     }
 }
@@ -319,6 +374,16 @@ impl Default for NamespaceConfig {
             init_scan_batch_size: 100,
             clean_scan_timeout: default_clean_ctx.scan_timeout.into(),
             clean_scan_batch_size: default_clean_ctx.batch_size,
+            clean_min_batch_size: default_clean_ctx.min_batch_size,
+            clean_max_batch_size: default_clean_ctx.max_batch_size,
+            clean_slow_write_threshold: default_clean_ctx.slow_write_threshold.into(),
+            clean_bucket_parallelism: default_clean_ctx.bucket_parallelism,
+            sequence_warn_ratio: DEFAULT_SEQUENCE_WARN_RATIO,
+            eager_clean_deleted_logs: false,
+            write_coalesce_window: None,
+            region_write_limits: Vec::new(),
+            bucket_compaction_live_ratio: None,
+            repair_missing_table_unit_meta: false,
         }
     }
 }
@@ -3,32 +3,40 @@
 //! Wal namespace.
 
 use std::{
-    collections::{BTreeMap, HashMap},
-    fmt, str,
+    cmp,
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fmt, mem,
+    ops::RangeInclusive,
+    str,
     sync::{Arc, Mutex, RwLock},
     time::Duration,
 };
 
 use common_types::{
+    bytes::BytesMut,
     table::{Location, TableId},
     time::Timestamp,
 };
 use common_util::{config::ReadableDuration, define_result, runtime::Runtime};
 use log::{debug, error, info};
-use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
 use table_kv::{
-    ScanContext as KvScanContext, ScanIter, TableError, TableKv, WriteBatch, WriteContext,
+    KeyBoundary, ScanContext as KvScanContext, ScanIter, ScanRequest as KvScanRequest, TableError,
+    TableKv, WriteBatch, WriteContext,
 };
 
 use crate::{
-    kv_encoder::CommonLogKey,
+    kv_encoder::{self, CommonLogEncoding, CommonLogKey},
     log_batch::LogWriteBatch,
     manager::{self, ReadContext, ReadRequest, RegionId, ScanContext, ScanRequest, SequenceNumber},
     table_kv_impl::{
         consts, encoding,
+        metrics::WRITE_THROTTLE_DURATION_HISTOGRAM,
         model::{BucketEntry, NamespaceConfig, NamespaceEntry},
-        table_unit::{TableLogIterator, TableUnit, TableUnitRef},
+        rate_limiter::RegionWriteLimiter,
+        table_unit::{ReadLogOutcome, TableLogIterator, TableUnit, TableUnitRef, TableUnitStats},
         timed_task::{TaskHandle, TimedTask},
+        write_coalescer::LogWriteCoalescer,
         WalRuntimes,
     },
 };
@@ -208,10 +216,77 @@ pub enum Error {
         table_id: TableId,
         source: crate::table_kv_impl::table_unit::Error,
     },
+
+    #[snafu(display(
+        "Wal shards of bucket map to the same table, namespace:{}, table_name:{}, shard_id:{}, other_shard_id:{}.\nBacktrace:\n{}",
+        namespace,
+        table_name,
+        shard_id,
+        other_shard_id,
+        backtrace
+    ))]
+    ShardTableCollision {
+        namespace: String,
+        table_name: String,
+        shard_id: usize,
+        other_shard_id: usize,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Bucket has no wal shard, namespace:{}, bucket:{:?}.\nBacktrace:\n{}",
+        namespace,
+        bucket,
+        backtrace
+    ))]
+    EmptyBucket {
+        namespace: String,
+        bucket: BucketEntry,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Failed to compact bucket, namespace:{}, table_name:{}, err:{}",
+        namespace,
+        table_name,
+        source,
+    ))]
+    CompactBucket {
+        namespace: String,
+        table_name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[snafu(display(
+        "Failed to load start sequence, namespace:{}, region_id:{}, table_id:{}, err:{}",
+        namespace,
+        region_id,
+        table_id,
+        source
+    ))]
+    LoadStartSequence {
+        namespace: String,
+        region_id: RegionId,
+        table_id: TableId,
+        source: crate::table_kv_impl::table_unit::Error,
+    },
 }
 
 define_result!(Error);
 
+impl Error {
+    /// Returns true if the operation failed because scanning the table units
+    /// timeout, so it can be retried with a longer scan timeout.
+    pub fn is_scan_timeout(&self) -> bool {
+        match self {
+            Error::OpenTableUnit { source, .. }
+            | Error::CreateTableUnit { source, .. }
+            | Error::ReadTableUnit { source, .. } => source.is_scan_timeout(),
+            _ => false,
+        }
+    }
+}
+
 /// Duration of a bucket (1d).
 pub const BUCKET_DURATION_MS: i64 = 1000 * 3600 * 24;
 /// Check whether to create a new bucket every `BUCKET_DURATION_PERIOD`.
@@ -230,6 +305,9 @@ struct NamespaceInner<T> {
     operator: Mutex<TableOperator>,
     // Only one thread can persist and create a new bucket.
     bucket_creator: Mutex<BucketCreator>,
+    write_coalescer: Option<Arc<LogWriteCoalescer<T>>>,
+    /// Write rate limiters of the limited regions.
+    write_limiters: HashMap<RegionId, RegionWriteLimiter>,
     config: NamespaceConfig,
 }
 
@@ -337,6 +415,8 @@ impl<T: TableKv> NamespaceInner<T> {
     /// Open bucket, ensure all tables are created, and insert the bucket into
     /// the bucket set in memory.
     fn open_bucket(&self, bucket: Bucket) -> Result<BucketRef> {
+        bucket.validate_shard_tables(self.name())?;
+
         {
             // Create all wal shards of this bucket.
             let mut operator = self.operator.lock().unwrap();
@@ -486,6 +566,284 @@ impl<T: TableKv> NamespaceInner<T> {
 
         Ok(())
     }
+
+    /// Compact the sparse timed buckets, whose ratio of live logs is no larger
+    /// than `bucket_compaction_live_ratio`, into their next buckets.
+    ///
+    /// The bucket of `now` and its previous bucket are never compacted, as
+    /// they may still be written.
+    fn compact_sparse_buckets(&self, now: Timestamp) -> Result<()> {
+        let live_ratio = match self.config.bucket_compaction_live_ratio {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let now_start = now
+            .checked_floor_by_i64(BUCKET_DURATION_MS)
+            .context(BucketOutOfRange {
+                namespace: self.name(),
+                timestamp: now,
+            })?;
+        let writable_start_ms = now_start.as_i64().saturating_sub(BUCKET_DURATION_MS);
+
+        // Buckets are ordered by time.
+        let buckets = self.list_buckets();
+        let mut start_sequences = StartSequences::new();
+        for pair in buckets.windows(2) {
+            let (bucket, next_bucket) = (&pair[0], &pair[1]);
+            if bucket.gmt_start_ms().as_i64() >= writable_start_ms {
+                break;
+            }
+
+            let (live, total) = self.count_live_logs(bucket, &mut start_sequences)?;
+            if total == 0 || live as f64 > total as f64 * live_ratio {
+                continue;
+            }
+
+            self.compact_bucket(bucket, next_bucket, &mut start_sequences)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compact the `bucket` into the `next_bucket`, returns the number of
+    /// rewritten logs.
+    ///
+    /// The live logs are written into the next bucket first, then the bucket
+    /// record is deleted from the meta table, and the wal shard tables of the
+    /// bucket are dropped at last. The logs keep their keys, so it is safe to
+    /// rewrite them again if the compaction is interrupted, and readers skip
+    /// the logs duplicated in both buckets in the meantime. A crash after
+    /// deleting the bucket record leaks the tables but never loses logs.
+    fn compact_bucket(
+        &self,
+        bucket: &Bucket,
+        next_bucket: &Bucket,
+        start_sequences: &mut StartSequences,
+    ) -> Result<usize> {
+        assert!(bucket.gmt_start_ms() < next_bucket.gmt_start_ms());
+
+        let rewritten = self.rewrite_live_logs(bucket, next_bucket, start_sequences)?;
+
+        let mut batch = T::WriteBatch::default();
+        batch.delete(bucket.format_bucket_key(self.name()).as_bytes());
+        self.table_kv
+            .write(WriteContext::default(), &self.meta_table_name, batch)
+            .map_err(|e| Box::new(e) as _)
+            .context(PurgeBucket {
+                namespace: self.name(),
+            })?;
+        self.bucket_set
+            .write()
+            .unwrap()
+            .remove_timed_bucket(bucket.gmt_start_ms());
+
+        for table_name in &bucket.wal_shard_names {
+            self.table_kv
+                .drop_table(table_name)
+                .map_err(|e| Box::new(e) as _)
+                .context(DropShard {
+                    namespace: self.name(),
+                })?;
+        }
+
+        info!(
+            "Compact bucket into next bucket, namespace:{}, bucket:{:?}, next_bucket:{:?}, rewritten:{}",
+            self.name(),
+            bucket.entry,
+            next_bucket.entry,
+            rewritten
+        );
+
+        Ok(rewritten)
+    }
+
+    /// Write the live logs of the `bucket` into the `next_bucket`, returns the
+    /// number of rewritten logs.
+    fn rewrite_live_logs(
+        &self,
+        bucket: &Bucket,
+        next_bucket: &Bucket,
+        start_sequences: &mut StartSequences,
+    ) -> Result<usize> {
+        let batch_size = cmp::max(self.config.clean_scan_batch_size, 1);
+        let mut rewritten = 0;
+        for table_name in &bucket.wal_shard_names {
+            // Write batches and their sizes of the tables in the next bucket.
+            let mut batches: HashMap<&str, (T::WriteBatch, usize)> = HashMap::new();
+            self.for_each_log(table_name, |log_key, key, value| {
+                if !self.is_live_log(log_key, start_sequences)? {
+                    return Ok(());
+                }
+
+                let next_table = next_bucket.wal_shard_table(log_key.region_id);
+                let (batch, size) = batches.entry(next_table).or_default();
+                batch.insert_or_update(key, value);
+                *size += 1;
+                rewritten += 1;
+                if *size >= batch_size {
+                    *size = 0;
+                    self.write_rewritten_logs(next_table, mem::take(batch))?;
+                }
+
+                Ok(())
+            })?;
+
+            for (next_table, (batch, size)) in batches {
+                if size > 0 {
+                    self.write_rewritten_logs(next_table, batch)?;
+                }
+            }
+        }
+
+        Ok(rewritten)
+    }
+
+    fn write_rewritten_logs(&self, table_name: &str, batch: T::WriteBatch) -> Result<()> {
+        self.table_kv
+            .write(WriteContext::default(), table_name, batch)
+            .map_err(|e| Box::new(e) as _)
+            .context(CompactBucket {
+                namespace: self.name(),
+                table_name,
+            })
+    }
+
+    /// Count the live logs and all the logs in the `bucket`.
+    fn count_live_logs(
+        &self,
+        bucket: &Bucket,
+        start_sequences: &mut StartSequences,
+    ) -> Result<(usize, usize)> {
+        let (mut live, mut total) = (0, 0);
+        for table_name in &bucket.wal_shard_names {
+            self.for_each_log(table_name, |log_key, _, _| {
+                total += 1;
+                if self.is_live_log(log_key, start_sequences)? {
+                    live += 1;
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok((live, total))
+    }
+
+    /// Visit the logs of all regions in the wal shard table `table_name` with
+    /// their decoded keys, raw keys and raw values.
+    fn for_each_log<F>(&self, table_name: &str, mut f: F) -> Result<()>
+    where
+        F: FnMut(&CommonLogKey, &[u8], &[u8]) -> Result<()>,
+    {
+        let log_encoding = CommonLogEncoding::newest();
+        let scan_req = new_all_logs_scan_request(&log_encoding)
+            .map_err(|e| Box::new(e) as _)
+            .context(CompactBucket {
+                namespace: self.name(),
+                table_name,
+            })?;
+        let mut iter = self
+            .table_kv
+            .scan(self.config.new_init_scan_ctx(), table_name, scan_req)
+            .map_err(|e| Box::new(e) as _)
+            .context(CompactBucket {
+                namespace: self.name(),
+                table_name,
+            })?;
+
+        while iter.valid() {
+            let log_key = log_encoding
+                .decode_key(iter.key())
+                .map_err(|e| Box::new(e) as _)
+                .context(CompactBucket {
+                    namespace: self.name(),
+                    table_name,
+                })?;
+            f(&log_key, iter.key(), iter.value())?;
+
+            iter.next()
+                .map_err(|e| Box::new(e) as _)
+                .context(CompactBucket {
+                    namespace: self.name(),
+                    table_name,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the log is not deleted from its table unit.
+    fn is_live_log(
+        &self,
+        log_key: &CommonLogKey,
+        start_sequences: &mut StartSequences,
+    ) -> Result<bool> {
+        let start_sequence = match start_sequences.entry((log_key.region_id, log_key.table_id)) {
+            Entry::Occupied(v) => *v.get(),
+            Entry::Vacant(v) => {
+                *v.insert(self.load_start_sequence(log_key.region_id, log_key.table_id)?)
+            }
+        };
+
+        Ok(log_key.sequence_num >= start_sequence)
+    }
+
+    fn load_start_sequence(
+        &self,
+        region_id: RegionId,
+        table_id: TableId,
+    ) -> Result<SequenceNumber> {
+        if let Some(table_unit) = self.get_table_unit_from_memory(table_id) {
+            if table_unit.region_id() == region_id {
+                return Ok(table_unit.start_sequence());
+            }
+        }
+
+        let start_sequence = TableUnit::load_start_sequence(
+            &self.table_kv,
+            self.table_unit_meta_table(region_id),
+            table_id,
+        )
+        .context(LoadStartSequence {
+            namespace: self.name(),
+            region_id,
+            table_id,
+        })?;
+
+        // Keep all logs of the unknown table units.
+        Ok(start_sequence.unwrap_or(common_types::MIN_SEQUENCE_NUMBER))
+    }
+}
+
+/// Start sequences of the table units, keyed by (region id, table id).
+type StartSequences = HashMap<(RegionId, TableId), SequenceNumber>;
+
+/// Build the request to scan the logs of all regions in a wal shard table.
+fn new_all_logs_scan_request(
+    log_encoding: &CommonLogEncoding,
+) -> kv_encoder::Result<KvScanRequest> {
+    let mut key_buf = BytesMut::new();
+    let min_log_key = CommonLogKey::new(
+        RegionId::MIN,
+        TableId::MIN,
+        common_types::MIN_SEQUENCE_NUMBER,
+    );
+    log_encoding.encode_key(&mut key_buf, &min_log_key)?;
+    let start = KeyBoundary::included(&key_buf);
+
+    let max_log_key = CommonLogKey::new(
+        RegionId::MAX,
+        TableId::MAX,
+        common_types::MAX_SEQUENCE_NUMBER,
+    );
+    log_encoding.encode_key(&mut key_buf, &max_log_key)?;
+    let end = KeyBoundary::included(&key_buf);
+
+    Ok(KvScanRequest {
+        start,
+        end,
+        reverse: false,
+    })
 }
 
 // Async operations.
@@ -524,6 +882,7 @@ impl<T: TableKv> NamespaceInner<T> {
             region_id,
             table_id,
             buckets,
+            self.config.repair_missing_table_unit_meta,
         )
         .await
         .context(OpenTableUnit {
@@ -532,7 +891,7 @@ impl<T: TableKv> NamespaceInner<T> {
             table_id,
         })?;
         let table_unit = match table_unit_opt {
-            Some(v) => Arc::new(v),
+            Some(v) => Arc::new(v.with_sequence_warn_ratio(self.config.sequence_warn_ratio)),
             None => return Ok(None),
         };
 
@@ -595,17 +954,18 @@ impl<T: TableKv> NamespaceInner<T> {
             table_id,
         );
 
+        let table_unit = table_unit.with_sequence_warn_ratio(self.config.sequence_warn_ratio);
         let table_unit = self.insert_or_get_table_unit(Arc::new(table_unit));
 
         Ok(table_unit)
     }
 
-    /// Write log to this namespace.
+    /// Write log to this namespace, returns the sequences of the logs.
     async fn write_log(
         &self,
         ctx: &manager::WriteContext,
         batch: &LogWriteBatch,
-    ) -> Result<SequenceNumber> {
+    ) -> Result<RangeInclusive<SequenceNumber>> {
         let region_id = batch.location.shard_id as RegionId;
         let table_id = batch.location.table_id;
         let now = Timestamp::now();
@@ -614,16 +974,30 @@ impl<T: TableKv> NamespaceInner<T> {
 
         let table_unit = self.get_or_create_table_unit(region_id, table_id).await?;
 
-        let sequence = table_unit
-            .write_log(&self.table_kv, &bucket, ctx, batch)
-            .await
-            .context(WriteTableUnit {
-                namespace: self.name(),
-                region_id,
-                table_id,
-            })?;
+        if let Some(limiter) = self.write_limiters.get(&region_id) {
+            let wait = limiter.acquire(batch).await;
+            WRITE_THROTTLE_DURATION_HISTOGRAM.observe(wait.as_secs_f64());
+        }
+
+        let res = match &self.write_coalescer {
+            Some(coalescer) => {
+                table_unit
+                    .write_log_coalesced_with_sequences(coalescer, &bucket, batch)
+                    .await
+            }
+            None => {
+                table_unit
+                    .write_log_with_sequences(&self.table_kv, &bucket, ctx, batch)
+                    .await
+            }
+        };
+        let sequences = res.context(WriteTableUnit {
+            namespace: self.name(),
+            region_id,
+            table_id,
+        })?;
 
-        Ok(sequence)
+        Ok(sequences)
     }
 
     /// Get last sequence number of this region.
@@ -638,9 +1012,30 @@ impl<T: TableKv> NamespaceInner<T> {
         Ok(common_types::MIN_SEQUENCE_NUMBER)
     }
 
+    /// Get stats of the table unit, None if the table unit doesn't exist.
+    async fn table_unit_stats(&self, location: Location) -> Result<Option<TableUnitStats>> {
+        let region_id = location.shard_id as RegionId;
+        let table_id = location.table_id;
+
+        let table_unit = self.get_or_open_table_unit(region_id, table_id).await?;
+        Ok(table_unit.map(|v| v.stats(self.list_buckets().len())))
+    }
+
     /// Read log from this namespace. Note that the iterating the iterator may
     /// still block caller thread now.
     async fn read_log(&self, ctx: &ReadContext, req: &ReadRequest) -> Result<TableLogIterator<T>> {
+        let outcome = self.read_log_outcome(ctx, req).await?;
+
+        Ok(outcome.into_log_iter(&self.table_kv))
+    }
+
+    /// Read log from this namespace, tells the missing table unit and the empty
+    /// read range from the logs to iterate.
+    async fn read_log_outcome(
+        &self,
+        ctx: &ReadContext,
+        req: &ReadRequest,
+    ) -> Result<ReadLogOutcome<T>> {
         // TODO(yingwen): Skip buckets according to sequence range, avoid scan all
         // buckets.
         let buckets = self.list_buckets();
@@ -649,7 +1044,7 @@ impl<T: TableKv> NamespaceInner<T> {
         let table_id = req.location.table_id;
         if let Some(table_unit) = self.get_or_open_table_unit(region_id, table_id).await? {
             table_unit
-                .read_log(&self.table_kv, buckets, ctx, req)
+                .read_log_outcome(&self.table_kv, buckets, ctx, req)
                 .await
                 .context(ReadTableUnit {
                     namespace: self.name(),
@@ -657,20 +1052,65 @@ impl<T: TableKv> NamespaceInner<T> {
                     table_id,
                 })
         } else {
-            Ok(TableLogIterator::new_empty(self.table_kv.clone()))
+            Ok(ReadLogOutcome::TableUnitNotFound)
         }
     }
 
     /// Delete entries up to `sequence_num` of table unit identified by
     /// `location`.
     async fn delete_entries(&self, location: Location, sequence_num: SequenceNumber) -> Result<()> {
+        let region_id = location.shard_id as RegionId;
+        let table_id = location.table_id;
+        if let Some(table_unit) = self.get_or_open_table_unit(region_id, table_id).await? {
+            let table_unit_meta_table = self.table_unit_meta_table(table_id);
+
+            let res = if self.config.eager_clean_deleted_logs {
+                table_unit
+                    .delete_entries_up_to_eagerly(
+                        &self.table_kv,
+                        table_unit_meta_table,
+                        sequence_num,
+                        self.config.new_clean_ctx(),
+                        self.list_buckets(),
+                    )
+                    .await
+            } else {
+                table_unit
+                    .delete_entries_up_to(&self.table_kv, table_unit_meta_table, sequence_num)
+                    .await
+            };
+            res.context(DeleteEntries {
+                namespace: self.name(),
+                region_id,
+                table_id,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete entries with sequence in range `[from, to]` of table unit
+    /// identified by `location`, see [TableUnit::delete_entries_in_range].
+    async fn delete_entries_in_range(
+        &self,
+        location: Location,
+        from: SequenceNumber,
+        to: SequenceNumber,
+    ) -> Result<()> {
         let region_id = location.shard_id as RegionId;
         let table_id = location.table_id;
         if let Some(table_unit) = self.get_or_open_table_unit(region_id, table_id).await? {
             let table_unit_meta_table = self.table_unit_meta_table(table_id);
 
             table_unit
-                .delete_entries_up_to(&self.table_kv, table_unit_meta_table, sequence_num)
+                .delete_entries_in_range(
+                    &self.table_kv,
+                    table_unit_meta_table,
+                    from,
+                    to,
+                    self.config.new_clean_ctx(),
+                    self.list_buckets(),
+                )
                 .await
                 .context(DeleteEntries {
                     namespace: self.name(),
@@ -892,6 +1332,11 @@ impl<T> Namespace<T> {
     pub fn read_runtime(&self) -> &Arc<Runtime> {
         &self.inner.runtimes.read_runtime
     }
+
+    #[inline]
+    pub fn runtimes(&self) -> &WalRuntimes {
+        &self.inner.runtimes
+    }
 }
 
 // Blocking operations
@@ -991,6 +1436,20 @@ impl<T: TableKv> Namespace<T> {
         }
 
         let bucket_set = BucketSet::new(config.ttl.is_some());
+        let write_coalescer = config.write_coalesce_window.as_ref().map(|window| {
+            Arc::new(LogWriteCoalescer::new(
+                runtimes.write_runtime.clone(),
+                table_kv.clone(),
+                window.0,
+            ))
+        });
+        let write_limiters = config
+            .region_write_limits
+            .iter()
+            .filter_map(|limit| {
+                RegionWriteLimiter::new(limit).map(|limiter| (limit.region_id, limiter))
+            })
+            .collect();
 
         let inner = Arc::new(NamespaceInner {
             runtimes: runtimes.clone(),
@@ -1002,6 +1461,8 @@ impl<T: TableKv> Namespace<T> {
             table_unit_meta_tables,
             operator: Mutex::new(TableOperator),
             bucket_creator: Mutex::new(BucketCreator),
+            write_coalescer,
+            write_limiters,
             config,
         });
 
@@ -1095,12 +1556,25 @@ impl<T: TableKv> Namespace<T> {
 
 // Async operations.
 impl<T: TableKv> Namespace<T> {
-    /// Write log to this namespace.
+    /// Write log to this namespace, returns the max sequence of the logs.
     pub async fn write_log(
         &self,
         ctx: &manager::WriteContext,
         batch: &LogWriteBatch,
     ) -> Result<SequenceNumber> {
+        self.inner
+            .write_log(ctx, batch)
+            .await
+            .map(|sequences| *sequences.end())
+    }
+
+    /// Write log to this namespace, returns the sequences of the logs, the i-th
+    /// entry of `batch` is assigned the i-th sequence of the range.
+    pub async fn write_log_with_sequences(
+        &self,
+        ctx: &manager::WriteContext,
+        batch: &LogWriteBatch,
+    ) -> Result<RangeInclusive<SequenceNumber>> {
         self.inner.write_log(ctx, batch).await
     }
 
@@ -1109,6 +1583,12 @@ impl<T: TableKv> Namespace<T> {
         self.inner.last_sequence(location).await
     }
 
+    /// Get the operational overview of the table unit identified by
+    /// `location` in one call, None if the table unit doesn't exist.
+    pub async fn table_unit_stats(&self, location: Location) -> Result<Option<TableUnitStats>> {
+        self.inner.table_unit_stats(location).await
+    }
+
     /// Read log from this namespace. Note that the iterating the iterator may
     /// still block caller thread now.
     pub async fn read_log(
@@ -1119,6 +1599,18 @@ impl<T: TableKv> Namespace<T> {
         self.inner.read_log(ctx, req).await
     }
 
+    /// Read log from this namespace like [Namespace::read_log], but the
+    /// outcome distinguishes a missing table unit or an empty read range from
+    /// the logs to iterate, e.g. to catch reading an unexpected table during
+    /// recovery.
+    pub async fn read_log_outcome(
+        &self,
+        ctx: &ReadContext,
+        req: &ReadRequest,
+    ) -> Result<ReadLogOutcome<T>> {
+        self.inner.read_log_outcome(ctx, req).await
+    }
+
     /// Delete entries up to `sequence_num` of table unit identified by
     /// `region_id` and `table_id`.
     pub async fn delete_entries(
@@ -1129,6 +1621,21 @@ impl<T: TableKv> Namespace<T> {
         self.inner.delete_entries(location, sequence_num).await
     }
 
+    /// Delete entries with sequence in range `[from, to]` of table unit
+    /// identified by `location`.
+    ///
+    /// Only a range covering the start of the table unit is deleted logically,
+    /// a range in the middle just has its logs removed, see
+    /// [TableUnit::delete_entries_in_range].
+    pub async fn delete_entries_in_range(
+        &self,
+        location: Location,
+        from: SequenceNumber,
+        to: SequenceNumber,
+    ) -> Result<()> {
+        self.inner.delete_entries_in_range(location, from, to).await
+    }
+
     /// Scan logs of a whole region from this namespace.
     // TODO: maybe we should filter the log marked deleted,
     // but there isn't any actual benefit such as reducing network IO,
@@ -1141,6 +1648,19 @@ impl<T: TableKv> Namespace<T> {
         self.inner.scan_log(ctx, req).await
     }
 
+    /// Scan logs of table `table_id` in the region from this namespace, logs
+    /// of other tables sharing the same region are skipped.
+    pub async fn scan_table_log(
+        &self,
+        ctx: &ScanContext,
+        req: &ScanRequest,
+        table_id: TableId,
+    ) -> Result<TableLogIterator<T>> {
+        let iter = self.inner.scan_log(ctx, req).await?;
+
+        Ok(iter.with_table_filter(table_id))
+    }
+
     /// Stop background tasks and close this namespace.
     pub async fn close(&self) -> Result<()> {
         info!("Try to close namespace, namespace:{}", self.name());
@@ -1276,7 +1796,7 @@ pub struct Bucket {
 }
 
 impl Bucket {
-    fn new(namespace: &str, entry: BucketEntry) -> Self {
+    pub(crate) fn new(namespace: &str, entry: BucketEntry) -> Self {
         let mut wal_shard_names = Vec::with_capacity(entry.shard_num);
 
         for shard_id in 0..entry.shard_num {
@@ -1289,12 +1809,53 @@ impl Bucket {
             wal_shard_names.push(table_name);
         }
 
+        debug_assert!(
+            find_shard_collision(&wal_shard_names).is_none(),
+            "Wal shards of bucket map to the same table, namespace:{}, tables:{:?}",
+            namespace,
+            wal_shard_names
+        );
+
         Self {
             entry,
             wal_shard_names,
         }
     }
 
+    /// Ensure regions are routed to distinct tables unless they have the same
+    /// shard id.
+    ///
+    /// Regions with the same shard id share the same wal shard table by design,
+    /// and their logs are distinguished by the region id in the log key. But if
+    /// two shards map to the same table, regions of different shards would be
+    /// mixed silently.
+    fn validate_shard_tables(&self, namespace: &str) -> Result<()> {
+        ensure!(
+            !self.wal_shard_names.is_empty(),
+            EmptyBucket {
+                namespace,
+                bucket: self.entry,
+            }
+        );
+
+        match find_shard_collision(&self.wal_shard_names) {
+            Some((other_shard_id, shard_id)) => ShardTableCollision {
+                namespace,
+                table_name: &self.wal_shard_names[shard_id],
+                shard_id,
+                other_shard_id,
+            }
+            .fail(),
+            None => Ok(()),
+        }
+    }
+
+    /// Shard id of the wal shard table storing logs of the region.
+    #[inline]
+    pub fn shard_id(&self, region_id: RegionId) -> usize {
+        region_id as usize % self.wal_shard_names.len()
+    }
+
     #[inline]
     pub fn gmt_start_ms(&self) -> Timestamp {
         self.entry.gmt_start_ms()
@@ -1302,8 +1863,7 @@ impl Bucket {
 
     #[inline]
     pub fn wal_shard_table(&self, region_id: RegionId) -> &str {
-        let index = region_id as usize % self.wal_shard_names.len();
-        &self.wal_shard_names[index]
+        &self.wal_shard_names[self.shard_id(region_id)]
     }
 
     fn format_bucket_key(&self, namespace: &str) -> String {
@@ -1326,6 +1886,19 @@ impl Bucket {
 
 pub type BucketRef = Arc<Bucket>;
 
+/// Find two shards with the same table name, returns their shard ids in
+/// ascending order.
+fn find_shard_collision(wal_shard_names: &[String]) -> Option<(usize, usize)> {
+    let mut shard_ids = HashMap::with_capacity(wal_shard_names.len());
+    for (shard_id, table_name) in wal_shard_names.iter().enumerate() {
+        if let Some(other_shard_id) = shard_ids.insert(table_name.as_str(), shard_id) {
+            return Some((other_shard_id, shard_id));
+        }
+    }
+
+    None
+}
+
 async fn log_cleaner_routine<T: TableKv>(inner: Arc<NamespaceInner<T>>) {
     debug!(
         "Periodical log cleaning process start, namespace:{}",
@@ -1387,11 +1960,20 @@ async fn bucket_monitor_routine<T: TableKv>(inner: Arc<NamespaceInner<T>>, now:
         );
     }
 
-    debug!(
-        "Periodical bucket monitor process end, namespace:{}",
-        inner.name()
-    );
-}
+    if let Err(e) = inner.compact_sparse_buckets(now) {
+        error!(
+            "Failed to compact sparse buckets, namespace:{}, now:{:?}, err:{}",
+            inner.name(),
+            now,
+            e,
+        );
+    }
+
+    debug!(
+        "Periodical bucket monitor process end, namespace:{}",
+        inner.name()
+    );
+}
 
 fn start_bucket_monitor<T: TableKv>(
     runtime: &Runtime,
@@ -1411,20 +1993,34 @@ fn start_bucket_monitor<T: TableKv>(
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
 
     use common_types::{
         bytes::BytesMut,
         table::{Location, DEFAULT_SHARD_ID},
     };
     use common_util::runtime::{Builder, Runtime};
-    use table_kv::{memory::MemoryImpl, KeyBoundary, ScanContext, ScanRequest};
+    use table_kv::{
+        memory::{self, MemoryImpl, MemoryScanIter, MemoryWriteBatch},
+        Durability, KeyBoundary, ScanContext, ScanRequest,
+    };
 
     use super::*;
     use crate::{
-        kv_encoder::{LogBatchEncoder, LogEncoding},
+        kv_encoder::{CommonLogEncoding, LogBatchEncoder},
         log_batch::PayloadDecoder,
-        table_kv_impl::consts,
+        manager::{ReadBoundary, SyncLogIterator},
+        table_kv_impl::{
+            consts,
+            model::RegionWriteLimit,
+            table_unit::{MergedLogIterator, TableLogIterator},
+        },
         tests::util::{TestPayload, TestPayloadDecoder},
     };
 
@@ -1451,6 +2047,10 @@ mod tests {
         table_kv: T,
         runtime: Arc<Runtime>,
         ttl: Option<Duration>,
+        eager_clean_deleted_logs: bool,
+        write_coalesce_window: Option<Duration>,
+        region_write_limits: Vec<RegionWriteLimit>,
+        bucket_compaction_live_ratio: Option<f64>,
     }
 
     impl<T: TableKv> NamespaceMocker<T> {
@@ -1460,6 +2060,10 @@ mod tests {
                 table_kv,
                 runtime,
                 ttl: None,
+                eager_clean_deleted_logs: false,
+                write_coalesce_window: None,
+                region_write_limits: Vec::new(),
+                bucket_compaction_live_ratio: None,
             }
         }
 
@@ -1468,11 +2072,35 @@ mod tests {
             self
         }
 
+        fn eager_clean_deleted_logs(mut self, eager_clean_deleted_logs: bool) -> Self {
+            self.eager_clean_deleted_logs = eager_clean_deleted_logs;
+            self
+        }
+
+        fn write_coalesce_window(mut self, window: Option<Duration>) -> Self {
+            self.write_coalesce_window = window;
+            self
+        }
+
+        fn region_write_limits(mut self, limits: Vec<RegionWriteLimit>) -> Self {
+            self.region_write_limits = limits;
+            self
+        }
+
+        fn bucket_compaction_live_ratio(mut self, ratio: Option<f64>) -> Self {
+            self.bucket_compaction_live_ratio = ratio;
+            self
+        }
+
         fn build(self) -> Namespace<T> {
             let config = NamespaceConfig {
                 wal_shard_num: 4,
                 table_unit_meta_shard_num: 4,
                 ttl: self.ttl.map(Into::into),
+                eager_clean_deleted_logs: self.eager_clean_deleted_logs,
+                write_coalesce_window: self.write_coalesce_window.map(Into::into),
+                region_write_limits: self.region_write_limits,
+                bucket_compaction_live_ratio: self.bucket_compaction_live_ratio,
                 ..Default::default()
             };
             let wal_runtimes = new_wal_runtimes(self.runtime);
@@ -1515,6 +2143,71 @@ mod tests {
         assert_eq!(&expect_names[..], &bucket.wal_shard_names[..]);
     }
 
+    #[test]
+    fn test_bucket_shard_tables_of_regions() {
+        let gmt_start_ms = Timestamp::new(1648425600000);
+        let buckets = [
+            Bucket::new("test", BucketEntry::new_permanent(4)),
+            Bucket::new(
+                "test",
+                BucketEntry::new_timed(4, gmt_start_ms, BUCKET_DURATION_MS).unwrap(),
+            ),
+        ];
+
+        for bucket in &buckets {
+            bucket.validate_shard_tables("test").unwrap();
+
+            let region_ids = (0..1000).chain(RegionId::MAX - 1000..=RegionId::MAX);
+            let mut shard_tables: HashMap<usize, &str> = HashMap::new();
+            for region_id in region_ids {
+                let shard_id = bucket.shard_id(region_id);
+                assert_eq!(region_id as usize % 4, shard_id);
+
+                let table_name = bucket.wal_shard_table(region_id);
+                let expect = shard_tables.entry(shard_id).or_insert(table_name);
+                assert_eq!(*expect, table_name);
+            }
+
+            // Regions of different shards never share the same table.
+            assert_eq!(4, shard_tables.len());
+            let mut table_names: Vec<_> = shard_tables.values().collect();
+            table_names.sort();
+            table_names.dedup();
+            assert_eq!(4, table_names.len());
+        }
+    }
+
+    #[test]
+    fn test_bucket_shard_table_collision() {
+        let bucket = Bucket {
+            entry: BucketEntry::new_permanent(3),
+            wal_shard_names: vec![
+                "wal_test_permanent_000000".to_string(),
+                "wal_test_permanent_000001".to_string(),
+                "wal_test_permanent_000000".to_string(),
+            ],
+        };
+        let err = bucket.validate_shard_tables("test").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::ShardTableCollision {
+                    shard_id: 2,
+                    other_shard_id: 0,
+                    ..
+                }
+            ),
+            "err:{}",
+            err
+        );
+
+        let bucket = Bucket::new("test", BucketEntry::new_permanent(0));
+        assert!(matches!(
+            bucket.validate_shard_tables("test"),
+            Err(Error::EmptyBucket { .. })
+        ));
+    }
+
     #[test]
     fn test_permanent_bucket_set() {
         let entry = BucketEntry::new_permanent(4);
@@ -1732,10 +2425,13 @@ mod tests {
             let buckets = inner.list_buckets();
             assert_eq!(1, buckets.len());
 
-            let table = buckets[0].wal_shard_table(location.table_id);
-            let key_values = direct_read_logs_from_table(&table_kv, table, location.table_id).await;
+            let region_id = location.shard_id as RegionId;
+            let table = buckets[0].wal_shard_table(region_id);
+            let key_values =
+                direct_read_logs_from_table(&table_kv, table, region_id, location.table_id).await;
 
             // Logs from min sequence to seq1 should be deleted from the table.
+            assert_eq!(4, key_values.len());
             let mut expect_seq = seq1 + 1;
             let mut expect_val = 1005;
             for (k, v) in key_values {
@@ -1750,25 +2446,338 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_table_unit_stats() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+            assert!(namespace
+                .table_unit_stats(location)
+                .await
+                .unwrap()
+                .is_none());
+
+            let seq1 = write_test_payloads(&namespace, location, 1000, 1004).await;
+            let seq2 = write_test_payloads(&namespace, location, 1005, 1009).await;
+            let stats = namespace.table_unit_stats(location).await.unwrap().unwrap();
+            let expect = TableUnitStats {
+                region_id: DEFAULT_SHARD_ID as RegionId,
+                table_id: 123,
+                start_sequence: common_types::MIN_SEQUENCE_NUMBER,
+                last_sequence: seq2,
+                approximate_entries: 8,
+                num_buckets: 1,
+                last_clean: None,
+            };
+            assert_eq!(expect, stats);
+
+            namespace.delete_entries(location, seq1).await.unwrap();
+            let stats = namespace.table_unit_stats(location).await.unwrap().unwrap();
+            assert_eq!(seq1 + 1, stats.start_sequence);
+            assert_eq!(4, stats.approximate_entries);
+            assert!(stats.last_clean.is_none());
+
+            log_cleaner_routine(namespace.inner.clone()).await;
+            let stats = namespace.table_unit_stats(location).await.unwrap().unwrap();
+            let last_clean = stats.last_clean.unwrap();
+            assert_eq!(seq1 + 1, last_clean.end_sequence);
+            assert_eq!(4, last_clean.deleted);
+            assert_eq!(4, stats.approximate_entries);
+            assert_eq!(seq2, stats.last_sequence);
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_read_log_outcome() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+            let read_ctx = ReadContext::default();
+            let read_req = |start, end| ReadRequest {
+                location,
+                start,
+                end,
+            };
+
+            // No log of the table has been written.
+            let outcome = namespace
+                .read_log_outcome(&read_ctx, &read_req(ReadBoundary::Min, ReadBoundary::Max))
+                .await
+                .unwrap();
+            assert!(
+                matches!(outcome, ReadLogOutcome::TableUnitNotFound),
+                "{:?}",
+                outcome
+            );
+
+            write_test_payloads(&namespace, location, 1000, 1004).await;
+
+            // The range contains no sequence.
+            let outcome = namespace
+                .read_log_outcome(
+                    &read_ctx,
+                    &read_req(
+                        ReadBoundary::Excluded(common_types::MAX_SEQUENCE_NUMBER),
+                        ReadBoundary::Max,
+                    ),
+                )
+                .await
+                .unwrap();
+            assert!(
+                matches!(outcome, ReadLogOutcome::EmptyRange),
+                "{:?}",
+                outcome
+            );
+            let mut iter = outcome.into_log_iter(&table_kv);
+            assert!(iter.next_log_entry().unwrap().is_none());
+
+            let outcome = namespace
+                .read_log_outcome(&read_ctx, &read_req(ReadBoundary::Min, ReadBoundary::Max))
+                .await
+                .unwrap();
+            let mut iter = match outcome {
+                ReadLogOutcome::Logs(iter) => iter,
+                other => panic!("Unexpected outcome:{:?}", other),
+            };
+            let mut num_logs = 0;
+            while iter.next_log_entry().unwrap().is_some() {
+                num_logs += 1;
+            }
+            assert_eq!(4, num_logs);
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_scan_region_logs() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let region_id = DEFAULT_SHARD_ID as RegionId;
+
+            // Tables of the same region write in turn.
+            for (start, end) in [(100, 103), (200, 202)] {
+                for table_id in [303, 101, 202] {
+                    let location = Location::new(DEFAULT_SHARD_ID, table_id);
+                    write_test_payloads(&namespace, location, start, end).await;
+                }
+            }
+
+            let mut iter = TableLogIterator::new_region(
+                namespace.inner.list_buckets(),
+                region_id,
+                ScanContext::default(),
+                table_kv.clone(),
+            );
+            let decoder = TestPayloadDecoder;
+            let mut logs = Vec::new();
+            while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                let mut payload = log_entry.payload;
+                let val = decoder.decode(&mut payload).unwrap().val;
+                logs.push((log_entry.table_id, log_entry.sequence, val));
+            }
+
+            // Logs are ordered by table id and then sequence.
+            let mut expect_logs = Vec::new();
+            for table_id in [101, 202, 303] {
+                let vals = (100..103).chain(200..202);
+                for (sequence, val) in (1..).zip(vals) {
+                    expect_logs.push((table_id, sequence, val));
+                }
+            }
+            assert_eq!(expect_logs, logs);
+
+            // Other regions are not touched.
+            let mut iter = TableLogIterator::new_region(
+                namespace.inner.list_buckets(),
+                region_id + 1,
+                ScanContext::default(),
+                table_kv.clone(),
+            );
+            assert!(iter.next_log_entry().unwrap().is_none());
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_delete_entries_eagerly() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone())
+                .eager_clean_deleted_logs(true)
+                .build();
+            let table_id = 123;
+            let location = Location::new(DEFAULT_SHARD_ID, table_id);
+            let region_id = location.shard_id as RegionId;
+
+            let seq1 = write_test_payloads(&namespace, location, 1000, 1004).await;
+            write_test_payloads(&namespace, location, 1005, 1009).await;
+
+            // Records are deleted without running the log cleaner.
+            namespace.delete_entries(location, seq1).await.unwrap();
+
+            let mut iter = TableLogIterator::new(
+                namespace.inner.list_buckets(),
+                CommonLogKey::new(region_id, table_id, common_types::MIN_SEQUENCE_NUMBER),
+                CommonLogKey::new(region_id, table_id, common_types::MAX_SEQUENCE_NUMBER),
+                ScanContext::default(),
+                table_kv.clone(),
+            );
+            let mut sequences = Vec::new();
+            while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                sequences.push(log_entry.sequence);
+            }
+            let expect_sequences: Vec<_> = (seq1 + 1..seq1 + 5).collect();
+            assert_eq!(expect_sequences, sequences);
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    async fn read_all_logs<T: TableKv>(
+        namespace: &Namespace<T>,
+        location: Location,
+    ) -> Vec<(SequenceNumber, u32)> {
+        let read_req = ReadRequest {
+            location,
+            start: ReadBoundary::Min,
+            end: ReadBoundary::Max,
+        };
+        let mut iter = namespace
+            .read_log(&ReadContext::default(), &read_req)
+            .await
+            .unwrap();
+        let decoder = TestPayloadDecoder;
+        let mut logs = Vec::new();
+        while let Some(log_entry) = iter.next_log_entry().unwrap() {
+            let mut payload = log_entry.payload;
+            logs.push((
+                log_entry.sequence,
+                decoder.decode(&mut payload).unwrap().val,
+            ));
+        }
+
+        logs
+    }
+
+    #[test]
+    fn test_compact_sparse_bucket() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone())
+                .ttl(Some(Duration::from_millis(7 * BUCKET_DURATION_MS as u64)))
+                .bucket_compaction_live_ratio(Some(0.5))
+                .build();
+            let inner = &namespace.inner;
+
+            let today = Timestamp::now()
+                .checked_floor_by_i64(BUCKET_DURATION_MS)
+                .unwrap();
+            let days_ago = |days: i64| today.checked_add_i64(-days * BUCKET_DURATION_MS).unwrap();
+            let old_buckets = [
+                inner.get_or_create_bucket(days_ago(3)).unwrap(),
+                inner.get_or_create_bucket(days_ago(2)).unwrap(),
+            ];
+
+            // Each table writes 10 logs to the older bucket and 5 logs to the newer one.
+            let locations = [Location::new(1, 101), Location::new(2, 102)];
+            let write_ctx = manager::WriteContext::default();
+            for (bucket, (start, end)) in old_buckets.iter().zip([(0, 10), (10, 15)]) {
+                for location in locations {
+                    let table_unit = inner
+                        .get_or_create_table_unit(location.shard_id as RegionId, location.table_id)
+                        .await
+                        .unwrap();
+                    let log_batch = new_test_log_batch(location, start, end);
+                    table_unit
+                        .write_log(&inner.table_kv, bucket, &write_ctx, &log_batch)
+                        .await
+                        .unwrap();
+                }
+            }
+            // Only 3 of 20 logs in the older bucket are alive.
+            namespace.delete_entries(locations[0], 8).await.unwrap();
+            namespace.delete_entries(locations[1], 9).await.unwrap();
+
+            let mut expect_logs = Vec::new();
+            for location in locations {
+                expect_logs.push(read_all_logs(&namespace, location).await);
+            }
+            assert_eq!(
+                vec![9, 10],
+                expect_logs[0][..2].iter().map(|v| v.0).collect::<Vec<_>>()
+            );
+            assert_eq!(10, expect_logs[1][0].0);
+
+            // Logs are duplicated in both buckets if the compaction is interrupted
+            // after rewriting.
+            let mut start_sequences = StartSequences::new();
+            let rewritten = inner
+                .rewrite_live_logs(&old_buckets[0], &old_buckets[1], &mut start_sequences)
+                .unwrap();
+            assert_eq!(3, rewritten);
+            for (location, expect) in locations.iter().zip(&expect_logs) {
+                assert_eq!(*expect, read_all_logs(&namespace, *location).await);
+            }
+
+            // The newer bucket and the buckets written recently are not compacted.
+            inner.compact_sparse_buckets(today).unwrap();
+            let buckets = inner.list_buckets();
+            assert_eq!(old_buckets[1].gmt_start_ms(), buckets[0].gmt_start_ms());
+            for table_name in &old_buckets[0].wal_shard_names {
+                assert!(!table_kv.table_exists(table_name).unwrap());
+            }
+            for (location, expect) in locations.iter().zip(&expect_logs) {
+                assert_eq!(*expect, read_all_logs(&namespace, *location).await);
+
+                let region_id = location.shard_id as RegionId;
+                let table = old_buckets[1].wal_shard_table(region_id);
+                let logs =
+                    direct_read_logs_from_table(&table_kv, table, region_id, location.table_id)
+                        .await;
+                assert_eq!(expect.len(), logs.len());
+            }
+
+            namespace.close().await.unwrap();
+        });
+    }
+
     async fn direct_read_logs_from_table<T: TableKv>(
         table_kv: &T,
         table_name: &str,
         region_id: RegionId,
+        table_id: TableId,
     ) -> Vec<(SequenceNumber, TestPayload)> {
-        let log_encoding = LogEncoding::newest();
+        let log_encoding = CommonLogEncoding::newest();
 
         let mut start_key = BytesMut::new();
         log_encoding
             .encode_key(
                 &mut start_key,
-                &(region_id, common_types::MIN_SEQUENCE_NUMBER),
+                &CommonLogKey::new(region_id, table_id, common_types::MIN_SEQUENCE_NUMBER),
             )
             .unwrap();
         let mut end_key = BytesMut::new();
         log_encoding
             .encode_key(
                 &mut end_key,
-                &(region_id, common_types::MAX_SEQUENCE_NUMBER),
+                &CommonLogKey::new(region_id, table_id, common_types::MAX_SEQUENCE_NUMBER),
             )
             .unwrap();
 
@@ -1785,9 +2794,10 @@ mod tests {
         let mut key_values = Vec::new();
         while iter.valid() {
             let decoded_key = log_encoding.decode_key(iter.key()).unwrap();
+            assert_eq!(table_id, decoded_key.table_id);
             let mut raw_value = log_encoding.decode_value(iter.value()).unwrap();
             let decoded_value = decoder.decode(&mut raw_value).unwrap();
-            key_values.push((decoded_key.1, decoded_value));
+            key_values.push((decoded_key.sequence_num, decoded_value));
 
             iter.next().unwrap();
         }
@@ -1795,27 +2805,680 @@ mod tests {
         key_values
     }
 
+    fn new_test_log_batch(location: Location, start_val: u32, end_val: u32) -> LogWriteBatch {
+        let log_entries = (start_val..end_val).collect::<Vec<_>>();
+        let wal_encoder = LogBatchEncoder::create(location);
+        wal_encoder
+            .encode_batch::<TestPayload, u32>(&log_entries)
+            .expect("should succeed to encode payload batch")
+    }
+
     async fn write_test_payloads<T: TableKv>(
         namespace: &Namespace<T>,
         location: Location,
         start_sequence: u32,
         end_sequence: u32,
     ) -> SequenceNumber {
-        let mut payload_batch = Vec::with_capacity((end_sequence - start_sequence) as usize);
-        for val in start_sequence..end_sequence {
-            let payload = TestPayload { val };
-            payload_batch.push(payload);
-        }
-
-        let log_entries = (start_sequence..end_sequence).collect::<Vec<_>>();
-        let wal_encoder = LogBatchEncoder::create(location);
-        let log_batch = wal_encoder
-            .encode_batch::<TestPayload, u32>(&log_entries)
-            .expect("should succeed to encode payload batch");
+        let log_batch = new_test_log_batch(location, start_sequence, end_sequence);
         let write_ctx = manager::WriteContext::default();
         namespace
             .write_log(&write_ctx, &log_batch)
             .await
             .expect("should succeed to write log batch")
     }
+
+    #[derive(Debug, Snafu)]
+    enum MockError {
+        #[snafu(display("Memory table kv failed, err:{}", source))]
+        Memory { source: memory::Error },
+
+        #[snafu(display("Injected write failure, retryable:{}", retryable))]
+        Injected { retryable: bool },
+
+        #[snafu(display("Injected scan failure, timeout:{}", timeout))]
+        InjectedScan { timeout: bool },
+    }
+
+    impl TableError for MockError {
+        fn is_primary_key_duplicate(&self) -> bool {
+            match self {
+                Self::Memory { source } => source.is_primary_key_duplicate(),
+                Self::Injected { .. } | Self::InjectedScan { .. } => false,
+            }
+        }
+
+        fn is_retryable(&self) -> bool {
+            matches!(
+                self,
+                Self::Injected { retryable: true } | Self::InjectedScan { timeout: true }
+            )
+        }
+
+        fn is_timeout(&self) -> bool {
+            matches!(self, Self::InjectedScan { timeout: true })
+        }
+
+        fn is_table_not_found(&self) -> bool {
+            match self {
+                Self::Memory { source } => source.is_table_not_found(),
+                Self::Injected { .. } | Self::InjectedScan { .. } => false,
+            }
+        }
+    }
+
+    /// Table kv which fails the next `failures` writes to wal shard tables, the
+    /// scans of wal shard tables once `scan_failure` is set, and records the
+    /// batch size of each scan.
+    #[derive(Debug, Clone, Default)]
+    struct MockTableKv {
+        inner: MemoryImpl,
+        failures: Arc<AtomicUsize>,
+        retryable: bool,
+        scan_batch_sizes: Arc<Mutex<Vec<i32>>>,
+        wal_writes: Arc<AtomicUsize>,
+        /// Fail scans of wal shard tables with timeout or not.
+        scan_failure: Arc<Mutex<Option<bool>>>,
+        /// Durability of each write to wal shard tables.
+        wal_write_durabilities: Arc<Mutex<Vec<Durability>>>,
+        /// Table name and durability of each write, in the order of writing.
+        writes: Arc<Mutex<Vec<(String, Durability)>>>,
+    }
+
+    impl MockTableKv {
+        fn new(retryable: bool) -> Self {
+            Self {
+                retryable,
+                ..Default::default()
+            }
+        }
+
+        fn fail_next_writes(&self, failures: usize) {
+            self.failures.store(failures, Ordering::Relaxed);
+        }
+
+        fn remaining_failures(&self) -> usize {
+            self.failures.load(Ordering::Relaxed)
+        }
+
+        fn fail_scans(&self, timeout: Option<bool>) {
+            *self.scan_failure.lock().unwrap() = timeout;
+        }
+
+        fn take_scan_batch_sizes(&self) -> Vec<i32> {
+            std::mem::take(&mut *self.scan_batch_sizes.lock().unwrap())
+        }
+
+        fn wal_writes(&self) -> usize {
+            self.wal_writes.load(Ordering::Relaxed)
+        }
+
+        fn take_wal_write_durabilities(&self) -> Vec<Durability> {
+            std::mem::take(&mut *self.wal_write_durabilities.lock().unwrap())
+        }
+
+        fn take_writes(&self) -> Vec<(String, Durability)> {
+            std::mem::take(&mut *self.writes.lock().unwrap())
+        }
+    }
+
+    impl TableKv for MockTableKv {
+        type Error = MockError;
+        type ScanIter = MemoryScanIter;
+        type WriteBatch = MemoryWriteBatch;
+
+        fn table_exists(&self, table_name: &str) -> std::result::Result<bool, MockError> {
+            self.inner.table_exists(table_name).context(Memory)
+        }
+
+        fn create_table(&self, table_name: &str) -> std::result::Result<(), MockError> {
+            self.inner.create_table(table_name).context(Memory)
+        }
+
+        fn drop_table(&self, table_name: &str) -> std::result::Result<(), MockError> {
+            self.inner.drop_table(table_name).context(Memory)
+        }
+
+        fn truncate_table(&self, table_name: &str) -> std::result::Result<(), MockError> {
+            self.inner.truncate_table(table_name).context(Memory)
+        }
+
+        fn write(
+            &self,
+            ctx: WriteContext,
+            table_name: &str,
+            write_batch: MemoryWriteBatch,
+        ) -> std::result::Result<(), MockError> {
+            self.writes
+                .lock()
+                .unwrap()
+                .push((table_name.to_string(), ctx.durability));
+            let is_wal_shard = table_name.starts_with("wal_");
+            if is_wal_shard {
+                self.wal_writes.fetch_add(1, Ordering::Relaxed);
+                self.wal_write_durabilities
+                    .lock()
+                    .unwrap()
+                    .push(ctx.durability);
+            }
+            if is_wal_shard
+                && self
+                    .failures
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| v.checked_sub(1))
+                    .is_ok()
+            {
+                return Injected {
+                    retryable: self.retryable,
+                }
+                .fail();
+            }
+
+            self.inner
+                .write(ctx, table_name, write_batch)
+                .context(Memory)
+        }
+
+        fn scan(
+            &self,
+            ctx: ScanContext,
+            table_name: &str,
+            request: ScanRequest,
+        ) -> std::result::Result<MemoryScanIter, MockError> {
+            self.scan_batch_sizes.lock().unwrap().push(ctx.batch_size);
+            if table_name.starts_with("wal_") {
+                if let Some(timeout) = *self.scan_failure.lock().unwrap() {
+                    return InjectedScan { timeout }.fail();
+                }
+            }
+            self.inner.scan(ctx, table_name, request).context(Memory)
+        }
+
+        fn get(
+            &self,
+            table_name: &str,
+            key: &[u8],
+        ) -> std::result::Result<Option<Vec<u8>>, MockError> {
+            self.inner.get(table_name, key).context(Memory)
+        }
+    }
+
+    #[test]
+    fn test_write_log_durability() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::new(true);
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+            // Create the table unit first.
+            write_test_payloads(&namespace, location, 1000, 1001).await;
+            table_kv.take_wal_write_durabilities();
+
+            for durability in [manager::Durability::Buffered, manager::Durability::Durable] {
+                let log_batch = new_test_log_batch(location, 1001, 1002);
+                let write_ctx = manager::WriteContext {
+                    durability,
+                    ..Default::default()
+                };
+                namespace.write_log(&write_ctx, &log_batch).await.unwrap();
+            }
+
+            assert_eq!(
+                vec![Durability::Buffered, Durability::Durable],
+                table_kv.take_wal_write_durabilities()
+            );
+        });
+    }
+
+    #[test]
+    fn test_delete_entries_meta_durability() {
+        let runtime = new_runtime();
+
+        runtime.block_on(async {
+            for eager in [false, true] {
+                let table_kv = MockTableKv::new(true);
+                let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone())
+                    .eager_clean_deleted_logs(eager)
+                    .build();
+                let location = Location::new(DEFAULT_SHARD_ID, 123);
+                let seq = write_test_payloads(&namespace, location, 1000, 1004).await;
+                table_kv.take_writes();
+
+                namespace.delete_entries(location, seq).await.unwrap();
+
+                // The meta is always written durably, before any record is deleted.
+                let writes = table_kv.take_writes();
+                let (meta_table, meta_durability) = &writes[0];
+                assert!(meta_table.starts_with("table_unit_meta_"), "{:?}", writes);
+                assert_eq!(Durability::Durable, *meta_durability);
+                let record_deletes = &writes[1..];
+                assert_eq!(eager, !record_deletes.is_empty(), "{:?}", writes);
+                assert!(
+                    record_deletes
+                        .iter()
+                        .all(|(table_name, _)| table_name.starts_with("wal_")),
+                    "{:?}",
+                    writes
+                );
+
+                namespace.close().await.unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn test_scan_timeout_error() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::new(true);
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+            write_test_payloads(&namespace, location, 1000, 1005).await;
+
+            let read_ctx = ReadContext::default();
+            let read_req = ReadRequest {
+                location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            // Open the table unit by a new namespace, which scans the wal shard tables.
+            for (timeout, expect_timeout) in [(true, true), (false, false)] {
+                let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+                table_kv.fail_scans(Some(timeout));
+                let err = namespace.read_log(&read_ctx, &read_req).await.unwrap_err();
+                assert_eq!(expect_timeout, err.is_scan_timeout(), "err:{}", err);
+                table_kv.fail_scans(None);
+            }
+
+            // The read succeeds once the scan doesn't timeout.
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let mut iter = namespace.read_log(&read_ctx, &read_req).await.unwrap();
+            let mut num_logs = 0;
+            while iter.next_log_entry().unwrap().is_some() {
+                num_logs += 1;
+            }
+            assert_eq!(5, num_logs);
+        });
+    }
+
+    #[test]
+    fn test_write_log_retry() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::new(true);
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+            // Fail once and then succeed.
+            table_kv.fail_next_writes(1);
+            let seq1 = write_test_payloads(&namespace, location, 1000, 1005).await;
+            assert_eq!(0, table_kv.remaining_failures());
+            let seq2 = write_test_payloads(&namespace, location, 1005, 1010).await;
+            // Sequences are not re-allocated on retry.
+            assert_eq!(seq1 + 5, seq2);
+
+            let read_ctx = ReadContext::default();
+            let read_req = ReadRequest {
+                location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            let mut iter = namespace.read_log(&read_ctx, &read_req).await.unwrap();
+            let decoder = TestPayloadDecoder;
+            let mut expect_val = 1000;
+            while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                let mut payload = log_entry.payload;
+                assert_eq!(expect_val, decoder.decode(&mut payload).unwrap().val);
+                expect_val += 1;
+            }
+            assert_eq!(1010, expect_val);
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_write_log_no_retry() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::new(false);
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+            table_kv.fail_next_writes(2);
+            let log_entries = (1000..1005).collect::<Vec<u32>>();
+            let log_batch = LogBatchEncoder::create(location)
+                .encode_batch::<TestPayload, u32>(&log_entries)
+                .unwrap();
+            let write_ctx = manager::WriteContext::default();
+            assert!(namespace.write_log(&write_ctx, &log_batch).await.is_err());
+            // Non-retryable error fails immediately.
+            assert_eq!(1, table_kv.remaining_failures());
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_region_write_rate_limit() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let limited_region = 1;
+            let namespace = NamespaceMocker::new(table_kv, runtime.clone())
+                .region_write_limits(vec![RegionWriteLimit {
+                    region_id: limited_region,
+                    bytes_per_sec: None,
+                    entries_per_sec: Some(200),
+                }])
+                .build();
+
+            // Write 30 batches of 10 entries to each region.
+            let write_region = |region_id: RegionId| {
+                let location = Location::new(region_id as u32, 1001);
+                let namespace = &namespace;
+                async move {
+                    let begin = Instant::now();
+                    for i in 0..30 {
+                        write_test_payloads(namespace, location, i * 10, i * 10 + 10).await;
+                    }
+                    begin.elapsed()
+                }
+            };
+
+            // The first 200 entries of the limited region are free, and the left
+            // 100 entries take about 500ms, minus the time refilled by the writes.
+            let elapsed = write_region(limited_region).await;
+            assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+
+            // Other regions are never limited.
+            assert!(!namespace.inner.write_limiters.contains_key(&0));
+            write_region(0).await;
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_write_log_coalesced() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone())
+                .write_coalesce_window(Some(Duration::from_millis(20)))
+                .build();
+            let table_ids = [1001, 1002, 1003, 1004];
+            let batches_per_table = 5;
+
+            // Write batches of all tables concurrently, tables in the same region share
+            // the same wal shard table.
+            let writes = table_ids.iter().flat_map(|table_id| {
+                let location = Location::new(DEFAULT_SHARD_ID, *table_id);
+                let namespace = &namespace;
+                (0..batches_per_table).map(move |i| async move {
+                    let start = i * 10;
+                    let sequence =
+                        write_test_payloads(namespace, location, start, start + 10).await;
+                    (location, sequence)
+                })
+            });
+            let results = futures::future::join_all(writes).await;
+
+            let total_writes = table_ids.len() * batches_per_table as usize;
+            assert!(table_kv.wal_writes() < total_writes);
+
+            for table_id in table_ids {
+                let location = Location::new(DEFAULT_SHARD_ID, table_id);
+                // Each writer gets the max sequence of its own batch.
+                let mut sequences = results
+                    .iter()
+                    .filter(|(loc, _)| *loc == location)
+                    .map(|(_, seq)| *seq)
+                    .collect::<Vec<_>>();
+                sequences.sort_unstable();
+                let expect_sequences = (1..=batches_per_table as u64)
+                    .map(|i| i * 10)
+                    .collect::<Vec<_>>();
+                assert_eq!(expect_sequences, sequences);
+
+                let read_req = ReadRequest {
+                    location,
+                    start: ReadBoundary::Min,
+                    end: ReadBoundary::Max,
+                };
+                let mut iter = namespace
+                    .read_log(&ReadContext::default(), &read_req)
+                    .await
+                    .unwrap();
+                let decoder = TestPayloadDecoder;
+                let mut read_sequences = Vec::new();
+                let mut vals = Vec::new();
+                while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                    assert_eq!(table_id, log_entry.table_id);
+                    read_sequences.push(log_entry.sequence);
+                    let mut payload = log_entry.payload;
+                    vals.push(decoder.decode(&mut payload).unwrap().val);
+                }
+                let expect_read_sequences = (1..=batches_per_table as u64 * 10).collect::<Vec<_>>();
+                assert_eq!(expect_read_sequences, read_sequences);
+                vals.sort_unstable();
+                assert_eq!((0..batches_per_table * 10).collect::<Vec<_>>(), vals);
+            }
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_read_log_scan_batch_size() {
+        let runtime = new_runtime();
+        let table_kv = MockTableKv::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv.clone(), runtime.clone()).build();
+            let location = Location::new(DEFAULT_SHARD_ID, 123);
+            write_test_payloads(&namespace, location, 1000, 1005).await;
+
+            let read_req = ReadRequest {
+                location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            for batch_size in [7, 1024] {
+                let read_ctx = ReadContext {
+                    batch_size,
+                    ..Default::default()
+                };
+                table_kv.take_scan_batch_sizes();
+                let mut iter = namespace.read_log(&read_ctx, &read_req).await.unwrap();
+                let mut count = 0;
+                while iter.next_log_entry().unwrap().is_some() {
+                    count += 1;
+                }
+                assert_eq!(5, count);
+
+                let scan_batch_sizes = table_kv.take_scan_batch_sizes();
+                assert!(!scan_batch_sizes.is_empty());
+                for v in scan_batch_sizes {
+                    assert_eq!(batch_size as i32, v);
+                }
+            }
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_merged_log_iterator() {
+        let runtime = new_runtime();
+        let table_kv = MemoryImpl::default();
+
+        runtime.block_on(async {
+            let namespace = NamespaceMocker::new(table_kv, runtime.clone()).build();
+            let locations = [
+                Location::new(1, 101),
+                Location::new(2, 102),
+                // No logs are written to this region.
+                Location::new(3, 103),
+            ];
+
+            // Sequences of region 1 are [1, 4], sequences of region 2 are [1, 2].
+            write_test_payloads(&namespace, locations[0], 1000, 1002).await;
+            write_test_payloads(&namespace, locations[1], 2000, 2001).await;
+            write_test_payloads(&namespace, locations[0], 1002, 1004).await;
+            write_test_payloads(&namespace, locations[1], 2001, 2002).await;
+
+            let read_ctx = ReadContext::default();
+            let mut iters = Vec::with_capacity(locations.len());
+            for location in locations {
+                let read_req = ReadRequest {
+                    location,
+                    start: ReadBoundary::Min,
+                    end: ReadBoundary::Max,
+                };
+                iters.push(namespace.read_log(&read_ctx, &read_req).await.unwrap());
+            }
+
+            let mut merged_iter = MergedLogIterator::new(iters);
+            let decoder = TestPayloadDecoder;
+            let mut entries = Vec::new();
+            while let Some((region_id, log_entry)) = merged_iter.next_log_entry().unwrap() {
+                let mut payload = log_entry.payload;
+                let val = decoder.decode(&mut payload).unwrap().val;
+                entries.push((region_id, log_entry.sequence, val));
+            }
+
+            let expect_entries = vec![
+                (1, 1, 1000),
+                (2, 1, 2000),
+                (1, 2, 1001),
+                (2, 2, 2001),
+                (1, 3, 1002),
+                (1, 4, 1003),
+            ];
+            assert_eq!(expect_entries, entries);
+            // The iterator keeps returning None after exhausted.
+            assert!(merged_iter.next_log_entry().unwrap().is_none());
+
+            namespace.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_read_log_skip_missing_bucket() {
+        let table_kv = MemoryImpl::default();
+        let (region_id, table_id) = (1, 101);
+        let buckets = [
+            // Gmt time: 2022-03-20 00:00:00
+            new_timed_bucket(Timestamp::new(1647734400000)),
+            // Gmt time: 2022-03-21 00:00:00
+            new_timed_bucket(Timestamp::new(1647820800000)),
+            // Gmt time: 2022-03-22 00:00:00
+            new_timed_bucket(Timestamp::new(1647907200000)),
+        ];
+
+        // Each bucket holds two logs.
+        let log_encoding = CommonLogEncoding::newest();
+        let (mut key_buf, mut value_buf) = (BytesMut::new(), BytesMut::new());
+        let mut sequence = 1;
+        for bucket in &buckets {
+            let table_name = bucket.wal_shard_table(region_id);
+            table_kv.create_table(table_name).unwrap();
+
+            let mut wb = MemoryWriteBatch::default();
+            for _ in 0..2 {
+                let log_key = CommonLogKey::new(region_id, table_id, sequence);
+                log_encoding.encode_key(&mut key_buf, &log_key).unwrap();
+                let payload = TestPayload {
+                    val: sequence as u32,
+                };
+                log_encoding.encode_value(&mut value_buf, &payload).unwrap();
+                wb.insert(&key_buf, &value_buf);
+
+                sequence += 1;
+            }
+            table_kv
+                .write(WriteContext::default(), table_name, wb)
+                .unwrap();
+        }
+
+        let mut iter = TableLogIterator::new(
+            buckets.to_vec(),
+            CommonLogKey::new(region_id, table_id, common_types::MIN_SEQUENCE_NUMBER),
+            CommonLogKey::new(region_id, table_id, common_types::MAX_SEQUENCE_NUMBER),
+            ScanContext::default(),
+            table_kv.clone(),
+        );
+
+        let log_entry = iter.next_log_entry().unwrap().unwrap();
+        assert_eq!(1, log_entry.sequence);
+
+        // Drop the middle bucket during iteration.
+        table_kv
+            .drop_table(buckets[1].wal_shard_table(region_id))
+            .unwrap();
+
+        let decoder = TestPayloadDecoder;
+        let mut sequences = Vec::new();
+        while let Some(log_entry) = iter.next_log_entry().unwrap() {
+            let mut payload = log_entry.payload;
+            let val = decoder.decode(&mut payload).unwrap().val;
+            assert_eq!(log_entry.sequence, val as u64);
+            sequences.push(log_entry.sequence);
+        }
+        assert_eq!(vec![2, 5, 6], sequences);
+    }
+
+    #[test]
+    fn test_scan_log_with_table_filter() {
+        let table_kv = MemoryImpl::default();
+        let region_id = 1;
+        // Gmt time: 2022-03-20 00:00:00
+        let bucket = new_timed_bucket(Timestamp::new(1647734400000));
+        let table_name = bucket.wal_shard_table(region_id);
+        table_kv.create_table(table_name).unwrap();
+
+        // Tables 101, 102 and 103 share the region and write in turn.
+        let log_encoding = CommonLogEncoding::newest();
+        let (mut key_buf, mut value_buf) = (BytesMut::new(), BytesMut::new());
+        let mut wb = MemoryWriteBatch::default();
+        for sequence in 1..=9 {
+            let table_id = 101 + (sequence - 1) % 3;
+            let log_key = CommonLogKey::new(region_id, table_id, sequence);
+            log_encoding.encode_key(&mut key_buf, &log_key).unwrap();
+            let payload = TestPayload {
+                val: sequence as u32,
+            };
+            log_encoding.encode_value(&mut value_buf, &payload).unwrap();
+            wb.insert(&key_buf, &value_buf);
+        }
+        table_kv
+            .write(WriteContext::default(), table_name, wb)
+            .unwrap();
+
+        let mut iter = TableLogIterator::new(
+            vec![bucket],
+            CommonLogKey::new(region_id, TableId::MIN, SequenceNumber::MIN),
+            CommonLogKey::new(region_id, TableId::MAX, SequenceNumber::MAX),
+            ScanContext::default(),
+            table_kv,
+        )
+        .with_table_filter(102);
+
+        let decoder = TestPayloadDecoder;
+        let mut sequences = Vec::new();
+        while let Some(log_entry) = iter.next_log_entry().unwrap() {
+            assert_eq!(102, log_entry.table_id);
+            let mut payload = log_entry.payload;
+            let val = decoder.decode(&mut payload).unwrap().val;
+            assert_eq!(log_entry.sequence, val as u64);
+            sequences.push(log_entry.sequence);
+        }
+        assert_eq!(vec![2, 5, 8], sequences);
+        assert!(iter.next_log_entry().unwrap().is_none());
+    }
 }
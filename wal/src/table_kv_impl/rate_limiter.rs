@@ -0,0 +1,195 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Write rate limiters of regions.
+//!
+//! Regions share the same table kv backend, so a region written too fast may
+//! starve others. A limited region waits for enough tokens before writing its
+//! logs, which applies backpressure to its writers.
+
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{log_batch::LogWriteBatch, table_kv_impl::model::RegionWriteLimit};
+
+/// Token bucket refilled at a fixed rate.
+///
+/// The bucket holds at most one second of tokens, and an acquisition larger
+/// than the available tokens is allowed to overdraw the bucket, so a request
+/// is never starved by its size, and the followers wait for the debt to be
+/// paid off.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Tokens refilled per second.
+    rate: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// Available tokens, negative if overdrawn.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a bucket refilled with `rate` (> 0) tokens per second, it starts
+    /// full.
+    pub fn new(rate: u64) -> Self {
+        assert!(rate > 0);
+
+        let rate = rate as f64;
+        Self {
+            rate,
+            state: Mutex::new(BucketState {
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take `tokens` from the bucket and returns the time to wait before using
+    /// them.
+    fn reserve(&self, tokens: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(state.last_refill);
+        state.tokens = (state.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        state.last_refill = now;
+
+        state.tokens -= tokens as f64;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+
+    /// Acquire `tokens` from the bucket, returns the time waited.
+    pub async fn acquire(&self, tokens: u64) -> Duration {
+        let wait = self.reserve(tokens);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        wait
+    }
+}
+
+/// Limits the bytes and entries written to a region per second.
+#[derive(Debug)]
+pub struct RegionWriteLimiter {
+    bytes: Option<TokenBucket>,
+    entries: Option<TokenBucket>,
+}
+
+impl RegionWriteLimiter {
+    /// Create the limiter, returns None if `limit` limits nothing.
+    pub fn new(limit: &RegionWriteLimit) -> Option<Self> {
+        let bytes = limit.bytes_per_sec.filter(|v| *v > 0).map(TokenBucket::new);
+        let entries = limit
+            .entries_per_sec
+            .filter(|v| *v > 0)
+            .map(TokenBucket::new);
+        if bytes.is_none() && entries.is_none() {
+            return None;
+        }
+
+        Some(Self { bytes, entries })
+    }
+
+    /// Wait until the `batch` is allowed to be written, returns the time
+    /// waited.
+    pub async fn acquire(&self, batch: &LogWriteBatch) -> Duration {
+        // Reserve both before waiting, so the waits overlap.
+        let bytes_wait = self.bytes.as_ref().map_or(Duration::ZERO, |bucket| {
+            let bytes = batch.entries.iter().map(|v| v.payload.len() as u64).sum();
+            bucket.reserve(bytes)
+        });
+        let entries_wait = self.entries.as_ref().map_or(Duration::ZERO, |bucket| {
+            bucket.reserve(batch.entries.len() as u64)
+        });
+
+        let wait = bytes_wait.max(entries_wait);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_types::table::Location;
+
+    use super::*;
+    use crate::log_batch::LogWriteEntry;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket_converges_to_rate() {
+        let rate = 2000;
+        let bucket = TokenBucket::new(rate);
+
+        // The initial tokens are available immediately.
+        assert_eq!(Duration::ZERO, bucket.acquire(rate).await);
+
+        let begin = Instant::now();
+        let mut total_wait = Duration::ZERO;
+        for _ in 0..20 {
+            total_wait += bucket.acquire(50).await;
+        }
+        let elapsed = begin.elapsed();
+
+        // 1000 tokens at 2000/s takes 500ms, the paused clock only advances by
+        // the waits, which may be rounded up to the timer resolution.
+        let expect = Duration::from_millis(500);
+        assert!(
+            total_wait >= expect * 99 / 100,
+            "total_wait:{:?}",
+            total_wait
+        );
+        assert!(
+            total_wait <= expect * 101 / 100,
+            "total_wait:{:?}",
+            total_wait
+        );
+        assert!(elapsed >= expect * 99 / 100, "elapsed:{:?}", elapsed);
+        assert!(elapsed < expect * 11 / 10, "elapsed:{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_region_write_limiter() {
+        let limit = RegionWriteLimit {
+            region_id: 0,
+            bytes_per_sec: None,
+            entries_per_sec: None,
+        };
+        assert!(RegionWriteLimiter::new(&limit).is_none());
+
+        let limit = RegionWriteLimit {
+            region_id: 0,
+            bytes_per_sec: Some(20_000),
+            entries_per_sec: Some(100),
+        };
+        let limiter = RegionWriteLimiter::new(&limit).unwrap();
+        let mut batch = LogWriteBatch::new(Location::new(0, 1));
+        for _ in 0..10 {
+            batch.push(LogWriteEntry {
+                payload: vec![0; 100],
+            });
+        }
+
+        // Write 150 entries of 15KB, the entries limit is the bottleneck: the
+        // first 100 entries are free and the left 50 entries take 500ms, while
+        // the bytes never wait.
+        let begin = Instant::now();
+        for _ in 0..15 {
+            limiter.acquire(&batch).await;
+        }
+        let elapsed = begin.elapsed();
+        let expect = Duration::from_millis(500);
+        assert!(elapsed >= expect * 99 / 100, "elapsed:{:?}", elapsed);
+        assert!(elapsed < expect * 11 / 10, "elapsed:{:?}", elapsed);
+    }
+}
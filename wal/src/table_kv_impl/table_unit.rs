@@ -3,22 +3,27 @@
 //! Table unit in wal.
 
 use std::{
-    cmp,
+    cmp::{self, Reverse},
+    collections::{BTreeMap, BinaryHeap},
     convert::TryInto,
-    mem,
+    fmt, mem,
+    ops::RangeInclusive,
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    thread,
+    time::{Duration, Instant},
 };
 
 use common_types::{bytes::BytesMut, table::TableId};
 use common_util::{define_result, runtime::Runtime};
-use log::debug;
+use log::{debug, error, info, warn};
+use serde_derive::{Deserialize, Serialize};
 use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
 use table_kv::{
-    KeyBoundary, ScanContext, ScanIter, ScanRequest, TableError, TableKv, WriteBatch, WriteContext,
+    Durability, KeyBoundary, ScanContext, ScanIter, ScanRequest, TableError, TableKv, WriteBatch,
+    WriteContext,
 };
 use tokio::sync::Mutex;
 
@@ -26,7 +31,10 @@ use crate::{
     kv_encoder::{CommonLogEncoding, CommonLogKey},
     log_batch::{LogEntry, LogWriteBatch},
     manager::{self, ReadContext, ReadRequest, RegionId, SequenceNumber, SyncLogIterator},
-    table_kv_impl::{encoding, model::TableUnitEntry, namespace::BucketRef, WalRuntimes},
+    table_kv_impl::{
+        encoding, metrics::CLEAN_BATCH_SIZE_GAUGE, model::TableUnitEntry, namespace::BucketRef,
+        write_coalescer::LogWriteCoalescer, WalRuntimes,
+    },
 };
 
 #[derive(Debug, Snafu)]
@@ -57,6 +65,11 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[snafu(display("Scan table timeout, err:{}", source))]
+    ScanTimeout {
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     #[snafu(display("Failed to write value, key:{}, err:{}", key, source))]
     WriteValue {
         key: String,
@@ -75,6 +88,34 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Sequences are not reserved, region_id:{}, table_id:{}, start_sequence:{}, number:{}.\nBacktrace:\n{}",
+        region_id,
+        table_id,
+        start_sequence,
+        number,
+        backtrace
+    ))]
+    SequenceNotReserved {
+        region_id: RegionId,
+        table_id: TableId,
+        start_sequence: SequenceNumber,
+        number: u64,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Number of sequences to reserve must be positive, region_id:{}, table_id:{}.\nBacktrace:\n{}",
+        region_id,
+        table_id,
+        backtrace
+    ))]
+    ReserveZeroSequences {
+        region_id: RegionId,
+        table_id: TableId,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Failed to write log to table, region_id:{}, err:{}",
         region_id,
@@ -95,6 +136,22 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Invalid range to delete, region_id:{}, table_id:{}, from:{}, to:{}.\nBacktrace:\n{}",
+        region_id,
+        table_id,
+        from,
+        to,
+        backtrace
+    ))]
+    InvalidDeleteRange {
+        region_id: RegionId,
+        table_id: TableId,
+        from: SequenceNumber,
+        to: SequenceNumber,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to execute in runtime, err:{}", source))]
     RuntimeExec { source: common_util::runtime::Error },
 
@@ -103,12 +160,79 @@ pub enum Error {
         region_id: RegionId,
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+
+    #[snafu(display(
+        "Failed to clean logs of buckets, region_id:{}, table_id:{}, failed:{}, total:{}, errs:{}.\nBacktrace:\n{}",
+        region_id,
+        table_id,
+        failed,
+        total,
+        msg,
+        backtrace
+    ))]
+    CleanBuckets {
+        region_id: RegionId,
+        table_id: TableId,
+        failed: usize,
+        total: usize,
+        msg: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Checkpoint of log iterator mismatches, region_id:{}, msg:{}.\nBacktrace:\n{}",
+        region_id,
+        msg,
+        backtrace
+    ))]
+    CheckpointMismatch {
+        region_id: RegionId,
+        msg: String,
+        backtrace: Backtrace,
+    },
 }
 
 define_result!(Error);
 
-/// Default batch size (100) to clean records.
-const DEFAULT_CLEAN_BATCH_SIZE: i32 = 100;
+impl Error {
+    /// Returns true if a scan of table kv is timeout, reading again with a
+    /// longer [ScanContext::timeout] may succeed.
+    pub fn is_scan_timeout(&self) -> bool {
+        matches!(self, Error::ScanTimeout { .. })
+    }
+}
+
+/// Convert the scan error of table kv into [Error], timeout is distinguished
+/// from other failures.
+fn scan_error<E: TableError + Send + Sync + 'static>(e: E) -> Error {
+    if e.is_timeout() {
+        Error::ScanTimeout {
+            source: Box::new(e),
+        }
+    } else {
+        Error::Scan {
+            source: Box::new(e),
+        }
+    }
+}
+
+/// Default timeout (10s) of scanning records to clean.
+const DEFAULT_CLEAN_SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default min batch size (10) to clean records.
+const DEFAULT_CLEAN_MIN_BATCH_SIZE: usize = 10;
+/// Default max batch size (1000) to clean records.
+const DEFAULT_CLEAN_MAX_BATCH_SIZE: usize = 1000;
+/// Default threshold (500ms) of a slow delete write.
+const DEFAULT_CLEAN_SLOW_WRITE_THRESHOLD: Duration = Duration::from_millis(500);
+/// Max retry times of writing logs on retryable errors.
+const WRITE_LOG_MAX_RETRY: u32 = 3;
+/// Initial backoff of retrying to write logs, doubled on each retry.
+const WRITE_LOG_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+/// Default fraction (0.9) of max sequence number to start warning about the
+/// sequence overflow.
+pub const DEFAULT_SEQUENCE_WARN_RATIO: f64 = 0.9;
+/// Min interval (60s) between two warnings of sequence overflow.
+const SEQUENCE_WARN_INTERVAL: Duration = Duration::from_secs(60);
 
 struct TableUnitState {
     /// Region id of this table unit
@@ -121,6 +245,10 @@ struct TableUnitState {
     /// Last sequence (inclusive) of this table unit, update is protected by the
     /// `writer` lock.
     last_sequence: AtomicU64,
+    /// The last successful clean of the deleted logs, None if never cleaned.
+    last_clean: std::sync::Mutex<Option<LastClean>>,
+    /// Reads in progress, see [ActiveReads].
+    active_reads: Arc<ActiveReads>,
 }
 
 impl TableUnitState {
@@ -146,21 +274,225 @@ impl TableUnitState {
             start_sequence: self.start_sequence.load(Ordering::Relaxed),
         }
     }
+
+    /// Register a read from `request_start_sequence`, returns the start
+    /// sequence to read from, which is never before the start sequence of the
+    /// table unit, and the guard to unregister the read.
+    fn register_read(
+        &self,
+        request_start_sequence: SequenceNumber,
+    ) -> (SequenceNumber, ActiveReadGuard) {
+        let mut start_sequences = self.active_reads.start_sequences.lock().unwrap();
+        // The start sequence is loaded under the lock, so a concurrent clean
+        // either sees this read, or only cleans the logs before the loaded start
+        // sequence.
+        let start_sequence = cmp::max(self.start_sequence(), request_start_sequence);
+        *start_sequences.entry(start_sequence).or_default() += 1;
+
+        let guard = ActiveReadGuard {
+            active_reads: self.active_reads.clone(),
+            start_sequence,
+        };
+        (start_sequence, guard)
+    }
+
+    /// End sequence (exclusive) of the deleted logs which are safe to clean,
+    /// i.e. not read by any read in progress.
+    fn clean_end_sequence(&self) -> SequenceNumber {
+        let start_sequences = self.active_reads.start_sequences.lock().unwrap();
+        let start_sequence = self.start_sequence();
+        match start_sequences.keys().next() {
+            Some(min_read_start) => cmp::min(*min_read_start, start_sequence),
+            None => start_sequence,
+        }
+    }
+}
+
+/// Start sequences of the reads in progress of a table unit.
+///
+/// The storage doesn't provide snapshot isolation, so cleaning the deleted
+/// logs is coordinated with the reads instead: a read registers the start
+/// sequence it reads from, and the logs at or after the min registered start
+/// sequence are not cleaned until the read finishes, i.e. its iterator is
+/// dropped. So a read started before a clean never observes partially deleted
+/// logs, it returns the logs as if it read a snapshot taken at its start, even
+/// if they are marked deleted after that.
+///
+/// NOTICE: Deleting a middle range by [TableUnit::delete_entries_in_range] is
+/// not coordinated, as the range is deleted on the demand of the caller.
+#[derive(Debug, Default)]
+struct ActiveReads {
+    /// Number of reads in progress of each start sequence.
+    start_sequences: std::sync::Mutex<BTreeMap<SequenceNumber, usize>>,
+}
+
+/// Unregister the read from [ActiveReads] when dropped.
+#[derive(Debug)]
+struct ActiveReadGuard {
+    active_reads: Arc<ActiveReads>,
+    start_sequence: SequenceNumber,
+}
+
+impl Drop for ActiveReadGuard {
+    fn drop(&mut self) {
+        let mut start_sequences = self.active_reads.start_sequences.lock().unwrap();
+        if let Some(num_reads) = start_sequences.get_mut(&self.start_sequence) {
+            *num_reads -= 1;
+            if *num_reads == 0 {
+                start_sequences.remove(&self.start_sequence);
+            }
+        }
+    }
+}
+
+/// Progress of cleaning deleted logs, reported after each batch of records is
+/// deleted.
+#[derive(Debug, Clone, Copy)]
+pub struct CleanProgress<'a> {
+    /// Wal shard table the batch is deleted from.
+    pub table_name: &'a str,
+    /// Records deleted from all the buckets by this clean so far.
+    pub total_deleted: usize,
+}
+
+/// Callback invoked with the [CleanProgress] of a clean.
+#[derive(Clone)]
+pub struct CleanProgressCallback(Arc<dyn Fn(&CleanProgress) + Send + Sync>);
+
+impl CleanProgressCallback {
+    pub fn new(f: impl Fn(&CleanProgress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for CleanProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CleanProgressCallback").finish()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CleanContext {
     pub scan_timeout: Duration,
+    /// Initial batch size to delete records, the actual batch size is adjusted
+    /// between `min_batch_size` and `max_batch_size` according to the latency
+    /// of delete writes.
     pub batch_size: usize,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    /// Batch size shrinks if a delete write costs more than this threshold,
+    /// otherwise it grows.
+    pub slow_write_threshold: Duration,
+    /// Max number of buckets to clean concurrently, the records of one bucket
+    /// are always cleaned sequentially.
+    pub bucket_parallelism: usize,
+    /// Invoked between batches, after each batch is deleted, so a slow
+    /// callback delays the clean but never the writes.
+    pub progress_callback: Option<CleanProgressCallback>,
 }
 
 impl Default for CleanContext {
     fn default() -> Self {
         Self {
-            scan_timeout: Duration::from_secs(10),
-            batch_size: DEFAULT_CLEAN_BATCH_SIZE as usize,
+            scan_timeout: DEFAULT_CLEAN_SCAN_TIMEOUT,
+            batch_size: manager::DEFAULT_CLEAN_BATCH_SIZE,
+            min_batch_size: DEFAULT_CLEAN_MIN_BATCH_SIZE,
+            max_batch_size: DEFAULT_CLEAN_MAX_BATCH_SIZE,
+            slow_write_threshold: DEFAULT_CLEAN_SLOW_WRITE_THRESHOLD,
+            bucket_parallelism: 1,
+            progress_callback: None,
+        }
+    }
+}
+
+/// Batch size of cleaning records which adapts to the latency of the backend.
+///
+/// It grows additively while delete writes are fast and halves once a write is
+/// slower than the threshold, always bounded by `[min, max]`.
+#[derive(Debug)]
+struct AdaptiveBatchSize {
+    current: usize,
+    min: usize,
+    max: usize,
+    slow_write_threshold: Duration,
+}
+
+impl AdaptiveBatchSize {
+    fn new(ctx: &CleanContext) -> Self {
+        let min = cmp::max(ctx.min_batch_size, 1);
+        let max = cmp::max(ctx.max_batch_size, min);
+        let current = ctx.batch_size.clamp(min, max);
+        CLEAN_BATCH_SIZE_GAUGE.set(current as i64);
+
+        Self {
+            current,
+            min,
+            max,
+            slow_write_threshold: ctx.slow_write_threshold,
         }
     }
+
+    #[inline]
+    fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Adjust the batch size by the `cost` of last delete write.
+    fn observe(&mut self, cost: Duration) {
+        let next = if cost > self.slow_write_threshold {
+            self.current / 2
+        } else {
+            self.current + cmp::max(self.current / 4, 1)
+        };
+        self.current = next.clamp(self.min, self.max);
+
+        CLEAN_BATCH_SIZE_GAUGE.set(self.current as i64);
+    }
+}
+
+/// Info of the last successful clean of the deleted logs of a table unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastClean {
+    /// Logs with sequence less than it are cleaned.
+    pub end_sequence: SequenceNumber,
+    /// Records deleted from all the buckets by the clean.
+    pub deleted: usize,
+    /// When the clean finished.
+    pub finished_at: Instant,
+}
+
+/// Operational overview of a table unit, see [TableUnit::stats].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableUnitStats {
+    pub region_id: RegionId,
+    pub table_id: TableId,
+    pub start_sequence: SequenceNumber,
+    pub last_sequence: SequenceNumber,
+    /// Number of logs in `[start_sequence, last_sequence]`, which is
+    /// approximate as the range deleted in the middle of the table unit is
+    /// still counted.
+    pub approximate_entries: u64,
+    /// Number of the buckets storing the logs.
+    pub num_buckets: usize,
+    pub last_clean: Option<LastClean>,
+}
+
+/// Sequence range of logs available in a table unit.
+///
+/// The range is empty if `start > last`, e.g. all logs have been deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceRange {
+    /// Start sequence (inclusive).
+    pub start: SequenceNumber,
+    /// Last sequence (inclusive).
+    pub last: SequenceNumber,
+}
+
+impl SequenceRange {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start > self.last
+    }
 }
 
 /// Table unit can be viewed as an append only log file.
@@ -176,6 +508,13 @@ impl TableUnit {
     /// ensure the meta data of this table unit is stored in
     /// `table_unit_meta_table`, and the wal log records are stored in
     /// `buckets`.
+    ///
+    /// If `repair_missing_meta` is true and the meta data is missing while
+    /// some logs of the table unit exist (e.g. the write of the meta data is
+    /// lost), the meta data is rebuilt with the oldest log as the start
+    /// sequence and written back. Note that the deleted logs whose records are
+    /// not cleaned yet become visible again after repaired.
+    #[allow(clippy::too_many_arguments)]
     pub async fn open<T: TableKv>(
         runtimes: WalRuntimes,
         table_kv: &T,
@@ -185,16 +524,45 @@ impl TableUnit {
         table_id: TableId,
         // Buckets ordered by time.
         buckets: Vec<BucketRef>,
+        repair_missing_meta: bool,
     ) -> Result<Option<TableUnit>> {
         let table_kv = table_kv.clone();
         let table_unit_meta_table = table_unit_meta_table.to_string();
         let rt = runtimes.bg_runtime.clone();
 
         rt.spawn_blocking(move || {
-            // Load of create table unit entry.
+            // Load or repair table unit entry.
+            let mut writer = TableUnitWriter::default();
             let table_unit_entry =
                 match Self::load_table_unit_entry(&table_kv, &table_unit_meta_table, table_id)? {
                     Some(v) => v,
+                    None if repair_missing_meta => {
+                        let first_sequence = Self::load_first_sequence(
+                            &table_kv,
+                            scan_ctx.clone(),
+                            region_id,
+                            table_id,
+                            &buckets,
+                        )?;
+                        let start_sequence = match first_sequence {
+                            Some(v) => v,
+                            None => return Ok(None),
+                        };
+
+                        warn!(
+                            "Repair missing table unit entry, region_id:{}, table_id:{}, start_sequence:{}",
+                            region_id, table_id, start_sequence
+                        );
+                        let entry = TableUnitEntry {
+                            table_id,
+                            start_sequence,
+                        };
+                        writer.insert_or_load_table_unit_entry(
+                            &table_kv,
+                            &table_unit_meta_table,
+                            entry,
+                        )?
+                    }
                     None => return Ok(None),
                 };
 
@@ -209,8 +577,10 @@ impl TableUnit {
                     table_id,
                     start_sequence: AtomicU64::new(table_unit_entry.start_sequence),
                     last_sequence: AtomicU64::new(last_sequence),
+                    last_clean: Default::default(),
+                    active_reads: Default::default(),
                 },
-                writer: Mutex::new(TableUnitWriter),
+                writer: Mutex::new(writer),
             }))
         })
         .await
@@ -237,7 +607,7 @@ impl TableUnit {
 
         rt.spawn_blocking(move || {
             // Load of create table unit entry.
-            let mut writer = TableUnitWriter;
+            let mut writer = TableUnitWriter::default();
             let table_unit_entry =
                 match Self::load_table_unit_entry(&table_kv, &table_unit_meta_table, table_id)? {
                     Some(v) => v,
@@ -262,6 +632,8 @@ impl TableUnit {
                     table_id,
                     start_sequence: AtomicU64::new(table_unit_entry.start_sequence),
                     last_sequence: AtomicU64::new(last_sequence),
+                    last_clean: Default::default(),
+                    active_reads: Default::default(),
                 },
                 writer: Mutex::new(writer),
             })
@@ -270,6 +642,7 @@ impl TableUnit {
         .context(RuntimeExec)?
     }
 
+    /// Write logs of `log_batch`, returns the max sequence of the logs.
     pub async fn write_log<T: TableKv>(
         &self,
         table_kv: &T,
@@ -277,6 +650,20 @@ impl TableUnit {
         ctx: &manager::WriteContext,
         log_batch: &LogWriteBatch,
     ) -> Result<SequenceNumber> {
+        self.write_log_with_sequences(table_kv, bucket, ctx, log_batch)
+            .await
+            .map(|sequences| *sequences.end())
+    }
+
+    /// Similar to `write_log()`, but returns the sequences of the logs, the
+    /// i-th entry of `log_batch` is assigned the i-th sequence of the range.
+    pub async fn write_log_with_sequences<T: TableKv>(
+        &self,
+        table_kv: &T,
+        bucket: &BucketRef,
+        ctx: &manager::WriteContext,
+        log_batch: &LogWriteBatch,
+    ) -> Result<RangeInclusive<SequenceNumber>> {
         let mut writer = self.writer.lock().await;
         writer
             .write_log(
@@ -290,6 +677,113 @@ impl TableUnit {
             .await
     }
 
+    /// Write logs through the `coalescer`, so the logs may be written to
+    /// table kv together with logs of other table units.
+    pub async fn write_log_coalesced<T: TableKv>(
+        &self,
+        coalescer: &Arc<LogWriteCoalescer<T>>,
+        bucket: &BucketRef,
+        log_batch: &LogWriteBatch,
+    ) -> Result<SequenceNumber> {
+        self.write_log_coalesced_with_sequences(coalescer, bucket, log_batch)
+            .await
+            .map(|sequences| *sequences.end())
+    }
+
+    /// Similar to `write_log_coalesced()`, but returns the sequences of the
+    /// logs like `write_log_with_sequences()`.
+    pub async fn write_log_coalesced_with_sequences<T: TableKv>(
+        &self,
+        coalescer: &Arc<LogWriteCoalescer<T>>,
+        bucket: &BucketRef,
+        log_batch: &LogWriteBatch,
+    ) -> Result<RangeInclusive<SequenceNumber>> {
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_log_coalesced(coalescer, &self.state, bucket, log_batch)
+            .await
+    }
+
+    /// Reserve a continuous range of `number` sequences and returns the start
+    /// sequence of the range, the logs of the range should be written later by
+    /// `commit_sequences()`.
+    ///
+    /// The last sequence of the table unit is advanced on reservation, but the
+    /// reserved sequences are not persisted until committed, so a range never
+    /// committed is simply reused after the table unit is reopened.
+    pub async fn reserve_sequences(&self, number: u64) -> Result<SequenceNumber> {
+        ensure!(
+            number > 0,
+            ReserveZeroSequences {
+                region_id: self.state.region_id,
+                table_id: self.state.table_id,
+            }
+        );
+
+        let mut writer = self.writer.lock().await;
+        let start_sequence = writer.alloc_sequence_num(&self.state, number)?;
+        writer.reserved_sequences.insert(start_sequence, number);
+
+        Ok(start_sequence)
+    }
+
+    /// Write logs of `log_batch` with the sequences reserved by
+    /// `reserve_sequences()`, the `log_batch` must fill the whole reserved
+    /// range starting from `start_sequence`. Returns the max sequence of the
+    /// written logs.
+    ///
+    /// Ranges can be committed in any order, and the writer lock is not held
+    /// during writing, so the commits of different ranges run concurrently.
+    /// The range is still reserved if the commit fails, so it can be retried.
+    pub async fn commit_sequences<T: TableKv>(
+        &self,
+        table_kv: &T,
+        bucket: &BucketRef,
+        start_sequence: SequenceNumber,
+        log_batch: &LogWriteBatch,
+    ) -> Result<SequenceNumber> {
+        let number = log_batch.len() as u64;
+        {
+            let mut writer = self.writer.lock().await;
+            ensure!(
+                writer.reserved_sequences.get(&start_sequence) == Some(&number),
+                SequenceNotReserved {
+                    region_id: self.state.region_id,
+                    table_id: self.state.table_id,
+                    start_sequence,
+                    number,
+                }
+            );
+            // Take the range so it can't be committed twice concurrently.
+            writer.reserved_sequences.remove(&start_sequence);
+        }
+
+        let region_id = self.state.region_id;
+        let table_name = bucket.wal_shard_table(region_id);
+        let res = write_logs_with_retry(
+            &self.runtimes.write_runtime,
+            table_kv,
+            &WriteContext::default(),
+            table_name,
+            region_id,
+            || TableUnitWriter::build_log_write_batch::<T>(&self.state, start_sequence, log_batch),
+        )
+        .await;
+        if res.is_err() {
+            let mut writer = self.writer.lock().await;
+            writer.reserved_sequences.insert(start_sequence, number);
+        }
+        res?;
+
+        Ok(start_sequence + number - 1)
+    }
+
+    /// Returns the number of reserved but not committed sequences.
+    pub async fn num_reserved_sequences(&self) -> u64 {
+        let writer = self.writer.lock().await;
+        writer.reserved_sequences.values().sum()
+    }
+
     pub async fn read_log<T: TableKv>(
         &self,
         table_kv: &T,
@@ -297,16 +791,30 @@ impl TableUnit {
         ctx: &ReadContext,
         request: &ReadRequest,
     ) -> Result<TableLogIterator<T>> {
+        let outcome = self
+            .read_log_outcome(table_kv, buckets, ctx, request)
+            .await?;
+
+        Ok(outcome.into_log_iter(table_kv))
+    }
+
+    /// Read logs of the request range, the returned outcome tells whether the
+    /// range contains no sequence at all.
+    pub async fn read_log_outcome<T: TableKv>(
+        &self,
+        table_kv: &T,
+        buckets: Vec<BucketRef>,
+        ctx: &ReadContext,
+        request: &ReadRequest,
+    ) -> Result<ReadLogOutcome<T>> {
         // Prepare start/end sequence to read, now this doesn't provide snapshot
         // isolation semantics since delete and write operations may happen
-        // during reading start/end sequence.
-        let start_sequence = match request.start.as_start_sequence_number() {
-            Some(request_start_sequence) => {
-                let table_unit_start_sequence = self.state.start_sequence();
-                // Avoid reading deleted log entries.
-                cmp::max(table_unit_start_sequence, request_start_sequence)
-            }
-            None => return Ok(TableLogIterator::new_empty(table_kv.clone())),
+        // during reading start/end sequence. But the logs after the start
+        // sequence won't be cleaned until the returned iterator is dropped, see
+        // [ActiveReads].
+        let request_start_sequence = match request.start.as_start_sequence_number() {
+            Some(v) => v,
+            None => return Ok(ReadLogOutcome::EmptyRange),
         };
         let end_sequence = match request.end.as_end_sequence_number() {
             Some(request_end_sequence) => {
@@ -314,28 +822,82 @@ impl TableUnit {
                 // Avoid reading entries newer than current last sequence.
                 cmp::min(table_unit_last_sequence, request_end_sequence)
             }
-            None => return Ok(TableLogIterator::new_empty(table_kv.clone())),
+            None => return Ok(ReadLogOutcome::EmptyRange),
         };
+        // Avoid reading deleted log entries.
+        let (start_sequence, read_guard) = self.state.register_read(request_start_sequence);
 
         let region_id = self.state.region_id;
         let table_id = self.state.table_id;
         let min_log_key = CommonLogKey::new(region_id, table_id, start_sequence);
         let max_log_key = CommonLogKey::new(region_id, table_id, end_sequence);
 
-        let scan_ctx = ScanContext {
-            timeout: ctx.timeout,
-            ..Default::default()
-        };
+        let scan_ctx = new_read_scan_ctx(ctx);
 
-        Ok(TableLogIterator::new(
+        let iter = TableLogIterator::new(
             buckets,
             min_log_key,
             max_log_key,
             scan_ctx,
             table_kv.clone(),
-        ))
+        )
+        .with_read_guard(read_guard);
+        Ok(ReadLogOutcome::Logs(iter))
+    }
+
+    /// Count log entries with sequence in range `[start_sequence,
+    /// end_sequence]` across given `buckets`.
+    ///
+    /// Only keys are examined, the payloads are never decoded, so it is cheap
+    /// enough to estimate the work of replaying.
+    pub async fn count_log_entries<T: TableKv>(
+        &self,
+        table_kv: &T,
+        buckets: Vec<BucketRef>,
+        ctx: &ReadContext,
+        start_sequence: SequenceNumber,
+        end_sequence: SequenceNumber,
+    ) -> Result<u64> {
+        // Avoid counting deleted entries or entries newer than current last sequence.
+        let start_sequence = cmp::max(self.state.start_sequence(), start_sequence);
+        let end_sequence = cmp::min(self.state.last_sequence(), end_sequence);
+        if start_sequence > end_sequence {
+            return Ok(0);
+        }
+
+        let region_id = self.state.region_id;
+        let table_id = self.state.table_id;
+        let min_log_key = CommonLogKey::new(region_id, table_id, start_sequence);
+        let max_log_key = CommonLogKey::new(region_id, table_id, end_sequence);
+        let scan_ctx = new_read_scan_ctx(ctx);
+
+        let table_kv = table_kv.clone();
+        self.runtimes
+            .read_runtime
+            .spawn_blocking(move || {
+                let mut total = 0;
+                for bucket in &buckets {
+                    let table_name = bucket.wal_shard_table(region_id);
+                    total += Self::count_log_entries_in_table(
+                        &table_kv,
+                        scan_ctx.clone(),
+                        table_name,
+                        &min_log_key,
+                        &max_log_key,
+                    )?;
+                }
+
+                Ok(total)
+            })
+            .await
+            .context(RuntimeExec)?
     }
 
+    /// Delete entries up to `sequence_num`, the updated table unit entry is
+    /// always durable before it returns, so the log cleaner never deletes
+    /// records the table unit still starts from after a crash.
+    ///
+    /// The records are left to the log cleaner.
     pub async fn delete_entries_up_to<T: TableKv>(
         &self,
         table_kv: &T,
@@ -354,6 +916,102 @@ impl TableUnit {
             .await
     }
 
+    /// Delete entries up to `sequence_num` and then delete their records from
+    /// `buckets` at once, instead of waiting for the log cleaner.
+    ///
+    /// The table unit entry is durable before deleting the records, so the
+    /// records left by a crash in between are still deleted by the log cleaner.
+    pub async fn delete_entries_up_to_eagerly<T: TableKv>(
+        self: &Arc<Self>,
+        table_kv: &T,
+        table_unit_meta_table: &str,
+        sequence_num: SequenceNumber,
+        clean_ctx: CleanContext,
+        buckets: Vec<BucketRef>,
+    ) -> Result<()> {
+        self.delete_entries_up_to(table_kv, table_unit_meta_table, sequence_num)
+            .await?;
+
+        let table_unit = self.clone();
+        let table_kv = table_kv.clone();
+        self.runtimes
+            .bg_runtime
+            .spawn_blocking(move || table_unit.clean_deleted_logs(&table_kv, &clean_ctx, &buckets))
+            .await
+            .context(RuntimeExec)?
+    }
+
+    /// Delete entries with sequence in range `[from, to]` and delete their
+    /// records from `buckets` at once.
+    ///
+    /// Only a prefix of the logs can be deleted logically, so:
+    /// - If the range covers the start sequence, this is the same as
+    ///   `delete_entries_up_to_eagerly(to)`, the start sequence is advanced
+    ///   past `to`.
+    /// - Otherwise the range is in the middle or at the suffix, the records are
+    ///   deleted but the start and last sequence are kept, so the sequences of
+    ///   the range still belong to this table unit and are never reused, and
+    ///   reading the table unit just skips the hole.
+    ///
+    /// The part of the range after the last sequence is ignored.
+    pub async fn delete_entries_in_range<T: TableKv>(
+        self: &Arc<Self>,
+        table_kv: &T,
+        table_unit_meta_table: &str,
+        from: SequenceNumber,
+        to: SequenceNumber,
+        clean_ctx: CleanContext,
+        buckets: Vec<BucketRef>,
+    ) -> Result<()> {
+        ensure!(
+            from <= to,
+            InvalidDeleteRange {
+                region_id: self.state.region_id,
+                table_id: self.state.table_id,
+                from,
+                to,
+            }
+        );
+
+        if from <= self.state.start_sequence() {
+            return self
+                .delete_entries_up_to_eagerly(
+                    table_kv,
+                    table_unit_meta_table,
+                    to,
+                    clean_ctx,
+                    buckets,
+                )
+                .await;
+        }
+
+        let to = cmp::min(to, self.state.last_sequence());
+        if from > to {
+            return Ok(());
+        }
+
+        info!(
+            "Delete records of a middle range of table unit, the range is still kept in the table unit, region_id:{}, table_id:{}, from:{}, to:{}",
+            self.state.region_id, self.state.table_id, from, to
+        );
+
+        let table_unit = self.clone();
+        let table_kv = table_kv.clone();
+        self.runtimes
+            .bg_runtime
+            .spawn_blocking(move || {
+                table_unit.clean_logs_in_range(
+                    &table_kv,
+                    &clean_ctx,
+                    &buckets,
+                    from,
+                    to.saturating_add(1),
+                )
+            })
+            .await
+            .context(RuntimeExec)?
+    }
+
     #[inline]
     pub fn table_id(&self) -> TableId {
         self.state.table_id
@@ -368,11 +1026,77 @@ impl TableUnit {
     pub fn last_sequence(&self) -> SequenceNumber {
         self.state.last_sequence()
     }
+
+    #[inline]
+    pub fn start_sequence(&self) -> SequenceNumber {
+        self.state.start_sequence()
+    }
+
+    /// Returns the range of sequence still available in this table unit.
+    #[inline]
+    pub fn sequence_range(&self) -> SequenceRange {
+        SequenceRange {
+            start: self.state.start_sequence(),
+            last: self.state.last_sequence(),
+        }
+    }
+
+    /// Returns the [TableUnitStats] of this table unit, whose logs are stored
+    /// in `num_buckets` buckets.
+    pub fn stats(&self, num_buckets: usize) -> TableUnitStats {
+        let SequenceRange { start, last } = self.sequence_range();
+        // No log is assigned the min sequence.
+        let first = cmp::max(start, common_types::MIN_SEQUENCE_NUMBER + 1);
+        let approximate_entries = if last >= first { last - first + 1 } else { 0 };
+
+        TableUnitStats {
+            region_id: self.state.region_id,
+            table_id: self.state.table_id,
+            start_sequence: start,
+            last_sequence: last,
+            approximate_entries,
+            num_buckets,
+            last_clean: *self.state.last_clean.lock().unwrap(),
+        }
+    }
+
+    /// Returns true if some logs at the head of this table unit have been
+    /// deleted.
+    #[inline]
+    pub fn has_deleted_prefix(&self) -> bool {
+        self.state.start_sequence() > common_types::MIN_SEQUENCE_NUMBER
+    }
+
+    /// Returns how many sequence numbers can still be allocated before the
+    /// sequence of this table unit overflows.
+    #[inline]
+    pub fn remaining_sequence_capacity(&self) -> u64 {
+        common_types::MAX_SEQUENCE_NUMBER - self.state.last_sequence()
+    }
+
+    /// Warn once the used sequence exceeds `ratio` of the max sequence number.
+    pub fn with_sequence_warn_ratio(mut self, ratio: f64) -> Self {
+        self.writer.get_mut().sequence_warn_threshold =
+            (common_types::MAX_SEQUENCE_NUMBER as f64 * ratio) as SequenceNumber;
+        self
+    }
 }
 
 // Blocking operations:
 impl TableUnit {
-    fn load_table_unit_entry<T: TableKv>(
+    /// Load the start sequence of the table unit from `table_unit_meta_table`,
+    /// returns None if the table unit doesn't exist.
+    pub fn load_start_sequence<T: TableKv>(
+        table_kv: &T,
+        table_unit_meta_table: &str,
+        table_id: TableId,
+    ) -> Result<Option<SequenceNumber>> {
+        let entry = Self::load_table_unit_entry(table_kv, table_unit_meta_table, table_id)?;
+
+        Ok(entry.map(|v| v.start_sequence))
+    }
+
+    fn load_table_unit_entry<T: TableKv>(
         table_kv: &T,
         table_unit_meta_table: &str,
         table_id: TableId,
@@ -414,12 +1138,55 @@ impl TableUnit {
         Ok(last_sequence)
     }
 
+    /// Load the sequence of the oldest log of the table unit from `buckets`,
+    /// returns None if no log exists.
+    fn load_first_sequence<T: TableKv>(
+        table_kv: &T,
+        scan_ctx: ScanContext,
+        region_id: RegionId,
+        table_id: TableId,
+        buckets: &[BucketRef],
+    ) -> Result<Option<SequenceNumber>> {
+        // Logs of the table unit in older buckets have smaller sequences.
+        for bucket in buckets {
+            let table_name = bucket.wal_shard_table(region_id);
+
+            if let Some(seq) = Self::load_edge_sequence_from_table(
+                table_kv,
+                scan_ctx.clone(),
+                table_name,
+                region_id,
+                table_id,
+                false,
+            )? {
+                return Ok(Some(seq));
+            }
+        }
+
+        Ok(None)
+    }
+
     fn load_last_sequence_from_table<T: TableKv>(
         table_kv: &T,
         scan_ctx: ScanContext,
         table_name: &str,
         region_id: RegionId,
         table_id: TableId,
+    ) -> Result<Option<SequenceNumber>> {
+        Self::load_edge_sequence_from_table(
+            table_kv, scan_ctx, table_name, region_id, table_id, true,
+        )
+    }
+
+    /// Load the max sequence of the table unit in the table if `reverse` is
+    /// true, otherwise the min sequence.
+    fn load_edge_sequence_from_table<T: TableKv>(
+        table_kv: &T,
+        scan_ctx: ScanContext,
+        table_name: &str,
+        region_id: RegionId,
+        table_id: TableId,
+        reverse: bool,
     ) -> Result<Option<SequenceNumber>> {
         let log_encoding = CommonLogEncoding::newest();
         let mut encode_buf = BytesMut::new();
@@ -441,14 +1208,12 @@ impl TableUnit {
         let scan_req = ScanRequest {
             start: scan_start,
             end: scan_end,
-            // We need to find the maximum sequence number.
-            reverse: true,
+            reverse,
         };
 
         let iter = table_kv
             .scan(scan_ctx, table_name, scan_req)
-            .map_err(|e| Box::new(e) as _)
-            .context(Scan)?;
+            .map_err(scan_error)?;
 
         if !iter.valid() {
             return Ok(None);
@@ -463,6 +1228,56 @@ impl TableUnit {
         Ok(Some(log_key.sequence_num))
     }
 
+    fn count_log_entries_in_table<T: TableKv>(
+        table_kv: &T,
+        scan_ctx: ScanContext,
+        table_name: &str,
+        min_log_key: &CommonLogKey,
+        max_log_key: &CommonLogKey,
+    ) -> Result<u64> {
+        let log_encoding = CommonLogEncoding::newest();
+        let mut encode_buf = BytesMut::new();
+        log_encoding
+            .encode_key(&mut encode_buf, min_log_key)
+            .context(LogCodec)?;
+        let start = KeyBoundary::included(&encode_buf);
+        log_encoding
+            .encode_key(&mut encode_buf, max_log_key)
+            .context(LogCodec)?;
+        let end = KeyBoundary::included(&encode_buf);
+
+        let scan_req = ScanRequest {
+            start,
+            end,
+            reverse: false,
+        };
+        let mut iter = table_kv
+            .scan(scan_ctx, table_name, scan_req)
+            .map_err(scan_error)?;
+
+        let mut count = 0;
+        while iter.valid() {
+            if log_encoding.is_log_key(iter.key()).context(LogCodec)? {
+                count += 1;
+            }
+
+            if !iter.next().map_err(scan_error)? {
+                break;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Clean the records of deleted logs from all `buckets`.
+    ///
+    /// The deleted logs still being read by the reads in progress are kept, and
+    /// will be cleaned by the next clean after the reads finish, see
+    /// [ActiveReads].
+    ///
+    /// At most `ctx.bucket_parallelism` buckets are cleaned concurrently. A
+    /// failed bucket doesn't stop cleaning the others, and all the failures
+    /// are reported by one error after every bucket is visited.
     // TODO: unfortunately, we can just check and delete the
     pub fn clean_deleted_logs<T: TableKv>(
         &self,
@@ -470,18 +1285,39 @@ impl TableUnit {
         ctx: &CleanContext,
         buckets: &[BucketRef],
     ) -> Result<()> {
-        // Inclusive min log key.
-        let min_log_key = CommonLogKey::new(
-            self.state.region_id,
-            self.state.table_id,
+        let end_sequence = self.state.clean_end_sequence();
+        if end_sequence < self.state.start_sequence() {
+            debug!(
+                "Clean of deleted logs is limited by the active reads, region_id:{}, table_id:{}, end_sequence:{}",
+                self.state.region_id, self.state.table_id, end_sequence
+            );
+        }
+
+        self.clean_logs_in_range(
+            table_kv,
+            ctx,
+            buckets,
             common_types::MIN_SEQUENCE_NUMBER,
-        );
+            end_sequence,
+        )
+    }
+
+    /// Delete records with sequence in range `[start_sequence, end_sequence)`
+    /// from `buckets`.
+    fn clean_logs_in_range<T: TableKv>(
+        &self,
+        table_kv: &T,
+        ctx: &CleanContext,
+        buckets: &[BucketRef],
+        start_sequence: SequenceNumber,
+        end_sequence: SequenceNumber,
+    ) -> Result<()> {
+        // Inclusive min log key.
+        let min_log_key =
+            CommonLogKey::new(self.state.region_id, self.state.table_id, start_sequence);
         // Exlusive max log key.
-        let max_log_key = CommonLogKey::new(
-            self.state.region_id,
-            self.state.table_id,
-            self.state.start_sequence(),
-        );
+        let max_log_key =
+            CommonLogKey::new(self.state.region_id, self.state.table_id, end_sequence);
 
         let mut seek_key_buf = BytesMut::new();
         let log_encoding = CommonLogEncoding::newest();
@@ -492,7 +1328,7 @@ impl TableUnit {
         log_encoding
             .encode_key(&mut seek_key_buf, &max_log_key)
             .context(LogCodec)?;
-        // We should not clean record with start sequence, so we use exclusive boundary.
+        // We should not clean record with end sequence, so we use exclusive boundary.
         let end = KeyBoundary::excluded(&seek_key_buf);
 
         let scan_req = ScanRequest {
@@ -505,20 +1341,93 @@ impl TableUnit {
             batch_size: ctx
                 .batch_size
                 .try_into()
-                .unwrap_or(DEFAULT_CLEAN_BATCH_SIZE),
+                .unwrap_or(manager::DEFAULT_CLEAN_BATCH_SIZE as i32),
         };
 
-        for bucket in buckets {
+        let total_deleted = AtomicUsize::new(0);
+        let clean_bucket = |bucket: &BucketRef| {
             let table_name = bucket.wal_shard_table(self.state.region_id);
-            let iter = table_kv
-                .scan(scan_ctx.clone(), table_name, scan_req.clone())
-                .map_err(|e| Box::new(e) as _)
-                .context(Scan)?;
+            self.clean_bucket(
+                table_kv,
+                ctx,
+                table_name,
+                &scan_ctx,
+                &scan_req,
+                &total_deleted,
+            )
+            .map_err(|e| (table_name.to_string(), e))
+        };
+
+        let parallelism = ctx.bucket_parallelism.clamp(1, cmp::max(buckets.len(), 1));
+        let failures = if parallelism == 1 {
+            buckets
+                .iter()
+                .filter_map(|bucket| clean_bucket(bucket).err())
+                .collect::<Vec<_>>()
+        } else {
+            // Workers take the next bucket to clean until all buckets are taken.
+            let next_bucket = AtomicUsize::new(0);
+            let failures = std::sync::Mutex::new(Vec::new());
+            thread::scope(|scope| {
+                for _ in 0..parallelism {
+                    scope.spawn(|| {
+                        while let Some(bucket) =
+                            buckets.get(next_bucket.fetch_add(1, Ordering::Relaxed))
+                        {
+                            if let Err(failure) = clean_bucket(bucket) {
+                                failures.lock().unwrap().push(failure);
+                            }
+                        }
+                    });
+                }
+            });
+            failures.into_inner().unwrap()
+        };
 
-            self.clean_logs_from_iter(table_kv, ctx, table_name, iter)?;
+        if failures.is_empty() {
+            *self.state.last_clean.lock().unwrap() = Some(LastClean {
+                end_sequence,
+                deleted: total_deleted.into_inner(),
+                finished_at: Instant::now(),
+            });
+            return Ok(());
         }
 
-        Ok(())
+        for (table_name, e) in &failures {
+            error!(
+                "Failed to clean logs of bucket, region_id:{}, table_id:{}, table_name:{}, err:{}",
+                self.state.region_id, self.state.table_id, table_name, e
+            );
+        }
+        let msg = failures
+            .iter()
+            .map(|(table_name, e)| format!("{}: {}", table_name, e))
+            .collect::<Vec<_>>()
+            .join("; ");
+        CleanBuckets {
+            region_id: self.state.region_id,
+            table_id: self.state.table_id,
+            failed: failures.len(),
+            total: buckets.len(),
+            msg,
+        }
+        .fail()
+    }
+
+    fn clean_bucket<T: TableKv>(
+        &self,
+        table_kv: &T,
+        ctx: &CleanContext,
+        table_name: &str,
+        scan_ctx: &ScanContext,
+        scan_req: &ScanRequest,
+        clean_total_deleted: &AtomicUsize,
+    ) -> Result<()> {
+        let iter = table_kv
+            .scan(scan_ctx.clone(), table_name, scan_req.clone())
+            .map_err(scan_error)?;
+
+        self.clean_logs_from_iter(table_kv, ctx, table_name, iter, clean_total_deleted)
     }
 
     fn clean_logs_from_iter<T: TableKv>(
@@ -527,40 +1436,55 @@ impl TableUnit {
         ctx: &CleanContext,
         table_name: &str,
         mut iter: T::ScanIter,
+        clean_total_deleted: &AtomicUsize,
     ) -> Result<()> {
-        let mut write_batch = T::WriteBatch::with_capacity(ctx.batch_size);
+        let report_progress = |deleted: usize| {
+            let total_deleted = clean_total_deleted.fetch_add(deleted, Ordering::Relaxed) + deleted;
+            if let Some(callback) = &ctx.progress_callback {
+                (callback.0)(&CleanProgress {
+                    table_name,
+                    total_deleted,
+                });
+            }
+        };
+
+        let mut batch_size = AdaptiveBatchSize::new(ctx);
+        let mut write_batch = T::WriteBatch::with_capacity(batch_size.current());
         let (mut write_batch_size, mut total_deleted) = (0, 0);
         while iter.valid() {
             write_batch.delete(iter.key());
             write_batch_size += 1;
             total_deleted += 1;
 
-            if write_batch_size >= ctx.batch_size {
+            if write_batch_size >= batch_size.current() {
                 let wb = mem::replace(
                     &mut write_batch,
-                    T::WriteBatch::with_capacity(ctx.batch_size),
+                    T::WriteBatch::with_capacity(batch_size.current()),
                 );
-                write_batch_size = 0;
+                let deleted = mem::take(&mut write_batch_size);
+                let begin = Instant::now();
                 table_kv
                     .write(WriteContext::default(), table_name, wb)
                     .map_err(|e| Box::new(e) as _)
                     .context(Delete {
                         region_id: self.state.table_id,
                     })?;
+                batch_size.observe(begin.elapsed());
+                report_progress(deleted);
             }
 
-            let has_next = iter.next().map_err(|e| Box::new(e) as _).context(Scan)?;
+            let has_next = iter.next().map_err(scan_error)?;
             if !has_next {
-                let wb = mem::replace(
-                    &mut write_batch,
-                    T::WriteBatch::with_capacity(ctx.batch_size),
-                );
+                let wb = mem::replace(&mut write_batch, T::WriteBatch::default());
                 table_kv
                     .write(WriteContext::default(), table_name, wb)
                     .map_err(|e| Box::new(e) as _)
                     .context(Delete {
                         region_id: self.state.table_id,
                     })?;
+                if write_batch_size > 0 {
+                    report_progress(write_batch_size);
+                }
 
                 break;
             }
@@ -568,8 +1492,8 @@ impl TableUnit {
 
         if total_deleted > 0 {
             debug!(
-                "Clean logs of table unit, region_id:{}, table_name:{}, total_deleted:{}",
-                self.state.table_id, table_name, total_deleted
+                "Clean logs of table unit, region_id:{}, table_name:{}, total_deleted:{}, batch_size:{}",
+                self.state.table_id, table_name, total_deleted, batch_size.current()
             );
         }
 
@@ -579,6 +1503,64 @@ impl TableUnit {
 
 pub type TableUnitRef = Arc<TableUnit>;
 
+/// Build the [ScanContext] to read logs from [ReadContext].
+fn new_read_scan_ctx(ctx: &ReadContext) -> ScanContext {
+    let batch_size = ctx
+        .batch_size
+        .try_into()
+        .ok()
+        .filter(|v| *v > 0)
+        .unwrap_or(ScanContext::DEFAULT_BATCH_SIZE);
+
+    ScanContext {
+        timeout: ctx.timeout,
+        batch_size,
+    }
+}
+
+/// Position of a [TableLogIterator], from which a new iterator can resume
+/// reading, e.g. after a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogIteratorCheckpoint {
+    /// Index of the current bucket.
+    bucket_index: usize,
+    /// Start time (in ms) of the buckets up to the current one, to validate the
+    /// buckets of the resumed iterator.
+    bucket_starts: Vec<i64>,
+    region_id: RegionId,
+    table_id: TableId,
+    sequence_num: SequenceNumber,
+    /// Whether the log at the position has been returned.
+    visited: bool,
+}
+
+/// Outcome of reading the logs of a table, which tells a read empty by design
+/// from a read of a table unit that doesn't exist.
+#[derive(Debug)]
+pub enum ReadLogOutcome<T: TableKv> {
+    /// Iterator of the logs in the read range, which may still yield nothing.
+    Logs(TableLogIterator<T>),
+    /// The read range contains no sequence, e.g. the start is excluded
+    /// `MAX_SEQUENCE_NUMBER`.
+    EmptyRange,
+    /// The table unit doesn't exist, e.g. no log of the table has been written
+    /// or the table unit is not persisted in the region.
+    TableUnitNotFound,
+}
+
+impl<T: TableKv> ReadLogOutcome<T> {
+    /// Convert into the log iterator, which is empty if there is no log to
+    /// read.
+    pub fn into_log_iter(self, table_kv: &T) -> TableLogIterator<T> {
+        match self {
+            ReadLogOutcome::Logs(iter) => iter,
+            ReadLogOutcome::EmptyRange | ReadLogOutcome::TableUnitNotFound => {
+                TableLogIterator::new_empty(table_kv.clone())
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TableLogIterator<T: TableKv> {
     buckets: Vec<BucketRef>,
@@ -593,8 +1575,19 @@ pub struct TableLogIterator<T: TableKv> {
     // The `current_iter` should be either a valid iterator or None.
     current_iter: Option<T::ScanIter>,
     log_encoding: CommonLogEncoding,
-    // TODO(ygf11): Remove this after issue#120 is resolved.
-    previous_value: Vec<u8>,
+    // Whether `current_iter` still points to the entry returned by the last
+    // `next_log_entry()` call. The iterator is stepped lazily in the next call
+    // so the returned payload can borrow the value of `current_iter` directly.
+    need_step: bool,
+    // Only returns logs of this table if it is set.
+    table_id_filter: Option<TableId>,
+    // Whether `current_log_key` has been visited, the next scan starts after
+    // it to skip the duplicate logs in a compacting bucket and its next bucket.
+    current_key_visited: bool,
+    // Buffer to encode the keys of scan requests, reused across scans.
+    scan_key_buf: BytesMut,
+    // Keeps the logs to read from being cleaned until this iterator is dropped.
+    _read_guard: Option<ActiveReadGuard>,
 }
 
 impl<T: TableKv> TableLogIterator<T> {
@@ -608,7 +1601,11 @@ impl<T: TableKv> TableLogIterator<T> {
             current_bucket_index: 0,
             current_iter: None,
             log_encoding: CommonLogEncoding::newest(),
-            previous_value: Vec::default(),
+            need_step: false,
+            table_id_filter: None,
+            current_key_visited: false,
+            scan_key_buf: BytesMut::new(),
+            _read_guard: None,
         }
     }
 
@@ -628,25 +1625,130 @@ impl<T: TableKv> TableLogIterator<T> {
             current_bucket_index: 0,
             current_iter: None,
             log_encoding: CommonLogEncoding::newest(),
-            previous_value: Vec::default(),
+            need_step: false,
+            table_id_filter: None,
+            current_key_visited: false,
+            scan_key_buf: BytesMut::new(),
+            _read_guard: None,
         }
     }
 
+    fn with_read_guard(mut self, read_guard: ActiveReadGuard) -> Self {
+        self._read_guard = Some(read_guard);
+        self
+    }
+
+    /// Iterate logs of all tables in the region `region_id`, the logs are
+    /// ordered by (table id, sequence).
+    pub fn new_region(
+        buckets: Vec<BucketRef>,
+        region_id: RegionId,
+        scan_ctx: ScanContext,
+        table_kv: T,
+    ) -> Self {
+        Self::new(
+            buckets,
+            CommonLogKey::new(region_id, TableId::MIN, common_types::MIN_SEQUENCE_NUMBER),
+            CommonLogKey::new(region_id, TableId::MAX, common_types::MAX_SEQUENCE_NUMBER),
+            scan_ctx,
+            table_kv,
+        )
+    }
+
+    /// Only returns logs of table `table_id`, logs of other tables in the
+    /// same region are skipped by their keys without decoding the values.
+    pub fn with_table_filter(mut self, table_id: TableId) -> Self {
+        self.table_id_filter = Some(table_id);
+        self
+    }
+
+    /// Checkpoint the current position, a new iterator over the same range
+    /// resumed from it by [TableLogIterator::resume] returns the logs after the
+    /// last returned one.
+    pub fn checkpoint(&self) -> LogIteratorCheckpoint {
+        let num_started = cmp::min(self.current_bucket_index + 1, self.buckets.len());
+        let bucket_starts = self.buckets[..num_started]
+            .iter()
+            .map(|bucket| bucket.gmt_start_ms().as_i64())
+            .collect();
+
+        LogIteratorCheckpoint {
+            bucket_index: self.current_bucket_index,
+            bucket_starts,
+            region_id: self.current_log_key.region_id,
+            table_id: self.current_log_key.table_id,
+            sequence_num: self.current_log_key.sequence_num,
+            visited: self.current_key_visited,
+        }
+    }
+
+    /// Resume from the position of `checkpoint`, which must be taken before any
+    /// log is read by this iterator.
+    ///
+    /// The buckets up to the checkpointed one must be the same as the buckets
+    /// of the checkpointed iterator, the later buckets are allowed to change as
+    /// they haven't been read yet.
+    pub fn resume(mut self, checkpoint: &LogIteratorCheckpoint) -> Result<Self> {
+        let region_id = self.region_id();
+        ensure!(
+            checkpoint.region_id == region_id,
+            CheckpointMismatch {
+                region_id,
+                msg: format!("checkpoint of region {}", checkpoint.region_id),
+            }
+        );
+        let num_started = checkpoint.bucket_starts.len();
+        ensure!(
+            num_started <= self.buckets.len()
+                && self.buckets[..num_started]
+                    .iter()
+                    .zip(&checkpoint.bucket_starts)
+                    .all(|(bucket, start)| bucket.gmt_start_ms().as_i64() == *start),
+            CheckpointMismatch {
+                region_id,
+                msg: format!(
+                    "buckets changed, checkpointed:{:?}, current:{:?}",
+                    checkpoint.bucket_starts,
+                    self.buckets
+                        .iter()
+                        .map(|bucket| bucket.gmt_start_ms().as_i64())
+                        .collect::<Vec<_>>()
+                ),
+            }
+        );
+
+        self.current_bucket_index = checkpoint.bucket_index;
+        self.current_log_key = CommonLogKey::new(
+            checkpoint.region_id,
+            checkpoint.table_id,
+            checkpoint.sequence_num,
+        );
+        self.current_key_visited = checkpoint.visited;
+        self.current_iter = None;
+        self.need_step = false;
+
+        Ok(self)
+    }
+
     #[inline]
     fn no_more_data(&self) -> bool {
         self.current_bucket_index >= self.buckets.len() || self.current_log_key > self.max_log_key
     }
 
-    fn new_scan_request(&self) -> Result<ScanRequest> {
-        let mut seek_key_buf = BytesMut::new();
+    fn new_scan_request(&mut self) -> Result<ScanRequest> {
+        let seek_key_buf = &mut self.scan_key_buf;
         self.log_encoding
-            .encode_key(&mut seek_key_buf, &self.current_log_key)
+            .encode_key(seek_key_buf, &self.current_log_key)
             .context(LogCodec)?;
-        let start = KeyBoundary::included(&seek_key_buf);
+        let start = if self.current_key_visited {
+            KeyBoundary::excluded(seek_key_buf)
+        } else {
+            KeyBoundary::included(seek_key_buf)
+        };
         self.log_encoding
-            .encode_key(&mut seek_key_buf, &self.max_log_key)
+            .encode_key(seek_key_buf, &self.max_log_key)
             .context(LogCodec)?;
-        let end = KeyBoundary::included(&seek_key_buf);
+        let end = KeyBoundary::included(seek_key_buf);
 
         Ok(ScanRequest {
             start,
@@ -670,11 +1772,23 @@ impl<T: TableKv> TableLogIterator<T> {
             }
 
             let table_name = self.buckets[self.current_bucket_index].wal_shard_table(region_id);
-            let iter = self
+            let iter = match self
                 .table_kv
                 .scan(self.scan_ctx.clone(), table_name, scan_req.clone())
-                .map_err(|e| Box::new(e) as _)
-                .context(Scan)?;
+            {
+                Ok(iter) => iter,
+                // The bucket may be purged concurrently (e.g. expired by ttl), skip it.
+                Err(e) if e.is_table_not_found() => {
+                    warn!(
+                        "Skip missing bucket while reading logs, table:{}, err:{}",
+                        table_name, e
+                    );
+
+                    self.current_bucket_index += 1;
+                    continue;
+                }
+                Err(e) => return Err(scan_error(e)),
+            };
             if iter.valid() {
                 self.current_iter = Some(iter);
                 return Ok(true);
@@ -686,9 +1800,35 @@ impl<T: TableKv> TableLogIterator<T> {
         Ok(false)
     }
 
+    /// Region id of the logs to iterate.
+    #[inline]
+    pub fn region_id(&self) -> RegionId {
+        self.max_log_key.region_id
+    }
+
+    /// Returns the entry returned by last `next_log_entry()` again, returns
+    /// None if no entry is returned yet.
+    fn current_log_entry(&self) -> manager::Result<Option<LogEntry<&'_ [u8]>>> {
+        let current_iter = match &self.current_iter {
+            Some(iter) if self.need_step => iter,
+            _ => return Ok(None),
+        };
+        let payload = self
+            .log_encoding
+            .decode_value(current_iter.value())
+            .map_err(|e| Box::new(e) as _)
+            .context(manager::Encoding)?;
+
+        Ok(Some(LogEntry {
+            table_id: self.current_log_key.table_id,
+            sequence: self.current_log_key.sequence_num,
+            payload,
+        }))
+    }
+
     fn step_current_iter(&mut self) -> Result<()> {
         if let Some(iter) = &mut self.current_iter {
-            if !iter.next().map_err(|e| Box::new(e) as _).context(Scan)? {
+            if !iter.next().map_err(scan_error)? {
                 self.current_iter = None;
                 self.current_bucket_index += 1;
             }
@@ -700,57 +1840,202 @@ impl<T: TableKv> TableLogIterator<T> {
 
 impl<T: TableKv> SyncLogIterator for TableLogIterator<T> {
     fn next_log_entry(&mut self) -> manager::Result<Option<LogEntry<&'_ [u8]>>> {
-        if self.no_more_data() {
-            return Ok(None);
-        }
-
-        // If `current_iter` is None, scan from current to last bucket util we get a
-        // valid iterator.
-        if self.current_iter.is_none() {
-            let has_valid_iter = self
-                .scan_buckets()
+        // The entry returned by last call is no longer borrowed, step current
+        // iterator now, if it becomes invalid, reset `current_iter` to None and
+        // advance `current_bucket_index`.
+        if self.need_step {
+            self.need_step = false;
+            self.step_current_iter()
                 .map_err(|e| Box::new(e) as _)
                 .context(manager::Read)?;
-            if !has_valid_iter {
-                assert!(self.no_more_data());
+        }
+
+        loop {
+            if self.no_more_data() {
                 return Ok(None);
             }
+
+            // If `current_iter` is None, scan from current to last bucket util we get a
+            // valid iterator.
+            if self.current_iter.is_none() {
+                let has_valid_iter = self
+                    .scan_buckets()
+                    .map_err(|e| Box::new(e) as _)
+                    .context(manager::Read)?;
+                if !has_valid_iter {
+                    assert!(self.no_more_data());
+                    return Ok(None);
+                }
+            }
+
+            // Fetch and decode current log key.
+            let current_iter = self.current_iter.as_ref().unwrap();
+            self.current_log_key = self
+                .log_encoding
+                .decode_key(current_iter.key())
+                .map_err(|e| Box::new(e) as _)
+                .context(manager::Decoding)?;
+            self.current_key_visited = true;
+
+            match self.table_id_filter {
+                // Skip logs of other tables before decoding the value.
+                Some(table_id) if table_id != self.current_log_key.table_id => {
+                    self.step_current_iter()
+                        .map_err(|e| Box::new(e) as _)
+                        .context(manager::Read)?;
+                }
+                _ => break,
+            }
         }
+        self.need_step = true;
 
-        // Fetch and decode current log entry.
-        let current_iter = self.current_iter.as_ref().unwrap();
-        self.current_log_key = self
-            .log_encoding
-            .decode_key(current_iter.key())
-            .map_err(|e| Box::new(e) as _)
-            .context(manager::Decoding)?;
-        let payload = self
-            .log_encoding
-            .decode_value(current_iter.value())
-            .map_err(|e| Box::new(e) as _)
-            .context(manager::Encoding)?;
+        // The payload borrows the value of `current_iter` so no copy is needed.
 
-        // To unblock pr#119, we use the following to simple resolve borrow-check error.
-        // detail info: https://github.com/CeresDB/ceresdb/issues/120
-        self.previous_value = payload.to_owned();
+        self.current_log_entry()
+    }
+}
 
-        // Step current iterator, if it becomes invalid, reset `current_iter` to None
-        // and advance `current_bucket_index`.
-        self.step_current_iter()
-            .map_err(|e| Box::new(e) as _)
-            .context(manager::Read)?;
+/// Iterator merges logs from multiple [TableLogIterator]s in the order of
+/// sequence, each log entry is returned along with its region id.
+#[derive(Debug)]
+pub struct MergedLogIterator<T: TableKv> {
+    iters: Vec<TableLogIterator<T>>,
+    /// Sequence of the current entry of each unfinished iterator, along with
+    /// the index of the iterator.
+    heads: BinaryHeap<Reverse<(SequenceNumber, usize)>>,
+    /// Index of the iterator whose entry is returned by last call, it is
+    /// advanced lazily in the next call.
+    pending: Option<usize>,
+    initialized: bool,
+}
 
-        let log_entry = LogEntry {
-            table_id: self.current_log_key.table_id,
-            sequence: self.current_log_key.sequence_num,
-            payload: self.previous_value.as_slice(),
+impl<T: TableKv> MergedLogIterator<T> {
+    pub fn new(iters: Vec<TableLogIterator<T>>) -> Self {
+        let heads = BinaryHeap::with_capacity(iters.len());
+
+        Self {
+            iters,
+            heads,
+            pending: None,
+            initialized: false,
+        }
+    }
+
+    fn advance(&mut self, index: usize) -> manager::Result<()> {
+        if let Some(log_entry) = self.iters[index].next_log_entry()? {
+            self.heads.push(Reverse((log_entry.sequence, index)));
+        }
+
+        Ok(())
+    }
+
+    pub fn next_log_entry(&mut self) -> manager::Result<Option<(RegionId, LogEntry<&'_ [u8]>)>> {
+        if !self.initialized {
+            for index in 0..self.iters.len() {
+                self.advance(index)?;
+            }
+            self.initialized = true;
+        }
+
+        if let Some(index) = self.pending.take() {
+            self.advance(index)?;
+        }
+
+        let index = match self.heads.pop() {
+            Some(Reverse((_, index))) => index,
+            None => return Ok(None),
         };
+        self.pending = Some(index);
+
+        let iter = &self.iters[index];
+        let log_entry = iter.current_log_entry()?;
+
+        Ok(log_entry.map(|v| (iter.region_id(), v)))
+    }
+}
+
+struct TableUnitWriter {
+    /// A warning is logged once last sequence exceeds this threshold.
+    sequence_warn_threshold: SequenceNumber,
+    /// Time of last warning about sequence overflow, used to throttle the
+    /// warnings.
+    last_sequence_warn_time: Option<Instant>,
+    /// Sequences reserved but not committed yet, start sequence -> number of
+    /// sequences.
+    reserved_sequences: BTreeMap<SequenceNumber, u64>,
+}
 
-        Ok(Some(log_entry))
+impl Default for TableUnitWriter {
+    fn default() -> Self {
+        Self {
+            sequence_warn_threshold: (common_types::MAX_SEQUENCE_NUMBER as f64
+                * DEFAULT_SEQUENCE_WARN_RATIO)
+                as SequenceNumber,
+            last_sequence_warn_time: None,
+            reserved_sequences: BTreeMap::new(),
+        }
     }
 }
 
-struct TableUnitWriter;
+/// Convert the wal write context to the write context of table kv.
+fn to_table_kv_write_ctx(ctx: &manager::WriteContext) -> WriteContext {
+    let durability = match ctx.durability {
+        manager::Durability::Durable => Durability::Durable,
+        manager::Durability::Buffered => Durability::Buffered,
+    };
+
+    WriteContext { durability }
+}
+
+/// Write the batch built by `build_batch` to `table_name` with `ctx`, retrying
+/// on retryable errors. The batch is rebuilt on each attempt.
+pub(crate) async fn write_logs_with_retry<T, F>(
+    runtime: &Runtime,
+    table_kv: &T,
+    ctx: &WriteContext,
+    table_name: &str,
+    region_id: RegionId,
+    build_batch: F,
+) -> Result<()>
+where
+    T: TableKv,
+    F: Fn() -> Result<T::WriteBatch>,
+{
+    let mut retry = 0;
+    loop {
+        let wb = build_batch()?;
+
+        let table_kv = table_kv.clone();
+        let ctx = ctx.clone();
+        let name = table_name.to_string();
+        let res = runtime
+            .spawn_blocking(move || table_kv.write(ctx, &name, wb))
+            .await
+            .context(RuntimeExec)?;
+
+        match res {
+            Ok(()) => return Ok(()),
+            // The previous attempt may have been applied although it was reported as
+            // failed, the logs with these sequences are already written.
+            Err(e) if retry > 0 && e.is_primary_key_duplicate() => return Ok(()),
+            Err(e) if e.is_retryable() && retry < WRITE_LOG_MAX_RETRY => {
+                let backoff = WRITE_LOG_RETRY_BACKOFF * (1 << retry);
+                retry += 1;
+                warn!(
+                    "Retry writing logs, region_id:{}, table_name:{}, retry:{}, backoff:{:?}, err:{}",
+                    region_id, table_name, retry, backoff, e
+                );
+
+                tokio::time::sleep(backoff).await;
+            }
+            Err(e) => {
+                return Err(e)
+                    .map_err(|e| Box::new(e) as _)
+                    .context(WriteLog { region_id });
+            }
+        }
+    }
+}
 
 // Blocking operations.
 impl TableUnitWriter {
@@ -802,7 +2087,13 @@ impl TableUnitWriter {
         batch.insert_or_update(key.as_bytes(), &value);
 
         table_kv
-            .write(WriteContext::default(), table_unit_meta_table, batch)
+            .write(
+                WriteContext {
+                    durability: Durability::Durable,
+                },
+                table_unit_meta_table,
+                batch,
+            )
             .map_err(|e| Box::new(e) as _)
             .context(WriteValue { key: &key })
     }
@@ -825,8 +2116,36 @@ impl TableUnitWriter {
         let last_sequence = table_unit_state
             .last_sequence
             .fetch_add(number, Ordering::Relaxed);
+        self.maybe_warn_sequence_overflow(table_unit_state, last_sequence.saturating_add(number));
+
         Ok(last_sequence + 1)
     }
+
+    fn maybe_warn_sequence_overflow(
+        &mut self,
+        table_unit_state: &TableUnitState,
+        last_sequence: SequenceNumber,
+    ) {
+        if last_sequence < self.sequence_warn_threshold {
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_warn_time) = self.last_sequence_warn_time {
+            if now.duration_since(last_warn_time) < SEQUENCE_WARN_INTERVAL {
+                return;
+            }
+        }
+        self.last_sequence_warn_time = Some(now);
+
+        warn!(
+            "Sequence of table unit is close to overflow, region_id:{}, table_id:{}, last_sequence:{}, remaining:{}",
+            table_unit_state.region_id,
+            table_unit_state.table_id,
+            last_sequence,
+            common_types::MAX_SEQUENCE_NUMBER - last_sequence,
+        );
+    }
 }
 
 impl TableUnitWriter {
@@ -838,7 +2157,7 @@ impl TableUnitWriter {
         bucket: &BucketRef,
         ctx: &manager::WriteContext,
         log_batch: &LogWriteBatch,
-    ) -> Result<SequenceNumber> {
+    ) -> Result<RangeInclusive<SequenceNumber>> {
         debug!(
             "Wal table unit begin writing, ctx:{:?}, region_id:{}, table_id:{}, log_entries_num:{}",
             ctx,
@@ -847,44 +2166,98 @@ impl TableUnitWriter {
             log_batch.entries.len()
         );
 
-        let log_encoding = CommonLogEncoding::newest();
         let entries_num = log_batch.len() as u64;
         let region_id = table_unit_state.region_id;
+        // Sequence numbers are allocated only once, retries reuse the same range.
+        let start_sequence_num = self.alloc_sequence_num(table_unit_state, entries_num)?;
+        let max_sequence_num = start_sequence_num + entries_num - 1;
+
+        let table_name = bucket.wal_shard_table(region_id);
+        let write_ctx = to_table_kv_write_ctx(ctx);
+        write_logs_with_retry(runtime, table_kv, &write_ctx, table_name, region_id, || {
+            Self::build_log_write_batch::<T>(table_unit_state, start_sequence_num, log_batch)
+        })
+        .await?;
+
+        Ok(start_sequence_num..=max_sequence_num)
+    }
+
+    /// Similar to `write_log()`, but the logs are submitted to the `coalescer`
+    /// and written together with logs of other table units.
+    async fn write_log_coalesced<T: TableKv>(
+        &mut self,
+        coalescer: &Arc<LogWriteCoalescer<T>>,
+        table_unit_state: &TableUnitState,
+        bucket: &BucketRef,
+        log_batch: &LogWriteBatch,
+    ) -> Result<RangeInclusive<SequenceNumber>> {
+        let entries_num = log_batch.len() as u64;
+        let region_id = table_unit_state.region_id;
+        let start_sequence_num = self.alloc_sequence_num(table_unit_state, entries_num)?;
+        let max_sequence_num = start_sequence_num + entries_num - 1;
+
+        let entries = Self::encode_log_entries(table_unit_state, start_sequence_num, log_batch)?;
+        let table_name = bucket.wal_shard_table(region_id);
+        coalescer
+            .write(table_name, region_id, entries)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .context(WriteLog { region_id })?;
+
+        Ok(start_sequence_num..=max_sequence_num)
+    }
+
+    fn encode_log_entries(
+        table_unit_state: &TableUnitState,
+        start_sequence_num: SequenceNumber,
+        log_batch: &LogWriteBatch,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let log_encoding = CommonLogEncoding::newest();
+        let region_id = table_unit_state.region_id;
         let table_id = table_unit_state.table_id;
-        let (wb, max_sequence_num) = {
-            let mut wb = T::WriteBatch::with_capacity(log_batch.len());
-            let mut next_sequence_num = self.alloc_sequence_num(table_unit_state, entries_num)?;
-            let mut key_buf = BytesMut::new();
+        let mut key_buf = BytesMut::new();
 
-            for entry in &log_batch.entries {
+        log_batch
+            .entries
+            .iter()
+            .zip(start_sequence_num..)
+            .map(|(entry, sequence_num)| {
                 log_encoding
                     .encode_key(
                         &mut key_buf,
-                        &CommonLogKey::new(region_id, table_id, next_sequence_num),
+                        &CommonLogKey::new(region_id, table_id, sequence_num),
                     )
                     .context(LogCodec)?;
-                wb.insert(&key_buf, &entry.payload);
-
-                next_sequence_num += 1;
-            }
-
-            (wb, next_sequence_num - 1)
-        };
-
-        let table_kv = table_kv.clone();
-        let bucket = bucket.clone();
-        runtime
-            .spawn_blocking(move || {
-                let table_name = bucket.wal_shard_table(region_id);
-                table_kv
-                    .write(WriteContext::default(), table_name, wb)
-                    .map_err(|e| Box::new(e) as _)
-                    .context(WriteLog { region_id })
+                Ok((key_buf.to_vec(), entry.payload.clone()))
             })
-            .await
-            .context(RuntimeExec)??;
+            .collect()
+    }
+
+    fn build_log_write_batch<T: TableKv>(
+        table_unit_state: &TableUnitState,
+        start_sequence_num: SequenceNumber,
+        log_batch: &LogWriteBatch,
+    ) -> Result<T::WriteBatch> {
+        let log_encoding = CommonLogEncoding::newest();
+        let region_id = table_unit_state.region_id;
+        let table_id = table_unit_state.table_id;
+        let mut wb = T::WriteBatch::with_capacity(log_batch.len());
+        let mut next_sequence_num = start_sequence_num;
+        let mut key_buf = BytesMut::new();
+
+        for entry in &log_batch.entries {
+            log_encoding
+                .encode_key(
+                    &mut key_buf,
+                    &CommonLogKey::new(region_id, table_id, next_sequence_num),
+                )
+                .context(LogCodec)?;
+            wb.insert(&key_buf, &entry.payload);
+
+            next_sequence_num += 1;
+        }
 
-        Ok(max_sequence_num)
+        Ok(wb)
     }
 
     /// Delete entries in the range `[0, sequence_num]`.
@@ -943,3 +2316,732 @@ impl TableUnitWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common_types::{
+        table::{Location, DEFAULT_SHARD_ID},
+        time::Timestamp,
+    };
+    use common_util::runtime::Builder;
+    use table_kv::memory::{MemoryImpl, MemoryWriteBatch};
+
+    use super::*;
+    use crate::{
+        kv_encoder::LogBatchEncoder,
+        log_batch::PayloadDecoder,
+        manager::ReadBoundary,
+        table_kv_impl::{model::BucketEntry, namespace::Bucket},
+        tests::util::{TestPayload, TestPayloadDecoder},
+    };
+
+    const TABLE_UNIT_META_TABLE: &str = "table_unit_meta_test";
+
+    fn new_runtime() -> Arc<Runtime> {
+        Arc::new(Builder::default().enable_all().build().unwrap())
+    }
+
+    /// Table units backed by the in-memory table kv.
+    struct TableUnitHarness {
+        runtimes: WalRuntimes,
+        table_kv: MemoryImpl,
+        buckets: Vec<BucketRef>,
+    }
+
+    impl TableUnitHarness {
+        fn new(runtime: Arc<Runtime>) -> Self {
+            Self::with_buckets(runtime, 1)
+        }
+
+        /// Create the harness with a permanent bucket and `num_buckets - 1`
+        /// timed buckets.
+        fn with_buckets(runtime: Arc<Runtime>, num_buckets: usize) -> Self {
+            let table_kv = MemoryImpl::default();
+            table_kv.create_table(TABLE_UNIT_META_TABLE).unwrap();
+
+            let mut buckets = vec![Arc::new(Bucket::new("test", BucketEntry::new_permanent(1)))];
+            for i in 1..num_buckets {
+                let entry =
+                    BucketEntry::new_timed(1, Timestamp::new(i as i64 * 1000), 1000).unwrap();
+                buckets.push(Arc::new(Bucket::new("test", entry)));
+            }
+            for bucket in &buckets {
+                table_kv
+                    .create_table(bucket.wal_shard_table(DEFAULT_SHARD_ID as RegionId))
+                    .unwrap();
+            }
+
+            Self {
+                runtimes: WalRuntimes {
+                    read_runtime: runtime.clone(),
+                    write_runtime: runtime.clone(),
+                    bg_runtime: runtime,
+                },
+                table_kv,
+                buckets,
+            }
+        }
+
+        async fn open_or_create(&self, location: Location) -> TableUnit {
+            TableUnit::open_or_create(
+                self.runtimes.clone(),
+                &self.table_kv,
+                ScanContext::default(),
+                TABLE_UNIT_META_TABLE,
+                location.shard_id as RegionId,
+                location.table_id,
+                self.buckets.clone(),
+            )
+            .await
+            .unwrap()
+        }
+
+        async fn open(&self, location: Location, repair_missing_meta: bool) -> Option<TableUnit> {
+            TableUnit::open(
+                self.runtimes.clone(),
+                &self.table_kv,
+                ScanContext::default(),
+                TABLE_UNIT_META_TABLE,
+                location.shard_id as RegionId,
+                location.table_id,
+                self.buckets.clone(),
+                repair_missing_meta,
+            )
+            .await
+            .unwrap()
+        }
+
+        async fn write(&self, table_unit: &TableUnit, location: Location, vals: &[u32]) -> u64 {
+            self.write_to_bucket(table_unit, location, 0, vals).await
+        }
+
+        async fn write_to_bucket(
+            &self,
+            table_unit: &TableUnit,
+            location: Location,
+            bucket_index: usize,
+            vals: &[u32],
+        ) -> u64 {
+            let log_batch = LogBatchEncoder::create(location)
+                .encode_batch::<TestPayload, u32>(vals)
+                .unwrap();
+
+            table_unit
+                .write_log(
+                    &self.table_kv,
+                    &self.buckets[bucket_index],
+                    &manager::WriteContext::default(),
+                    &log_batch,
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn read(
+            &self,
+            table_unit: &TableUnit,
+            location: Location,
+        ) -> Vec<(SequenceNumber, u32)> {
+            let request = ReadRequest {
+                location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            let mut iter = table_unit
+                .read_log(
+                    &self.table_kv,
+                    self.buckets.clone(),
+                    &ReadContext::default(),
+                    &request,
+                )
+                .await
+                .unwrap();
+
+            let mut logs = Vec::new();
+            while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                let mut payload = log_entry.payload;
+                let val = TestPayloadDecoder.decode(&mut payload).unwrap().val;
+                logs.push((log_entry.sequence, val));
+            }
+            logs
+        }
+
+        /// Number of records in the wal shard table, including the deleted but
+        /// not cleaned logs.
+        fn num_records(&self) -> usize {
+            self.num_bucket_records(0)
+        }
+
+        fn num_bucket_records(&self, bucket_index: usize) -> usize {
+            let table_name =
+                self.buckets[bucket_index].wal_shard_table(DEFAULT_SHARD_ID as RegionId);
+            let request = ScanRequest {
+                start: KeyBoundary::min_included(),
+                end: KeyBoundary::max_included(),
+                reverse: false,
+            };
+            let mut iter = self
+                .table_kv
+                .scan(ScanContext::default(), table_name, request)
+                .unwrap();
+
+            let mut num = 0;
+            while iter.valid() {
+                num += 1;
+                iter.next().unwrap();
+            }
+            num
+        }
+    }
+
+    #[test]
+    fn test_table_unit_write_read_delete_clean() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            assert_eq!(
+                common_types::MIN_SEQUENCE_NUMBER,
+                table_unit.last_sequence()
+            );
+
+            let vals = (0..15).collect::<Vec<_>>();
+            assert_eq!(10, harness.write(&table_unit, location, &vals[..10]).await);
+            assert_eq!(15, harness.write(&table_unit, location, &vals[10..]).await);
+
+            let expect_logs = (1..=15).zip(0..15).collect::<Vec<_>>();
+            assert_eq!(expect_logs, harness.read(&table_unit, location).await);
+
+            // Deleted logs are invisible but the records are kept until cleaned.
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 10)
+                .await
+                .unwrap();
+            assert_eq!(11, table_unit.start_sequence());
+            assert_eq!(
+                &expect_logs[10..],
+                &harness.read(&table_unit, location).await[..]
+            );
+            assert_eq!(15, harness.num_records());
+
+            table_unit
+                .clean_deleted_logs(
+                    &harness.table_kv,
+                    &CleanContext::default(),
+                    &harness.buckets,
+                )
+                .unwrap();
+            assert_eq!(5, harness.num_records());
+
+            // Sequences and deleted logs are recovered after reopen.
+            let table_unit = harness.open_or_create(location).await;
+            assert_eq!(11, table_unit.start_sequence());
+            assert_eq!(15, table_unit.last_sequence());
+            assert_eq!(
+                &expect_logs[10..],
+                &harness.read(&table_unit, location).await[..]
+            );
+            assert_eq!(16, harness.write(&table_unit, location, &[15]).await);
+        });
+    }
+
+    #[test]
+    fn test_clean_during_read() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            let vals = (0..15).collect::<Vec<_>>();
+            assert_eq!(15, harness.write(&table_unit, location, &vals).await);
+
+            let request = ReadRequest {
+                location,
+                start: ReadBoundary::Min,
+                end: ReadBoundary::Max,
+            };
+            let mut iter = table_unit
+                .read_log(
+                    &harness.table_kv,
+                    harness.buckets.clone(),
+                    &ReadContext::default(),
+                    &request,
+                )
+                .await
+                .unwrap();
+            let mut logs = Vec::new();
+            for _ in 0..3 {
+                let log_entry = iter.next_log_entry().unwrap().unwrap();
+                let mut payload = log_entry.payload;
+                let val = TestPayloadDecoder.decode(&mut payload).unwrap().val;
+                logs.push((log_entry.sequence, val));
+            }
+
+            // Delete and clean while reading, the logs read by the iterator are kept.
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 10)
+                .await
+                .unwrap();
+            table_unit
+                .clean_deleted_logs(
+                    &harness.table_kv,
+                    &CleanContext::default(),
+                    &harness.buckets,
+                )
+                .unwrap();
+            assert_eq!(15, harness.num_records());
+            assert_eq!(
+                common_types::MIN_SEQUENCE_NUMBER,
+                table_unit
+                    .stats(harness.buckets.len())
+                    .last_clean
+                    .unwrap()
+                    .end_sequence
+            );
+
+            // The read started before the clean returns all the logs without gaps.
+            while let Some(log_entry) = iter.next_log_entry().unwrap() {
+                let mut payload = log_entry.payload;
+                let val = TestPayloadDecoder.decode(&mut payload).unwrap().val;
+                logs.push((log_entry.sequence, val));
+            }
+            let expect_logs = (1..=15).zip(0..15).collect::<Vec<_>>();
+            assert_eq!(expect_logs, logs);
+
+            // New reads don't see the deleted logs, and they are cleaned after the
+            // read finishes.
+            assert_eq!(
+                &expect_logs[10..],
+                &harness.read(&table_unit, location).await[..]
+            );
+            drop(iter);
+            table_unit
+                .clean_deleted_logs(
+                    &harness.table_kv,
+                    &CleanContext::default(),
+                    &harness.buckets,
+                )
+                .unwrap();
+            assert_eq!(5, harness.num_records());
+        });
+    }
+
+    #[test]
+    fn test_table_unit_write_with_sequences() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+        let new_batch = |vals: &[u32]| {
+            LogBatchEncoder::create(location)
+                .encode_batch::<TestPayload, u32>(vals)
+                .unwrap()
+        };
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            let bucket = &harness.buckets[0];
+            let ctx = manager::WriteContext::default();
+
+            let mut written = Vec::new();
+            for vals in [&[10, 11, 12][..], &[13], &[14, 15]] {
+                let sequences = table_unit
+                    .write_log_with_sequences(&harness.table_kv, bucket, &ctx, &new_batch(vals))
+                    .await
+                    .unwrap();
+                assert_eq!(vals.len(), sequences.clone().count());
+                assert_eq!(table_unit.last_sequence(), *sequences.end());
+                written.extend(sequences.zip(vals.iter().copied()));
+            }
+            // Sequences are contiguous across the batches.
+            assert_eq!((1..=6).zip(10..16).collect::<Vec<_>>(), written);
+            assert_eq!(written, harness.read(&table_unit, location).await);
+
+            // The max sequence is still returned by `write_log()`.
+            assert_eq!(7, harness.write(&table_unit, location, &[16]).await);
+        });
+    }
+
+    #[test]
+    fn test_table_unit_reserve_commit_sequences() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+        let new_batch = |vals: &[u32]| {
+            LogBatchEncoder::create(location)
+                .encode_batch::<TestPayload, u32>(vals)
+                .unwrap()
+        };
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            let bucket = &harness.buckets[0];
+
+            assert_eq!(1, table_unit.reserve_sequences(3).await.unwrap());
+            assert_eq!(4, table_unit.reserve_sequences(2).await.unwrap());
+            assert_eq!(5, table_unit.last_sequence());
+            assert!(matches!(
+                table_unit.reserve_sequences(0).await,
+                Err(Error::ReserveZeroSequences { .. })
+            ));
+
+            // Commit the later range first.
+            assert_eq!(
+                5,
+                table_unit
+                    .commit_sequences(&harness.table_kv, bucket, 4, &new_batch(&[3, 4]))
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                vec![(4, 3), (5, 4)],
+                harness.read(&table_unit, location).await
+            );
+
+            // The batch must fill the reserved range.
+            assert!(matches!(
+                table_unit
+                    .commit_sequences(&harness.table_kv, bucket, 1, &new_batch(&[0, 1]))
+                    .await,
+                Err(Error::SequenceNotReserved { .. })
+            ));
+            assert_eq!(
+                3,
+                table_unit
+                    .commit_sequences(&harness.table_kv, bucket, 1, &new_batch(&[0, 1, 2]))
+                    .await
+                    .unwrap()
+            );
+            // A range can't be committed twice.
+            assert!(table_unit
+                .commit_sequences(&harness.table_kv, bucket, 1, &new_batch(&[0, 1, 2]))
+                .await
+                .is_err());
+            assert_eq!(0, table_unit.num_reserved_sequences().await);
+
+            let expect_logs = (1..=5).zip(0..5).collect::<Vec<_>>();
+            assert_eq!(expect_logs, harness.read(&table_unit, location).await);
+
+            // Normal writes are interleaved with reservations.
+            assert_eq!(6, table_unit.reserve_sequences(2).await.unwrap());
+            assert_eq!(8, harness.write(&table_unit, location, &[7]).await);
+            assert_eq!(2, table_unit.num_reserved_sequences().await);
+
+            // The range not committed doesn't affect the last sequence after reopen.
+            let table_unit = harness.open_or_create(location).await;
+            assert_eq!(8, table_unit.last_sequence());
+            assert_eq!(0, table_unit.num_reserved_sequences().await);
+            assert_eq!(9, table_unit.reserve_sequences(1).await.unwrap());
+            let table_unit = harness.open_or_create(location).await;
+            assert_eq!(8, table_unit.last_sequence());
+            assert_eq!(9, harness.write(&table_unit, location, &[8]).await);
+        });
+    }
+
+    #[test]
+    fn test_table_unit_read_resume_from_checkpoint() {
+        let runtime = new_runtime();
+        let harness = TableUnitHarness::with_buckets(runtime.clone(), 3);
+        let location = Location::new(DEFAULT_SHARD_ID, 1);
+        let request = ReadRequest {
+            location,
+            start: ReadBoundary::Min,
+            end: ReadBoundary::Max,
+        };
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            for (bucket_index, vals) in [(0, [1, 2, 3]), (1, [4, 5, 6]), (2, [7, 8, 9])] {
+                harness
+                    .write_to_bucket(&table_unit, location, bucket_index, &vals)
+                    .await;
+            }
+            let expect_logs = harness.read(&table_unit, location).await;
+            assert_eq!(9, expect_logs.len());
+
+            let read_log = |buckets: Vec<BucketRef>| {
+                let harness = &harness;
+                let table_unit = &table_unit;
+                let request = &request;
+                async move {
+                    table_unit
+                        .read_log(&harness.table_kv, buckets, &ReadContext::default(), request)
+                        .await
+                        .unwrap()
+                }
+            };
+            let collect_logs = |iter: &mut TableLogIterator<MemoryImpl>, limit: usize| {
+                let mut logs = Vec::new();
+                while logs.len() < limit {
+                    match iter.next_log_entry().unwrap() {
+                        Some(log_entry) => {
+                            let mut payload = log_entry.payload;
+                            let val = TestPayloadDecoder.decode(&mut payload).unwrap().val;
+                            logs.push((log_entry.sequence, val));
+                        }
+                        None => break,
+                    }
+                }
+                logs
+            };
+
+            for num_read in 0..=expect_logs.len() {
+                let mut iter = read_log(harness.buckets.clone()).await;
+                let mut logs = collect_logs(&mut iter, num_read);
+                // The checkpoint is serializable to be persisted.
+                let encoded = serde_json::to_vec(&iter.checkpoint()).unwrap();
+                let checkpoint: LogIteratorCheckpoint = serde_json::from_slice(&encoded).unwrap();
+
+                let mut resumed = read_log(harness.buckets.clone())
+                    .await
+                    .resume(&checkpoint)
+                    .unwrap();
+                logs.extend(collect_logs(&mut resumed, usize::MAX));
+                assert_eq!(expect_logs, logs, "num_read:{}", num_read);
+            }
+
+            // Resuming fails if the read buckets are changed.
+            let mut iter = read_log(harness.buckets.clone()).await;
+            collect_logs(&mut iter, 5);
+            let checkpoint = iter.checkpoint();
+            let res = read_log(harness.buckets[1..].to_vec())
+                .await
+                .resume(&checkpoint);
+            assert!(matches!(res, Err(Error::CheckpointMismatch { .. })));
+            // The buckets not read yet are allowed to change.
+            let mut resumed = read_log(harness.buckets[..2].to_vec())
+                .await
+                .resume(&checkpoint)
+                .unwrap();
+            assert_eq!(
+                expect_logs[5..6].to_vec(),
+                collect_logs(&mut resumed, usize::MAX)
+            );
+        });
+    }
+
+    #[test]
+    fn test_table_unit_repair_missing_meta() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            // Nothing to repair without logs.
+            assert!(harness.open(location, true).await.is_none());
+
+            let table_unit = harness.open_or_create(location).await;
+            let vals = (0..10).collect::<Vec<_>>();
+            harness.write(&table_unit, location, &vals).await;
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 3)
+                .await
+                .unwrap();
+            table_unit
+                .clean_deleted_logs(
+                    &harness.table_kv,
+                    &CleanContext::default(),
+                    &harness.buckets,
+                )
+                .unwrap();
+
+            // Lose the meta data but keep the logs.
+            let key = encoding::format_table_unit_key(location.table_id);
+            let mut batch = MemoryWriteBatch::default();
+            batch.delete(key.as_bytes());
+            harness
+                .table_kv
+                .write(WriteContext::default(), TABLE_UNIT_META_TABLE, batch)
+                .unwrap();
+            assert!(harness.open(location, false).await.is_none());
+
+            let table_unit = harness.open(location, true).await.unwrap();
+            assert_eq!(4, table_unit.start_sequence());
+            assert_eq!(10, table_unit.last_sequence());
+            let expect_logs = (4..=10).zip(3..10).collect::<Vec<_>>();
+            assert_eq!(expect_logs, harness.read(&table_unit, location).await);
+
+            // The repaired meta data is written back.
+            let table_unit = harness.open(location, false).await.unwrap();
+            assert_eq!(4, table_unit.start_sequence());
+            assert_eq!(11, harness.write(&table_unit, location, &[10]).await);
+        });
+    }
+
+    #[test]
+    fn test_table_unit_delete_entries_in_range() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::new(runtime.clone());
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            let table_unit = Arc::new(harness.open_or_create(location).await);
+            let vals = (0..10).collect::<Vec<_>>();
+            assert_eq!(10, harness.write(&table_unit, location, &vals).await);
+
+            let (harness, table_unit) = (&harness, &table_unit);
+            let delete_range = move |from, to| async move {
+                table_unit
+                    .delete_entries_in_range(
+                        &harness.table_kv,
+                        TABLE_UNIT_META_TABLE,
+                        from,
+                        to,
+                        CleanContext::default(),
+                        harness.buckets.clone(),
+                    )
+                    .await
+            };
+            let read_sequences = move || async move {
+                harness
+                    .read(table_unit, location)
+                    .await
+                    .into_iter()
+                    .map(|(sequence, _)| sequence)
+                    .collect::<Vec<_>>()
+            };
+
+            // Prefix: the start sequence is advanced.
+            delete_range(1, 3).await.unwrap();
+            assert_eq!(4, table_unit.start_sequence());
+            assert_eq!(vec![4, 5, 6, 7, 8, 9, 10], read_sequences().await);
+            assert_eq!(7, harness.num_records());
+
+            // Middle: the records are deleted but the start sequence is kept.
+            delete_range(6, 7).await.unwrap();
+            assert_eq!(4, table_unit.start_sequence());
+            assert_eq!(10, table_unit.last_sequence());
+            assert_eq!(vec![4, 5, 8, 9, 10], read_sequences().await);
+            assert_eq!(5, harness.num_records());
+
+            // Suffix: the range after the last sequence is ignored, and the deleted
+            // sequences are not reused.
+            delete_range(9, 20).await.unwrap();
+            assert_eq!(4, table_unit.start_sequence());
+            assert_eq!(10, table_unit.last_sequence());
+            assert_eq!(vec![4, 5, 8], read_sequences().await);
+            assert_eq!(3, harness.num_records());
+            assert_eq!(11, harness.write(table_unit, location, &vals[..1]).await);
+            assert_eq!(vec![4, 5, 8, 11], read_sequences().await);
+
+            let err = delete_range(8, 5).await.unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidDeleteRange { .. }),
+                "err:{}",
+                err
+            );
+        });
+    }
+
+    #[test]
+    fn test_table_unit_clean_progress() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::with_buckets(runtime.clone(), 2);
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+            let vals = (0..10).collect::<Vec<_>>();
+            harness
+                .write_to_bucket(&table_unit, location, 0, &vals)
+                .await;
+            harness
+                .write_to_bucket(&table_unit, location, 1, &vals[..9])
+                .await;
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 19)
+                .await
+                .unwrap();
+
+            let progresses = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let progresses_clone = progresses.clone();
+            let clean_ctx = CleanContext {
+                batch_size: 3,
+                min_batch_size: 3,
+                max_batch_size: 3,
+                progress_callback: Some(CleanProgressCallback::new(move |progress| {
+                    progresses_clone
+                        .lock()
+                        .unwrap()
+                        .push((progress.table_name.to_string(), progress.total_deleted));
+                })),
+                ..Default::default()
+            };
+            table_unit
+                .clean_deleted_logs(&harness.table_kv, &clean_ctx, &harness.buckets)
+                .unwrap();
+
+            // 10 records in bucket 0 are deleted in 4 batches and 9 records in
+            // bucket 1 are deleted in 3 batches.
+            let table_names = harness
+                .buckets
+                .iter()
+                .map(|bucket| {
+                    bucket
+                        .wal_shard_table(DEFAULT_SHARD_ID as RegionId)
+                        .to_string()
+                })
+                .collect::<Vec<_>>();
+            let expect = [(0, 3), (0, 6), (0, 9), (0, 10), (1, 13), (1, 16), (1, 19)]
+                .into_iter()
+                .map(|(index, total)| (table_names[index].clone(), total))
+                .collect::<Vec<_>>();
+            assert_eq!(expect, *progresses.lock().unwrap());
+        });
+    }
+
+    #[test]
+    fn test_table_unit_clean_buckets_in_parallel() {
+        let runtime = Arc::new(Builder::default().enable_all().build().unwrap());
+        let harness = TableUnitHarness::with_buckets(runtime.clone(), 4);
+        let location = Location::new(DEFAULT_SHARD_ID, 123);
+
+        runtime.block_on(async {
+            let table_unit = harness.open_or_create(location).await;
+
+            // Write logs 1..=5 to bucket 0, 6..=10 to bucket 1 and so on.
+            for bucket_index in 0..harness.buckets.len() {
+                let vals = (0..5).collect::<Vec<_>>();
+                harness
+                    .write_to_bucket(&table_unit, location, bucket_index, &vals)
+                    .await;
+                assert_eq!(5, harness.num_bucket_records(bucket_index));
+            }
+
+            // Keep logs from sequence 8, which is in bucket 1.
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 7)
+                .await
+                .unwrap();
+            assert_eq!(8, table_unit.start_sequence());
+
+            let clean_ctx = CleanContext {
+                batch_size: 2,
+                min_batch_size: 1,
+                bucket_parallelism: 3,
+                ..Default::default()
+            };
+            table_unit
+                .clean_deleted_logs(&harness.table_kv, &clean_ctx, &harness.buckets)
+                .unwrap();
+            let num_records = (0..harness.buckets.len())
+                .map(|i| harness.num_bucket_records(i))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![0, 3, 5, 5], num_records);
+
+            // Delete all the logs.
+            table_unit
+                .delete_entries_up_to(&harness.table_kv, TABLE_UNIT_META_TABLE, 20)
+                .await
+                .unwrap();
+            table_unit
+                .clean_deleted_logs(&harness.table_kv, &clean_ctx, &harness.buckets)
+                .unwrap();
+            for i in 0..harness.buckets.len() {
+                assert_eq!(0, harness.num_bucket_records(i));
+            }
+        });
+    }
+}
@@ -19,7 +19,7 @@ use crate::{
     table_kv_impl::{
         model::NamespaceConfig,
         namespace::{Namespace, NamespaceRef},
-        WalRuntimes,
+        WalRuntimes, WalRuntimesStats,
     },
 };
 
@@ -87,6 +87,11 @@ impl<T: TableKv> WalNamespaceImpl<T> {
 
         Ok(())
     }
+
+    /// Snapshot of the stats of the runtimes used by the wal.
+    pub fn runtime_stats(&self) -> WalRuntimesStats {
+        self.namespace.runtimes().stats()
+    }
 }
 
 impl<T> fmt::Debug for WalNamespaceImpl<T> {
@@ -0,0 +1,160 @@
+// Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Coalesce log writes of different table units.
+//!
+//! Table units in the same region share the same wal shard table, so logs
+//! written to the same shard table within a short window are collected and
+//! written by a single table kv write, which amortizes the cost of the round
+//! trip to the table kv.
+//!
+//! The sequence numbers are still allocated by each table unit before
+//! submitting its logs, and the table unit holds its writer lock until the
+//! coalesced write finishes, so the sequences of one table unit are always
+//! monotonic.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common_util::{define_result, runtime::Runtime};
+use log::debug;
+use snafu::Snafu;
+use table_kv::{TableKv, WriteBatch, WriteContext};
+use tokio::sync::oneshot;
+
+use crate::{manager::RegionId, table_kv_impl::table_unit};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Failed to write coalesced logs, table_name:{}, err:{}",
+        table_name,
+        msg
+    ))]
+    WriteCoalesced { table_name: String, msg: String },
+
+    #[snafu(display("Coalesced write is cancelled, table_name:{}", table_name))]
+    Cancelled { table_name: String },
+}
+
+define_result!(Error);
+
+/// Result of a coalesced write, the error is shared by all the writers so it
+/// is converted into string.
+type WriteResult = std::result::Result<(), String>;
+
+/// Logs submitted to one wal shard table and not written yet.
+#[derive(Default)]
+struct PendingWrites {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    waiters: Vec<oneshot::Sender<WriteResult>>,
+}
+
+pub struct LogWriteCoalescer<T> {
+    runtime: Arc<Runtime>,
+    table_kv: T,
+    /// Time to wait for more logs after the first log of a batch arrives.
+    window: Duration,
+    /// Pending writes, keyed by the name of wal shard table.
+    pending: Mutex<HashMap<String, PendingWrites>>,
+}
+
+impl<T: TableKv> LogWriteCoalescer<T> {
+    pub fn new(runtime: Arc<Runtime>, table_kv: T, window: Duration) -> Self {
+        Self {
+            runtime,
+            table_kv,
+            window,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Write the encoded log `entries` to `table_name`, the entries may be
+    /// written together with entries submitted by others.
+    pub async fn write(
+        self: &Arc<Self>,
+        table_name: &str,
+        region_id: RegionId,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        let is_first = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.get_mut(table_name) {
+                Some(writes) => {
+                    writes.entries.extend(entries);
+                    writes.waiters.push(tx);
+                    false
+                }
+                None => {
+                    pending.insert(
+                        table_name.to_string(),
+                        PendingWrites {
+                            entries,
+                            waiters: vec![tx],
+                        },
+                    );
+                    true
+                }
+            }
+        };
+
+        // The first writer of a batch schedules the flush, which is spawned so it
+        // won't be cancelled if the writer is dropped.
+        if is_first {
+            let coalescer = self.clone();
+            let table_name = table_name.to_string();
+            self.runtime.spawn(async move {
+                tokio::time::sleep(coalescer.window).await;
+                coalescer.flush(&table_name, region_id).await;
+            });
+        }
+
+        match rx.await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(msg)) => WriteCoalesced { table_name, msg }.fail(),
+            Err(_) => Cancelled { table_name }.fail(),
+        }
+    }
+
+    async fn flush(&self, table_name: &str, region_id: RegionId) {
+        let writes = match self.pending.lock().unwrap().remove(table_name) {
+            Some(v) => v,
+            None => return,
+        };
+
+        debug!(
+            "Wal write coalescer flush logs, table_name:{}, writers:{}, entries:{}",
+            table_name,
+            writes.waiters.len(),
+            writes.entries.len()
+        );
+
+        // Logs of different writers are written together, so they are always
+        // written durably.
+        let entries = &writes.entries;
+        let res = table_unit::write_logs_with_retry(
+            &self.runtime,
+            &self.table_kv,
+            &WriteContext::default(),
+            table_name,
+            region_id,
+            || {
+                let mut wb = T::WriteBatch::with_capacity(entries.len());
+                for (key, value) in entries {
+                    wb.insert(key, value);
+                }
+                Ok(wb)
+            },
+        )
+        .await
+        .map_err(|e| e.to_string());
+
+        for waiter in writes.waiters {
+            // The writer may be dropped, just ignore it.
+            let _ = waiter.send(res.clone());
+        }
+    }
+}